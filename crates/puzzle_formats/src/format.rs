@@ -1,5 +1,149 @@
+use external::{ExternalPuzzleFormat, fpuzzles::FPuzzlesFormat, sudokupad::SudokupadSclFormat};
+use full::{Converted, FullPuzzle};
+
 pub mod external;
 pub mod full;
 pub mod logical;
 pub mod parsing_resolver;
 pub mod visual;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FormatError {
+    #[error("Invalid puzzle json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Puzzle json is neither f-puzzles, SudokuPad nor full json")]
+    UnknownFormat,
+    #[error("{0}")]
+    Unsupported(&'static str),
+    #[error("Invalid grid size {0}, expected 1 to {max}", max = full::MAX_SIZE)]
+    InvalidSize(i64),
+    #[error("Grid does not have {0} rows of {0} cells")]
+    InvalidGrid(u8),
+    #[error("Cell {0} is outside of the grid")]
+    CellOutOfGrid(Box<str>),
+    #[error("Invalid value `{value}` in cell {cell}")]
+    InvalidValue { cell: Box<str>, value: Box<str> },
+}
+
+/// Which format a [`PuzzleFormat`] holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatKind {
+    FPuzzles,
+    SudokupadScl,
+    Full,
+}
+
+/// A decoded puzzle in any of the supported formats.
+#[derive(Debug, Clone)]
+pub enum PuzzleFormat {
+    FPuzzles(Box<FPuzzlesFormat>),
+    SudokupadScl(Box<SudokupadSclFormat>),
+    Full(Box<FullPuzzle>),
+}
+
+impl PuzzleFormat {
+    #[must_use]
+    pub fn kind(&self) -> FormatKind {
+        match self {
+            Self::FPuzzles(_) => FormatKind::FPuzzles,
+            Self::SudokupadScl(_) => FormatKind::SudokupadScl,
+            Self::Full(_) => FormatKind::Full,
+        }
+    }
+
+    /// Parse json of the given format, detected by its top level fields if `None`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the json is invalid or its format is not detected.
+    pub fn from_json(json: &str, kind: Option<FormatKind>) -> Result<Self, FormatError> {
+        let kind = match kind {
+            Some(kind) => kind,
+            None => detect(&serde_json::from_str(json)?)?,
+        };
+        Ok(match kind {
+            FormatKind::FPuzzles => Self::FPuzzles(FPuzzlesFormat::from_json(json)?.into()),
+            FormatKind::SudokupadScl => {
+                Self::SudokupadScl(SudokupadSclFormat::from_json(json)?.into())
+            }
+            FormatKind::Full => Self::Full(serde_json::from_str::<FullPuzzle>(json)?.into()),
+        })
+    }
+
+    /// Pretty printed json of the format.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if serialization fails.
+    pub fn to_json(&self) -> Result<String, FormatError> {
+        match self {
+            Self::FPuzzles(puzzle) => puzzle.to_json(),
+            Self::SudokupadScl(puzzle) => puzzle.to_json(),
+            Self::Full(puzzle) => Ok(serde_json::to_string_pretty(puzzle)?),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an error if the puzzle can't be represented as [`FullPuzzle`].
+    pub fn to_full(&self) -> Result<Converted<FullPuzzle>, FormatError> {
+        match self {
+            Self::FPuzzles(puzzle) => puzzle.to_full(),
+            Self::SudokupadScl(puzzle) => puzzle.to_full(),
+            Self::Full(puzzle) => {
+                puzzle.validate()?;
+                Ok(Converted::new((**puzzle).clone()))
+            }
+        }
+    }
+
+    /// Replace the solution with `solution`, given as rows of values.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the format can't hold the solution.
+    pub fn set_solution(&mut self, solution: &[Box<[u8]>]) -> Result<(), FormatError> {
+        match self {
+            Self::FPuzzles(puzzle) => puzzle.set_solution(solution),
+            Self::SudokupadScl(puzzle) => puzzle.set_solution(solution)?,
+            Self::Full(puzzle) => puzzle.solution = Some(solution.into()),
+        }
+        Ok(())
+    }
+
+    /// Convert via [`FullPuzzle`], collecting everything dropped on the way.
+    /// Converting into the same format returns the puzzle unchanged.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the puzzle can't be represented in `kind`.
+    pub fn convert(&self, kind: FormatKind) -> Result<Converted<PuzzleFormat>, FormatError> {
+        if kind == self.kind() {
+            return Ok(Converted::new(self.clone()));
+        }
+
+        let full = self.to_full()?;
+        let converted = match kind {
+            FormatKind::FPuzzles => {
+                FPuzzlesFormat::from_full(&full.puzzle)?.map(|puzzle| Self::FPuzzles(puzzle.into()))
+            }
+            FormatKind::SudokupadScl => SudokupadSclFormat::from_full(&full.puzzle)?
+                .map(|puzzle| Self::SudokupadScl(puzzle.into())),
+            FormatKind::Full => Converted::new(Self::Full(full.puzzle.clone().into())),
+        };
+        Ok(converted.with_dropped(full.dropped))
+    }
+}
+
+fn detect(value: &serde_json::Value) -> Result<FormatKind, FormatError> {
+    let has = |key| value.get(key).is_some();
+    if has("grid") {
+        Ok(FormatKind::FPuzzles)
+    } else if has("givens") {
+        Ok(FormatKind::Full)
+    } else if has("cells") || has("metadata") {
+        Ok(FormatKind::SudokupadScl)
+    } else {
+        Err(FormatError::UnknownFormat)
+    }
+}
@@ -1,16 +1,37 @@
+use super::{
+    FormatError,
+    full::{Converted, FullPuzzle},
+};
+
 pub mod fpuzzles;
 pub mod penpa;
 pub mod sudokumaker;
 pub mod sudokupad;
 
-pub trait ExternalPuzzleFormat {
-    // This trait specifies, how a format is loaded
-    // conversion from / to string
-    // conversion from / to our own format
+/// How a format is loaded from and written to json, and converted from and to [`FullPuzzle`].
+///
+/// Formats that are not supported yet return [`FormatError::Unsupported`],
+/// with a hint how to load the puzzle instead.
+pub trait ExternalPuzzleFormat: Sized {
+    /// # Errors
+    ///
+    /// This function will return an error if the json is not valid for the format.
+    fn from_json(json: &str) -> Result<Self, FormatError>;
+
+    /// Pretty printed json.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if serialization fails.
+    fn to_json(&self) -> Result<String, FormatError>;
 
-    //TODO: Implement this trait for all 4 formats (Penpa and Sudokumaker can have stubs, that only return an Error, eg. Format not Supported)
-    // Maybe even with a custom message:
+    /// # Errors
+    ///
+    /// This function will return an error if the puzzle can't be represented as [`FullPuzzle`].
+    fn to_full(&self) -> Result<Converted<FullPuzzle>, FormatError>;
 
-    // Sudokumaker: Sudokumaker is not supported yet, instead export the puzzle to Sudokupad before loading
-    // Penpa: Penpa is not supported yet, instead use the Online Tool (https://marktekfan.github.io/sudokupad-penpa-import/) to convert the puzzle before loading
+    /// # Errors
+    ///
+    /// This function will return an error if the puzzle can't be represented in the format.
+    fn from_full(full: &FullPuzzle) -> Result<Converted<Self>, FormatError>;
 }
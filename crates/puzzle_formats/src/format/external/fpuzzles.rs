@@ -5,10 +5,9 @@ use grid_cell::GridCell;
 use negative::Negative;
 use serde::{Deserialize, Serialize};
 
-use super::ExternalPuzzleFormat;
-
 mod cell_pos;
 mod constraint;
+mod conversion;
 mod cosmetic;
 mod direction;
 mod grid_cell;
@@ -130,5 +129,3 @@ pub struct FPuzzlesFormat {
     #[serde(rename = "solution", default, skip_serializing_if = "is_empty")]
     solution: Box<[Box<str>]>,
 }
-
-impl ExternalPuzzleFormat for FPuzzlesFormat {}
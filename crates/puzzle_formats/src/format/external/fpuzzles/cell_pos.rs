@@ -5,8 +5,8 @@ use serde::{Deserialize, Serialize, de};
 
 #[derive(Eq, PartialEq, Clone, Copy)]
 pub struct CellPos {
-    row: i8,
-    column: i8,
+    pub(super) row: i8,
+    pub(super) column: i8,
 }
 
 impl fmt::Debug for CellPos {
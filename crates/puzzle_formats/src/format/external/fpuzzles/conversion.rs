@@ -0,0 +1,376 @@
+use crate::{
+    format::{
+        FormatError,
+        external::ExternalPuzzleFormat,
+        full::{Converted, FullCell, FullConstraint, FullPuzzle, MAX_SIZE},
+    },
+    serialization::StrOrInt,
+};
+
+use super::{
+    FPuzzlesFormat, cell_pos::CellPos, constraint::Constraint, grid_cell::GridCell,
+    highlight_color::HighlightColor, negative::Negative, region::Region,
+};
+
+impl ExternalPuzzleFormat for FPuzzlesFormat {
+    fn from_json(json: &str) -> Result<Self, FormatError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    fn to_json(&self) -> Result<String, FormatError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    fn to_full(&self) -> Result<Converted<FullPuzzle>, FormatError> {
+        let size = u8::try_from(self.size)
+            .ok()
+            .filter(|size| (1..=MAX_SIZE).contains(size))
+            .ok_or(FormatError::InvalidSize(self.size.into()))?;
+        let mut full = FullPuzzle::new(size)?;
+        let mut dropped = Vec::new();
+
+        full.title = self.title.clone();
+        full.author = self.author.clone();
+        full.rules = self.ruleset.clone();
+
+        if self.grid.len() != usize::from(size)
+            || self.grid.iter().any(|row| row.len() != usize::from(size))
+        {
+            return Err(FormatError::InvalidGrid(size));
+        }
+        let mut regions = false;
+        let mut highlights = false;
+        for (row, cells) in self.grid.iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                regions |= matches!(cell.region, Some(Region::InRegion(_)));
+                highlights |= cell.c != HighlightColor::default()
+                    || cell.highlight != HighlightColor::default();
+                if !cell.given {
+                    continue;
+                }
+                if let Some(value) = &cell.value {
+                    full.givens[row][column] = Some(parse_value(value, size, row, column)?);
+                }
+            }
+        }
+        if regions {
+            dropped.push("region".into());
+        }
+        if highlights {
+            dropped.push("highlight".into());
+        }
+
+        let mut constraints = Vec::new();
+        for (flag, constraint) in [
+            (self.diagonal_positive, FullConstraint::PositiveDiagonal),
+            (self.diagonal_negative, FullConstraint::NegativeDiagonal),
+            (self.antiknight, FullConstraint::AntiKnight),
+            (self.antiking, FullConstraint::AntiKing),
+        ] {
+            if flag {
+                constraints.push(constraint);
+            }
+        }
+        let mut invalid_sums = false;
+        for cage in &self.killercage {
+            let value = cage
+                .value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty());
+            let sum = value.and_then(|value| value.parse().ok());
+            invalid_sums |= value.is_some() && sum.is_none();
+            constraints.push(FullConstraint::Killer {
+                cells: to_full_cells(&cage.cells, size)?,
+                sum,
+            });
+        }
+        if invalid_sums {
+            dropped.push("killercage.value".into());
+        }
+        for thermometer in &self.thermometer {
+            for line in &thermometer.lines {
+                constraints.push(FullConstraint::Thermometer {
+                    cells: to_full_cells(line, size)?,
+                });
+            }
+        }
+        for arrow in &self.arrow {
+            let bulb = to_full_cells(&arrow.cells, size)?;
+            for line in &arrow.lines {
+                let line = to_full_cells(line, size)?
+                    .into_iter()
+                    .filter(|cell| !bulb.contains(cell))
+                    .collect();
+                constraints.push(FullConstraint::Arrow {
+                    bulb: bulb.clone(),
+                    line,
+                });
+            }
+        }
+        full.constraints = constraints.into();
+
+        if !self.solution.is_empty() {
+            match parse_solution(&self.solution, size) {
+                Some(solution) => full.solution = Some(solution),
+                None => dropped.push("solution".into()),
+            }
+        }
+
+        dropped.extend(self.unsupported_fields());
+
+        full.validate()?;
+        Ok(Converted {
+            puzzle: full,
+            dropped,
+        })
+    }
+
+    fn from_full(full: &FullPuzzle) -> Result<Converted<Self>, FormatError> {
+        full.validate()?;
+
+        let grid = full
+            .givens
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| GridCell {
+                        value: value.map(|value| StrOrInt::Int(value.into())),
+                        given: value.is_some(),
+                        ..GridCell::default()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut puzzle = FPuzzlesFormat {
+            title: full.title.clone(),
+            author: full.author.clone(),
+            ruleset: full.rules.clone(),
+            size: full.size.into(),
+            highlight_conflicts: true,
+            grid,
+            ..FPuzzlesFormat::default()
+        };
+
+        let (mut killercage, mut thermometer, mut arrow) = (Vec::new(), Vec::new(), Vec::new());
+        for constraint in &full.constraints {
+            match constraint {
+                FullConstraint::Killer { cells, sum } => killercage.push(Constraint {
+                    cells: to_cell_positions(cells),
+                    value: sum.map(|sum| sum.to_string().into()),
+                    ..Constraint::default()
+                }),
+                FullConstraint::Thermometer { cells } => thermometer.push(Constraint {
+                    lines: [to_cell_positions(cells)].into(),
+                    ..Constraint::default()
+                }),
+                FullConstraint::Arrow { bulb, line } => {
+                    // f-puzzles arrows start in the bulb cell closest to the line
+                    let start = line.first().and_then(|first| {
+                        bulb.iter().min_by_key(|cell| {
+                            cell.row
+                                .abs_diff(first.row)
+                                .max(cell.column.abs_diff(first.column))
+                        })
+                    });
+                    let cells: Vec<FullCell> = start.into_iter().chain(line).copied().collect();
+                    arrow.push(Constraint {
+                        cells: to_cell_positions(bulb),
+                        lines: [to_cell_positions(&cells)].into(),
+                        ..Constraint::default()
+                    });
+                }
+                FullConstraint::PositiveDiagonal => puzzle.diagonal_positive = true,
+                FullConstraint::NegativeDiagonal => puzzle.diagonal_negative = true,
+                FullConstraint::AntiKnight => puzzle.antiknight = true,
+                FullConstraint::AntiKing => puzzle.antiking = true,
+            }
+        }
+        puzzle.killercage = killercage.into();
+        puzzle.thermometer = thermometer.into();
+        puzzle.arrow = arrow.into();
+
+        if let Some(solution) = &full.solution {
+            puzzle.set_solution(solution);
+        }
+
+        Ok(Converted::new(puzzle))
+    }
+}
+
+impl FPuzzlesFormat {
+    /// Replace the solution with `solution`, given as rows of values.
+    pub fn set_solution(&mut self, solution: &[Box<[u8]>]) {
+        self.solution = solution
+            .iter()
+            .flatten()
+            .map(|value| value.to_string().into())
+            .collect();
+    }
+
+    /// Names of the set fields without an equivalent in [`FullPuzzle`].
+    fn unsupported_fields(&self) -> impl Iterator<Item = Box<str>> {
+        [
+            ("disjointgroups", self.disjointgroups),
+            ("nonconsecutive", self.nonconsecutive),
+            ("extraregion", !self.extraregion.is_empty()),
+            ("odd", !self.odd.is_empty()),
+            ("even", !self.even.is_empty()),
+            ("palindrome", !self.palindrome.is_empty()),
+            ("littlekillersum", !self.littlekillersum.is_empty()),
+            ("sandwichsum", !self.sandwichsum.is_empty()),
+            ("difference", !self.difference.is_empty()),
+            ("negative", self.negative != Negative::default()),
+            ("ratio", !self.ratio.is_empty()),
+            ("clone", !self.clone.is_empty()),
+            ("betweenline", !self.betweenline.is_empty()),
+            ("minimum", !self.minimum.is_empty()),
+            ("maximum", !self.maximum.is_empty()),
+            ("xv", !self.xv.is_empty()),
+            ("quadruple", !self.quadruple.is_empty()),
+            ("text", !self.text.is_empty()),
+            ("circle", !self.circle.is_empty()),
+            ("rectangle", !self.rectangle.is_empty()),
+            ("line", !self.line.is_empty()),
+            ("cage", !self.cage.is_empty()),
+        ]
+        .into_iter()
+        .filter(|(_, present)| *present)
+        .map(|(name, _)| name.into())
+    }
+}
+
+fn parse_value(value: &StrOrInt, size: u8, row: usize, column: usize) -> Result<u8, FormatError> {
+    value
+        .as_i32()
+        .and_then(|value| u8::try_from(value).ok())
+        .filter(|value| (1..=size).contains(value))
+        .ok_or_else(|| FormatError::InvalidValue {
+            cell: format!("R{}C{}", row + 1, column + 1).into(),
+            value: value.as_str().into(),
+        })
+}
+
+/// Solutions are the values of all cells, row by row.
+fn parse_solution(solution: &[Box<str>], size: u8) -> Option<Box<[Box<[u8]>]>> {
+    if solution.len() != usize::from(size) * usize::from(size) {
+        return None;
+    }
+    let values = solution
+        .iter()
+        .map(|value| {
+            value
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|value| (1..=size).contains(value))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(values.chunks(usize::from(size)).map(Into::into).collect())
+}
+
+fn to_full_cells(cells: &[CellPos], size: u8) -> Result<Box<[FullCell]>, FormatError> {
+    cells
+        .iter()
+        .map(|pos| {
+            let convert = |n: i8| {
+                u8::try_from(n)
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .filter(|n| *n < size)
+            };
+            match (convert(pos.row), convert(pos.column)) {
+                (Some(row), Some(column)) => Ok(FullCell::new(row, column)),
+                _ => Err(FormatError::CellOutOfGrid(
+                    format!("R{}C{}", pos.row, pos.column).into(),
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Cells of a validated puzzle, so inside of a grid of at most [`MAX_SIZE`].
+fn to_cell_positions(cells: &[FullCell]) -> Box<[CellPos]> {
+    let convert = |n: u8| i8::try_from(n.saturating_add(1)).unwrap_or(i8::MAX);
+    cells
+        .iter()
+        .map(|cell| CellPos {
+            row: convert(cell.row),
+            column: convert(cell.column),
+        })
+        .collect()
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use crate::format::{
+        external::{ExternalPuzzleFormat, fpuzzles::FPuzzlesFormat},
+        full::{FullCell, FullConstraint},
+    };
+
+    #[test]
+    fn to_full_and_back() {
+        let payload = include_str!("../../../../assets/puzzleid.txt").trim();
+        let json = String::from_utf16(&lz_str::decompress_from_base64(payload).unwrap()).unwrap();
+        let puzzle = FPuzzlesFormat::from_json(&json).unwrap();
+
+        let full = puzzle.to_full().unwrap();
+        assert_eq!(full.puzzle.size, 9);
+        assert_eq!(full.puzzle.title.as_ref(), "Custom Sudoku");
+        assert_eq!(full.puzzle.given(FullCell::new(0, 0)), Some(1));
+        assert!(full.puzzle.constraints.contains(&FullConstraint::Killer {
+            cells: [FullCell::new(3, 1), FullCell::new(4, 1)].into(),
+            sum: Some(8),
+        }));
+        assert!(
+            full.puzzle.constraints.contains(&FullConstraint::Arrow {
+                bulb: [FullCell::new(0, 6), FullCell::new(0, 7)].into(),
+                line: [
+                    FullCell::new(1, 6),
+                    FullCell::new(2, 6),
+                    FullCell::new(1, 7)
+                ]
+                .into(),
+            })
+        );
+        let count = |kind| {
+            full.puzzle
+                .constraints
+                .iter()
+                .filter(|constraint| constraint.kind() == kind)
+                .count()
+        };
+        assert_eq!(count("thermometer"), 3);
+        assert_eq!(count("killer"), 2);
+        assert!(
+            full.dropped
+                .iter()
+                .any(|name| name.as_ref() == "littlekillersum")
+        );
+        assert!(
+            !full
+                .dropped
+                .iter()
+                .any(|name| name.as_ref() == "killercage")
+        );
+
+        let back = FPuzzlesFormat::from_full(&full.puzzle).unwrap();
+        assert!(back.dropped.is_empty());
+        let again = back.puzzle.to_full().unwrap();
+        assert_eq!(again.puzzle, full.puzzle);
+        assert!(again.dropped.is_empty());
+
+        // Sums which are no number are reported instead of turning into cages without a sum
+        let mut invalid = back.puzzle;
+        invalid.killercage[0].value = Some("8?".into());
+        let converted = invalid.to_full().unwrap();
+        assert!(
+            converted
+                .dropped
+                .iter()
+                .any(|name| name.as_ref() == "killercage.value")
+        );
+    }
+}
@@ -4,14 +4,14 @@ use crate::serialization::{StrOrInt, deserialize_some, is_default, is_empty};
 
 use super::{highlight_color::HighlightColor, region::Region};
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct GridCell {
     #[serde(rename = "value", default, skip_serializing_if = "Option::is_none")]
-    value: Option<StrOrInt>,
+    pub(super) value: Option<StrOrInt>,
 
     #[serde(rename = "given", default, skip_serializing_if = "is_default")]
-    given: bool,
+    pub(super) given: bool,
 
     #[serde(
         rename = "region",
@@ -19,28 +19,28 @@ pub struct GridCell {
         skip_serializing_if = "Option::is_none",
         deserialize_with = "deserialize_some"
     )]
-    region: Option<Region>,
+    pub(super) region: Option<Region>,
 
     #[serde(rename = "c", default, skip_serializing_if = "is_default")]
-    c: HighlightColor,
+    pub(super) c: HighlightColor,
 
     #[serde(
         rename = "centerPencilMarks",
         default,
         skip_serializing_if = "is_empty"
     )]
-    center_pencil_marks: Box<[StrOrInt]>,
+    pub(super) center_pencil_marks: Box<[StrOrInt]>,
 
     #[serde(
         rename = "cornerPencilMarks",
         default,
         skip_serializing_if = "is_empty"
     )]
-    corner_pencil_marks: Box<[StrOrInt]>,
+    pub(super) corner_pencil_marks: Box<[StrOrInt]>,
 
     #[serde(rename = "highlight", default, skip_serializing_if = "is_default")]
-    highlight: HighlightColor,
+    pub(super) highlight: HighlightColor,
 
     #[serde(rename = "candidates", default, skip_serializing_if = "is_empty")]
-    candidates: Box<[i32]>,
+    pub(super) candidates: Box<[i32]>,
 }
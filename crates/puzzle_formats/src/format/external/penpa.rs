@@ -1,5 +1,27 @@
 use super::ExternalPuzzleFormat;
+use crate::format::{
+    FormatError,
+    full::{Converted, FullPuzzle},
+};
+
+pub const UNSUPPORTED: &str = "Penpa is not supported yet, instead use the Online Tool (https://marktekfan.github.io/sudokupad-penpa-import/) to convert the puzzle before loading";
 
 pub struct PenpaFormat {}
 
-impl ExternalPuzzleFormat for PenpaFormat {}
+impl ExternalPuzzleFormat for PenpaFormat {
+    fn from_json(_json: &str) -> Result<Self, FormatError> {
+        Err(FormatError::Unsupported(UNSUPPORTED))
+    }
+
+    fn to_json(&self) -> Result<String, FormatError> {
+        Err(FormatError::Unsupported(UNSUPPORTED))
+    }
+
+    fn to_full(&self) -> Result<Converted<FullPuzzle>, FormatError> {
+        Err(FormatError::Unsupported(UNSUPPORTED))
+    }
+
+    fn from_full(_full: &FullPuzzle) -> Result<Converted<Self>, FormatError> {
+        Err(FormatError::Unsupported(UNSUPPORTED))
+    }
+}
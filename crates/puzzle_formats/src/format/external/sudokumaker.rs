@@ -1,5 +1,28 @@
 use super::ExternalPuzzleFormat;
+use crate::format::{
+    FormatError,
+    full::{Converted, FullPuzzle},
+};
+
+pub const UNSUPPORTED: &str =
+    "Sudokumaker is not supported yet, instead export the puzzle to Sudokupad before loading";
 
 pub struct SudokumakerFormat {}
 
-impl ExternalPuzzleFormat for SudokumakerFormat {}
+impl ExternalPuzzleFormat for SudokumakerFormat {
+    fn from_json(_json: &str) -> Result<Self, FormatError> {
+        Err(FormatError::Unsupported(UNSUPPORTED))
+    }
+
+    fn to_json(&self) -> Result<String, FormatError> {
+        Err(FormatError::Unsupported(UNSUPPORTED))
+    }
+
+    fn to_full(&self) -> Result<Converted<FullPuzzle>, FormatError> {
+        Err(FormatError::Unsupported(UNSUPPORTED))
+    }
+
+    fn from_full(_full: &FullPuzzle) -> Result<Converted<Self>, FormatError> {
+        Err(FormatError::Unsupported(UNSUPPORTED))
+    }
+}
@@ -1,4 +1,4 @@
-use super::{ExternalPuzzleFormat, fpuzzles::FPuzzlesFormat};
+use super::fpuzzles::FPuzzlesFormat;
 use crate::serialization::is_empty;
 use arrow::Arrow;
 use cage::Cage;
@@ -14,6 +14,7 @@ use trigger_effect::TriggerEffect;
 mod arrow;
 mod cage;
 mod cell;
+mod conversion;
 mod cosmetic;
 mod line;
 mod metadata;
@@ -21,6 +22,8 @@ mod pos;
 mod region;
 mod trigger_effect;
 
+pub use conversion::SCF_UNSUPPORTED;
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(deny_unknown_fields)]
 // TODO: Positions are zero based (if not in R-C- format)
@@ -60,10 +63,8 @@ pub struct SudokupadSclFormat {
 }
 
 pub struct SudokupadScfFormat {}
+
+#[derive(Debug, Default, Clone)]
 pub struct SudokupadFPuzFormat {
     puzzle: FPuzzlesFormat,
 }
-
-impl ExternalPuzzleFormat for SudokupadSclFormat {}
-impl ExternalPuzzleFormat for SudokupadScfFormat {}
-impl ExternalPuzzleFormat for SudokupadFPuzFormat {}
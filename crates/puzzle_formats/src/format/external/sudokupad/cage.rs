@@ -8,17 +8,17 @@ use super::pos::Pos;
 #[serde(deny_unknown_fields)]
 pub struct Cage {
     #[serde(rename = "cells", default)]
-    cells: Box<[Pos<i32>]>,
+    pub(super) cells: Box<[Pos<i32>]>,
 
     #[serde(rename = "value", default, skip_serializing_if = "Option::is_none")]
-    value: Option<Box<str>>,
+    pub(super) value: Option<Box<str>>,
 
     #[serde(rename = "unique", default, skip_serializing_if = "is_default")]
-    unique: bool,
+    pub(super) unique: bool,
 
     #[serde(rename = "hidden", default, skip_serializing_if = "is_default")]
-    hidden: bool,
+    pub(super) hidden: bool,
 
     #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
-    kind: Option<Box<str>>,
+    pub(super) kind: Option<Box<str>>,
 }
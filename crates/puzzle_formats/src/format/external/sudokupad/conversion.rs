@@ -0,0 +1,481 @@
+use csscolorparser::Color;
+use serde_json::{Map, Value};
+
+use crate::format::{
+    FormatError,
+    external::{ExternalPuzzleFormat, fpuzzles::FPuzzlesFormat},
+    full::{Converted, FullCell, FullConstraint, FullPuzzle, MAX_SIZE},
+};
+
+use super::{
+    SudokupadFPuzFormat, SudokupadScfFormat, SudokupadSclFormat, arrow::Arrow, cage::Cage,
+    cell::Cell, cosmetic::Cosmetic, line::Line, metadata::Metadata, pos::Pos, region::Region,
+};
+
+const SOLUTION_UNSUPPORTED: &str = "SudokuPad scl solutions only hold values of a single digit";
+
+pub const SCF_UNSUPPORTED: &str =
+    "SudokuPad scf puzzles are not supported yet, instead share the puzzle as f-puzzles or scl";
+
+const THERMOMETER_COLOR: Color = Color::new(0.81, 0.81, 0.81, 1.0);
+const ARROW_COLOR: Color = Color::new(0.63, 0.63, 0.63, 1.0);
+const DIAGONAL_COLOR: Color = Color::new(0.2, 0.73, 0.9, 1.0);
+const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+
+impl ExternalPuzzleFormat for SudokupadSclFormat {
+    fn from_json(json: &str) -> Result<Self, FormatError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    fn to_json(&self) -> Result<String, FormatError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    fn to_full(&self) -> Result<Converted<FullPuzzle>, FormatError> {
+        let rows = self.cells.len();
+        let size = u8::try_from(rows)
+            .ok()
+            .filter(|size| (1..=MAX_SIZE).contains(size))
+            .ok_or(FormatError::InvalidSize(
+                i64::try_from(rows).unwrap_or(i64::MAX),
+            ))?;
+        if self.cells.iter().any(|row| row.len() != rows) {
+            return Err(FormatError::InvalidGrid(size));
+        }
+
+        let mut full = FullPuzzle::new(size)?;
+        let mut dropped = Vec::new();
+        full.title = self.metadata.title.clone();
+        full.author = self.metadata.author.clone();
+        full.rules = self.metadata.rules.clone();
+
+        let mut decorations = false;
+        for (row, cells) in self.cells.iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                let Some(fields) = cell.rest_values.as_object() else {
+                    continue;
+                };
+                decorations |= fields.keys().any(|key| key != "value");
+                if let Some(value) = fields.get("value") {
+                    full.givens[row][column] = Some(parse_value(value, size, row, column)?);
+                }
+            }
+        }
+        if decorations {
+            dropped.push("cells".into());
+        }
+
+        let mut constraints = Vec::new();
+        if self.metadata.antiknight {
+            constraints.push(FullConstraint::AntiKnight);
+        }
+        if self.metadata.antiking {
+            constraints.push(FullConstraint::AntiKing);
+        }
+        let mut typed_cages = false;
+        let mut invalid_sums = false;
+        for cage in &self.cages {
+            if cage.kind.is_some() {
+                typed_cages = true;
+                continue;
+            }
+            let value = cage
+                .value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty());
+            let sum = value.and_then(|value| value.parse().ok());
+            invalid_sums |= value.is_some() && sum.is_none();
+            constraints.push(FullConstraint::Killer {
+                cells: to_full_cells(&cage.cells, size)?,
+                sum,
+            });
+        }
+        if typed_cages {
+            dropped.push("cages".into());
+        }
+        if invalid_sums {
+            dropped.push("cages.value".into());
+        }
+        full.constraints = constraints.into();
+
+        if !self.metadata.solution.is_empty() {
+            match parse_solution(&self.metadata.solution, size) {
+                Some(solution) => full.solution = Some(solution),
+                None => dropped.push("solution".into()),
+            }
+        }
+
+        if !self.regions.is_empty() && !are_boxes(&self.regions, &full)? {
+            dropped.push("regions".into());
+        }
+        dropped.extend(
+            [
+                ("msgcorrect", self.metadata.msgcorrect.is_some()),
+                ("lines", !self.lines.is_empty()),
+                ("overlays", !self.overlays.is_empty()),
+                ("underlays", !self.underlays.is_empty()),
+                ("foglight", !self.foglight.is_empty()),
+                ("arrows", !self.arrows.is_empty()),
+                ("triggereffect", !self.triggereffect.is_empty()),
+            ]
+            .into_iter()
+            .filter(|(_, present)| *present)
+            .map(|(name, _)| name.into()),
+        );
+
+        full.validate()?;
+        Ok(Converted {
+            puzzle: full,
+            dropped,
+        })
+    }
+
+    /// Constraints without an equivalent in `SudokuPad` are drawn, like `SudokuPad` draws
+    /// f-puzzles constraints.
+    fn from_full(full: &FullPuzzle) -> Result<Converted<Self>, FormatError> {
+        full.validate()?;
+
+        let cells = full
+            .givens
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| {
+                        let mut fields = Map::new();
+                        if let Some(value) = value {
+                            fields.insert("value".into(), (*value).into());
+                        }
+                        Cell {
+                            rest_values: Value::Object(fields),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut puzzle = SudokupadSclFormat {
+            metadata: Metadata {
+                title: full.title.clone(),
+                author: full.author.clone(),
+                rules: full.rules.clone(),
+                ..Metadata::default()
+            },
+            cells,
+            regions: boxes(full),
+            ..SudokupadSclFormat::default()
+        };
+
+        let (mut cages, mut lines, mut arrows, mut underlays, mut overlays) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        let size = f64::from(full.size);
+        for constraint in &full.constraints {
+            match constraint {
+                FullConstraint::Killer { cells, sum } => cages.push(Cage {
+                    cells: cells.iter().copied().map(to_pos).collect(),
+                    value: sum.map(|sum| sum.to_string().into()),
+                    unique: true,
+                    ..Cage::default()
+                }),
+                FullConstraint::Thermometer { cells } => {
+                    if let Some(bulb) = cells.first() {
+                        underlays.push(circle(*bulb, THERMOMETER_COLOR, THERMOMETER_COLOR));
+                    }
+                    lines.push(line(
+                        cells.iter().copied().map(center).collect(),
+                        THERMOMETER_COLOR,
+                        9.6,
+                    ));
+                }
+                FullConstraint::Arrow { bulb, line } => {
+                    for cell in bulb {
+                        overlays.push(circle(*cell, WHITE, ARROW_COLOR));
+                    }
+                    let start = line.first().and_then(|first| {
+                        bulb.iter().min_by_key(|cell| {
+                            cell.row
+                                .abs_diff(first.row)
+                                .max(cell.column.abs_diff(first.column))
+                        })
+                    });
+                    arrows.push(Arrow {
+                        way_points: start.into_iter().chain(line).copied().map(center).collect(),
+                        color: Some(ARROW_COLOR),
+                        thickness: Some(2.0),
+                        head_length: Some(0.3),
+                    });
+                }
+                FullConstraint::PositiveDiagonal => lines.push(self::line(
+                    [Pos { x: size, y: 0.0 }, Pos { x: 0.0, y: size }].into(),
+                    DIAGONAL_COLOR,
+                    2.0,
+                )),
+                FullConstraint::NegativeDiagonal => lines.push(self::line(
+                    [Pos { x: 0.0, y: 0.0 }, Pos { x: size, y: size }].into(),
+                    DIAGONAL_COLOR,
+                    2.0,
+                )),
+                FullConstraint::AntiKnight => puzzle.metadata.antiknight = true,
+                FullConstraint::AntiKing => puzzle.metadata.antiking = true,
+            }
+        }
+        puzzle.cages = cages.into();
+        puzzle.lines = lines.into();
+        puzzle.arrows = arrows.into();
+        puzzle.underlays = underlays.into();
+        puzzle.overlays = overlays.into();
+
+        let mut dropped = Vec::new();
+        if let Some(solution) = &full.solution
+            && puzzle.set_solution(solution).is_err()
+        {
+            dropped.push("solution".into());
+        }
+
+        Ok(Converted { puzzle, dropped })
+    }
+}
+
+impl SudokupadSclFormat {
+    /// Replace the solution with `solution`, given as rows of values.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a value has more than one digit.
+    pub fn set_solution(&mut self, solution: &[Box<[u8]>]) -> Result<(), FormatError> {
+        let digits = solution
+            .iter()
+            .flatten()
+            .map(|value| char::from_digit((*value).into(), 10))
+            .collect::<Option<String>>()
+            .ok_or(FormatError::Unsupported(SOLUTION_UNSUPPORTED))?;
+        self.metadata.solution = digits.into();
+        Ok(())
+    }
+}
+
+impl ExternalPuzzleFormat for SudokupadScfFormat {
+    fn from_json(_json: &str) -> Result<Self, FormatError> {
+        Err(FormatError::Unsupported(SCF_UNSUPPORTED))
+    }
+
+    fn to_json(&self) -> Result<String, FormatError> {
+        Err(FormatError::Unsupported(SCF_UNSUPPORTED))
+    }
+
+    fn to_full(&self) -> Result<Converted<FullPuzzle>, FormatError> {
+        Err(FormatError::Unsupported(SCF_UNSUPPORTED))
+    }
+
+    fn from_full(_full: &FullPuzzle) -> Result<Converted<Self>, FormatError> {
+        Err(FormatError::Unsupported(SCF_UNSUPPORTED))
+    }
+}
+
+impl ExternalPuzzleFormat for SudokupadFPuzFormat {
+    fn from_json(json: &str) -> Result<Self, FormatError> {
+        Ok(Self {
+            puzzle: FPuzzlesFormat::from_json(json)?,
+        })
+    }
+
+    fn to_json(&self) -> Result<String, FormatError> {
+        self.puzzle.to_json()
+    }
+
+    fn to_full(&self) -> Result<Converted<FullPuzzle>, FormatError> {
+        self.puzzle.to_full()
+    }
+
+    fn from_full(full: &FullPuzzle) -> Result<Converted<Self>, FormatError> {
+        Ok(FPuzzlesFormat::from_full(full)?.map(|puzzle| Self { puzzle }))
+    }
+}
+
+fn parse_value(value: &Value, size: u8, row: usize, column: usize) -> Result<u8, FormatError> {
+    let parsed = match value {
+        Value::Number(number) => number.as_u64(),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    };
+    parsed
+        .and_then(|value| u8::try_from(value).ok())
+        .filter(|value| (1..=size).contains(value))
+        .ok_or_else(|| FormatError::InvalidValue {
+            cell: format!("R{}C{}", row + 1, column + 1).into(),
+            value: value.to_string().into(),
+        })
+}
+
+/// Solutions are single digits of all cells, row by row, so only grids up to 9 are supported.
+fn parse_solution(solution: &str, size: u8) -> Option<Box<[Box<[u8]>]>> {
+    let values = solution
+        .chars()
+        .map(|digit| {
+            digit
+                .to_digit(10)
+                .and_then(|digit| u8::try_from(digit).ok())
+                .filter(|digit| (1..=size).contains(digit))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if values.len() != usize::from(size) * usize::from(size) {
+        return None;
+    }
+    Some(values.chunks(usize::from(size)).map(Into::into).collect())
+}
+
+/// Cage cells are `[row, column]`, zero based.
+fn to_full_cells(cells: &[Pos<i32>], size: u8) -> Result<Box<[FullCell]>, FormatError> {
+    cells
+        .iter()
+        .map(|pos| {
+            let convert = |n: i32| u8::try_from(n).ok().filter(|n| *n < size);
+            match (convert(pos.x), convert(pos.y)) {
+                (Some(row), Some(column)) => Ok(FullCell::new(row, column)),
+                _ => Err(FormatError::CellOutOfGrid(
+                    format!("R{}C{}", i64::from(pos.x) + 1, i64::from(pos.y) + 1).into(),
+                )),
+            }
+        })
+        .collect()
+}
+
+fn to_pos(cell: FullCell) -> Pos<i32> {
+    Pos {
+        x: cell.row.into(),
+        y: cell.column.into(),
+    }
+}
+
+fn center(cell: FullCell) -> Pos<f64> {
+    Pos {
+        x: f64::from(cell.row) + 0.5,
+        y: f64::from(cell.column) + 0.5,
+    }
+}
+
+fn circle(cell: FullCell, background: Color, border: Color) -> Cosmetic {
+    Cosmetic {
+        center: center(cell),
+        width: Some(0.85),
+        height: Some(0.85),
+        rounded: true,
+        background_color: Some(background),
+        border_color: Some(border),
+        ..Cosmetic::default()
+    }
+}
+
+fn line(way_points: Box<[Pos<f64>]>, color: Color, thickness: f64) -> Line {
+    Line {
+        way_points,
+        color: Some(color),
+        thickness: Some(thickness),
+        ..Line::default()
+    }
+}
+
+fn boxes(full: &FullPuzzle) -> Box<[Region]> {
+    let (height, width) = full.box_dims();
+    let mut regions = vec![Vec::new(); usize::from(full.size)];
+    for row in 0..full.size {
+        for column in 0..full.size {
+            let index = usize::from(row / height) * usize::from(full.size / width)
+                + usize::from(column / width);
+            regions[index].push(to_pos(FullCell::new(row, column)));
+        }
+    }
+    regions
+        .into_iter()
+        .map(|cells| Region {
+            cells: cells.into(),
+        })
+        .collect()
+}
+
+/// Whether the regions are the default boxes, in any order.
+fn are_boxes(regions: &[Region], full: &FullPuzzle) -> Result<bool, FormatError> {
+    let mut regions = regions
+        .iter()
+        .map(|region| {
+            let mut cells = to_full_cells(&region.cells, full.size)?;
+            cells.sort_unstable();
+            Ok(cells)
+        })
+        .collect::<Result<Vec<_>, FormatError>>()?;
+    let mut boxes = boxes(full)
+        .iter()
+        .map(|region| {
+            let mut cells = to_full_cells(&region.cells, full.size)?;
+            cells.sort_unstable();
+            Ok(cells)
+        })
+        .collect::<Result<Vec<_>, FormatError>>()?;
+    regions.sort_unstable();
+    boxes.sort_unstable();
+    Ok(regions == boxes)
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use crate::format::{
+        external::{ExternalPuzzleFormat, sudokupad::SudokupadSclFormat},
+        full::{FullCell, FullConstraint, FullPuzzle},
+    };
+
+    #[test]
+    fn to_full_and_back() {
+        let mut full = FullPuzzle::new(6).unwrap();
+        full.title = "Small".into();
+        full.givens[0][0] = Some(6);
+        full.givens[5][2] = Some(1);
+        full.constraints = [
+            FullConstraint::AntiKing,
+            FullConstraint::Killer {
+                cells: [FullCell::new(1, 1), FullCell::new(2, 1)].into(),
+                sum: Some(9),
+            },
+        ]
+        .into();
+        full.solution = Some(
+            (0..6u8)
+                .map(|row| (0..6u8).map(|column| (row + column) % 6 + 1).collect())
+                .collect(),
+        );
+
+        let scl = SudokupadSclFormat::from_full(&full).unwrap().puzzle;
+        assert_eq!(scl.regions.len(), 6);
+        let json = scl.to_json().unwrap();
+        assert!(json.contains(r#""value": 6"#));
+
+        let back = SudokupadSclFormat::from_json(&json)
+            .unwrap()
+            .to_full()
+            .unwrap();
+        assert!(back.dropped.is_empty(), "{:?}", back.dropped);
+        assert_eq!(back.puzzle, full);
+
+        let mut invalid = SudokupadSclFormat::from_json(&json).unwrap();
+        invalid.cages[0].value = Some("nine".into());
+        let invalid = invalid.to_full().unwrap();
+        assert_eq!(invalid.dropped, ["cages.value".into()]);
+
+        full.constraints = [FullConstraint::Thermometer {
+            cells: [FullCell::new(0, 0), FullCell::new(0, 1)].into(),
+        }]
+        .into();
+        let back = SudokupadSclFormat::from_full(&full)
+            .unwrap()
+            .puzzle
+            .to_full()
+            .unwrap();
+        assert!(back.puzzle.constraints.is_empty());
+        assert_eq!(back.dropped, ["lines".into(), "underlays".into()]);
+
+        let mut scl = SudokupadSclFormat::from_full(&full).unwrap().puzzle;
+        assert!(scl.set_solution(&[[10].into()]).is_err());
+        scl.set_solution(&[[1, 2].into(), [2, 1].into()]).unwrap();
+        assert_eq!(scl.metadata.solution.as_ref(), "1221");
+    }
+}
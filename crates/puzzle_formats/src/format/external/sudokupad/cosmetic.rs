@@ -9,35 +9,35 @@ use super::pos::Pos;
 #[serde(deny_unknown_fields)]
 pub struct Cosmetic {
     #[serde(rename = "center")]
-    center: Pos<f64>,
+    pub(super) center: Pos<f64>,
 
     #[serde(rename = "width", skip_serializing_if = "Option::is_none")]
-    width: Option<f64>,
+    pub(super) width: Option<f64>,
 
     #[serde(rename = "height", skip_serializing_if = "Option::is_none")]
-    height: Option<f64>,
+    pub(super) height: Option<f64>,
 
     #[serde(rename = "thickness", skip_serializing_if = "Option::is_none")]
-    thickness: Option<f64>,
+    pub(super) thickness: Option<f64>,
 
     #[serde(rename = "angle", skip_serializing_if = "Option::is_none")]
-    angle: Option<f64>,
+    pub(super) angle: Option<f64>,
 
     #[serde(rename = "rounded", default, skip_serializing_if = "is_default")]
-    rounded: bool,
+    pub(super) rounded: bool,
 
     #[serde(rename = "backgroundColor", skip_serializing_if = "Option::is_none")]
-    background_color: Option<Color>,
+    pub(super) background_color: Option<Color>,
 
     #[serde(rename = "borderColor", skip_serializing_if = "Option::is_none")]
-    border_color: Option<Color>,
+    pub(super) border_color: Option<Color>,
 
     #[serde(rename = "values", default, skip_serializing_if = "Option::is_none")]
-    text: Option<StrOrInt>,
+    pub(super) text: Option<StrOrInt>,
 
     #[serde(rename = "fontSize", default, skip_serializing_if = "Option::is_none")]
-    font_size: Option<i32>,
+    pub(super) font_size: Option<i32>,
 
     #[serde(rename = "stroke", skip_serializing_if = "Option::is_none")]
-    stroke: Option<Color>,
+    pub(super) stroke: Option<Color>,
 }
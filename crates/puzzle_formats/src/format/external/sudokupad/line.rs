@@ -9,33 +9,33 @@ use super::pos::Pos;
 #[serde(deny_unknown_fields)]
 pub struct Line {
     #[serde(rename = "wayPoints", default, skip_serializing_if = "is_default")]
-    way_points: Box<[Pos<f64>]>,
+    pub(super) way_points: Box<[Pos<f64>]>,
 
     #[serde(rename = "color", skip_serializing_if = "Option::is_none")]
-    color: Option<Color>,
+    pub(super) color: Option<Color>,
 
     // Maybe can be string
     #[serde(rename = "thickness", skip_serializing_if = "Option::is_none")]
-    thickness: Option<f64>,
+    pub(super) thickness: Option<f64>,
 
     #[serde(rename = "target", default, skip_serializing_if = "Option::is_none")]
-    target_layer: Option<Box<str>>,
+    pub(super) target_layer: Option<Box<str>>,
 
     #[serde(rename = "d", default, skip_serializing_if = "Option::is_none")]
-    path: Option<Box<str>>,
+    pub(super) path: Option<Box<str>>,
 
     #[serde(
         rename = "stroke-linecap",
         default,
         skip_serializing_if = "Option::is_none"
     )]
-    stroke_linecap: Option<Box<str>>,
+    pub(super) stroke_linecap: Option<Box<str>>,
 
     #[serde(
         rename = "stroke-linejoin",
         default,
         skip_serializing_if = "Option::is_none"
     )]
-    stroke_linejoin: Option<Box<str>>,
+    pub(super) stroke_linejoin: Option<Box<str>>,
     // Maybe add field className
 }
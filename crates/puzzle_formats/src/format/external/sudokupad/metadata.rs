@@ -5,30 +5,30 @@ use serde::{Deserialize, Serialize};
 #[serde(deny_unknown_fields)]
 pub struct Metadata {
     #[serde(rename = "source", default)]
-    source: Box<str>,
+    pub(super) source: Box<str>,
 
     #[serde(rename = "title", default)]
-    title: Box<str>,
+    pub(super) title: Box<str>,
 
     #[serde(rename = "author", default)]
-    author: Box<str>,
+    pub(super) author: Box<str>,
 
     #[serde(rename = "rules", default)]
-    rules: Box<str>,
+    pub(super) rules: Box<str>,
 
     #[serde(rename = "solution", default, skip_serializing_if = "is_default")]
-    solution: Box<str>,
+    pub(super) solution: Box<str>,
 
     #[serde(
         rename = "msgcorrect",
         default,
         skip_serializing_if = "Option::is_none"
     )]
-    msgcorrect: Option<Box<str>>,
+    pub(super) msgcorrect: Option<Box<str>>,
 
     #[serde(rename = "antiknight", default, skip_serializing_if = "is_default")]
-    antiknight: bool,
+    pub(super) antiknight: bool,
 
     #[serde(rename = "antiking", default, skip_serializing_if = "is_default")]
-    antiking: bool,
+    pub(super) antiking: bool,
 }
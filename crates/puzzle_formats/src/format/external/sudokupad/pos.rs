@@ -2,8 +2,8 @@ use serde::{Deserialize, Serialize, de, ser::SerializeSeq};
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Pos<T> {
-    x: T,
-    y: T,
+    pub(super) x: T,
+    pub(super) y: T,
 }
 
 impl<T> Serialize for Pos<T>
@@ -5,5 +5,5 @@ use super::pos::Pos;
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(transparent)]
 pub struct Region {
-    cells: Box<[Pos<i32>]>,
+    pub(super) cells: Box<[Pos<i32>]>,
 }
@@ -1,9 +1,289 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::serialization::is_empty;
+
+use super::FormatError;
+
+/// Largest grid size supported by all formats.
+pub const MAX_SIZE: u8 = 16;
+
+/// Height and width of a box in a grid of `size`.
+///
+/// The height is the largest divisor of the size, that is not larger than its square root.
+#[must_use]
+pub fn box_dims(size: u8) -> (u8, u8) {
+    let height = (1..=size)
+        .take_while(|n| u16::from(*n) * u16::from(*n) <= u16::from(size))
+        .filter(|n| size.is_multiple_of(*n))
+        .last()
+        .unwrap_or(1);
+    (height, size / height)
+}
+
+/// A cell of the grid, zero based.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct FullCell {
+    pub row: u8,
+    pub column: u8,
+}
+
+impl FullCell {
+    #[must_use]
+    pub const fn new(row: u8, column: u8) -> Self {
+        Self { row, column }
+    }
+}
+
+/// Formats as one based `R<row>C<col>`.
+impl fmt::Display for FullCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "R{}C{}",
+            u16::from(self.row) + 1,
+            u16::from(self.column) + 1
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FullConstraint {
+    /// Cells in the cage contain no repeats and sum to the optional total.
+    Killer {
+        cells: Box<[FullCell]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sum: Option<u32>,
+    },
+    /// Values strictly increase from the bulb (first cell).
+    Thermometer { cells: Box<[FullCell]> },
+    /// Values along the line sum to the value of the bulb cells.
+    Arrow {
+        bulb: Box<[FullCell]>,
+        line: Box<[FullCell]>,
+    },
+    /// The diagonal from bottom left to top right contains no repeats.
+    PositiveDiagonal,
+    /// The diagonal from top left to bottom right contains no repeats.
+    NegativeDiagonal,
+    /// Cells a knight's move apart can not contain the same value.
+    AntiKnight,
+    /// Cells a king's move apart can not contain the same value.
+    AntiKing,
+}
+
+impl FullConstraint {
+    /// Short name of the constraint type, used for summaries.
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Killer { .. } => "killer",
+            Self::Thermometer { .. } => "thermometer",
+            Self::Arrow { .. } => "arrow",
+            Self::PositiveDiagonal => "diagonal+",
+            Self::NegativeDiagonal => "diagonal-",
+            Self::AntiKnight => "antiknight",
+            Self::AntiKing => "antiking",
+        }
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = &FullCell> {
+        let (a, b): (&[FullCell], &[FullCell]) = match self {
+            Self::Killer { cells, .. } | Self::Thermometer { cells } => (cells, &[]),
+            Self::Arrow { bulb, line } => (bulb, line),
+            _ => (&[], &[]),
+        };
+        a.iter().chain(b)
+    }
+}
+
+/// The puzzle model all external formats are converted from and to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct FullPuzzle {
-    //TODO
+    pub size: u8,
+
+    #[serde(default)]
+    pub title: Box<str>,
+
+    #[serde(default)]
+    pub author: Box<str>,
+
+    #[serde(default)]
+    pub rules: Box<str>,
+
+    /// Rows of values, `None` for empty cells.
+    pub givens: Box<[Box<[Option<u8>]>]>,
+
+    #[serde(default, skip_serializing_if = "is_empty")]
+    pub constraints: Box<[FullConstraint]>,
+
+    /// Rows of values of the complete solution.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solution: Option<Box<[Box<[u8]>]>>,
+}
+
+impl FullPuzzle {
+    /// A puzzle without givens or constraints.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the size is 0 or larger than [`MAX_SIZE`].
+    pub fn new(size: u8) -> Result<Self, FormatError> {
+        if size == 0 || size > MAX_SIZE {
+            return Err(FormatError::InvalidSize(size.into()));
+        }
+        Ok(Self {
+            size,
+            title: "".into(),
+            author: "".into(),
+            rules: "".into(),
+            givens: vec![vec![None; size.into()].into(); size.into()].into(),
+            constraints: Box::default(),
+            solution: None,
+        })
+    }
+
+    #[must_use]
+    pub fn given(&self, cell: FullCell) -> Option<u8> {
+        self.givens
+            .get(usize::from(cell.row))?
+            .get(usize::from(cell.column))
+            .copied()
+            .flatten()
+    }
+
+    /// Height and width of a box, see [`box_dims`].
+    #[must_use]
+    pub fn box_dims(&self) -> (u8, u8) {
+        box_dims(self.size)
+    }
+
+    /// Check the size, the shape of the grids and that all cells and values are inside the grid.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error for the first violation found.
+    pub fn validate(&self) -> Result<(), FormatError> {
+        if self.size == 0 || self.size > MAX_SIZE {
+            return Err(FormatError::InvalidSize(self.size.into()));
+        }
+        let check_value = |row: usize, column: usize, value: u8| {
+            if (1..=self.size).contains(&value) {
+                return Ok(());
+            }
+            Err(FormatError::InvalidValue {
+                cell: format!("R{}C{}", row + 1, column + 1).into(),
+                value: value.to_string().into(),
+            })
+        };
+
+        if !is_square(&self.givens, self.size) {
+            return Err(FormatError::InvalidGrid(self.size));
+        }
+        for (row, values) in self.givens.iter().enumerate() {
+            for (column, value) in values.iter().enumerate() {
+                if let Some(value) = value {
+                    check_value(row, column, *value)?;
+                }
+            }
+        }
+
+        if let Some(solution) = &self.solution {
+            if !is_square(solution, self.size) {
+                return Err(FormatError::InvalidGrid(self.size));
+            }
+            for (row, values) in solution.iter().enumerate() {
+                for (column, value) in values.iter().enumerate() {
+                    check_value(row, column, *value)?;
+                }
+            }
+        }
+
+        for cell in self.constraints.iter().flat_map(FullConstraint::cells) {
+            if cell.row >= self.size || cell.column >= self.size {
+                return Err(FormatError::CellOutOfGrid(cell.to_string().into()));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_square<T>(rows: &[Box<[T]>], size: u8) -> bool {
+    rows.len() == usize::from(size) && rows.iter().all(|row| row.len() == usize::from(size))
+}
+
+/// Result of a conversion, with the elements of the source that have no equivalent in the target.
+#[derive(Debug, Clone)]
+pub struct Converted<T> {
+    pub puzzle: T,
+    /// Names of the dropped elements, in the terms of the source format.
+    pub dropped: Vec<Box<str>>,
 }
 
-// External >> Full
+impl<T> Converted<T> {
+    /// A lossless conversion.
+    pub fn new(puzzle: T) -> Self {
+        Self {
+            puzzle,
+            dropped: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_dropped(mut self, dropped: Vec<Box<str>>) -> Self {
+        let mut all = dropped;
+        all.append(&mut self.dropped);
+        self.dropped = all;
+        self
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Converted<U> {
+        Converted {
+            puzzle: f(self.puzzle),
+            dropped: self.dropped,
+        }
+    }
+}
 
 // Full     >> T >>     Logical
 // Logical  >> T >>     Full
 // Full     >> T >>     Visual
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use super::{FullCell, FullConstraint, FullPuzzle};
+
+    #[test]
+    fn json_and_validation() {
+        let mut puzzle = FullPuzzle::new(4).unwrap();
+        puzzle.givens[0][1] = Some(3);
+        puzzle.constraints = [
+            FullConstraint::Killer {
+                cells: [FullCell::new(1, 1), FullCell::new(1, 2)].into(),
+                sum: Some(5),
+            },
+            FullConstraint::AntiKing,
+        ]
+        .into();
+        puzzle.validate().unwrap();
+
+        let json = serde_json::to_string(&puzzle).unwrap();
+        assert!(json.contains(r#"{"type":"killer","cells":[{"row":1,"column":1},"#));
+        assert!(json.contains(r#"{"type":"anti_king"}"#));
+        assert_eq!(serde_json::from_str::<FullPuzzle>(&json).unwrap(), puzzle);
+
+        puzzle.givens[3][3] = Some(5);
+        assert!(puzzle.validate().is_err());
+        puzzle.givens[3][3] = None;
+        puzzle.constraints = [FullConstraint::Thermometer {
+            cells: [FullCell::new(0, 4)].into(),
+        }]
+        .into();
+        assert!(puzzle.validate().is_err());
+    }
+}
@@ -3,5 +3,3 @@
 pub mod format;
 pub mod serialization;
 pub mod url;
-
-mod thread_test;
@@ -69,7 +69,7 @@ pub enum StrOrInt {
 
 impl StrOrInt {
     #[must_use]
-    pub fn as_str(&self) -> Cow<str> {
+    pub fn as_str(&self) -> Cow<'_, str> {
         match self {
             StrOrInt::Str(str) => Cow::Borrowed(str),
             StrOrInt::Int(n) => Cow::Owned(n.to_string()),
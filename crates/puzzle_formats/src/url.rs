@@ -1,12 +1,16 @@
 use serde_json::Value;
 
+mod batch;
 mod into_url;
+mod logic_masters;
 mod resolved_url;
 mod unresolved_url;
 pub mod url_fetcher;
 mod url_value;
 
+pub use batch::*;
 pub use into_url::*;
+pub use logic_masters::*;
 pub use resolved_url::*;
 pub use unresolved_url::*;
 pub use url_value::*;
@@ -0,0 +1,387 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use async_trait::async_trait;
+use futures_util::{
+    FutureExt, StreamExt,
+    future::{BoxFuture, Shared},
+    stream,
+};
+use url::Url;
+
+use crate::format::PuzzleFormat;
+
+use super::{
+    DecodeError, ParseError, Resolution, ResolutionError, ResolutionOptions, Resolved, UrlValue,
+    url_fetcher::UrlFetcher,
+};
+
+/// Resolves many Urls concurrently, sharing identical requests between them.
+#[derive(Debug, Clone)]
+pub struct BatchResolver {
+    options: ResolutionOptions,
+    concurrency: usize,
+}
+
+/// Result of a single Url of a batch.
+#[derive(Debug)]
+pub struct BatchItem<E: Error> {
+    pub input: Box<str>,
+    pub result: Result<Resolution, BatchError<E>>,
+    /// The puzzle the Url resolved to, decoded with [`ResolvedUrl::decode`](super::ResolvedUrl::decode).
+    /// `None` if it did not resolve, or resolved to a [page](Resolved::Page) instead of a puzzle.
+    pub puzzle: Option<Result<PuzzleFormat, DecodeError>>,
+}
+
+/// Fetcher errors are shared between all items waiting for the same request.
+#[derive(Debug, thiserror::Error)]
+pub enum BatchError<E: Error> {
+    #[error("Url could not be parsed: {0}")]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Resolution(#[from] ResolutionError<Arc<E>>),
+}
+
+impl Default for BatchResolver {
+    fn default() -> Self {
+        Self::new(ResolutionOptions::default(), 8)
+    }
+}
+
+impl BatchResolver {
+    /// Resolve at most `concurrency` Urls at the same time, at least one.
+    #[must_use]
+    pub fn new(options: ResolutionOptions, concurrency: usize) -> Self {
+        Self {
+            options,
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    /// Parse, resolve and decode all `urls`, returning one item per Url in the same order.
+    ///
+    /// Every distinct request is only made once per batch, so repeated links hit the
+    /// fetcher, and a cache in front of it, only once.
+    pub async fn resolve_all<F, I>(&self, fetcher: &F, urls: I) -> Vec<BatchItem<F::Error>>
+    where
+        F: UrlFetcher + Sync,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let fetcher = InFlight::new(fetcher);
+        let fetcher = &fetcher;
+
+        stream::iter(urls)
+            .map(|url| {
+                let input: Box<str> = url.as_ref().trim().into();
+                async move {
+                    let result = self.resolve_one(fetcher, &input).await;
+                    let puzzle = match &result {
+                        Ok(resolution) => match resolution.resolved() {
+                            Resolved::Puzzle(puzzle) => Some(puzzle.decode()),
+                            Resolved::Page(_) => None,
+                        },
+                        Err(_) => None,
+                    };
+                    BatchItem {
+                        input,
+                        result,
+                        puzzle,
+                    }
+                }
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    async fn resolve_one<F: UrlFetcher + Sync>(
+        &self,
+        fetcher: &InFlight<'_, F>,
+        input: &str,
+    ) -> Result<Resolution, BatchError<F::Error>> {
+        match UrlValue::parse(input)? {
+            UrlValue::Resolved(resolved) => {
+                Ok(Resolution::new(Resolved::Puzzle(resolved), Box::default()))
+            }
+            UrlValue::Unresolved(unresolved) => {
+                Ok(unresolved.resolve(fetcher, &self.options).await?)
+            }
+        }
+    }
+}
+
+type SharedFetch<'a, T, E> = Shared<BoxFuture<'a, Result<T, Arc<E>>>>;
+type Requests<'a, T, E> = Mutex<HashMap<(Url, bool), SharedFetch<'a, T, E>>>;
+
+/// Requests of the current batch, keyed by Url and whether the cache is bypassed.
+///
+/// Finished requests are kept, so later items get their value without fetching again.
+struct InFlight<'a, F: UrlFetcher + Sync> {
+    fetcher: &'a F,
+    redirects: Requests<'a, Option<Url>, F::Error>,
+    results: Requests<'a, Box<str>, F::Error>,
+}
+
+impl<'a, F: UrlFetcher + Sync> InFlight<'a, F> {
+    fn new(fetcher: &'a F) -> Self {
+        Self {
+            fetcher,
+            redirects: Mutex::default(),
+            results: Mutex::default(),
+        }
+    }
+
+    fn redirect(&self, url: Url, fresh: bool) -> SharedFetch<'a, Option<Url>, F::Error> {
+        let fetcher = self.fetcher;
+        let mut redirects = self
+            .redirects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        redirects
+            .entry((url.clone(), fresh))
+            .or_insert_with(|| {
+                async move {
+                    if fresh {
+                        fetcher.fetch_redirect_url_fresh(url).await
+                    } else {
+                        fetcher.fetch_redirect_url(url).await
+                    }
+                    .map_err(Arc::new)
+                }
+                .boxed()
+                .shared()
+            })
+            .clone()
+    }
+
+    fn result(&self, url: Url, fresh: bool) -> SharedFetch<'a, Box<str>, F::Error> {
+        let fetcher = self.fetcher;
+        let mut results = self.results.lock().unwrap_or_else(PoisonError::into_inner);
+        results
+            .entry((url.clone(), fresh))
+            .or_insert_with(|| {
+                async move {
+                    if fresh {
+                        fetcher.fetch_result_fresh(url).await
+                    } else {
+                        fetcher.fetch_result(url).await
+                    }
+                    .map_err(Arc::new)
+                }
+                .boxed()
+                .shared()
+            })
+            .clone()
+    }
+}
+
+#[async_trait]
+impl<F: UrlFetcher + Sync> UrlFetcher for InFlight<'_, F> {
+    type Error = Arc<F::Error>;
+
+    async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.redirect(url, false).await
+    }
+
+    async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.result(url, false).await
+    }
+
+    async fn fetch_redirect_url_fresh(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.redirect(url, true).await
+    }
+
+    async fn fetch_result_fresh(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.result(url, true).await
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        sync::{
+            Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    use async_trait::async_trait;
+    use url::Url;
+
+    use crate::{
+        format::FormatKind,
+        url::{
+            BatchError, BatchResolver, ResolutionError, ResolutionOptions, Resolved, UrlValue,
+            url_fetcher::{
+                UrlFetcher,
+                cache::{CacheError, UrlFetcherCache, UrlFetcherCacheExt},
+            },
+        },
+    };
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Not found: {0}")]
+    struct NotFound(Url);
+
+    /// Counts requests, and yields once so concurrent requests overlap.
+    #[derive(Default)]
+    struct SlowFetcher {
+        redirects: HashMap<Url, Url>,
+        requests: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl UrlFetcher for SlowFetcher {
+        type Error = NotFound;
+
+        async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            tokio::task::yield_now().await;
+            self.redirects
+                .get(&url)
+                .cloned()
+                .map(Some)
+                .ok_or(NotFound(url))
+        }
+
+        async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            tokio::task::yield_now().await;
+            Err(NotFound(url))
+        }
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Cache unavailable")]
+    struct Unavailable;
+
+    #[derive(Default)]
+    struct MemoryCache {
+        redirects: Mutex<HashMap<Url, Option<Url>>>,
+        lookups: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl UrlFetcher for MemoryCache {
+        type Error = CacheError<Unavailable>;
+
+        async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            let redirects = self.redirects.lock().unwrap();
+            redirects.get(&url).cloned().ok_or(CacheError::NoCacheValue)
+        }
+
+        async fn fetch_result(&self, _url: Url) -> Result<Box<str>, Self::Error> {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            Err(CacheError::NoCacheValue)
+        }
+    }
+
+    #[async_trait]
+    impl UrlFetcherCache for MemoryCache {
+        type FetchError = Unavailable;
+        type StoreError = Unavailable;
+
+        async fn store_redirect(&self, url: Url, value: Option<Url>) -> Result<(), Unavailable> {
+            self.redirects.lock().unwrap().insert(url, value);
+            Ok(())
+        }
+
+        async fn store_result(&self, _url: Url, _value: Box<str>) -> Result<(), Unavailable> {
+            Ok(())
+        }
+    }
+
+    fn puzzle_url() -> String {
+        let payload = include_str!("../../assets/puzzleid.txt").trim();
+        format!("https://f-puzzles.com/?load={payload}")
+    }
+
+    fn fetcher() -> SlowFetcher {
+        let mut fetcher = SlowFetcher::default();
+        for (from, to) in [
+            ("https://bit.ly/a", "https://tinyurl.com/abc"),
+            ("https://bit.ly/b", "https://tinyurl.com/abc"),
+            ("https://tinyurl.com/abc", &puzzle_url()),
+        ] {
+            fetcher
+                .redirects
+                .insert(Url::parse(from).unwrap(), Url::parse(to).unwrap());
+        }
+        fetcher
+    }
+
+    #[tokio::test]
+    async fn resolve_all() {
+        let fetcher = fetcher();
+        let urls = [
+            "https://bit.ly/a",
+            "https://bit.ly/b",
+            "https://f-puzzles.com/?id=abc",
+            "not a url",
+            "https://bit.ly/a",
+            "https://sudokupad.app/sclABC",
+            "https://missing.link/",
+        ];
+
+        let items = BatchResolver::new(ResolutionOptions::default(), 4)
+            .resolve_all(&fetcher, urls)
+            .await;
+
+        let inputs: Vec<&str> = items.iter().map(|item| &*item.input).collect();
+        assert_eq!(inputs, urls);
+
+        let expected = Resolved::Puzzle(match UrlValue::parse(puzzle_url().as_str()).unwrap() {
+            UrlValue::Resolved(resolved) => resolved,
+            UrlValue::Unresolved(_) => unreachable!(),
+        });
+        for item in [&items[0], &items[1], &items[2], &items[4]] {
+            assert_eq!(item.result.as_ref().unwrap().resolved(), &expected);
+            let puzzle = item.puzzle.as_ref().unwrap().as_ref().unwrap();
+            assert_eq!(puzzle.kind(), FormatKind::FPuzzles);
+        }
+        assert!(matches!(items[3].result, Err(BatchError::Parse(_))));
+        assert!(items[5].result.as_ref().unwrap().chain().is_empty());
+        // Resolved, but not valid puzzle data
+        assert!(matches!(items[5].puzzle, Some(Err(_))));
+        assert!(matches!(
+            items[6].result,
+            Err(BatchError::Resolution(ResolutionError::Fetcher(_)))
+        ));
+        assert!(items[3].puzzle.is_none() && items[6].puzzle.is_none());
+
+        // bit.ly/a, bit.ly/b, tinyurl.com/abc and missing.link
+        assert_eq!(fetcher.requests.load(Ordering::Relaxed), 4);
+    }
+
+    #[tokio::test]
+    async fn resolve_all_cached() {
+        let cache = MemoryCache::default();
+        let fetcher = fetcher().with_cache(cache);
+        let urls = ["https://bit.ly/a", "https://bit.ly/b", "https://bit.ly/a"];
+
+        let items = BatchResolver::new(ResolutionOptions::default(), 2)
+            .resolve_all(&fetcher, urls)
+            .await;
+        assert!(items.iter().all(|item| item.result.is_ok()));
+
+        let (cache, fetcher) = fetcher.into_parts();
+        assert_eq!(cache.lookups.load(Ordering::Relaxed), 3);
+        assert_eq!(fetcher.requests.load(Ordering::Relaxed), 3);
+
+        // A second batch is answered by the cache alone
+        let fetcher = fetcher.with_cache(cache);
+        let items = BatchResolver::default().resolve_all(&fetcher, urls).await;
+        assert!(items.iter().all(|item| item.result.is_ok()));
+
+        let (cache, fetcher) = fetcher.into_parts();
+        assert_eq!(cache.lookups.load(Ordering::Relaxed), 6);
+        assert_eq!(fetcher.requests.load(Ordering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,218 @@
+use std::{iter, sync::LazyLock};
+
+use ctreg::Capture;
+use itertools::Itertools;
+use url::Url;
+
+use super::UrlValue;
+
+ctreg::regex! { LinkRegex = r#"(?<link>https?://[^\s"'<>]+)"# }
+ctreg::regex! { HeadingRegex = r"(?is)<h2[^>]*>(?<content>.*?)</h2>" }
+ctreg::regex! { TitleRegex = r"(?is)<title[^>]*>(?<content>.*?)</title>" }
+ctreg::regex! { AuthorRegex = r#"(?is)<a[^>]*href="[^"]*Benutzer/eingestellt\.php\?name=[^"]*"[^>]*>(?<content>.*?)</a>"# }
+ctreg::regex! { TagRegex = r"(?<tag><[^>]*>)" }
+
+static LINK_RE: LazyLock<LinkRegex> = LazyLock::new(LinkRegex::new);
+static HEADING_RE: LazyLock<HeadingRegex> = LazyLock::new(HeadingRegex::new);
+static TITLE_RE: LazyLock<TitleRegex> = LazyLock::new(TitleRegex::new);
+static AUTHOR_RE: LazyLock<AuthorRegex> = LazyLock::new(AuthorRegex::new);
+static TAG_RE: LazyLock<TagRegex> = LazyLock::new(TagRegex::new);
+
+/// A puzzle page of the logic-masters.de Rätselportal, with the puzzle links found on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuzzlePage {
+    url: Url,
+    title: Option<Box<str>>,
+    author: Option<Box<str>>,
+    links: Vec<UrlValue>,
+}
+
+impl PuzzlePage {
+    /// Read title, author and puzzle links from the html of a page.
+    pub(crate) fn extract(url: Url, html: &str) -> Self {
+        let title = HEADING_RE
+            .captures(html)
+            .map(|captures| captures.content)
+            .or_else(|| TITLE_RE.captures(html).map(|captures| captures.content))
+            .and_then(|content| text(content.content));
+        let author = AUTHOR_RE
+            .captures(html)
+            .and_then(|captures| text(captures.content.content));
+
+        let links = find_all(html, |haystack| LINK_RE.find(haystack))
+            .map(|link| unescape(link.content.trim_end_matches(['.', ',', ';', ')'])))
+            .unique()
+            .filter_map(|link| UrlValue::parse(link.as_str()).ok())
+            .filter(UrlValue::is_puzzle_link)
+            .collect();
+
+        Self {
+            url,
+            title,
+            author,
+            links,
+        }
+    }
+
+    #[must_use]
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    #[must_use]
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    #[must_use]
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    /// `SudokuPad`, f-puzzles, Penpa and `SudokuMaker` links in order of appearance.
+    /// Short links are not resolved yet.
+    #[must_use]
+    pub fn links(&self) -> &[UrlValue] {
+        &self.links
+    }
+}
+
+/// All non-overlapping matches of `find`, which only finds the first one.
+fn find_all<'a>(
+    haystack: &'a str,
+    find: impl Fn(&'a str) -> Option<Capture<'a>>,
+) -> impl Iterator<Item = Capture<'a>> {
+    let mut offset = 0;
+    iter::from_fn(move || {
+        let found = find(&haystack[offset..])?;
+        let found = Capture {
+            start: offset + found.start,
+            end: offset + found.end,
+            content: found.content,
+        };
+        offset = found.end.max(found.start + 1).min(haystack.len());
+        Some(found)
+    })
+}
+
+/// Text content of an html fragment, `None` if it is empty.
+fn text(html: &str) -> Option<Box<str>> {
+    let mut without_tags = String::with_capacity(html.len());
+    let mut last = 0;
+    for tag in find_all(html, |haystack| TAG_RE.find(haystack)) {
+        without_tags.push_str(&html[last..tag.start]);
+        without_tags.push(' ');
+        last = tag.end;
+    }
+    without_tags.push_str(&html[last..]);
+
+    let text = unescape(&without_tags).split_whitespace().join(" ");
+    (!text.is_empty()).then(|| text.into())
+}
+
+/// Replace the html entities common in links and names.
+fn unescape(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        if let Some((c, end)) = entity {
+            text.push(c);
+            rest = &rest[end + 1..];
+        } else {
+            text.push('&');
+            rest = &rest[1..];
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(code) = entity.strip_prefix('#') {
+        let code = match code.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => code.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "auml" => 'ä',
+        "ouml" => 'ö',
+        "uuml" => 'ü',
+        "Auml" => 'Ä',
+        "Ouml" => 'Ö',
+        "Uuml" => 'Ü',
+        "szlig" => 'ß',
+        _ => return None,
+    })
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use crate::url::UrlValue;
+
+    use super::PuzzlePage;
+
+    const PAGE: &str = r#"<html>
+<head><title>Logic Masters Deutschland - R&auml;tselportal</title></head>
+<body>
+<h2>Fog &amp; Arrows</h2>
+<p>von <a href="/Raetselportal/Benutzer/eingestellt.php?name=Jane+Doe">Jane Doe</a></p>
+<div class="rp_html">
+<p>Play on <a href="https://sudokupad.app/psa4cv9rtx">SudokuPad</a>
+or <a href="https://f-puzzles.com/?id=27beqfmw">f-puzzles</a>.</p>
+<p>Penpa: https://swaroopg92.github.io/penpa-edit/?m=solve&amp;p=7VRNb5tAEL3zK6I9z4FdPmy4ua7dC3U_7CqKEIowIQ0KeF0MTbVW_ntmF1ywceVeckvl.</p>
+<a href="https://sudokupad.app/psa4cv9rtx">Again</a>
+<a href="https://www.youtube.com/watch?v=abc">Video</a>
+</div>
+</body>
+</html>"#;
+
+    #[test]
+    fn extract_page() {
+        let url = Url::parse("https://logic-masters.de/Raetselportal/Raetsel/zeigen.php?id=000N81")
+            .unwrap();
+        let page = PuzzlePage::extract(url.clone(), PAGE);
+
+        assert_eq!(page.url(), &url);
+        assert_eq!(page.title(), Some("Fog & Arrows"));
+        assert_eq!(page.author(), Some("Jane Doe"));
+        assert_eq!(
+            page.links(),
+            [
+                UrlValue::parse("https://sudokupad.app/psa4cv9rtx").unwrap(),
+                UrlValue::parse("https://f-puzzles.com/?id=27beqfmw").unwrap(),
+                UrlValue::parse(
+                    "https://swaroopg92.github.io/penpa-edit/?m=solve&p=7VRNb5tAEL3zK6I9z4FdPmy4ua7dC3U_7CqKEIowIQ0KeF0MTbVW_ntmF1ywceVeckvl"
+                )
+                .unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn extract_empty_page() {
+        let url = Url::parse("https://logic-masters.de/Raetselportal/Raetsel/zeigen.php?id=000000")
+            .unwrap();
+        let page = PuzzlePage::extract(url, "<title>Not found</title>");
+
+        assert_eq!(page.title(), Some("Not found"));
+        assert_eq!(page.author(), None);
+        assert!(page.links().is_empty());
+    }
+}
@@ -1,17 +1,73 @@
+use std::fmt;
+
 use url::Url;
 
-pub struct PuzzleFormat {} //TODO
+use crate::format::{
+    FormatError, FormatKind, PuzzleFormat,
+    external::{
+        ExternalPuzzleFormat,
+        fpuzzles::FPuzzlesFormat,
+        penpa, sudokumaker,
+        sudokupad::{SCF_UNSUPPORTED, SudokupadSclFormat},
+    },
+    full::Converted,
+};
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ResolvedUrl {
     inner: ResolvedUrlInner,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum DecodeError {
-    #[error("Placeholder Error")]
-    Placeholder,
+    #[error("Puzzle data is not valid compressed data")]
+    Decompression,
+    #[error("Decompressed puzzle data is not valid text: {0}")]
+    Utf16(#[from] std::string::FromUtf16Error),
+    #[error(transparent)]
+    Format(#[from] FormatError),
+}
+
+/// Site a puzzle is shared to with [`ResolvedUrl::encode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareTarget {
+    FPuzzles,
+    /// Keeps `SudokuPad` puzzles as scl, everything else is shared as f-puzzles.
+    SudokuPad,
+}
+
+/// Site and format a [`ResolvedUrl`] loads the puzzle from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PuzzleSource {
+    FPuzzles,
+    SudokuPadScl,
+    SudokuPadScf,
+    SudokuPadFPuz,
+    SudokuMaker,
+    Penpa,
+}
+
+impl PuzzleSource {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::FPuzzles => "f-puzzles",
+            Self::SudokuPadScl => "SudokuPad scl",
+            Self::SudokuPadScf => "SudokuPad scf",
+            Self::SudokuPadFPuz => "SudokuPad fpuz",
+            Self::SudokuMaker => "SudokuMaker",
+            Self::Penpa => "Penpa",
+        }
+    }
 }
 
+impl fmt::Display for PuzzleSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum ResolvedUrlInner {
     FPuzzles(Box<str>),
     SudokuPad(SudokuPadFullUrl),
@@ -19,12 +75,37 @@ pub(crate) enum ResolvedUrlInner {
     Penpa(Box<Url>),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum SudokuPadFullUrl {
     Scl(Box<str>),
     Scf(Box<str>),
     FPuz(Box<str>),
 }
 
+impl SudokuPadFullUrl {
+    /// Split a full puzzle id into its format prefix and payload.
+    /// Returns `None` for short ids, which have to be resolved first.
+    pub(crate) fn from_puzzleid(puzzleid: &str) -> Option<Self> {
+        if let Some(payload) = puzzleid
+            .strip_prefix("fpuzzles")
+            .or_else(|| puzzleid.strip_prefix("fpuz"))
+        {
+            return Some(Self::FPuz(payload.into()));
+        }
+
+        if let Some(payload) = puzzleid
+            .strip_prefix("scl")
+            .or_else(|| puzzleid.strip_prefix("ctc"))
+        {
+            return Some(Self::Scl(payload.into()));
+        }
+
+        puzzleid
+            .strip_prefix("scf")
+            .map(|payload| Self::Scf(payload.into()))
+    }
+}
+
 impl ResolvedUrl {
     pub(crate) fn new(inner: ResolvedUrlInner) -> Self {
         Self { inner }
@@ -32,29 +113,182 @@ impl ResolvedUrl {
 }
 
 impl ResolvedUrl {
+    #[must_use]
+    pub fn source(&self) -> PuzzleSource {
+        match &self.inner {
+            ResolvedUrlInner::FPuzzles(_) => PuzzleSource::FPuzzles,
+            ResolvedUrlInner::SudokuPad(SudokuPadFullUrl::Scl(_)) => PuzzleSource::SudokuPadScl,
+            ResolvedUrlInner::SudokuPad(SudokuPadFullUrl::Scf(_)) => PuzzleSource::SudokuPadScf,
+            ResolvedUrlInner::SudokuPad(SudokuPadFullUrl::FPuz(_)) => PuzzleSource::SudokuPadFPuz,
+            ResolvedUrlInner::SudokuMaker(_) => PuzzleSource::SudokuMaker,
+            ResolvedUrlInner::Penpa(_) => PuzzleSource::Penpa,
+        }
+    }
+
     #[allow(clippy::missing_errors_doc)]
     pub fn decode(&self) -> Result<PuzzleFormat, DecodeError> {
         match &self.inner {
-            ResolvedUrlInner::FPuzzles(id) => {
-                todo!("{id}")
-            }
-            ResolvedUrlInner::SudokuPad(full_url) => match full_url {
-                SudokuPadFullUrl::FPuz(id) => {
-                    todo!("{id}")
-                }
-                SudokuPadFullUrl::Scl(id) => {
-                    todo!("{id}")
-                }
-                SudokuPadFullUrl::Scf(id) => {
-                    todo!("{id}")
-                }
-            },
-            ResolvedUrlInner::SudokuMaker(id) => {
-                todo!("{id}")
-            }
-            ResolvedUrlInner::Penpa(id) => {
-                todo!("{id}")
+            ResolvedUrlInner::FPuzzles(payload)
+            | ResolvedUrlInner::SudokuPad(SudokuPadFullUrl::FPuz(payload)) => {
+                let json = decompress(payload)?;
+                Ok(PuzzleFormat::FPuzzles(
+                    FPuzzlesFormat::from_json(&json)?.into(),
+                ))
+            }
+            ResolvedUrlInner::SudokuPad(SudokuPadFullUrl::Scl(payload)) => {
+                let json = decompress(payload)?;
+                Ok(PuzzleFormat::SudokupadScl(
+                    SudokupadSclFormat::from_json(&json)?.into(),
+                ))
+            }
+            ResolvedUrlInner::SudokuPad(SudokuPadFullUrl::Scf(_)) => {
+                Err(FormatError::Unsupported(SCF_UNSUPPORTED).into())
             }
+            ResolvedUrlInner::SudokuMaker(_) => {
+                Err(FormatError::Unsupported(sudokumaker::UNSUPPORTED).into())
+            }
+            ResolvedUrlInner::Penpa(_) => Err(FormatError::Unsupported(penpa::UNSUPPORTED).into()),
         }
     }
+
+    /// Share a puzzle, converting it into a format the target site loads.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the puzzle can't be converted or serialized.
+    pub fn encode(
+        puzzle: &PuzzleFormat,
+        target: ShareTarget,
+    ) -> Result<Converted<Self>, FormatError> {
+        let kind = match (target, puzzle.kind()) {
+            (ShareTarget::SudokuPad, FormatKind::SudokupadScl) => FormatKind::SudokupadScl,
+            _ => FormatKind::FPuzzles,
+        };
+        let converted = puzzle.convert(kind)?;
+        let payload: Box<str> = compress(&converted.puzzle.to_json()?);
+        let inner = match (target, kind) {
+            (ShareTarget::FPuzzles, _) => ResolvedUrlInner::FPuzzles(payload),
+            (ShareTarget::SudokuPad, FormatKind::SudokupadScl) => {
+                ResolvedUrlInner::SudokuPad(SudokuPadFullUrl::Scl(payload))
+            }
+            (ShareTarget::SudokuPad, _) => {
+                ResolvedUrlInner::SudokuPad(SudokuPadFullUrl::FPuz(payload))
+            }
+        };
+        Ok(converted.map(|_| Self::new(inner)))
+    }
+
+    /// The share link of the puzzle.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the payload can't be part of a Url.
+    pub fn url(&self) -> Result<Url, url::ParseError> {
+        match &self.inner {
+            ResolvedUrlInner::FPuzzles(payload) => {
+                Url::parse_with_params("https://f-puzzles.com/", [("load", payload)])
+            }
+            ResolvedUrlInner::SudokuPad(full_url) => {
+                let (prefix, payload) = match full_url {
+                    SudokuPadFullUrl::Scl(payload) => ("scl", payload),
+                    SudokuPadFullUrl::Scf(payload) => ("scf", payload),
+                    SudokuPadFullUrl::FPuz(payload) => ("fpuzzles", payload),
+                };
+                Url::parse(&format!("https://sudokupad.app/{prefix}{payload}"))
+            }
+            ResolvedUrlInner::SudokuMaker(payload) => {
+                Url::parse_with_params("https://sudokumaker.app/", [("puzzle", payload)])
+            }
+            ResolvedUrlInner::Penpa(url) => Ok((**url).clone()),
+        }
+    }
+}
+
+/// Payloads are lz-string base64, where query decoding may have replaced `+` with spaces.
+fn decompress(payload: &str) -> Result<String, DecodeError> {
+    let payload = payload.trim().replace(' ', "+");
+    let bytes = lz_str::decompress_from_base64(&payload).ok_or(DecodeError::Decompression)?;
+    Ok(String::from_utf16(&bytes)?)
+}
+
+fn compress(json: &str) -> Box<str> {
+    lz_str::compress_to_base64(json).into()
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use crate::{
+        format::{FormatKind, PuzzleFormat, full::FullCell},
+        url::{ShareTarget, UrlValue},
+    };
+
+    use super::{PuzzleSource, ResolvedUrl};
+
+    fn decode(url: &str) -> PuzzleFormat {
+        match UrlValue::parse(url).unwrap() {
+            UrlValue::Resolved(resolved) => resolved.decode().unwrap(),
+            UrlValue::Unresolved(unresolved) => panic!("{url} is not resolved: {unresolved:?}"),
+        }
+    }
+
+    #[test]
+    fn decode_and_encode() {
+        let payload = include_str!("../../assets/puzzleid.txt").trim();
+        let url = format!("https://sudokupad.app/scl{payload}");
+        let UrlValue::Resolved(resolved) = UrlValue::parse(url.as_str()).unwrap() else {
+            panic!("{url} is resolved");
+        };
+        assert_eq!(resolved.source(), PuzzleSource::SudokuPadScl);
+
+        let fpuzzles = decode(&format!("https://f-puzzles.com/?load={payload}"));
+        assert_eq!(fpuzzles.kind(), FormatKind::FPuzzles);
+        let sudokupad = decode(&format!("https://sudokupad.app/fpuzzles{payload}"));
+        assert_eq!(sudokupad.kind(), FormatKind::FPuzzles);
+        let full = fpuzzles.to_full().unwrap().puzzle;
+
+        let shared = ResolvedUrl::encode(&fpuzzles, ShareTarget::FPuzzles).unwrap();
+        assert!(shared.dropped.is_empty());
+        let url = shared.puzzle.url().unwrap();
+        assert!(url.as_str().starts_with("https://f-puzzles.com/?load="));
+        assert_eq!(decode(url.as_str()).to_full().unwrap().puzzle, full);
+
+        let scl = fpuzzles.convert(FormatKind::SudokupadScl).unwrap().puzzle;
+        let url = ResolvedUrl::encode(&scl, ShareTarget::SudokuPad)
+            .unwrap()
+            .puzzle
+            .url()
+            .unwrap();
+        assert!(url.as_str().starts_with("https://sudokupad.app/scl"));
+        let decoded = decode(url.as_str());
+        assert_eq!(decoded.kind(), FormatKind::SudokupadScl);
+        assert_eq!(
+            decoded.to_full().unwrap().puzzle.given(FullCell::new(0, 0)),
+            Some(1)
+        );
+
+        let full = PuzzleFormat::Full(full.into());
+        let url = ResolvedUrl::encode(&full, ShareTarget::SudokuPad)
+            .unwrap()
+            .puzzle
+            .url()
+            .unwrap();
+        assert!(url.as_str().starts_with("https://sudokupad.app/fpuzzles"));
+    }
+
+    #[test]
+    fn decode_unsupported() {
+        let UrlValue::Resolved(resolved) =
+            UrlValue::parse("https://sudokumaker.app/?puzzle=abc").unwrap()
+        else {
+            panic!("sudokumaker urls are resolved");
+        };
+        assert!(resolved.decode().is_err());
+        let UrlValue::Resolved(resolved) =
+            UrlValue::parse("https://f-puzzles.com/?load=notlz").unwrap()
+        else {
+            panic!("f-puzzles load urls are resolved");
+        };
+        assert!(resolved.decode().is_err());
+    }
 }
@@ -1,12 +1,14 @@
-use std::error::Error;
+use std::{collections::HashSet, error::Error};
 
 use url::Url;
 
 use super::{
-    resolved_url::ResolvedUrl,
+    ParseError, PuzzlePage, UrlValue,
+    resolved_url::{ResolvedUrl, ResolvedUrlInner, SudokuPadFullUrl},
     url_fetcher::{BlockingUrlFetcher, UrlFetcher},
 };
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct UnresolvedUrl {
     inner: UnresolvedUrlInner,
 }
@@ -17,84 +19,239 @@ pub enum ResolutionError<E: Error> {
     Fetcher(#[from] E),
     #[error("Puzzle Id could not be inserted into Url: {0}")]
     MalformedId(#[source] url::ParseError),
+    #[error("Url {0} does not redirect to a puzzle")]
+    NoRedirect(Box<Url>),
+    #[error("Redirect could not be parsed: {0}")]
+    InvalidRedirect(#[source] ParseError),
+    #[error("Unrecognized puzzle data returned from {url}: {value}")]
+    UnrecognizedPayload { url: Box<Url>, value: Box<str> },
+    #[error("Url did not resolve after {0} requests")]
+    TooManyHops(usize),
+    #[error("Redirect cycle: {}", format_chain(.0))]
+    Cycle(Box<[Url]>),
+    #[error("Not following {0}, its host is unknown")]
+    UnknownHost(Box<Url>),
+    #[error("Not following {0}, its domain is not allowed")]
+    DomainNotAllowed(Box<Url>),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum UnresolvedUrlInner {
     // https://f-puzzles.com/?id=TINYURLID
     // get the redirect from https://tinyurl.com/TINYURLID OR the url itself (f-puzzles does not do any safety check, for the redirect)
     FPuzzles(Box<str>),
     // get the correct id as text response from https://sudokupad.app/api/puzzle/SHORTID
     SudokuPad(Box<str>),
+    // get the page from https://logic-masters.de/Raetselportal/Raetsel/zeigen.php?id=PUZZLEID and collect its links
+    LogicMasters(Box<str>),
     // Just resolve Redirect
     Unknown(Box<Url>),
 }
 
-#[derive(Debug, Default)]
-pub struct ResolutionOptions {}
+/// Controls which requests [`UnresolvedUrl::resolve`] may make.
+///
+/// Domains match the host itself and all of its subdomains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolutionOptions {
+    /// Maximum number of requests made while resolving a single Url.
+    pub max_hops: usize,
+    /// Follow redirects of Urls that are not known puzzle or shortener hosts.
+    pub follow_unknown_hosts: bool,
+    /// Only request these domains, if not empty.
+    pub allowed_domains: Vec<Box<str>>,
+    /// Never request these domains, takes precedence over `allowed_domains`.
+    pub denied_domains: Vec<Box<str>>,
+    /// Fetch every step again, instead of using cached values.
+    pub bypass_cache: bool,
+}
+
+impl Default for ResolutionOptions {
+    fn default() -> Self {
+        Self {
+            max_hops: 10,
+            follow_unknown_hosts: true,
+            allowed_domains: Vec::new(),
+            denied_domains: Vec::new(),
+            bypass_cache: false,
+        }
+    }
+}
+
+impl ResolutionOptions {
+    fn is_allowed(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.to_ascii_lowercase();
+        let matches = |domain: &str| {
+            let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+            host.strip_suffix(domain.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+        };
+        !self.denied_domains.iter().any(|domain| matches(domain))
+            && (self.allowed_domains.is_empty()
+                || self.allowed_domains.iter().any(|domain| matches(domain)))
+    }
+}
+
+/// What an [`UnresolvedUrl`] resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolved {
+    Puzzle(ResolvedUrl),
+    /// A page listing the links of a puzzle, which may need to be resolved themselves.
+    Page(PuzzlePage),
+}
+
+/// A resolved Url, together with the requests that led to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    resolved: Resolved,
+    chain: Box<[Url]>,
+}
+
+impl Resolution {
+    pub(crate) fn new(resolved: Resolved, chain: Box<[Url]>) -> Self {
+        Self { resolved, chain }
+    }
+
+    #[must_use]
+    pub fn resolved(&self) -> &Resolved {
+        &self.resolved
+    }
+
+    /// Urls requested while resolving, in order.
+    #[must_use]
+    pub fn chain(&self) -> &[Url] {
+        &self.chain
+    }
+
+    #[must_use]
+    pub fn into_resolved(self) -> Resolved {
+        self.resolved
+    }
+}
 
 impl UnresolvedUrl {
     pub(crate) fn new(inner: UnresolvedUrlInner) -> Self {
         Self { inner }
     }
+
+    pub(crate) fn inner(&self) -> &UnresolvedUrlInner {
+        &self.inner
+    }
 }
 
 impl UnresolvedUrl {
-    #[allow(clippy::missing_errors_doc)]
+    /// Resolve the Url, by following redirects or querying the puzzle api, until a puzzle
+    /// or a puzzle page is found.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if fetching fails, the Url does not lead to a puzzle,
+    /// or resolving it would need a request `options` do not allow.
     pub async fn resolve<F, E>(
         &self,
         fetcher: &F,
-        _options: &ResolutionOptions,
-    ) -> Result<ResolvedUrl, ResolutionError<E>>
+        options: &ResolutionOptions,
+    ) -> Result<Resolution, ResolutionError<E>>
     where
-        F: UrlFetcher<Error = E>,
+        F: UrlFetcher<Error = E> + Sync,
         E: Error + Send + Sync + 'static,
     {
         use ResolutionStep as R;
 
-        match self
-            .resolution_step()
-            .map_err(ResolutionError::MalformedId)?
-        {
-            R::FetchRedirectUrl(url) => {
-                let _value = fetcher.fetch_redirect_url(url.clone()).await?;
-            }
-            R::FetchResult(url) => {
-                let _value = fetcher.fetch_result(url).await?;
+        let mut chain = Chain::new(options);
+        let mut current = self.clone();
+        loop {
+            let next = match chain.next_step(&current)? {
+                R::FetchRedirectUrl(url) => {
+                    let redirect = if options.bypass_cache {
+                        fetcher.fetch_redirect_url_fresh(url.clone()).await?
+                    } else {
+                        fetcher.fetch_redirect_url(url.clone()).await?
+                    };
+                    follow_redirect(url, redirect)?
+                }
+                R::FetchResult(url) => {
+                    let value = if options.bypass_cache {
+                        fetcher.fetch_result_fresh(url.clone()).await?
+                    } else {
+                        fetcher.fetch_result(url.clone()).await?
+                    };
+                    parse_result(url, value)?
+                }
+                R::ScrapePage(url) => {
+                    let html = if options.bypass_cache {
+                        fetcher.fetch_result_fresh(url.clone()).await?
+                    } else {
+                        fetcher.fetch_result(url.clone()).await?
+                    };
+                    let page = PuzzlePage::extract(url, &html);
+                    return Ok(chain.finish(Resolved::Page(page)));
+                }
+            };
+
+            match next {
+                UrlValue::Resolved(resolved) => return Ok(chain.finish(Resolved::Puzzle(resolved))),
+                UrlValue::Unresolved(unresolved) => current = unresolved,
             }
         }
-
-        todo!(
-            "Only one value will be requested and processed based on url and maybe even options param"
-        )
     }
 
-    #[allow(clippy::missing_errors_doc)]
+    /// Resolve the Url, by following redirects or querying the puzzle api, until a puzzle
+    /// or a puzzle page is found.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if fetching fails, the Url does not lead to a puzzle,
+    /// or resolving it would need a request `options` do not allow.
     pub fn resolve_blocking<F, E>(
         &self,
         fetcher: &F,
-        _options: &ResolutionOptions,
-    ) -> Result<ResolvedUrl, ResolutionError<E>>
+        options: &ResolutionOptions,
+    ) -> Result<Resolution, ResolutionError<E>>
     where
         F: BlockingUrlFetcher<Error = E>,
         E: Error + 'static,
     {
         use ResolutionStep as R;
 
-        match self
-            .resolution_step()
-            .map_err(ResolutionError::MalformedId)?
-        {
-            R::FetchRedirectUrl(url) => {
-                let _value = fetcher.fetch_redirect_url_blocking(url.clone())?;
-            }
-            R::FetchResult(url) => {
-                let _value = fetcher.fetch_result_blocking(url)?;
+        let mut chain = Chain::new(options);
+        let mut current = self.clone();
+        loop {
+            let next = match chain.next_step(&current)? {
+                R::FetchRedirectUrl(url) => {
+                    let redirect = if options.bypass_cache {
+                        fetcher.fetch_redirect_url_fresh_blocking(url.clone())?
+                    } else {
+                        fetcher.fetch_redirect_url_blocking(url.clone())?
+                    };
+                    follow_redirect(url, redirect)?
+                }
+                R::FetchResult(url) => {
+                    let value = if options.bypass_cache {
+                        fetcher.fetch_result_fresh_blocking(url.clone())?
+                    } else {
+                        fetcher.fetch_result_blocking(url.clone())?
+                    };
+                    parse_result(url, value)?
+                }
+                R::ScrapePage(url) => {
+                    let html = if options.bypass_cache {
+                        fetcher.fetch_result_fresh_blocking(url.clone())?
+                    } else {
+                        fetcher.fetch_result_blocking(url.clone())?
+                    };
+                    let page = PuzzlePage::extract(url, &html);
+                    return Ok(chain.finish(Resolved::Page(page)));
+                }
+            };
+
+            match next {
+                UrlValue::Resolved(resolved) => return Ok(chain.finish(Resolved::Puzzle(resolved))),
+                UrlValue::Unresolved(unresolved) => current = unresolved,
             }
         }
-
-        todo!(
-            "Only one value will be requested and processed based on url and maybe even options param"
-        )
     }
 
     fn resolution_step(&self) -> Result<ResolutionStep, url::ParseError> {
@@ -110,12 +267,482 @@ impl UnresolvedUrl {
                 let url = Url::parse(format!("https://sudokupad.app/api/puzzle/{id}").as_str())?;
                 Ok(R::FetchResult(url))
             }
+            U::LogicMasters(id) => {
+                let url = Url::parse_with_params(
+                    "https://logic-masters.de/Raetselportal/Raetsel/zeigen.php",
+                    [("id", id.as_ref())],
+                )?;
+                Ok(R::ScrapePage(url))
+            }
             U::Unknown(url) => Ok(R::FetchRedirectUrl(*url.clone())),
         }
     }
 }
 
+/// Requests made so far, checked against the [`ResolutionOptions`] before every step.
+struct Chain<'a> {
+    options: &'a ResolutionOptions,
+    urls: Vec<Url>,
+    visited: HashSet<Url>,
+}
+
+impl<'a> Chain<'a> {
+    fn new(options: &'a ResolutionOptions) -> Self {
+        Self {
+            options,
+            urls: Vec::new(),
+            visited: HashSet::new(),
+        }
+    }
+
+    fn next_step<E: Error>(
+        &mut self,
+        current: &UnresolvedUrl,
+    ) -> Result<ResolutionStep, ResolutionError<E>> {
+        if let UnresolvedUrlInner::Unknown(url) = &current.inner
+            && !self.options.follow_unknown_hosts
+        {
+            return Err(ResolutionError::UnknownHost(url.clone()));
+        }
+
+        let step = current
+            .resolution_step()
+            .map_err(ResolutionError::MalformedId)?;
+        let url = step.url();
+
+        if !self.options.is_allowed(url) {
+            return Err(ResolutionError::DomainNotAllowed(Box::new(url.clone())));
+        }
+        if !self.visited.insert(url.clone()) {
+            self.urls.push(url.clone());
+            return Err(ResolutionError::Cycle(
+                std::mem::take(&mut self.urls).into(),
+            ));
+        }
+        if self.urls.len() >= self.options.max_hops {
+            return Err(ResolutionError::TooManyHops(self.options.max_hops));
+        }
+        self.urls.push(url.clone());
+
+        Ok(step)
+    }
+
+    fn finish(self, resolved: Resolved) -> Resolution {
+        Resolution::new(resolved, self.urls.into())
+    }
+}
+
+fn format_chain(chain: &[Url]) -> String {
+    chain
+        .iter()
+        .map(Url::as_str)
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
 enum ResolutionStep {
     FetchResult(Url),
     FetchRedirectUrl(Url),
+    ScrapePage(Url),
+}
+
+impl ResolutionStep {
+    fn url(&self) -> &Url {
+        match self {
+            ResolutionStep::FetchResult(url)
+            | ResolutionStep::FetchRedirectUrl(url)
+            | ResolutionStep::ScrapePage(url) => url,
+        }
+    }
+}
+
+/// A redirect is parsed again, as it might point to another unresolved Url.
+fn follow_redirect<E: Error>(
+    url: Url,
+    redirect: Option<Url>,
+) -> Result<UrlValue, ResolutionError<E>> {
+    let Some(redirect) = redirect else {
+        return Err(ResolutionError::NoRedirect(Box::new(url)));
+    };
+    UrlValue::parse(redirect).map_err(ResolutionError::InvalidRedirect)
+}
+
+/// The puzzle api returns the full puzzle id, including its format prefix.
+fn parse_result<E: Error>(url: Url, value: Box<str>) -> Result<UrlValue, ResolutionError<E>> {
+    match SudokuPadFullUrl::from_puzzleid(value.trim()) {
+        Some(full_url) => Ok(UrlValue::Resolved(ResolvedUrl::new(
+            ResolvedUrlInner::SudokuPad(full_url),
+        ))),
+        None => Err(ResolutionError::UnrecognizedPayload {
+            url: Box::new(url),
+            value,
+        }),
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, sync::Mutex};
+
+    use async_trait::async_trait;
+    use url::Url;
+
+    use super::UnresolvedUrlInner;
+    use crate::url::{
+        ParseError, Resolution, ResolutionError, ResolutionOptions, Resolved, UnresolvedUrl,
+        UrlValue,
+        url_fetcher::{BlockingUrlFetcher, UrlFetcher},
+    };
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Not found: {0}")]
+    struct NotFound(Url);
+
+    #[derive(Default)]
+    struct FakeFetcher {
+        redirects: HashMap<Url, Option<Url>>,
+        results: HashMap<Url, Box<str>>,
+        fresh: Mutex<Vec<Url>>,
+    }
+
+    impl FakeFetcher {
+        fn redirect(mut self, from: &str, to: Option<&str>) -> Self {
+            self.redirects.insert(
+                Url::parse(from).unwrap(),
+                to.map(|to| Url::parse(to).unwrap()),
+            );
+            self
+        }
+
+        fn result(mut self, from: &str, value: &str) -> Self {
+            self.results.insert(Url::parse(from).unwrap(), value.into());
+            self
+        }
+    }
+
+    impl BlockingUrlFetcher for FakeFetcher {
+        type Error = NotFound;
+
+        fn fetch_redirect_url_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+            self.redirects.get(&url).cloned().ok_or(NotFound(url))
+        }
+
+        fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+            self.results.get(&url).cloned().ok_or(NotFound(url))
+        }
+
+        fn fetch_redirect_url_fresh_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+            self.fresh.lock().unwrap().push(url.clone());
+            self.fetch_redirect_url_blocking(url)
+        }
+
+        fn fetch_result_fresh_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+            self.fresh.lock().unwrap().push(url.clone());
+            self.fetch_result_blocking(url)
+        }
+    }
+
+    #[async_trait]
+    impl UrlFetcher for FakeFetcher {
+        type Error = NotFound;
+
+        async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+            self.fetch_redirect_url_blocking(url)
+        }
+
+        async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+            self.fetch_result_blocking(url)
+        }
+    }
+
+    fn unresolved(url: &str) -> UnresolvedUrl {
+        match UrlValue::parse(url).unwrap() {
+            UrlValue::Unresolved(unresolved) => unresolved,
+            UrlValue::Resolved(resolved) => panic!("{url} is already resolved: {resolved:?}"),
+        }
+    }
+
+    fn resolved(url: &str) -> Resolved {
+        match UrlValue::parse(url).unwrap() {
+            UrlValue::Resolved(resolved) => Resolved::Puzzle(resolved),
+            UrlValue::Unresolved(unresolved) => panic!("{url} is not resolved: {unresolved:?}"),
+        }
+    }
+
+    fn urls(urls: &[&str]) -> Vec<Url> {
+        urls.iter().map(|url| Url::parse(url).unwrap()).collect()
+    }
+
+    fn fetcher() -> FakeFetcher {
+        FakeFetcher::default()
+            .redirect(
+                "https://tinyurl.com/abc123",
+                Some("https://f-puzzles.com/?load=N4IgzglgXg"),
+            )
+            .result("https://sudokupad.app/api/puzzle/short", "sclABCDEF\n")
+            .result("https://sudokupad.app/api/puzzle/other", "fpuzzlesXYZ")
+            .result("https://sudokupad.app/api/puzzle/broken", "<html>")
+            .redirect("https://short.link/x", Some("https://bit.ly/y"))
+            .redirect("https://bit.ly/y", Some("https://sudokupad.app/short"))
+            .redirect("https://bit.ly/fp", Some("https://tinyurl.com/abc123"))
+            .redirect("https://short.link/none", None)
+            .redirect("https://loop.link/a", Some("https://loop.link/b"))
+            .redirect("https://loop.link/b", Some("https://loop.link/a"))
+            .result(
+                "https://logic-masters.de/Raetselportal/Raetsel/zeigen.php?id=000N81",
+                r#"<h2>Arrows</h2>
+                <a href="/Raetselportal/Benutzer/eingestellt.php?name=Jane">Jane</a>
+                <a href="https://sudokupad.app/psa4cv9rtx">SudokuPad</a>
+                <a href="https://f-puzzles.com/?id=27beqfmw">f-puzzles</a>"#,
+            )
+    }
+
+    #[test]
+    fn resolve_blocking() {
+        let fetcher = fetcher();
+        let options = ResolutionOptions::default();
+        let resolve = |url| {
+            unresolved(url)
+                .resolve_blocking(&fetcher, &options)
+                .map(Resolution::into_resolved)
+        };
+
+        assert_eq!(
+            resolve("https://f-puzzles.com/?id=abc123").unwrap(),
+            resolved("https://f-puzzles.com/?load=N4IgzglgXg")
+        );
+        assert_eq!(
+            resolve("https://sudokupad.app/short").unwrap(),
+            resolved("https://sudokupad.app/sclABCDEF")
+        );
+        assert_eq!(
+            resolve("https://sudokupad.app/other").unwrap(),
+            resolved("https://sudokupad.app/fpuzzlesXYZ")
+        );
+        assert_eq!(
+            resolve("https://short.link/x").unwrap(),
+            resolved("https://sudokupad.app/sclABCDEF")
+        );
+
+        assert!(matches!(
+            resolve("https://short.link/none"),
+            Err(ResolutionError::NoRedirect(_))
+        ));
+        assert!(matches!(
+            resolve("https://sudokupad.app/broken"),
+            Err(ResolutionError::UnrecognizedPayload { .. })
+        ));
+        assert!(matches!(
+            resolve("https://unknown.link/"),
+            Err(ResolutionError::Fetcher(NotFound(_)))
+        ));
+    }
+
+    #[test]
+    fn resolve_chain() {
+        let fetcher = fetcher();
+        let resolution = unresolved("https://bit.ly/fp")
+            .resolve_blocking(&fetcher, &ResolutionOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            resolution.resolved(),
+            &resolved("https://f-puzzles.com/?load=N4IgzglgXg")
+        );
+        assert_eq!(
+            resolution.chain(),
+            urls(&["https://bit.ly/fp", "https://tinyurl.com/abc123"])
+        );
+    }
+
+    #[test]
+    fn resolve_cycle() {
+        let fetcher = fetcher();
+        let err = unresolved("https://loop.link/a")
+            .resolve_blocking(&fetcher, &ResolutionOptions::default())
+            .unwrap_err();
+
+        let ResolutionError::Cycle(chain) = &err else {
+            panic!("Expected a cycle, got {err:?}");
+        };
+        assert_eq!(
+            **chain,
+            urls(&[
+                "https://loop.link/a",
+                "https://loop.link/b",
+                "https://loop.link/a"
+            ])
+        );
+        assert_eq!(
+            err.to_string(),
+            "Redirect cycle: https://loop.link/a -> https://loop.link/b -> https://loop.link/a"
+        );
+    }
+
+    #[test]
+    fn resolve_with_options() {
+        let fetcher = fetcher();
+        let resolve =
+            |url, options: &ResolutionOptions| unresolved(url).resolve_blocking(&fetcher, options);
+
+        let options = ResolutionOptions {
+            max_hops: 2,
+            ..ResolutionOptions::default()
+        };
+        assert!(resolve("https://bit.ly/fp", &options).is_ok());
+        assert!(matches!(
+            resolve("https://short.link/x", &options),
+            Err(ResolutionError::TooManyHops(2))
+        ));
+
+        let options = ResolutionOptions {
+            follow_unknown_hosts: false,
+            ..ResolutionOptions::default()
+        };
+        assert!(resolve("https://f-puzzles.com/?id=abc123", &options).is_ok());
+        assert!(matches!(
+            resolve("https://bit.ly/fp", &options),
+            Err(ResolutionError::UnknownHost(_))
+        ));
+
+        let options = ResolutionOptions {
+            allowed_domains: vec!["tinyurl.com".into(), "bit.ly".into()],
+            ..ResolutionOptions::default()
+        };
+        assert!(resolve("https://bit.ly/fp", &options).is_ok());
+        assert!(matches!(
+            resolve("https://short.link/x", &options),
+            Err(ResolutionError::DomainNotAllowed(_))
+        ));
+
+        let options = ResolutionOptions {
+            denied_domains: vec!["tinyurl.com".into()],
+            ..ResolutionOptions::default()
+        };
+        assert!(resolve("https://short.link/x", &options).is_ok());
+        let Err(ResolutionError::DomainNotAllowed(url)) = resolve("https://bit.ly/fp", &options)
+        else {
+            panic!("tinyurl.com is denied");
+        };
+        assert_eq!(url.as_str(), "https://tinyurl.com/abc123");
+
+        let options = ResolutionOptions {
+            denied_domains: vec!["link".into()],
+            ..ResolutionOptions::default()
+        };
+        assert!(matches!(
+            resolve("https://short.link/x", &options),
+            Err(ResolutionError::DomainNotAllowed(_))
+        ));
+    }
+
+    #[test]
+    fn resolve_bypass_cache() {
+        let fetcher = fetcher();
+        unresolved("https://short.link/x")
+            .resolve_blocking(&fetcher, &ResolutionOptions::default())
+            .unwrap();
+        assert!(fetcher.fresh.lock().unwrap().is_empty());
+
+        let options = ResolutionOptions {
+            bypass_cache: true,
+            ..ResolutionOptions::default()
+        };
+        let resolution = unresolved("https://short.link/x")
+            .resolve_blocking(&fetcher, &options)
+            .unwrap();
+        assert_eq!(*fetcher.fresh.lock().unwrap(), resolution.chain());
+    }
+
+    #[test]
+    fn resolve_logic_masters() {
+        let fetcher = fetcher();
+        let resolution =
+            unresolved("https://www.logic-masters.de/Raetselportal/Raetsel/zeigen.php?id=000N81")
+                .resolve_blocking(&fetcher, &ResolutionOptions::default())
+                .unwrap();
+
+        let Resolved::Page(page) = resolution.resolved() else {
+            panic!("Expected a page, got {resolution:?}");
+        };
+        assert_eq!(page.title(), Some("Arrows"));
+        assert_eq!(page.author(), Some("Jane"));
+        assert_eq!(
+            page.links(),
+            [
+                UrlValue::parse("https://sudokupad.app/psa4cv9rtx").unwrap(),
+                UrlValue::parse("https://f-puzzles.com/?id=27beqfmw").unwrap(),
+            ]
+        );
+        assert_eq!(resolution.chain(), [page.url().clone()]);
+    }
+
+    #[test]
+    fn parse_logic_masters_urls() {
+        let blocks = include_str!("../../assets/test_urls/logic_masters.txt")
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|block| !block.is_empty());
+
+        for block in blocks {
+            let mut lines = block.lines();
+            let page = lines.next().unwrap();
+            assert!(
+                matches!(unresolved(page).inner, UnresolvedUrlInner::LogicMasters(_)),
+                "{page}"
+            );
+            for link in lines {
+                assert!(UrlValue::parse(link).unwrap().is_puzzle_link(), "{link}");
+            }
+        }
+
+        assert!(matches!(
+            UrlValue::parse("https://logic-masters.de/Raetselportal/index.php"),
+            Err(ParseError::UnknownPage(_))
+        ));
+        assert!(matches!(
+            UrlValue::parse("https://logic-masters.de/Raetselportal/Raetsel/zeigen.php"),
+            Err(ParseError::MissingId(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn resolve_async() {
+        let fetcher = fetcher();
+        let options = ResolutionOptions::default();
+
+        let resolution = unresolved("https://f-puzzles.com/?id=abc123")
+            .resolve(&fetcher, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            resolution.into_resolved(),
+            resolved("https://f-puzzles.com/?load=N4IgzglgXg")
+        );
+
+        let resolution = unresolved("https://short.link/x")
+            .resolve(&fetcher, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            resolution.chain(),
+            urls(&[
+                "https://short.link/x",
+                "https://bit.ly/y",
+                "https://sudokupad.app/api/puzzle/short"
+            ])
+        );
+        assert_eq!(
+            resolution.into_resolved(),
+            resolved("https://sudokupad.app/sclABCDEF")
+        );
+
+        assert!(matches!(
+            unresolved("https://loop.link/a")
+                .resolve(&fetcher, &options)
+                .await,
+            Err(ResolutionError::Cycle(_))
+        ));
+    }
 }
@@ -4,7 +4,12 @@ use async_trait::async_trait;
 use url::Url;
 
 pub mod cache;
+pub mod directory;
+pub mod maintenance;
 pub mod map_err;
+pub mod memory;
+pub mod rate_limit;
+pub mod replay;
 pub mod reqwest;
 pub mod rusqlite;
 pub mod tokio;
@@ -28,6 +33,24 @@ pub trait UrlFetcher {
     ///
     /// Errors are implementation-specific, and their behavior depends on the underlying fetcher.
     async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error>;
+
+    /// Like [`fetch_redirect_url`](UrlFetcher::fetch_redirect_url), but bypassing any cache.
+    ///
+    /// # Errors
+    ///
+    /// Errors are implementation-specific, and their behavior depends on the underlying fetcher.
+    async fn fetch_redirect_url_fresh(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.fetch_redirect_url(url).await
+    }
+
+    /// Like [`fetch_result`](UrlFetcher::fetch_result), but bypassing any cache.
+    ///
+    /// # Errors
+    ///
+    /// Errors are implementation-specific, and their behavior depends on the underlying fetcher.
+    async fn fetch_result_fresh(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.fetch_result(url).await
+    }
 }
 
 /// Blocking fetcher, used to define fetching behaviour.
@@ -48,4 +71,22 @@ pub trait BlockingUrlFetcher {
     ///
     /// Errors are implementation-specific, and their behavior depends on the underlying fetcher.
     fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error>;
+
+    /// Like [`fetch_redirect_url_blocking`](BlockingUrlFetcher::fetch_redirect_url_blocking), but bypassing any cache.
+    ///
+    /// # Errors
+    ///
+    /// Errors are implementation-specific, and their behavior depends on the underlying fetcher.
+    fn fetch_redirect_url_fresh_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.fetch_redirect_url_blocking(url)
+    }
+
+    /// Like [`fetch_result_blocking`](BlockingUrlFetcher::fetch_result_blocking), but bypassing any cache.
+    ///
+    /// # Errors
+    ///
+    /// Errors are implementation-specific, and their behavior depends on the underlying fetcher.
+    fn fetch_result_fresh_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.fetch_result_blocking(url)
+    }
 }
@@ -11,10 +11,27 @@ pub enum CacheError<E> {
     NoCacheValue,
     #[error("Error fetching Cache: {0}")]
     CacheFetchError(#[from] Box<E>),
+    /// Fetching the Url failed recently, so it is not retried yet.
+    #[error("Fetching failed before: {0}")]
+    CachedFailure(Box<str>),
+}
+
+/// Errors of cache lookups, which tell a missing value apart from failures.
+///
+/// Implemented by [`CacheError`] itself, and by errors of wrappers around caches
+/// which only know the cache error of the inner cache, like [`TokioUrlFetcher`](super::tokio::TokioUrlFetcher).
+pub trait IntoCacheError<E> {
+    fn into_cache_error(self) -> CacheError<E>;
+}
+
+impl<E> IntoCacheError<E> for CacheError<E> {
+    fn into_cache_error(self) -> CacheError<E> {
+        self
+    }
 }
 
 #[async_trait]
-pub trait UrlFetcherCache: UrlFetcher<Error = CacheError<Self::FetchError>> {
+pub trait UrlFetcherCache: UrlFetcher<Error: IntoCacheError<Self::FetchError>> {
     type FetchError: Error + Send + Sync + 'static;
     type StoreError: Error + Send + Sync + 'static;
 
@@ -22,6 +39,25 @@ pub trait UrlFetcherCache: UrlFetcher<Error = CacheError<Self::FetchError>> {
     async fn store_redirect(&self, url: Url, value: Option<Url>) -> Result<(), Self::StoreError>;
     #[allow(clippy::missing_errors_doc)]
     async fn store_result(&self, url: Url, value: Box<str>) -> Result<(), Self::StoreError>;
+
+    /// Remember that fetching the redirect failed, ignored by caches without negative caching.
+    #[allow(clippy::missing_errors_doc)]
+    async fn store_redirect_failure(
+        &self,
+        _url: Url,
+        _message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        Ok(())
+    }
+    /// Remember that fetching the result failed, ignored by caches without negative caching.
+    #[allow(clippy::missing_errors_doc)]
+    async fn store_result_failure(
+        &self,
+        _url: Url,
+        _message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -47,6 +83,25 @@ pub trait BlockingUrlFetcherCache:
     -> Result<(), Self::StoreError>;
     #[allow(clippy::missing_errors_doc)]
     fn store_result_blocking(&self, url: Url, value: Box<str>) -> Result<(), Self::StoreError>;
+
+    /// Remember that fetching the redirect failed, ignored by caches without negative caching.
+    #[allow(clippy::missing_errors_doc, clippy::boxed_local)]
+    fn store_redirect_failure_blocking(
+        &self,
+        _url: Url,
+        _message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        Ok(())
+    }
+    /// Remember that fetching the result failed, ignored by caches without negative caching.
+    #[allow(clippy::missing_errors_doc, clippy::boxed_local)]
+    fn store_result_failure_blocking(
+        &self,
+        _url: Url,
+        _message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        Ok(())
+    }
 }
 
 pub struct CachedFetcher<C, F> {
@@ -64,6 +119,22 @@ where
     }
 }
 
+impl<C, F> CachedFetcher<C, F> {
+    pub fn cache(&self) -> &C {
+        &self.cache
+    }
+
+    /// The fetcher used when the cache has no value.
+    pub fn fetcher(&self) -> &F {
+        &self.fetcher
+    }
+
+    /// Take the cache and fetcher apart again.
+    pub fn into_parts(self) -> (C, F) {
+        (self.cache, self.fetcher)
+    }
+}
+
 impl<C, F> CachedFetcher<C, F>
 where
     C: BlockingUrlFetcherCache,
@@ -110,6 +181,90 @@ where
     }
 }
 
+/// Serves values of the inner cache, but never stores anything into it.
+pub struct ReadOnlyCache<C> {
+    inner: C,
+}
+
+impl<C> ReadOnlyCache<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<C> UrlFetcher for ReadOnlyCache<C>
+where
+    C: UrlFetcherCache + Send + Sync,
+{
+    type Error = C::Error;
+
+    async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.inner.fetch_redirect_url(url).await
+    }
+
+    async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.inner.fetch_result(url).await
+    }
+}
+
+#[async_trait]
+impl<C> UrlFetcherCache for ReadOnlyCache<C>
+where
+    C: UrlFetcherCache + Send + Sync,
+{
+    type FetchError = C::FetchError;
+    type StoreError = C::StoreError;
+
+    async fn store_redirect(&self, _url: Url, _value: Option<Url>) -> Result<(), Self::StoreError> {
+        Ok(())
+    }
+
+    async fn store_result(&self, _url: Url, _value: Box<str>) -> Result<(), Self::StoreError> {
+        Ok(())
+    }
+}
+
+impl<C> BlockingUrlFetcher for ReadOnlyCache<C>
+where
+    C: BlockingUrlFetcherCache,
+{
+    type Error = CacheError<C::FetchError>;
+
+    fn fetch_redirect_url_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.inner.fetch_redirect_url_blocking(url)
+    }
+
+    fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.inner.fetch_result_blocking(url)
+    }
+}
+
+impl<C> BlockingUrlFetcherCache for ReadOnlyCache<C>
+where
+    C: BlockingUrlFetcherCache,
+{
+    type FetchError = C::FetchError;
+    type StoreError = C::StoreError;
+
+    fn store_redirect_blocking(
+        &self,
+        _url: Url,
+        _value: Option<Url>,
+    ) -> Result<(), Self::StoreError> {
+        Ok(())
+    }
+
+    #[allow(clippy::boxed_local)]
+    fn store_result_blocking(&self, _url: Url, _value: Box<str>) -> Result<(), Self::StoreError> {
+        Ok(())
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum CachedFetcherError<CF, CS, F> {
     #[error("Error fetching Cache: {0}")]
@@ -118,6 +273,137 @@ pub enum CachedFetcherError<CF, CS, F> {
     CacheStoreError(Box<CS>),
     #[error("Error fetching: {0}")]
     FetchError(Box<F>),
+    #[error("Fetching failed before: {0}")]
+    CachedFailure(Box<str>),
+}
+
+/// Value of a Url kept by a cache, picks the store methods of its kind.
+#[async_trait]
+trait CacheValue: Clone + Send + Sized + 'static {
+    async fn store<C: UrlFetcherCache + Sync>(
+        cache: &C,
+        url: Url,
+        value: Self,
+    ) -> Result<(), C::StoreError>;
+    async fn store_failure<C: UrlFetcherCache + Sync>(
+        cache: &C,
+        url: Url,
+        message: Box<str>,
+    ) -> Result<(), C::StoreError>;
+    fn store_blocking<C: BlockingUrlFetcherCache>(
+        cache: &C,
+        url: Url,
+        value: Self,
+    ) -> Result<(), C::StoreError>;
+    fn store_failure_blocking<C: BlockingUrlFetcherCache>(
+        cache: &C,
+        url: Url,
+        message: Box<str>,
+    ) -> Result<(), C::StoreError>;
+}
+
+#[async_trait]
+impl CacheValue for Option<Url> {
+    async fn store<C: UrlFetcherCache + Sync>(
+        cache: &C,
+        url: Url,
+        value: Self,
+    ) -> Result<(), C::StoreError> {
+        cache.store_redirect(url, value).await
+    }
+
+    async fn store_failure<C: UrlFetcherCache + Sync>(
+        cache: &C,
+        url: Url,
+        message: Box<str>,
+    ) -> Result<(), C::StoreError> {
+        cache.store_redirect_failure(url, message).await
+    }
+
+    fn store_blocking<C: BlockingUrlFetcherCache>(
+        cache: &C,
+        url: Url,
+        value: Self,
+    ) -> Result<(), C::StoreError> {
+        cache.store_redirect_blocking(url, value)
+    }
+
+    fn store_failure_blocking<C: BlockingUrlFetcherCache>(
+        cache: &C,
+        url: Url,
+        message: Box<str>,
+    ) -> Result<(), C::StoreError> {
+        cache.store_redirect_failure_blocking(url, message)
+    }
+}
+
+#[async_trait]
+impl CacheValue for Box<str> {
+    async fn store<C: UrlFetcherCache + Sync>(
+        cache: &C,
+        url: Url,
+        value: Self,
+    ) -> Result<(), C::StoreError> {
+        cache.store_result(url, value).await
+    }
+
+    async fn store_failure<C: UrlFetcherCache + Sync>(
+        cache: &C,
+        url: Url,
+        message: Box<str>,
+    ) -> Result<(), C::StoreError> {
+        cache.store_result_failure(url, message).await
+    }
+
+    fn store_blocking<C: BlockingUrlFetcherCache>(
+        cache: &C,
+        url: Url,
+        value: Self,
+    ) -> Result<(), C::StoreError> {
+        cache.store_result_blocking(url, value)
+    }
+
+    fn store_failure_blocking<C: BlockingUrlFetcherCache>(
+        cache: &C,
+        url: Url,
+        message: Box<str>,
+    ) -> Result<(), C::StoreError> {
+        cache.store_result_failure_blocking(url, message)
+    }
+}
+
+impl<C, F> CachedFetcher<C, F>
+where
+    C: UrlFetcherCache + Send + Sync,
+    F: UrlFetcher + Send + Sync,
+{
+    /// Store what the fetcher returned, so the fetched value replaces the cached one.
+    ///
+    /// Failures are stored too, for caches with negative caching. The fetch error is more useful
+    /// than one from storing the failure, so that one is only logged.
+    async fn store_fetched<T: CacheValue>(
+        &self,
+        url: Url,
+        fetched: Result<T, F::Error>,
+    ) -> Result<T, <Self as UrlFetcher>::Error> {
+        let value = match fetched {
+            Ok(value) => value,
+            Err(err) => {
+                if let Err(store_err) =
+                    T::store_failure(&self.cache, url.clone(), err.to_string().into()).await
+                {
+                    log::warn!("Error storing the failed fetch of {url}: {store_err}");
+                }
+                return Err(CachedFetcherError::FetchError(err.into()));
+            }
+        };
+
+        T::store(&self.cache, url, value.clone())
+            .await
+            .map_err(|err| CachedFetcherError::CacheStoreError(err.into()))?;
+
+        Ok(value)
+    }
 }
 
 #[async_trait]
@@ -129,46 +415,83 @@ where
     type Error = CachedFetcherError<C::FetchError, C::StoreError, F::Error>;
 
     async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
-        match self.cache.fetch_redirect_url(url.clone()).await {
+        match self
+            .cache
+            .fetch_redirect_url(url.clone())
+            .await
+            .map_err(IntoCacheError::into_cache_error)
+        {
             Ok(value) => return Ok(value),
             Err(CacheError::CacheFetchError(err)) => {
                 return Err(CachedFetcherError::CacheFetchError(err));
             }
+            Err(CacheError::CachedFailure(message)) => {
+                return Err(CachedFetcherError::CachedFailure(message));
+            }
             Err(CacheError::NoCacheValue) => {}
         }
 
-        let value = self
-            .fetcher
-            .fetch_redirect_url(url.clone())
-            .await
-            .map_err(|err| CachedFetcherError::FetchError(err.into()))?;
-
-        self.cache
-            .store_redirect(url, value.clone())
-            .await
-            .map_err(|err| CachedFetcherError::CacheStoreError(err.into()))?;
-
-        Ok(value)
+        let fetched = self.fetcher.fetch_redirect_url(url.clone()).await;
+        self.store_fetched(url, fetched).await
     }
 
     async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
-        match self.cache.fetch_result(url.clone()).await {
+        match self
+            .cache
+            .fetch_result(url.clone())
+            .await
+            .map_err(IntoCacheError::into_cache_error)
+        {
             Ok(value) => return Ok(value),
             Err(CacheError::CacheFetchError(err)) => {
                 return Err(CachedFetcherError::CacheFetchError(err));
             }
+            Err(CacheError::CachedFailure(message)) => {
+                return Err(CachedFetcherError::CachedFailure(message));
+            }
             Err(CacheError::NoCacheValue) => {}
         }
 
-        let value = self
-            .fetcher
-            .fetch_result(url.clone())
-            .await
-            .map_err(|err| CachedFetcherError::FetchError(err.into()))?;
+        let fetched = self.fetcher.fetch_result(url.clone()).await;
+        self.store_fetched(url, fetched).await
+    }
 
-        self.cache
-            .store_result(url, value.clone())
-            .await
+    /// Fetch without looking into the cache, see [`CachedFetcher::store_fetched`].
+    async fn fetch_redirect_url_fresh(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        let fetched = self.fetcher.fetch_redirect_url_fresh(url.clone()).await;
+        self.store_fetched(url, fetched).await
+    }
+
+    async fn fetch_result_fresh(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        let fetched = self.fetcher.fetch_result_fresh(url.clone()).await;
+        self.store_fetched(url, fetched).await
+    }
+}
+
+impl<C, F> CachedFetcher<C, F>
+where
+    C: BlockingUrlFetcherCache,
+    F: BlockingUrlFetcher,
+{
+    /// Like [`CachedFetcher::store_fetched`].
+    fn store_fetched_blocking<T: CacheValue>(
+        &self,
+        url: Url,
+        fetched: Result<T, F::Error>,
+    ) -> Result<T, <Self as BlockingUrlFetcher>::Error> {
+        let value = match fetched {
+            Ok(value) => value,
+            Err(err) => {
+                if let Err(store_err) =
+                    T::store_failure_blocking(&self.cache, url.clone(), err.to_string().into())
+                {
+                    log::warn!("Error storing the failed fetch of {url}: {store_err}");
+                }
+                return Err(CachedFetcherError::FetchError(err.into()));
+            }
+        };
+
+        T::store_blocking(&self.cache, url, value.clone())
             .map_err(|err| CachedFetcherError::CacheStoreError(err.into()))?;
 
         Ok(value)
@@ -188,19 +511,14 @@ where
             Err(CacheError::CacheFetchError(err)) => {
                 return Err(CachedFetcherError::CacheFetchError(err));
             }
+            Err(CacheError::CachedFailure(message)) => {
+                return Err(CachedFetcherError::CachedFailure(message));
+            }
             Err(CacheError::NoCacheValue) => {}
         }
 
-        let value = self
-            .fetcher
-            .fetch_redirect_url_blocking(url.clone())
-            .map_err(|err| CachedFetcherError::FetchError(err.into()))?;
-
-        self.cache
-            .store_redirect_blocking(url, value.clone())
-            .map_err(|err| CachedFetcherError::CacheStoreError(err.into()))?;
-
-        Ok(value)
+        let fetched = self.fetcher.fetch_redirect_url_blocking(url.clone());
+        self.store_fetched_blocking(url, fetched)
     }
 
     fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
@@ -209,18 +527,24 @@ where
             Err(CacheError::CacheFetchError(err)) => {
                 return Err(CachedFetcherError::CacheFetchError(err));
             }
+            Err(CacheError::CachedFailure(message)) => {
+                return Err(CachedFetcherError::CachedFailure(message));
+            }
             Err(CacheError::NoCacheValue) => {}
         }
 
-        let value = self
-            .fetcher
-            .fetch_result_blocking(url.clone())
-            .map_err(|err| CachedFetcherError::FetchError(err.into()))?;
+        let fetched = self.fetcher.fetch_result_blocking(url.clone());
+        self.store_fetched_blocking(url, fetched)
+    }
 
-        self.cache
-            .store_result_blocking(url, value.clone())
-            .map_err(|err| CachedFetcherError::CacheStoreError(err.into()))?;
+    /// Fetch without looking into the cache, see [`CachedFetcher::store_fetched`].
+    fn fetch_redirect_url_fresh_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        let fetched = self.fetcher.fetch_redirect_url_fresh_blocking(url.clone());
+        self.store_fetched_blocking(url, fetched)
+    }
 
-        Ok(value)
+    fn fetch_result_fresh_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        let fetched = self.fetcher.fetch_result_fresh_blocking(url.clone());
+        self.store_fetched_blocking(url, fetched)
     }
 }
@@ -0,0 +1,374 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use async_trait::async_trait;
+use url::Url;
+
+use super::{
+    BlockingUrlFetcher, UrlFetcher,
+    cache::{BlockingUrlFetcherCache, CacheError, UrlFetcherCache},
+    maintenance::{CacheEntry, EntryKind, EntryValue, unix_now},
+};
+
+const FNV_OFFSET_BASIS: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+const FNV_PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+/// Numbers the temporary files of this process, so concurrent writers never share one.
+static TEMPORARY_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Cache keeping one json file per entry in a directory, named by the hash of its Url.
+///
+/// File names only depend on the Url and files are pretty printed, so the directory can be
+/// checked in as test fixtures. Storing the value a file already holds leaves it untouched.
+/// Files are small and read and written in place, also from the async methods.
+#[derive(Debug, Clone)]
+pub struct DirectoryUrlFetcherCache {
+    root: PathBuf,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DirectoryCacheError {
+    #[error("Error accessing cache file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Invalid cache file {path}: {source}")]
+    Json {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+impl DirectoryUrlFetcherCache {
+    /// Use the directory `root`, which is created on the first store.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// `<root>/<kind>/<hash>.json`, with a stable 128 bit FNV-1a hash of the Url.
+    fn path(&self, kind: EntryKind, url: &Url) -> PathBuf {
+        let hash = url.as_str().bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u128::from(byte)).wrapping_mul(FNV_PRIME)
+        });
+        self.root
+            .join(kind.as_str())
+            .join(format!("{hash:032x}.json"))
+    }
+
+    /// The entry stored for `url`, `None` if there is none or the hash collides.
+    fn read(&self, kind: EntryKind, url: &Url) -> Result<Option<CacheEntry>, DirectoryCacheError> {
+        let path = self.path(kind, url);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(DirectoryCacheError::Io { path, source }),
+        };
+        let entry: CacheEntry = serde_json::from_str(&content)
+            .map_err(|source| DirectoryCacheError::Json { path, source })?;
+
+        Ok((entry.url == *url && entry.value.kind() == kind).then_some(entry))
+    }
+
+    /// Write via a temporary file, so readers never see half written entries.
+    /// Files already holding `value` keep their timestamp, so fixtures don't change.
+    fn write(&self, url: Url, value: EntryValue) -> Result<(), DirectoryCacheError> {
+        if let Ok(Some(existing)) = self.read(value.kind(), &url)
+            && existing.value == value
+        {
+            return Ok(());
+        }
+        let path = self.path(value.kind(), &url);
+        let entry = CacheEntry {
+            url,
+            value,
+            stored_at: unix_now(),
+            last_hit: None,
+        };
+        let mut content =
+            serde_json::to_string_pretty(&entry).map_err(|source| DirectoryCacheError::Json {
+                path: path.clone(),
+                source,
+            })?;
+        content.push('\n');
+
+        let temporary = path.with_extension(format!(
+            "json.{}-{}.tmp",
+            std::process::id(),
+            TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&temporary, content))
+            .and_then(|()| fs::rename(&temporary, &path))
+            .map_err(|source| {
+                let _ = fs::remove_file(&temporary);
+                DirectoryCacheError::Io { path, source }
+            })
+    }
+
+    fn redirect(&self, url: &Url) -> Result<Option<Url>, CacheError<DirectoryCacheError>> {
+        match self.read(EntryKind::Redirect, url).map_err(Box::new)? {
+            Some(CacheEntry {
+                value: EntryValue::Redirect { redirect_url },
+                ..
+            }) => Ok(redirect_url),
+            _ => Err(CacheError::NoCacheValue),
+        }
+    }
+
+    fn result(&self, url: &Url) -> Result<Box<str>, CacheError<DirectoryCacheError>> {
+        match self.read(EntryKind::Result, url).map_err(Box::new)? {
+            Some(CacheEntry {
+                value: EntryValue::Result { response_value },
+                ..
+            }) => Ok(response_value),
+            _ => Err(CacheError::NoCacheValue),
+        }
+    }
+}
+
+#[async_trait]
+impl UrlFetcher for DirectoryUrlFetcherCache {
+    type Error = CacheError<DirectoryCacheError>;
+
+    async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.redirect(&url)
+    }
+
+    async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.result(&url)
+    }
+}
+
+#[async_trait]
+impl UrlFetcherCache for DirectoryUrlFetcherCache {
+    type FetchError = DirectoryCacheError;
+    type StoreError = DirectoryCacheError;
+
+    async fn store_redirect(&self, url: Url, value: Option<Url>) -> Result<(), Self::StoreError> {
+        self.write(
+            url,
+            EntryValue::Redirect {
+                redirect_url: value,
+            },
+        )
+    }
+
+    async fn store_result(&self, url: Url, value: Box<str>) -> Result<(), Self::StoreError> {
+        self.write(
+            url,
+            EntryValue::Result {
+                response_value: value,
+            },
+        )
+    }
+}
+
+impl BlockingUrlFetcher for DirectoryUrlFetcherCache {
+    type Error = CacheError<DirectoryCacheError>;
+
+    fn fetch_redirect_url_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.redirect(&url)
+    }
+
+    fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.result(&url)
+    }
+}
+
+impl BlockingUrlFetcherCache for DirectoryUrlFetcherCache {
+    type FetchError = DirectoryCacheError;
+    type StoreError = DirectoryCacheError;
+
+    fn store_redirect_blocking(
+        &self,
+        url: Url,
+        value: Option<Url>,
+    ) -> Result<(), Self::StoreError> {
+        self.write(
+            url,
+            EntryValue::Redirect {
+                redirect_url: value,
+            },
+        )
+    }
+
+    fn store_result_blocking(&self, url: Url, value: Box<str>) -> Result<(), Self::StoreError> {
+        self.write(
+            url,
+            EntryValue::Result {
+                response_value: value,
+            },
+        )
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use url::Url;
+
+    use crate::url::url_fetcher::{
+        BlockingUrlFetcher, UrlFetcher,
+        cache::{BlockingUrlFetcherCache, CacheError, UrlFetcherCache},
+        maintenance::EntryKind,
+    };
+
+    use super::{DirectoryCacheError, DirectoryUrlFetcherCache};
+
+    /// A fresh directory below the system temp directory, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("puzzle-formats-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn store_and_fetch() {
+        let dir = TempDir::new("directory-cache");
+        let cache = DirectoryUrlFetcherCache::new(&dir.0);
+
+        let url1 = Url::parse("https://google.com/").unwrap();
+        let url2 = Url::parse("https://bing.com/").unwrap();
+
+        assert!(matches!(
+            cache.fetch_redirect_url_blocking(url1.clone()),
+            Err(CacheError::NoCacheValue)
+        ));
+
+        cache
+            .store_redirect_blocking(url1.clone(), Some(url2.clone()))
+            .unwrap();
+        cache.store_redirect_blocking(url2.clone(), None).unwrap();
+        cache
+            .store_result_blocking(url1.clone(), "VALUE".into())
+            .unwrap();
+
+        assert_eq!(
+            cache.fetch_redirect_url_blocking(url1.clone()).unwrap(),
+            Some(url2.clone())
+        );
+        assert_eq!(
+            cache.fetch_redirect_url_blocking(url2.clone()).unwrap(),
+            None
+        );
+        assert_eq!(
+            cache.fetch_result_blocking(url1.clone()).unwrap(),
+            "VALUE".into()
+        );
+        assert!(matches!(
+            cache.fetch_result_blocking(url2.clone()),
+            Err(CacheError::NoCacheValue)
+        ));
+
+        // File names only depend on the Url
+        let path = cache.path(EntryKind::Redirect, &url1);
+        assert!(path.starts_with(dir.0.join("redirect")));
+        assert_eq!(
+            path,
+            DirectoryUrlFetcherCache::new(&dir.0).path(EntryKind::Redirect, &url1)
+        );
+        assert_eq!(fs::read_dir(dir.0.join("redirect")).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn store_same_value() {
+        let dir = TempDir::new("directory-cache-same");
+        let cache = DirectoryUrlFetcherCache::new(&dir.0);
+        let url = Url::parse("https://google.com/").unwrap();
+
+        cache
+            .store_result_blocking(url.clone(), "VALUE".into())
+            .unwrap();
+        let path = cache.path(EntryKind::Result, &url);
+        let content =
+            fs::read_to_string(&path)
+                .unwrap()
+                .replacen("\"stored_at\": ", "\"stored_at\": 1", 1);
+        fs::write(&path, &content).unwrap();
+
+        cache
+            .store_result_blocking(url.clone(), "VALUE".into())
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        cache
+            .store_result_blocking(url.clone(), "OTHER".into())
+            .unwrap();
+        assert_ne!(fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(cache.fetch_result_blocking(url).unwrap(), "OTHER".into());
+    }
+
+    #[test]
+    fn concurrent_writers() {
+        let dir = TempDir::new("directory-cache-concurrent");
+        let cache = DirectoryUrlFetcherCache::new(&dir.0);
+        let url = Url::parse("https://google.com/").unwrap();
+
+        std::thread::scope(|scope| {
+            for value in ["A", "B", "C", "D"] {
+                let (cache, url) = (&cache, &url);
+                scope.spawn(move || {
+                    for _ in 0..20 {
+                        cache
+                            .store_result_blocking(url.clone(), value.into())
+                            .unwrap();
+                        cache
+                            .store_result_blocking(url.clone(), "OTHER".into())
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(cache.fetch_result_blocking(url).unwrap(), "OTHER".into());
+        assert_eq!(fs::read_dir(dir.0.join("result")).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn invalid_file() {
+        let dir = TempDir::new("directory-cache-invalid");
+        let cache = DirectoryUrlFetcherCache::new(&dir.0);
+        let url = Url::parse("https://google.com/").unwrap();
+
+        cache
+            .store_result(url.clone(), "VALUE".into())
+            .await
+            .unwrap();
+        assert_eq!(
+            cache.fetch_result(url.clone()).await.unwrap(),
+            "VALUE".into()
+        );
+
+        let path = cache.path(EntryKind::Result, &url);
+        fs::write(&path, "not json").unwrap();
+        assert!(matches!(
+            cache.fetch_result(url).await,
+            Err(CacheError::CacheFetchError(err)) if matches!(*err, DirectoryCacheError::Json { .. })
+        ));
+    }
+}
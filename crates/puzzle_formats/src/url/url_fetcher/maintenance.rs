@@ -0,0 +1,280 @@
+use std::{
+    error::Error,
+    io::{self, BufRead, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Seconds since the unix epoch, the unit of all cache timestamps.
+#[must_use]
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| {
+            i64::try_from(since.as_secs()).unwrap_or(i64::MAX)
+        })
+}
+
+/// Which kind of value a cache entry holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Redirect,
+    Result,
+    RedirectFailure,
+    ResultFailure,
+}
+
+impl EntryKind {
+    pub const ALL: [EntryKind; 4] = [
+        Self::Redirect,
+        Self::Result,
+        Self::RedirectFailure,
+        Self::ResultFailure,
+    ];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Redirect => "redirect",
+            Self::Result => "result",
+            Self::RedirectFailure => "redirect_failure",
+            Self::ResultFailure => "result_failure",
+        }
+    }
+}
+
+/// Everything known about an entry, except its value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryMetadata {
+    pub url: Url,
+    pub kind: EntryKind,
+    /// Size of the value in bytes.
+    pub size: usize,
+    /// Seconds since the unix epoch.
+    pub stored_at: i64,
+    /// Seconds since the unix epoch, `None` if the entry was never read.
+    pub last_hit: Option<i64>,
+}
+
+/// The value of an entry, tagged with its kind.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EntryValue {
+    Redirect { redirect_url: Option<Url> },
+    Result { response_value: Box<str> },
+    RedirectFailure { message: Box<str> },
+    ResultFailure { message: Box<str> },
+}
+
+impl EntryValue {
+    #[must_use]
+    pub fn kind(&self) -> EntryKind {
+        match self {
+            Self::Redirect { .. } => EntryKind::Redirect,
+            Self::Result { .. } => EntryKind::Result,
+            Self::RedirectFailure { .. } => EntryKind::RedirectFailure,
+            Self::ResultFailure { .. } => EntryKind::ResultFailure,
+        }
+    }
+}
+
+/// A complete entry, as exported and imported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: Url,
+    #[serde(flatten)]
+    pub value: EntryValue,
+    /// Seconds since the unix epoch.
+    pub stored_at: i64,
+    /// Seconds since the unix epoch, `None` if the entry was never read.
+    #[serde(default)]
+    pub last_hit: Option<i64>,
+}
+
+/// Async inspection and cleanup of a cache.
+#[async_trait]
+pub trait CacheMaintenance {
+    type MaintenanceError: Error + Send + Sync + 'static;
+
+    /// Metadata of all entries, ordered by Url.
+    #[allow(clippy::missing_errors_doc)]
+    async fn entries(&self) -> Result<Vec<EntryMetadata>, Self::MaintenanceError>;
+
+    #[allow(clippy::missing_errors_doc)]
+    async fn entry(
+        &self,
+        url: Url,
+        kind: EntryKind,
+    ) -> Result<Option<EntryMetadata>, Self::MaintenanceError>;
+
+    /// Delete all entries of `url`, returning how many were deleted.
+    #[allow(clippy::missing_errors_doc)]
+    async fn invalidate(&self, url: Url) -> Result<usize, Self::MaintenanceError>;
+
+    /// Delete all entries whose Url starts with `prefix`, returning how many were deleted.
+    #[allow(clippy::missing_errors_doc)]
+    async fn invalidate_prefix(&self, prefix: &str) -> Result<usize, Self::MaintenanceError>;
+
+    /// Delete expired entries and compact the storage, returning how many were deleted.
+    #[allow(clippy::missing_errors_doc)]
+    async fn vacuum(&self) -> Result<usize, Self::MaintenanceError>;
+
+    /// All entries including their values, ordered by Url.
+    #[allow(clippy::missing_errors_doc)]
+    async fn export(&self) -> Result<Vec<CacheEntry>, Self::MaintenanceError>;
+
+    /// Insert `entries`, replacing existing ones, returning how many were inserted.
+    #[allow(clippy::missing_errors_doc)]
+    async fn import(&self, entries: Vec<CacheEntry>) -> Result<usize, Self::MaintenanceError>;
+}
+
+/// Blocking inspection and cleanup of a cache.
+pub trait BlockingCacheMaintenance {
+    type MaintenanceError: Error + 'static;
+
+    /// Metadata of all entries, ordered by Url.
+    #[allow(clippy::missing_errors_doc)]
+    fn entries_blocking(&self) -> Result<Vec<EntryMetadata>, Self::MaintenanceError>;
+
+    #[allow(clippy::missing_errors_doc)]
+    fn entry_blocking(
+        &self,
+        url: Url,
+        kind: EntryKind,
+    ) -> Result<Option<EntryMetadata>, Self::MaintenanceError>;
+
+    /// Delete all entries of `url`, returning how many were deleted.
+    #[allow(clippy::missing_errors_doc)]
+    fn invalidate_blocking(&self, url: Url) -> Result<usize, Self::MaintenanceError>;
+
+    /// Delete all entries whose Url starts with `prefix`, returning how many were deleted.
+    #[allow(clippy::missing_errors_doc)]
+    fn invalidate_prefix_blocking(&self, prefix: &str) -> Result<usize, Self::MaintenanceError>;
+
+    /// Delete expired entries and compact the storage, returning how many were deleted.
+    #[allow(clippy::missing_errors_doc)]
+    fn vacuum_blocking(&self) -> Result<usize, Self::MaintenanceError>;
+
+    /// All entries including their values, ordered by Url.
+    #[allow(clippy::missing_errors_doc)]
+    fn export_blocking(&self) -> Result<Vec<CacheEntry>, Self::MaintenanceError>;
+
+    /// Insert `entries`, replacing existing ones, returning how many were inserted.
+    #[allow(clippy::missing_errors_doc)]
+    fn import_blocking(&self, entries: Vec<CacheEntry>) -> Result<usize, Self::MaintenanceError>;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JsonLinesError {
+    #[error("Error reading entries: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid entry in line {line}: {source}")]
+    Json {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// Write one json object per entry and line.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+pub fn write_json_lines<W: Write>(mut writer: W, entries: &[CacheEntry]) -> io::Result<()> {
+    for entry in entries {
+        serde_json::to_writer(&mut writer, entry)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+/// Read entries written by [`write_json_lines`], skipping empty lines.
+///
+/// # Errors
+///
+/// This function will return an error if reading fails, or a line is not a valid entry.
+pub fn read_json_lines<R: BufRead>(reader: R) -> Result<Vec<CacheEntry>, JsonLinesError> {
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|source| JsonLinesError::Json {
+            line: index + 1,
+            source,
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use super::{CacheEntry, EntryValue, JsonLinesError, read_json_lines, write_json_lines};
+
+    #[test]
+    fn json_lines() {
+        let entries = vec![
+            CacheEntry {
+                url: Url::parse("https://tinyurl.com/abc").unwrap(),
+                value: EntryValue::Redirect {
+                    redirect_url: Some(Url::parse("https://f-puzzles.com/?load=N4Ig").unwrap()),
+                },
+                stored_at: 1_000,
+                last_hit: Some(1_010),
+            },
+            CacheEntry {
+                url: Url::parse("https://sudokupad.app/api/puzzle/abc").unwrap(),
+                value: EntryValue::Result {
+                    response_value: "line\nbreak".into(),
+                },
+                stored_at: 1_000,
+                last_hit: None,
+            },
+            CacheEntry {
+                url: Url::parse("https://missing.link/").unwrap(),
+                value: EntryValue::RedirectFailure {
+                    message: "Not found".into(),
+                },
+                stored_at: 1_000,
+                last_hit: None,
+            },
+        ];
+
+        let mut written = Vec::new();
+        write_json_lines(&mut written, &entries).unwrap();
+        let written = String::from_utf8(written).unwrap();
+
+        assert_eq!(written.lines().count(), 3);
+        assert!(written.starts_with(
+            r#"{"url":"https://tinyurl.com/abc","kind":"redirect","redirect_url":"https://f-puzzles.com/?load=N4Ig","stored_at":1000,"last_hit":1010}"#
+        ));
+        assert_eq!(
+            read_json_lines(format!("{written}\n").as_bytes()).unwrap(),
+            entries
+        );
+    }
+
+    #[test]
+    fn json_lines_invalid() {
+        let lines = concat!(
+            r#"{"url":"https://bit.ly/a","kind":"redirect","redirect_url":null,"stored_at":0}"#,
+            "\n",
+            r#"{"url":"https://bit.ly/b","kind":"unknown","stored_at":0}"#,
+        );
+
+        assert!(matches!(
+            read_json_lines(lines.as_bytes()),
+            Err(JsonLinesError::Json { line: 2, .. })
+        ));
+    }
+}
@@ -30,6 +30,20 @@ where
     async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
         self.inner.fetch_result(url).await.map_err(&self.mapper)
     }
+
+    async fn fetch_redirect_url_fresh(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.inner
+            .fetch_redirect_url_fresh(url)
+            .await
+            .map_err(&self.mapper)
+    }
+
+    async fn fetch_result_fresh(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.inner
+            .fetch_result_fresh(url)
+            .await
+            .map_err(&self.mapper)
+    }
 }
 
 impl<F, Map, E> BlockingUrlFetcher for MapErrUrlFetcher<F, Map, E>
@@ -49,6 +63,18 @@ where
     fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
         self.inner.fetch_result_blocking(url).map_err(&self.mapper)
     }
+
+    fn fetch_redirect_url_fresh_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.inner
+            .fetch_redirect_url_fresh_blocking(url)
+            .map_err(&self.mapper)
+    }
+
+    fn fetch_result_fresh_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.inner
+            .fetch_result_fresh_blocking(url)
+            .map_err(&self.mapper)
+    }
 }
 
 pub trait UrlFetcherErrExt: UrlFetcher + Sized {
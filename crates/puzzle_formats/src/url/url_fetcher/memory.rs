@@ -0,0 +1,348 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    sync::{Mutex, PoisonError},
+};
+
+use async_trait::async_trait;
+use url::Url;
+
+use super::{
+    BlockingUrlFetcher, UrlFetcher,
+    cache::{BlockingUrlFetcherCache, CacheError, UrlFetcherCache},
+};
+
+/// Cache keeping values in memory, evicting the least recently used ones above its capacity.
+#[derive(Debug, Default)]
+pub struct MemoryUrlFetcherCache {
+    capacity: Option<usize>,
+    lru: Mutex<Lru>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Redirect(Url),
+    Result(Url),
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Redirect(Option<Url>),
+    Result(Box<str>),
+}
+
+/// Entries with the tick of their last use, and the entries ordered by that tick.
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<Key, (Value, u64)>,
+    order: BTreeMap<u64, Key>,
+    tick: u64,
+}
+
+impl Lru {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, key: &Key) -> Option<Value> {
+        let tick = self.next_tick();
+        let (value, last_used) = self.entries.get_mut(key)?;
+        self.order.remove(last_used);
+        self.order.insert(tick, key.clone());
+        *last_used = tick;
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: Key, value: Value, capacity: Option<usize>) {
+        let tick = self.next_tick();
+        if let Some((_, last_used)) = self.entries.insert(key.clone(), (value, tick)) {
+            self.order.remove(&last_used);
+        }
+        self.order.insert(tick, key);
+
+        while capacity.is_some_and(|capacity| self.entries.len() > capacity) {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+impl MemoryUrlFetcherCache {
+    /// An empty cache without a limit.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty cache holding at most `capacity` entries, at least one.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity.max(1)),
+            lru: Mutex::default(),
+        }
+    }
+
+    /// Number of cached redirects and results.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.lru.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn redirect(&self, url: Url) -> Result<Option<Url>, CacheError<Infallible>> {
+        match self.lock().get(&Key::Redirect(url)) {
+            Some(Value::Redirect(value)) => Ok(value),
+            _ => Err(CacheError::NoCacheValue),
+        }
+    }
+
+    fn result(&self, url: Url) -> Result<Box<str>, CacheError<Infallible>> {
+        match self.lock().get(&Key::Result(url)) {
+            Some(Value::Result(value)) => Ok(value),
+            _ => Err(CacheError::NoCacheValue),
+        }
+    }
+
+    fn insert(&self, key: Key, value: Value) {
+        self.lock().insert(key, value, self.capacity);
+    }
+}
+
+#[async_trait]
+impl UrlFetcher for MemoryUrlFetcherCache {
+    type Error = CacheError<Infallible>;
+
+    async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.redirect(url)
+    }
+
+    async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.result(url)
+    }
+}
+
+#[async_trait]
+impl UrlFetcherCache for MemoryUrlFetcherCache {
+    type FetchError = Infallible;
+    type StoreError = Infallible;
+
+    async fn store_redirect(&self, url: Url, value: Option<Url>) -> Result<(), Self::StoreError> {
+        self.insert(Key::Redirect(url), Value::Redirect(value));
+        Ok(())
+    }
+
+    async fn store_result(&self, url: Url, value: Box<str>) -> Result<(), Self::StoreError> {
+        self.insert(Key::Result(url), Value::Result(value));
+        Ok(())
+    }
+}
+
+impl BlockingUrlFetcher for MemoryUrlFetcherCache {
+    type Error = CacheError<Infallible>;
+
+    fn fetch_redirect_url_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.redirect(url)
+    }
+
+    fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.result(url)
+    }
+}
+
+impl BlockingUrlFetcherCache for MemoryUrlFetcherCache {
+    type FetchError = Infallible;
+    type StoreError = Infallible;
+
+    fn store_redirect_blocking(
+        &self,
+        url: Url,
+        value: Option<Url>,
+    ) -> Result<(), Self::StoreError> {
+        self.insert(Key::Redirect(url), Value::Redirect(value));
+        Ok(())
+    }
+
+    fn store_result_blocking(&self, url: Url, value: Box<str>) -> Result<(), Self::StoreError> {
+        self.insert(Key::Result(url), Value::Result(value));
+        Ok(())
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use url::Url;
+
+    use crate::url::url_fetcher::{
+        BlockingUrlFetcher, UrlFetcher,
+        cache::{
+            BlockingUrlFetcherCache, BlockingUrlFetcherCacheExt, CacheError, ReadOnlyCache,
+            UrlFetcherCacheExt,
+        },
+    };
+
+    use super::MemoryUrlFetcherCache;
+
+    #[test]
+    fn least_recently_used() {
+        let cache = MemoryUrlFetcherCache::with_capacity(2);
+
+        let url1 = Url::parse("https://google.com/").unwrap();
+        let url2 = Url::parse("https://bing.com/").unwrap();
+        let url3 = Url::parse("https://yahoo.com/").unwrap();
+
+        cache
+            .store_redirect_blocking(url1.clone(), Some(url2.clone()))
+            .unwrap();
+        cache
+            .store_result_blocking(url1.clone(), "VALUE".into())
+            .unwrap();
+        assert_eq!(
+            cache.fetch_redirect_url_blocking(url1.clone()).unwrap(),
+            Some(url2.clone())
+        );
+
+        cache.store_redirect_blocking(url3.clone(), None).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert!(matches!(
+            cache.fetch_result_blocking(url1.clone()),
+            Err(CacheError::NoCacheValue)
+        ));
+        assert_eq!(
+            cache.fetch_redirect_url_blocking(url1.clone()).unwrap(),
+            Some(url2.clone())
+        );
+        assert_eq!(cache.fetch_redirect_url_blocking(url3).unwrap(), None);
+    }
+
+    /// Answers every redirect request with a fixed Url.
+    #[derive(Default)]
+    struct CountingFetcher {
+        requests: AtomicUsize,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Unreachable")]
+    struct Unreachable;
+
+    #[async_trait]
+    impl UrlFetcher for CountingFetcher {
+        type Error = Unreachable;
+
+        async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+            self.fetch_redirect_url_blocking(url)
+        }
+
+        async fn fetch_result(&self, _url: Url) -> Result<Box<str>, Self::Error> {
+            Err(Unreachable)
+        }
+    }
+
+    impl BlockingUrlFetcher for CountingFetcher {
+        type Error = Unreachable;
+
+        fn fetch_redirect_url_blocking(&self, _url: Url) -> Result<Option<Url>, Self::Error> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            Ok(Some(Url::parse("https://sudokupad.app/abc").unwrap()))
+        }
+
+        fn fetch_result_blocking(&self, _url: Url) -> Result<Box<str>, Self::Error> {
+            Err(Unreachable)
+        }
+    }
+
+    #[tokio::test]
+    async fn with_cache() {
+        let url = Url::parse("https://bit.ly/a").unwrap();
+
+        let fetcher = CountingFetcher::default().with_cache(MemoryUrlFetcherCache::new());
+        fetcher.fetch_redirect_url(url.clone()).await.unwrap();
+        fetcher.fetch_redirect_url(url.clone()).await.unwrap();
+        let (_, fetcher) = fetcher.into_parts();
+        assert_eq!(fetcher.requests.load(Ordering::Relaxed), 1);
+
+        let fetcher = fetcher.with_cache_blocking(MemoryUrlFetcherCache::new());
+        fetcher.fetch_redirect_url_blocking(url.clone()).unwrap();
+        fetcher.fetch_redirect_url_blocking(url).unwrap();
+        let (_, fetcher) = fetcher.into_parts();
+        assert_eq!(fetcher.requests.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn stacked_caches() {
+        let url = Url::parse("https://bit.ly/a").unwrap();
+
+        let fetcher = CountingFetcher::default()
+            .with_cache(MemoryUrlFetcherCache::new())
+            .with_cache(MemoryUrlFetcherCache::new());
+        fetcher.fetch_redirect_url(url.clone()).await.unwrap();
+
+        // A miss of a fresh outer cache is answered by the inner one
+        let (_, inner) = fetcher.into_parts();
+        let fetcher = inner.with_cache(MemoryUrlFetcherCache::new());
+        fetcher.fetch_redirect_url(url.clone()).await.unwrap();
+        assert_eq!(
+            fetcher.fetcher().fetcher().requests.load(Ordering::Relaxed),
+            1
+        );
+
+        // Only fresh fetches bypass both
+        fetcher.fetch_redirect_url_fresh(url.clone()).await.unwrap();
+        assert_eq!(
+            fetcher.fetcher().fetcher().requests.load(Ordering::Relaxed),
+            2
+        );
+
+        let (_, inner) = fetcher.into_parts();
+        let (_, counting) = inner.into_parts();
+        let fetcher = counting
+            .with_cache_blocking(MemoryUrlFetcherCache::new())
+            .with_cache_blocking(MemoryUrlFetcherCache::new());
+        fetcher.fetch_redirect_url_blocking(url.clone()).unwrap();
+        let (_, inner) = fetcher.into_parts();
+        let fetcher = inner.with_cache_blocking(MemoryUrlFetcherCache::new());
+        fetcher.fetch_redirect_url_blocking(url.clone()).unwrap();
+        assert_eq!(
+            fetcher.fetcher().fetcher().requests.load(Ordering::Relaxed),
+            3
+        );
+        fetcher.fetch_redirect_url_fresh_blocking(url).unwrap();
+        assert_eq!(
+            fetcher.fetcher().fetcher().requests.load(Ordering::Relaxed),
+            4
+        );
+    }
+
+    #[test]
+    fn read_only() {
+        let url = Url::parse("https://bit.ly/a").unwrap();
+        let cached = Url::parse("https://bit.ly/cached").unwrap();
+
+        let cache = MemoryUrlFetcherCache::new();
+        cache.store_redirect_blocking(cached.clone(), None).unwrap();
+
+        let fetcher = CountingFetcher::default().with_cache_blocking(ReadOnlyCache::new(cache));
+        fetcher.fetch_redirect_url_blocking(url.clone()).unwrap();
+        fetcher.fetch_redirect_url_blocking(url).unwrap();
+        fetcher.fetch_redirect_url_blocking(cached).unwrap();
+        let (cache, fetcher) = fetcher.into_parts();
+        assert_eq!(fetcher.requests.load(Ordering::Relaxed), 2);
+        assert_eq!(cache.into_inner().len(), 1);
+    }
+}
@@ -0,0 +1,442 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use url::Url;
+
+use super::{BlockingUrlFetcher, UrlFetcher};
+
+/// Allowed request rate of a host: one request per `interval`, with up to `burst` at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    interval: Duration,
+    burst: u32,
+}
+
+impl RateLimit {
+    /// Allow `requests` requests per `period`, without bursts.
+    #[must_use]
+    pub fn new(requests: u32, period: Duration) -> Self {
+        Self {
+            interval: period / requests.max(1),
+            burst: 1,
+        }
+    }
+
+    #[must_use]
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    /// Allow up to `burst` requests at once, after the host was idle for a while.
+    #[must_use]
+    pub fn with_burst(self, burst: u32) -> Self {
+        Self {
+            burst: burst.max(1),
+            ..self
+        }
+    }
+}
+
+/// Source of time for [`RateLimitedFetcher`], replaceable in tests.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    fn sleep_until_blocking(&self, deadline: Instant);
+}
+
+#[async_trait]
+pub trait AsyncClock: Clock {
+    async fn sleep_until(&self, deadline: Instant);
+}
+
+/// The real time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until_blocking(&self, deadline: Instant) {
+        std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "reqwest"))]
+#[async_trait]
+impl AsyncClock for SystemClock {
+    async fn sleep_until(&self, deadline: Instant) {
+        tokio::time::sleep_until(deadline.into()).await;
+    }
+}
+
+/// A clock only moving when advanced or slept on, sleeping returns immediately.
+///
+/// Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+}
+
+impl ManualClock {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn sleep_until_blocking(&self, deadline: Instant) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        *now = (*now).max(deadline);
+    }
+}
+
+#[async_trait]
+impl AsyncClock for ManualClock {
+    async fn sleep_until(&self, deadline: Instant) {
+        self.sleep_until_blocking(deadline);
+    }
+}
+
+/// Delays requests to stay within the [`RateLimit`] of their host.
+///
+/// Every request reserves the next free slot of its host when it arrives, so waiting
+/// requests are sent in the order they were made.
+pub struct RateLimitedFetcher<F, C = SystemClock> {
+    inner: F,
+    clock: C,
+    default: Option<RateLimit>,
+    hosts: HashMap<Box<str>, RateLimit>,
+    /// Earliest time the next request of each host could be sent, if it had no burst.
+    next_slots: Mutex<HashMap<Box<str>, Instant>>,
+}
+
+impl<F> RateLimitedFetcher<F> {
+    /// Limit all hosts to `default`, unless set otherwise with [`host`](Self::host).
+    pub fn new(inner: F, default: Option<RateLimit>) -> Self {
+        Self::with_clock(inner, default, SystemClock)
+    }
+}
+
+impl<F, C: Clock> RateLimitedFetcher<F, C> {
+    pub fn with_clock(inner: F, default: Option<RateLimit>, clock: C) -> Self {
+        Self {
+            inner,
+            clock,
+            default,
+            hosts: HashMap::new(),
+            next_slots: Mutex::default(),
+        }
+    }
+
+    /// Limit requests to exactly `host`, its subdomains are not affected.
+    #[must_use]
+    pub fn host(mut self, host: &str, limit: RateLimit) -> Self {
+        self.hosts.insert(host.to_ascii_lowercase().into(), limit);
+        self
+    }
+
+    /// Reserve a slot for a request to `url`, `None` if its host is not limited.
+    fn reserve(&self, url: &Url) -> Option<Instant> {
+        let host = url.host_str()?;
+        let limit = self.hosts.get(host).or(self.default.as_ref())?;
+
+        let now = self.clock.now();
+        let mut next_slots = self
+            .next_slots
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let next = next_slots.get(host).map_or(now, |next| (*next).max(now));
+        let tolerance = limit.interval * (limit.burst - 1);
+        let slot = next
+            .checked_sub(tolerance)
+            .map_or(now, |slot| slot.max(now));
+        next_slots.insert(host.into(), next + limit.interval);
+
+        Some(slot)
+    }
+
+    fn wait_blocking(&self, url: &Url) {
+        if let Some(slot) = self.reserve(url)
+            && slot > self.clock.now()
+        {
+            self.clock.sleep_until_blocking(slot);
+        }
+    }
+}
+
+impl<F, C: AsyncClock> RateLimitedFetcher<F, C> {
+    async fn wait(&self, url: &Url) {
+        if let Some(slot) = self.reserve(url)
+            && slot > self.clock.now()
+        {
+            self.clock.sleep_until(slot).await;
+        }
+    }
+}
+
+pub trait RateLimitExt: Sized {
+    fn rate_limited(self, default: Option<RateLimit>) -> RateLimitedFetcher<Self>;
+}
+
+impl<F> RateLimitExt for F {
+    fn rate_limited(self, default: Option<RateLimit>) -> RateLimitedFetcher<Self> {
+        RateLimitedFetcher::new(self, default)
+    }
+}
+
+#[async_trait]
+impl<F, C> UrlFetcher for RateLimitedFetcher<F, C>
+where
+    F: UrlFetcher + Send + Sync,
+    C: AsyncClock,
+{
+    type Error = F::Error;
+
+    async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.wait(&url).await;
+        self.inner.fetch_redirect_url(url).await
+    }
+
+    async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.wait(&url).await;
+        self.inner.fetch_result(url).await
+    }
+
+    async fn fetch_redirect_url_fresh(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.wait(&url).await;
+        self.inner.fetch_redirect_url_fresh(url).await
+    }
+
+    async fn fetch_result_fresh(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.wait(&url).await;
+        self.inner.fetch_result_fresh(url).await
+    }
+}
+
+impl<F, C> BlockingUrlFetcher for RateLimitedFetcher<F, C>
+where
+    F: BlockingUrlFetcher,
+    C: Clock,
+{
+    type Error = F::Error;
+
+    fn fetch_redirect_url_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.wait_blocking(&url);
+        self.inner.fetch_redirect_url_blocking(url)
+    }
+
+    fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.wait_blocking(&url);
+        self.inner.fetch_result_blocking(url)
+    }
+
+    fn fetch_redirect_url_fresh_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.wait_blocking(&url);
+        self.inner.fetch_redirect_url_fresh_blocking(url)
+    }
+
+    fn fetch_result_fresh_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.wait_blocking(&url);
+        self.inner.fetch_result_fresh_blocking(url)
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use std::{
+        convert::Infallible,
+        sync::Mutex,
+        time::{Duration, Instant},
+    };
+
+    use async_trait::async_trait;
+    use futures_util::future::join_all;
+    use url::Url;
+
+    use crate::url::url_fetcher::{BlockingUrlFetcher, UrlFetcher};
+
+    use super::{Clock, ManualClock, RateLimit, RateLimitedFetcher};
+
+    /// Records when each Url was requested.
+    struct Recorder {
+        clock: ManualClock,
+        requests: Mutex<Vec<(String, Instant)>>,
+    }
+
+    impl Recorder {
+        fn new(clock: &ManualClock) -> Self {
+            Self {
+                clock: clock.clone(),
+                requests: Mutex::default(),
+            }
+        }
+
+        fn record(&self, url: &Url) {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push((url.to_string(), self.clock.now()));
+        }
+    }
+
+    impl BlockingUrlFetcher for Recorder {
+        type Error = Infallible;
+
+        fn fetch_redirect_url_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+            self.record(&url);
+            Ok(None)
+        }
+
+        fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+            self.record(&url);
+            Ok("".into())
+        }
+    }
+
+    #[async_trait]
+    impl UrlFetcher for Recorder {
+        type Error = Infallible;
+
+        async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+            self.fetch_redirect_url_blocking(url)
+        }
+
+        async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+            self.fetch_result_blocking(url)
+        }
+    }
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    /// Request offsets from `start` in milliseconds, per Url.
+    fn offsets(
+        fetcher: &RateLimitedFetcher<Recorder, ManualClock>,
+        start: Instant,
+    ) -> Vec<(String, u128)> {
+        let requests = fetcher.inner.requests.lock().unwrap();
+        requests
+            .iter()
+            .map(|(url, time)| (url.clone(), (*time - start).as_millis()))
+            .collect()
+    }
+
+    fn limited(clock: &ManualClock) -> RateLimitedFetcher<Recorder, ManualClock> {
+        RateLimitedFetcher::with_clock(
+            Recorder::new(clock),
+            Some(RateLimit::per_second(10)),
+            clock.clone(),
+        )
+        .host("tinyurl.com", RateLimit::per_second(1).with_burst(2))
+    }
+
+    #[test]
+    fn limit_per_host_blocking() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let fetcher = limited(&clock);
+
+        for _ in 0..4 {
+            fetcher
+                .fetch_redirect_url_blocking(url("https://tinyurl.com/a"))
+                .unwrap();
+        }
+        fetcher
+            .fetch_result_blocking(url("https://sudokupad.app/api/puzzle/x"))
+            .unwrap();
+        fetcher
+            .fetch_result_blocking(url("https://sudokupad.app/api/puzzle/y"))
+            .unwrap();
+
+        let tinyurl = "https://tinyurl.com/a".to_owned();
+        assert_eq!(
+            offsets(&fetcher, start),
+            [
+                (tinyurl.clone(), 0),
+                (tinyurl.clone(), 0),
+                (tinyurl.clone(), 1000),
+                (tinyurl, 2000),
+                ("https://sudokupad.app/api/puzzle/x".to_owned(), 2000),
+                ("https://sudokupad.app/api/puzzle/y".to_owned(), 2100),
+            ]
+        );
+    }
+
+    #[test]
+    fn burst_refills() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let fetcher = limited(&clock);
+
+        for _ in 0..2 {
+            fetcher
+                .fetch_result_blocking(url("https://tinyurl.com/a"))
+                .unwrap();
+        }
+        clock.advance(Duration::from_secs(5));
+        for _ in 0..3 {
+            fetcher
+                .fetch_result_blocking(url("https://tinyurl.com/a"))
+                .unwrap();
+        }
+
+        let offsets: Vec<u128> = offsets(&fetcher, start)
+            .into_iter()
+            .map(|(_, offset)| offset)
+            .collect();
+        assert_eq!(offsets, [0, 0, 5000, 5000, 6000]);
+    }
+
+    #[tokio::test]
+    async fn queue_in_order() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let fetcher = RateLimitedFetcher::with_clock(
+            Recorder::new(&clock),
+            Some(RateLimit::new(2, Duration::from_secs(1))),
+            clock.clone(),
+        );
+
+        let urls: Vec<Url> = (0..4)
+            .map(|i| url(&format!("https://tinyurl.com/{i}")))
+            .collect();
+        join_all(
+            urls.iter()
+                .map(|url| fetcher.fetch_redirect_url(url.clone())),
+        )
+        .await;
+
+        assert_eq!(
+            offsets(&fetcher, start),
+            [
+                ("https://tinyurl.com/0".to_owned(), 0),
+                ("https://tinyurl.com/1".to_owned(), 500),
+                ("https://tinyurl.com/2".to_owned(), 1000),
+                ("https://tinyurl.com/3".to_owned(), 1500),
+            ]
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+use async_trait::async_trait;
+use url::Url;
+
+use super::{
+    BlockingUrlFetcher, UrlFetcher,
+    maintenance::{
+        CacheEntry, EntryKind, EntryValue, JsonLinesError, read_json_lines, unix_now,
+        write_json_lines,
+    },
+};
+
+/// Records every response of the inner fetcher, to be saved as fixtures for a [`ReplayFetcher`].
+///
+/// Failures are recorded too, and replayed as [`ReplayError::RecordedFailure`].
+pub struct RecordingFetcher<F> {
+    inner: F,
+    records: Mutex<BTreeMap<(Url, EntryKind), CacheEntry>>,
+}
+
+impl<F> RecordingFetcher<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            records: Mutex::default(),
+        }
+    }
+
+    /// All recorded entries, ordered by Url, the latest response per request.
+    pub fn entries(&self) -> Vec<CacheEntry> {
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Write the recorded entries as json lines to `path`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can't be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_json_lines(BufWriter::new(File::create(path)?), &self.entries())
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    /// Keep one entry per request, a failure replaces a success and the other way around.
+    fn record(&self, url: &Url, value: EntryValue) {
+        let request = match value.kind() {
+            EntryKind::Redirect | EntryKind::RedirectFailure => EntryKind::Redirect,
+            EntryKind::Result | EntryKind::ResultFailure => EntryKind::Result,
+        };
+        let entry = CacheEntry {
+            url: url.clone(),
+            value,
+            stored_at: unix_now(),
+            last_hit: None,
+        };
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert((url.clone(), request), entry);
+    }
+
+    fn record_redirect<E: ToString>(&self, url: &Url, response: &Result<Option<Url>, E>) {
+        self.record(
+            url,
+            match response {
+                Ok(redirect_url) => EntryValue::Redirect {
+                    redirect_url: redirect_url.clone(),
+                },
+                Err(err) => EntryValue::RedirectFailure {
+                    message: err.to_string().into(),
+                },
+            },
+        );
+    }
+
+    fn record_result<E: ToString>(&self, url: &Url, response: &Result<Box<str>, E>) {
+        self.record(
+            url,
+            match response {
+                Ok(response_value) => EntryValue::Result {
+                    response_value: response_value.clone(),
+                },
+                Err(err) => EntryValue::ResultFailure {
+                    message: err.to_string().into(),
+                },
+            },
+        );
+    }
+}
+
+#[async_trait]
+impl<F: UrlFetcher + Send + Sync> UrlFetcher for RecordingFetcher<F> {
+    type Error = F::Error;
+
+    async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        let response = self.inner.fetch_redirect_url(url.clone()).await;
+        self.record_redirect(&url, &response);
+        response
+    }
+
+    async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        let response = self.inner.fetch_result(url.clone()).await;
+        self.record_result(&url, &response);
+        response
+    }
+
+    async fn fetch_redirect_url_fresh(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        let response = self.inner.fetch_redirect_url_fresh(url.clone()).await;
+        self.record_redirect(&url, &response);
+        response
+    }
+
+    async fn fetch_result_fresh(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        let response = self.inner.fetch_result_fresh(url.clone()).await;
+        self.record_result(&url, &response);
+        response
+    }
+}
+
+impl<F: BlockingUrlFetcher> BlockingUrlFetcher for RecordingFetcher<F> {
+    type Error = F::Error;
+
+    fn fetch_redirect_url_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        let response = self.inner.fetch_redirect_url_blocking(url.clone());
+        self.record_redirect(&url, &response);
+        response
+    }
+
+    fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        let response = self.inner.fetch_result_blocking(url.clone());
+        self.record_result(&url, &response);
+        response
+    }
+
+    fn fetch_redirect_url_fresh_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        let response = self.inner.fetch_redirect_url_fresh_blocking(url.clone());
+        self.record_redirect(&url, &response);
+        response
+    }
+
+    fn fetch_result_fresh_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        let response = self.inner.fetch_result_fresh_blocking(url.clone());
+        self.record_result(&url, &response);
+        response
+    }
+}
+
+/// Serves recorded responses only, without ever touching the network.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayFetcher {
+    redirects: HashMap<Url, Result<Option<Url>, Box<str>>>,
+    results: HashMap<Url, Result<Box<str>, Box<str>>>,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Request not recorded: {} {url}", kind.as_str())]
+    NotRecorded { kind: EntryKind, url: Url },
+    #[error("Recorded failure for {url}: {message}")]
+    RecordedFailure { url: Url, message: Box<str> },
+}
+
+impl ReplayFetcher {
+    /// Replay `entries`, later entries replace earlier ones for the same request.
+    pub fn new(entries: impl IntoIterator<Item = CacheEntry>) -> Self {
+        let mut fetcher = Self::default();
+        for entry in entries {
+            match entry.value {
+                EntryValue::Redirect { redirect_url } => {
+                    fetcher.redirects.insert(entry.url, Ok(redirect_url));
+                }
+                EntryValue::RedirectFailure { message } => {
+                    fetcher.redirects.insert(entry.url, Err(message));
+                }
+                EntryValue::Result { response_value } => {
+                    fetcher.results.insert(entry.url, Ok(response_value));
+                }
+                EntryValue::ResultFailure { message } => {
+                    fetcher.results.insert(entry.url, Err(message));
+                }
+            }
+        }
+        fetcher
+    }
+
+    /// Replay the json lines fixtures at `path`, as written by [`RecordingFetcher::save`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can't be read or contains invalid entries.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, JsonLinesError> {
+        let entries = read_json_lines(BufReader::new(File::open(path)?))?;
+        Ok(Self::new(entries))
+    }
+
+    fn redirect(&self, url: Url) -> Result<Option<Url>, ReplayError> {
+        match self.redirects.get(&url) {
+            Some(Ok(redirect_url)) => Ok(redirect_url.clone()),
+            Some(Err(message)) => Err(ReplayError::RecordedFailure {
+                url,
+                message: message.clone(),
+            }),
+            None => Err(ReplayError::NotRecorded {
+                kind: EntryKind::Redirect,
+                url,
+            }),
+        }
+    }
+
+    fn result(&self, url: Url) -> Result<Box<str>, ReplayError> {
+        match self.results.get(&url) {
+            Some(Ok(response_value)) => Ok(response_value.clone()),
+            Some(Err(message)) => Err(ReplayError::RecordedFailure {
+                url,
+                message: message.clone(),
+            }),
+            None => Err(ReplayError::NotRecorded {
+                kind: EntryKind::Result,
+                url,
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl UrlFetcher for ReplayFetcher {
+    type Error = ReplayError;
+
+    async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.redirect(url)
+    }
+
+    async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.result(url)
+    }
+}
+
+impl BlockingUrlFetcher for ReplayFetcher {
+    type Error = ReplayError;
+
+    fn fetch_redirect_url_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.redirect(url)
+    }
+
+    fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.result(url)
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use crate::url::{
+        ResolutionError, ResolutionOptions, Resolved, UrlValue,
+        url_fetcher::{
+            BlockingUrlFetcher,
+            maintenance::{EntryKind, read_json_lines, write_json_lines},
+        },
+    };
+
+    use super::{RecordingFetcher, ReplayError, ReplayFetcher};
+
+    fn fixtures() -> ReplayFetcher {
+        let fixtures = include_str!("../../../assets/test_urls/replay.jsonl");
+        ReplayFetcher::new(read_json_lines(fixtures.as_bytes()).unwrap())
+    }
+
+    fn resolve(
+        fetcher: &impl BlockingUrlFetcher<Error = ReplayError>,
+        url: &str,
+    ) -> Result<Resolved, ResolutionError<ReplayError>> {
+        let UrlValue::Unresolved(unresolved) = UrlValue::parse(url).unwrap() else {
+            panic!("{url} is already resolved");
+        };
+        unresolved
+            .resolve_blocking(fetcher, &ResolutionOptions::default())
+            .map(|resolution| resolution.resolved().clone())
+    }
+
+    #[test]
+    fn replay() {
+        let fetcher = fixtures();
+
+        let UrlValue::Resolved(expected) =
+            UrlValue::parse("https://sudokupad.app/sclABCDEF").unwrap()
+        else {
+            unreachable!();
+        };
+        assert_eq!(
+            resolve(&fetcher, "https://short.link/x").unwrap(),
+            Resolved::Puzzle(expected)
+        );
+
+        let err = resolve(&fetcher, "https://short.link/unknown").unwrap_err();
+        assert!(matches!(
+            &err,
+            ResolutionError::Fetcher(ReplayError::NotRecorded {
+                kind: EntryKind::Redirect,
+                ..
+            })
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Fetcher Error: Request not recorded: redirect https://short.link/unknown"
+        );
+
+        assert!(matches!(
+            resolve(&fetcher, "https://bit.ly/gone"),
+            Err(ResolutionError::Fetcher(
+                ReplayError::RecordedFailure { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let recorder = RecordingFetcher::new(fixtures());
+        assert!(resolve(&recorder, "https://short.link/x").is_ok());
+        assert!(resolve(&recorder, "https://bit.ly/gone").is_err());
+        assert!(resolve(&recorder, "https://short.link/unknown").is_err());
+
+        let entries = recorder.entries();
+        let urls: Vec<&str> = entries.iter().map(|entry| entry.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://bit.ly/gone",
+                "https://bit.ly/y",
+                "https://short.link/unknown",
+                "https://short.link/x",
+                "https://sudokupad.app/api/puzzle/short",
+            ]
+        );
+
+        let mut saved = Vec::new();
+        write_json_lines(&mut saved, &entries).unwrap();
+        let replay = ReplayFetcher::new(read_json_lines(saved.as_slice()).unwrap());
+
+        assert!(resolve(&replay, "https://short.link/x").is_ok());
+        assert!(matches!(
+            resolve(&replay, "https://short.link/unknown"),
+            Err(ResolutionError::Fetcher(
+                ReplayError::RecordedFailure { .. }
+            ))
+        ));
+        assert!(matches!(
+            replay.fetch_result_blocking(Url::parse("https://bit.ly/y").unwrap()),
+            Err(ReplayError::NotRecorded {
+                kind: EntryKind::Result,
+                ..
+            })
+        ));
+    }
+}
@@ -1,17 +1,51 @@
 #![cfg(any(feature = "reqwest", feature = "reqwest-blocking"))]
-pub struct ReqwestUrlFetcher {}
 
-impl ReqwestUrlFetcher {
-    #[must_use]
-    pub const fn new() -> Self {
-        ReqwestUrlFetcher {}
-    }
+use std::{
+    borrow::Cow,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use reqwest::{
+    StatusCode,
+    header::{HeaderMap, RETRY_AFTER},
+};
+use url::Url;
+
+/// Maximum number of redirects followed when fetching a result.
+const MAX_REDIRECTS: usize = 10;
+
+/// Fetches Urls with long-lived reqwest clients, configured by [`ReqwestUrlFetcherBuilder`].
+///
+/// The clients are created on first use, unless the fetcher is created with
+/// [`ReqwestUrlFetcherBuilder::build`], which reports client errors right away.
+#[derive(Debug)]
+pub struct ReqwestUrlFetcher {
+    settings: ReqwestUrlFetcherBuilder,
+    #[cfg(feature = "reqwest")]
+    clients: Mutex<Option<reqwest_async::Clients>>,
+    /// Separate, so async users never start the runtime of the blocking client.
+    #[cfg(feature = "reqwest-blocking")]
+    blocking_clients: Mutex<Option<reqwest_blocking::Clients>>,
 }
 
-impl Default for ReqwestUrlFetcher {
-    fn default() -> Self {
-        Self::new()
-    }
+#[derive(Debug, Clone)]
+pub struct ReqwestUrlFetcherBuilder {
+    user_agent: Cow<'static, str>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    max_body_size: Option<usize>,
+    retry: RetryPolicy,
+}
+
+/// Retries of requests answered with 429 or 5xx, or failing to connect or time out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further one.
+    pub initial_backoff: Duration,
+    /// Upper bound of a single delay, also for delays requested with `Retry-After`.
+    pub max_backoff: Duration,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -22,96 +56,589 @@ pub enum Error {
     MalformedLocation(#[from] reqwest::header::ToStrError),
     #[error("Location Header is malformed: {0}")]
     InvalidLocationUrl(#[source] url::ParseError),
+    #[error("Request to {url} failed with status {status}")]
+    Status { url: Box<Url>, status: StatusCode },
+    #[error("Response body of {url} is larger than {limit} bytes")]
+    BodyTooLarge { url: Box<Url>, limit: usize },
+    #[error("Error reading response body: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+impl ReqwestUrlFetcher {
+    /// Create a fetcher with the default settings of [`ReqwestUrlFetcherBuilder`].
+    #[must_use]
+    pub const fn new() -> Self {
+        ReqwestUrlFetcherBuilder::new().into_fetcher()
+    }
+
+    #[must_use]
+    pub const fn builder() -> ReqwestUrlFetcherBuilder {
+        ReqwestUrlFetcherBuilder::new()
+    }
+
+    /// The async clients, created on first use.
+    #[cfg(feature = "reqwest")]
+    fn clients(&self) -> Result<reqwest_async::Clients> {
+        let mut clients = self.clients.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(clients) = &*clients {
+            return Ok(clients.clone());
+        }
+        let created = reqwest_async::Clients::new(&self.settings)?;
+        *clients = Some(created.clone());
+        Ok(created)
+    }
+}
+
+impl Default for ReqwestUrlFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for ReqwestUrlFetcherBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReqwestUrlFetcherBuilder {
+    const fn new() -> Self {
+        Self {
+            user_agent: Cow::Borrowed(concat!("puzzle-formats/", env!("CARGO_PKG_VERSION"))),
+            connect_timeout: Some(Duration::from_secs(10)),
+            read_timeout: Some(Duration::from_secs(30)),
+            max_body_size: Some(16 * 1024 * 1024),
+            retry: RetryPolicy::standard(),
+        }
+    }
+
+    /// The fetcher without clients yet.
+    const fn into_fetcher(self) -> ReqwestUrlFetcher {
+        ReqwestUrlFetcher {
+            settings: self,
+            #[cfg(feature = "reqwest")]
+            clients: Mutex::new(None),
+            #[cfg(feature = "reqwest-blocking")]
+            blocking_clients: Mutex::new(None),
+        }
+    }
+
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: impl Into<Cow<'static, str>>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Timeout between two reads of a response.
+    /// The blocking client has no such timeout, it limits the whole request instead.
+    #[must_use]
+    pub fn read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Responses with larger bodies fail with [`Error::BodyTooLarge`].
+    #[must_use]
+    pub fn max_body_size(mut self, limit: Option<usize>) -> Self {
+        self.max_body_size = limit;
+        self
+    }
+
+    #[must_use]
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Create the fetcher and its async client.
+    /// The blocking client is still created on first use.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the client can not be created.
+    pub fn build(self) -> Result<ReqwestUrlFetcher> {
+        let fetcher = self.into_fetcher();
+        #[cfg(feature = "reqwest")]
+        fetcher.clients()?;
+        Ok(fetcher)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl RetryPolicy {
+    const fn standard() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+
+    #[must_use]
+    pub const fn none() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    /// Delay before retrying after `attempt` failed attempts, `None` if no retries are left.
+    fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2_u32.saturating_pow(attempt));
+        Some(retry_after.unwrap_or(backoff).min(self.max_backoff))
+    }
+}
+
+fn is_error_status(status: StatusCode) -> bool {
+    status.is_client_error() || status.is_server_error()
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn is_retryable_error(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout()
+}
+
+/// Only the delay-seconds form of `Retry-After` is supported.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+fn redirect_location(url: &Url, headers: &HeaderMap) -> Result<Option<Url>> {
+    let Some(location) = headers.get(reqwest::header::LOCATION) else {
+        return Ok(None);
+    };
+
+    let url = url
+        .join(location.to_str()?)
+        .map_err(Error::InvalidLocationUrl)?;
+
+    Ok(Some(url))
+}
+
+fn status_error(url: &Url, status: StatusCode) -> Error {
+    Error::Status {
+        url: Box::new(url.clone()),
+        status,
+    }
+}
+
+fn body_too_large(url: &Url, limit: usize) -> Error {
+    Error::BodyTooLarge {
+        url: Box::new(url.clone()),
+        limit,
+    }
+}
+
 #[cfg(feature = "reqwest")]
 mod reqwest_async {
     use crate::url::url_fetcher::UrlFetcher;
     use async_trait::async_trait;
     use url::Url;
 
-    use super::ReqwestUrlFetcher;
+    use super::{
+        MAX_REDIRECTS, ReqwestUrlFetcher, ReqwestUrlFetcherBuilder, body_too_large,
+        is_error_status, is_retryable_error, is_retryable_status, redirect_location, retry_after,
+        status_error,
+    };
 
-    #[async_trait]
-    impl UrlFetcher for ReqwestUrlFetcher {
-        type Error = super::Error;
+    #[derive(Debug, Clone)]
+    pub(super) struct Clients {
+        redirect: reqwest::Client,
+        result: reqwest::Client,
+    }
 
-        async fn fetch_redirect_url(&self, url: Url) -> super::Result<Option<Url>> {
-            let client = reqwest::ClientBuilder::new()
-                .redirect(reqwest::redirect::Policy::none())
-                .build()?;
+    impl Clients {
+        pub(super) fn new(settings: &ReqwestUrlFetcherBuilder) -> super::Result<Self> {
+            let builder = || {
+                let mut builder = reqwest::ClientBuilder::new().user_agent(&*settings.user_agent);
+                if let Some(timeout) = settings.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                if let Some(timeout) = settings.read_timeout {
+                    builder = builder.read_timeout(timeout);
+                }
+                builder
+            };
 
-            let response = client.get(url.clone()).send().await?;
+            Ok(Self {
+                redirect: builder()
+                    .redirect(reqwest::redirect::Policy::none())
+                    .build()?,
+                result: builder()
+                    .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+                    .build()?,
+            })
+        }
+    }
 
-            let location = response.headers().get(reqwest::header::LOCATION);
+    impl ReqwestUrlFetcher {
+        async fn send(
+            &self,
+            client: &reqwest::Client,
+            url: &Url,
+        ) -> super::Result<reqwest::Response> {
+            let retry = self.settings.retry;
+            let mut attempt = 0;
+            loop {
+                let delay = match client.get(url.clone()).send().await {
+                    Ok(response) if is_error_status(response.status()) => {
+                        let status = response.status();
+                        let delay = is_retryable_status(status)
+                            .then(|| retry.delay(attempt, retry_after(response.headers())))
+                            .flatten();
+                        let Some(delay) = delay else {
+                            return Err(status_error(url, status));
+                        };
+                        delay
+                    }
+                    Ok(response) => return Ok(response),
+                    Err(err) => match retry.delay(attempt, None) {
+                        Some(delay) if is_retryable_error(&err) => delay,
+                        _ => return Err(err.into()),
+                    },
+                };
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
 
-            let Some(location) = location else {
-                return Ok(None);
-            };
+    #[async_trait]
+    impl UrlFetcher for ReqwestUrlFetcher {
+        type Error = super::Error;
 
-            let url = url
-                .join(location.to_str()?)
-                .map_err(super::Error::InvalidLocationUrl)?;
+        async fn fetch_redirect_url(&self, url: Url) -> super::Result<Option<Url>> {
+            let response = self.send(&self.clients()?.redirect, &url).await?;
 
-            Ok(Some(url))
+            redirect_location(&url, response.headers())
         }
 
         async fn fetch_result(&self, url: Url) -> super::Result<Box<str>> {
-            let client = reqwest::ClientBuilder::new()
-                .redirect(reqwest::redirect::Policy::limited(10))
-                .build()?;
+            let mut response = self.send(&self.clients()?.result, &url).await?;
+            // Redirects are followed, one left over has no usable location
+            if !response.status().is_success() {
+                return Err(status_error(&url, response.status()));
+            }
 
-            let response = client.get(url).send().await?;
+            let limit = self.settings.max_body_size;
+            if let (Some(limit), Some(length)) = (limit, response.content_length())
+                && length > limit as u64
+            {
+                return Err(body_too_large(&url, limit));
+            }
 
-            let text = response.text().await?;
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await? {
+                body.extend_from_slice(&chunk);
+                if let Some(limit) = limit
+                    && body.len() > limit
+                {
+                    return Err(body_too_large(&url, limit));
+                }
+            }
 
-            Ok(text.into_boxed_str())
+            Ok(String::from_utf8_lossy(&body).into())
         }
     }
 }
 
 #[cfg(feature = "reqwest-blocking")]
 mod reqwest_blocking {
+    use std::{io::Read, sync::PoisonError};
+
     use crate::url::url_fetcher::BlockingUrlFetcher;
     use url::Url;
 
-    use super::ReqwestUrlFetcher;
+    use super::{
+        MAX_REDIRECTS, ReqwestUrlFetcher, ReqwestUrlFetcherBuilder, body_too_large,
+        is_error_status, is_retryable_error, is_retryable_status, redirect_location, retry_after,
+        status_error,
+    };
+
+    #[derive(Debug, Clone)]
+    pub(super) struct Clients {
+        redirect: reqwest::blocking::Client,
+        result: reqwest::blocking::Client,
+    }
+
+    impl Clients {
+        fn new(settings: &ReqwestUrlFetcherBuilder) -> super::Result<Self> {
+            let builder = || {
+                reqwest::blocking::ClientBuilder::new()
+                    .user_agent(&*settings.user_agent)
+                    .connect_timeout(settings.connect_timeout)
+                    .timeout(settings.read_timeout)
+            };
+
+            Ok(Self {
+                redirect: builder()
+                    .redirect(reqwest::redirect::Policy::none())
+                    .build()?,
+                result: builder()
+                    .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+                    .build()?,
+            })
+        }
+    }
+
+    impl ReqwestUrlFetcher {
+        fn blocking_clients(&self) -> super::Result<Clients> {
+            let mut clients = self
+                .blocking_clients
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if let Some(clients) = &*clients {
+                return Ok(clients.clone());
+            }
+            let created = Clients::new(&self.settings)?;
+            *clients = Some(created.clone());
+            Ok(created)
+        }
+
+        fn send_blocking(
+            &self,
+            client: &reqwest::blocking::Client,
+            url: &Url,
+        ) -> super::Result<reqwest::blocking::Response> {
+            let retry = self.settings.retry;
+            let mut attempt = 0;
+            loop {
+                let delay = match client.get(url.clone()).send() {
+                    Ok(response) if is_error_status(response.status()) => {
+                        let status = response.status();
+                        let delay = is_retryable_status(status)
+                            .then(|| retry.delay(attempt, retry_after(response.headers())))
+                            .flatten();
+                        let Some(delay) = delay else {
+                            return Err(status_error(url, status));
+                        };
+                        delay
+                    }
+                    Ok(response) => return Ok(response),
+                    Err(err) => match retry.delay(attempt, None) {
+                        Some(delay) if is_retryable_error(&err) => delay,
+                        _ => return Err(err.into()),
+                    },
+                };
+                std::thread::sleep(delay);
+                attempt += 1;
+            }
+        }
+    }
 
     impl BlockingUrlFetcher for ReqwestUrlFetcher {
         type Error = super::Error;
 
         fn fetch_redirect_url_blocking(&self, url: Url) -> super::Result<Option<Url>> {
-            let client = reqwest::blocking::ClientBuilder::new()
-                .redirect(reqwest::redirect::Policy::none())
-                .build()?;
+            let response = self.send_blocking(&self.blocking_clients()?.redirect, &url)?;
 
-            let response = client.get(url.clone()).send()?;
-
-            let location = response.headers().get(reqwest::header::LOCATION);
+            redirect_location(&url, response.headers())
+        }
 
-            let Some(location) = location else {
-                return Ok(None);
-            };
+        fn fetch_result_blocking(&self, url: Url) -> super::Result<Box<str>> {
+            let response = self.send_blocking(&self.blocking_clients()?.result, &url)?;
+            // Redirects are followed, one left over has no usable location
+            if !response.status().is_success() {
+                return Err(status_error(&url, response.status()));
+            }
 
-            let url = url
-                .join(location.to_str()?)
-                .map_err(super::Error::InvalidLocationUrl)?;
+            let mut body = Vec::new();
+            match self.settings.max_body_size {
+                Some(limit) => {
+                    response.take(limit as u64 + 1).read_to_end(&mut body)?;
+                    if body.len() > limit {
+                        return Err(body_too_large(&url, limit));
+                    }
+                }
+                None => {
+                    response.take(u64::MAX).read_to_end(&mut body)?;
+                }
+            }
 
-            Ok(Some(url))
+            Ok(String::from_utf8_lossy(&body).into())
         }
+    }
+}
 
-        fn fetch_result_blocking(&self, url: Url) -> super::Result<Box<str>> {
-            let client = reqwest::blocking::ClientBuilder::new()
-                .redirect(reqwest::redirect::Policy::limited(10))
-                .build()?;
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread::JoinHandle,
+        time::Duration,
+    };
 
-            let response = client.get(url).send()?;
+    use url::Url;
 
-            let text = response.text()?;
+    use crate::url::url_fetcher::UrlFetcher;
 
-            Ok(text.into_boxed_str())
-        }
+    use super::{Error, ReqwestUrlFetcher, RetryPolicy};
+
+    /// Answer one connection per response, returning the received request heads.
+    fn serve(responses: &[&str]) -> (Url, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let responses: Vec<String> = responses.iter().map(ToString::to_string).collect();
+
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    request.push_str(&line);
+                }
+                requests.push(request);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    fn fetcher(max_retries: u32) -> ReqwestUrlFetcher {
+        ReqwestUrlFetcher::builder()
+            .user_agent("puzzle-test")
+            .max_body_size(Some(16))
+            .retry(RetryPolicy {
+                max_retries,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(10),
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn retry_transient_errors() {
+        let (url, server) = serve(&[
+            &response("503 Service Unavailable", "Retry-After: 0\r\n", ""),
+            &response("429 Too Many Requests", "", ""),
+            &response("200 OK", "", "sclABC"),
+        ]);
+
+        let value = fetcher(2).fetch_result(url).await.unwrap();
+        assert_eq!(&*value, "sclABC");
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.to_lowercase().contains("user-agent: puzzle-test"))
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_exhausted() {
+        let (url, server) = serve(&[
+            &response("500 Internal Server Error", "", ""),
+            &response("502 Bad Gateway", "", ""),
+        ]);
+
+        let err = fetcher(1).fetch_redirect_url(url).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Status { status, .. } if status.as_u16() == 502),
+            "{err}"
+        );
+        server.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn error_status() {
+        let (url, server) = serve(&[
+            &response("404 Not Found", "", "missing"),
+            &response("410 Gone", "", ""),
+        ]);
+
+        // Not retried, and not taken as the result
+        let fetcher = ReqwestUrlFetcher::new();
+        let err = fetcher.fetch_result(url.clone()).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Status { status, .. } if status.as_u16() == 404),
+            "{err}"
+        );
+        let err = fetcher.fetch_redirect_url(url).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Status { status, .. } if status.as_u16() == 410),
+            "{err}"
+        );
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn redirect_and_body_limit() {
+        let (url, server) = serve(&[
+            &response("302 Found", "Location: /target\r\n", ""),
+            &response("200 OK", "", "a body longer than sixteen bytes"),
+        ]);
+
+        let fetcher = fetcher(0);
+        let redirect = fetcher.fetch_redirect_url(url.clone()).await.unwrap();
+        assert_eq!(redirect, Some(url.join("/target").unwrap()));
+
+        let err = fetcher.fetch_result(url).await.unwrap_err();
+        assert!(
+            matches!(err, Error::BodyTooLarge { limit: 16, .. }),
+            "{err}"
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn backoff() {
+        let retry = RetryPolicy {
+            max_retries: 4,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+
+        assert_eq!(retry.delay(0, None), Some(Duration::from_millis(100)));
+        assert_eq!(retry.delay(2, None), Some(Duration::from_millis(400)));
+        assert_eq!(retry.delay(3, None), Some(Duration::from_millis(500)));
+        assert_eq!(
+            retry.delay(1, Some(Duration::from_mins(1))),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(retry.delay(4, None), None);
+        assert_eq!(RetryPolicy::none().delay(0, None), None);
     }
 }
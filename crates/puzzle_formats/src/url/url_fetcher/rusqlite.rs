@@ -1,17 +1,104 @@
 #![cfg(any(feature = "rusqlite", feature = "tokio-rusqlite"))]
 
+use std::time::Duration;
+
 use rusqlite::OptionalExtension;
 use url::Url;
 
+use super::maintenance::unix_now;
+
 mod rusqlite_async;
 mod rusqlite_blocking;
+mod rusqlite_maintenance;
 
 #[cfg(feature = "tokio-rusqlite")]
 pub use rusqlite_async::{FetchError, RusqliteUrlFetcherCache};
 #[cfg(feature = "rusqlite")]
 pub use rusqlite_blocking::{BlockingFetchError, RusqliteBlockingUrlFetcherCache};
 
+/// Version of the table layout, stored as `user_version` of the database.
+const SCHEMA_VERSION: i32 = 1;
+
+/// How long to wait for other processes sharing the database file.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// When cached entries expire, and how many are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Maximum age of cached redirects, kept forever if `None`.
+    pub redirect_ttl: Option<Duration>,
+    /// Maximum age of cached results, kept forever if `None`.
+    pub result_ttl: Option<Duration>,
+    /// How long failed fetches are remembered, failures are not cached if `None`.
+    pub failure_ttl: Option<Duration>,
+    /// Maximum number of entries per table, the least recently used ones are evicted first.
+    /// Remembered failures are limited the same way, oldest first.
+    pub max_entries: Option<usize>,
+}
+
+impl Default for CachePolicy {
+    /// Redirects are kept forever, results for a week and failures are not cached.
+    fn default() -> Self {
+        Self {
+            redirect_ttl: None,
+            result_ttl: Some(Duration::from_hours(24 * 7)),
+            failure_ttl: None,
+            max_entries: None,
+        }
+    }
+}
+
+impl CachePolicy {
+    /// Never expire or evict anything, and don't cache failures.
+    #[must_use]
+    pub fn forever() -> Self {
+        Self {
+            redirect_ttl: None,
+            result_ttl: None,
+            failure_ttl: None,
+            max_entries: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Table {
+    Redirect,
+    Result,
+}
+
+impl Table {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Redirect => "redirect_cache",
+            Self::Result => "result_cache",
+        }
+    }
+
+    /// Kind of a `failure_cache` entry.
+    fn kind(self) -> &'static str {
+        match self {
+            Self::Redirect => "redirect",
+            Self::Result => "result",
+        }
+    }
+
+    fn ttl(self, policy: &CachePolicy) -> Option<Duration> {
+        match self {
+            Self::Redirect => policy.redirect_ttl,
+            Self::Result => policy.result_ttl,
+        }
+    }
+}
+
+fn is_expired(stored_at: i64, ttl: Option<Duration>, now: i64) -> bool {
+    ttl.is_some_and(|ttl| {
+        now.saturating_sub(stored_at) > i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX)
+    })
+}
+
 fn create_tables(connection: &rusqlite::Connection) -> rusqlite::Result<()> {
+    connection.busy_timeout(BUSY_TIMEOUT)?;
     connection.execute(
         "CREATE TABLE IF NOT EXISTS redirect_cache (
                 request_url     TEXT NOT NULL PRIMARY KEY,
@@ -27,29 +114,188 @@ fn create_tables(connection: &rusqlite::Connection) -> rusqlite::Result<()> {
         (),
     )?;
 
-    Ok(())
+    migrate(connection)
+}
+
+/// Bring tables created by older versions up to [`SCHEMA_VERSION`].
+///
+/// New databases start out with the original tables, so they take the same path.
+/// Entries from before timestamps existed count as stored when they are migrated,
+/// so they expire one TTL after the upgrade instead of right away.
+///
+/// The version is read inside a write transaction, so concurrent processes migrate only once.
+fn migrate(connection: &rusqlite::Connection) -> rusqlite::Result<()> {
+    let user_version = |connection: &rusqlite::Connection| -> rusqlite::Result<i32> {
+        connection.pragma_query_value(None, "user_version", |row| row.get(0))
+    };
+    if user_version(connection)? >= SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let transaction =
+        rusqlite::Transaction::new_unchecked(connection, rusqlite::TransactionBehavior::Immediate)?;
+    let version = user_version(&transaction)?;
+    if version >= SCHEMA_VERSION {
+        return Ok(());
+    }
+    if version < 1 {
+        transaction.execute_batch(
+            "ALTER TABLE redirect_cache ADD COLUMN stored_at INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE redirect_cache ADD COLUMN last_hit INTEGER;
+            ALTER TABLE result_cache ADD COLUMN stored_at INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE result_cache ADD COLUMN last_hit INTEGER;
+            CREATE TABLE failure_cache (
+                request_url     TEXT NOT NULL,
+                kind            TEXT NOT NULL,
+                message         TEXT NOT NULL,
+                stored_at       INTEGER NOT NULL,
+                PRIMARY KEY (request_url, kind)
+            );",
+        )?;
+        let now = unix_now();
+        transaction.execute("UPDATE redirect_cache SET stored_at = ?1", [now])?;
+        transaction.execute("UPDATE result_cache SET stored_at = ?1", [now])?;
+    }
+    transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    transaction.commit()
 }
 
 fn store_redirect(
     connection: &rusqlite::Connection,
     url: &url::Url,
     value: Option<&url::Url>,
+    now: i64,
+    policy: &CachePolicy,
 ) -> rusqlite::Result<()> {
     connection.execute(
-        "INSERT OR REPLACE INTO redirect_cache (request_url, redirect_url) VALUES (?1, ?2)",
-        (url.to_string(), value.map(url::Url::to_string)),
+        "INSERT OR REPLACE INTO redirect_cache (request_url, redirect_url, stored_at, last_hit)
+            VALUES (?1, ?2, ?3, NULL)",
+        (url.to_string(), value.map(url::Url::to_string), now),
     )?;
-    Ok(())
+    clear_failure(connection, url, Table::Redirect)?;
+    evict(connection, Table::Redirect, policy.max_entries)
 }
 
 fn store_result(
     connection: &rusqlite::Connection,
     url: &url::Url,
     value: &str,
+    now: i64,
+    policy: &CachePolicy,
+) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO result_cache (request_url, response_value, stored_at, last_hit)
+            VALUES (?1, ?2, ?3, NULL)",
+        (url.to_string(), value.to_string(), now),
+    )?;
+    clear_failure(connection, url, Table::Result)?;
+    evict(connection, Table::Result, policy.max_entries)
+}
+
+/// Remember a failed fetch, does nothing without a failure TTL.
+fn store_failure(
+    connection: &rusqlite::Connection,
+    url: &url::Url,
+    table: Table,
+    message: &str,
+    now: i64,
+    policy: &CachePolicy,
+) -> rusqlite::Result<()> {
+    if policy.failure_ttl.is_none() {
+        return Ok(());
+    }
+    connection.execute(
+        "INSERT OR REPLACE INTO failure_cache (request_url, kind, message, stored_at)
+            VALUES (?1, ?2, ?3, ?4)",
+        (url.to_string(), table.kind(), message, now),
+    )?;
+    evict_failures(connection, now, policy)
+}
+
+/// Delete expired failures, then the oldest ones above `max_entries`.
+fn evict_failures(
+    connection: &rusqlite::Connection,
+    now: i64,
+    policy: &CachePolicy,
+) -> rusqlite::Result<()> {
+    if let Some(ttl) = policy.failure_ttl {
+        let cutoff = now.saturating_sub(i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX));
+        connection.execute("DELETE FROM failure_cache WHERE stored_at < ?1", (cutoff,))?;
+    }
+    let Some(max_entries) = policy.max_entries else {
+        return Ok(());
+    };
+
+    let count: usize =
+        connection.query_row("SELECT COUNT(*) FROM failure_cache", (), |row| row.get(0))?;
+    if count <= max_entries {
+        return Ok(());
+    }
+    connection.execute(
+        "DELETE FROM failure_cache WHERE rowid IN (
+            SELECT rowid FROM failure_cache ORDER BY stored_at ASC LIMIT ?1
+        )",
+        (count - max_entries,),
+    )?;
+    Ok(())
+}
+
+fn clear_failure(
+    connection: &rusqlite::Connection,
+    url: &url::Url,
+    table: Table,
+) -> rusqlite::Result<()> {
+    connection.execute(
+        "DELETE FROM failure_cache WHERE request_url=?1 AND kind=?2",
+        (url.to_string(), table.kind()),
+    )?;
+    Ok(())
+}
+
+/// Delete the least recently used entries of `table` above `max_entries`.
+fn evict(
+    connection: &rusqlite::Connection,
+    table: Table,
+    max_entries: Option<usize>,
+) -> rusqlite::Result<()> {
+    let Some(max_entries) = max_entries else {
+        return Ok(());
+    };
+    let name = table.name();
+
+    let count: usize =
+        connection.query_row(&format!("SELECT COUNT(*) FROM {name}"), (), |row| {
+            row.get(0)
+        })?;
+    if count <= max_entries {
+        return Ok(());
+    }
+
+    connection.execute(
+        &format!(
+            "DELETE FROM {name} WHERE request_url IN (
+                SELECT request_url FROM {name}
+                ORDER BY COALESCE(last_hit, stored_at) ASC, stored_at ASC
+                LIMIT ?1
+            )"
+        ),
+        (count - max_entries,),
+    )?;
+    Ok(())
+}
+
+fn touch(
+    connection: &rusqlite::Connection,
+    url: &url::Url,
+    table: Table,
+    now: i64,
 ) -> rusqlite::Result<()> {
     connection.execute(
-        "INSERT OR REPLACE INTO result_cache (request_url, response_value) VALUES (?1, ?2)",
-        (url.to_string(), value.to_string()),
+        &format!(
+            "UPDATE {} SET last_hit=?2 WHERE request_url=?1",
+            table.name()
+        ),
+        (url.to_string(), now),
     )?;
     Ok(())
 }
@@ -57,58 +303,99 @@ fn store_result(
 #[derive(Debug, Clone)]
 enum FetchReturn<T> {
     Found(T),
+    /// Fetching failed recently, with this message.
+    Failed(Box<str>),
     NotThere,
 }
 
-impl<T> FetchReturn<T> {
-    fn from_option(option: Option<T>) -> Self {
-        match option {
-            Some(v) => Self::Found(v),
-            None => Self::NotThere,
-        }
+/// Message of a remembered failure, if it has not expired yet.
+fn fetch_failure(
+    connection: &rusqlite::Connection,
+    url: &url::Url,
+    table: Table,
+    now: i64,
+    policy: &CachePolicy,
+) -> rusqlite::Result<Option<Box<str>>> {
+    if policy.failure_ttl.is_none() {
+        return Ok(None);
     }
 
-    fn into_option(self) -> Option<T> {
-        match self {
-            Self::Found(v) => Some(v),
-            Self::NotThere => None,
+    let failure: Option<(String, i64)> = connection
+        .query_row(
+            "SELECT message, stored_at FROM failure_cache WHERE request_url=?1 AND kind=?2",
+            (url.to_string(), table.kind()),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    Ok(failure
+        .filter(|(_, stored_at)| !is_expired(*stored_at, policy.failure_ttl, now))
+        .map(|(message, _)| message.into_boxed_str()))
+}
+
+/// Expired and missing values fall back to a remembered failure.
+fn fetch_value<T>(
+    connection: &rusqlite::Connection,
+    url: &url::Url,
+    table: Table,
+    value: Option<(T, i64)>,
+    now: i64,
+    policy: &CachePolicy,
+) -> rusqlite::Result<FetchReturn<T>> {
+    match value {
+        Some((value, stored_at)) if !is_expired(stored_at, table.ttl(policy), now) => {
+            touch(connection, url, table, now)?;
+            Ok(FetchReturn::Found(value))
         }
+        _ => Ok(fetch_failure(connection, url, table, now, policy)?
+            .map_or(FetchReturn::NotThere, FetchReturn::Failed)),
     }
 }
 
 fn fetch_redirect_url(
     connection: &rusqlite::Connection,
     url: &url::Url,
+    now: i64,
+    policy: &CachePolicy,
 ) -> rusqlite::Result<Result<FetchReturn<Option<url::Url>>, url::ParseError>> {
-    let mut statement =
-        connection.prepare("SELECT redirect_url FROM redirect_cache WHERE request_url=?1")?;
+    let mut statement = connection
+        .prepare("SELECT redirect_url, stored_at FROM redirect_cache WHERE request_url=?1")?;
 
-    let redirect_url: Option<Option<String>> = statement
-        .query_row((url.to_string(),), |row| row.get(0))
+    let redirect_url: Option<(Option<String>, i64)> = statement
+        .query_row((url.to_string(),), |row| Ok((row.get(0)?, row.get(1)?)))
         .optional()?;
 
-    let fetch_return = match redirect_url {
-        Some(Some(url_string)) => Url::parse(&url_string).map(|url| FetchReturn::Found(Some(url))),
-        Some(None) => Ok(FetchReturn::Found(None)),
-        None => Ok(FetchReturn::NotThere),
+    let redirect_url = match redirect_url {
+        Some((Some(url_string), stored_at)) => match Url::parse(&url_string) {
+            Ok(url) => Some((Some(url), stored_at)),
+            Err(err) => return Ok(Err(err)),
+        },
+        Some((None, stored_at)) => Some((None, stored_at)),
+        None => None,
     };
 
-    Ok(fetch_return)
+    fetch_value(connection, url, Table::Redirect, redirect_url, now, policy).map(Ok)
 }
 
 fn fetch_result(
     connection: &rusqlite::Connection,
     url: &url::Url,
+    now: i64,
+    policy: &CachePolicy,
 ) -> rusqlite::Result<FetchReturn<Box<str>>> {
-    let mut statement =
-        connection.prepare("SELECT response_value FROM result_cache WHERE request_url=?1")?;
+    let mut statement = connection
+        .prepare("SELECT response_value, stored_at FROM result_cache WHERE request_url=?1")?;
 
-    let redirect_url: Option<String> = statement
-        .query_row((url.to_string(),), |row| row.get(0))
+    let response_value: Option<(String, i64)> = statement
+        .query_row((url.to_string(),), |row| Ok((row.get(0)?, row.get(1)?)))
         .optional()?;
 
-    let fetch_return =
-        FetchReturn::from_option(redirect_url.map(std::string::String::into_boxed_str));
-
-    Ok(fetch_return)
+    fetch_value(
+        connection,
+        url,
+        Table::Result,
+        response_value.map(|(value, stored_at)| (value.into_boxed_str(), stored_at)),
+        now,
+        policy,
+    )
 }
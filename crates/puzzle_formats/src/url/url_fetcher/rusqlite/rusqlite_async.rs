@@ -1,13 +1,18 @@
 #![cfg(feature = "tokio-rusqlite")]
 
+use std::path::Path;
+
 use async_trait::async_trait;
 
+use url::Url;
+
 use crate::url::url_fetcher::{
     UrlFetcher,
     cache::{CacheError, UrlFetcherCache},
+    maintenance::{CacheEntry, CacheMaintenance, EntryKind, EntryMetadata},
 };
 
-use super::FetchReturn;
+use super::{CachePolicy, FetchReturn, Table, rusqlite_maintenance};
 
 #[derive(Debug, thiserror::Error)]
 pub enum FetchError {
@@ -19,23 +24,72 @@ pub enum FetchError {
 
 pub struct RusqliteUrlFetcherCache {
     connection: tokio_rusqlite::Connection,
+    policy: CachePolicy,
+    now: fn() -> i64,
 }
 
 impl RusqliteUrlFetcherCache {
-    /// Create a new Instance.
-    /// This will also create database tables via the connection.
+    /// Create a new Instance with the default [`CachePolicy`].
+    /// This will also create or migrate database tables via the connection.
     ///
     /// # Errors
     ///
     /// This function will return an error if an error occurs during table creation.
     pub async fn new(connection: tokio_rusqlite::Connection) -> tokio_rusqlite::Result<Self> {
+        Self::with_policy(connection, CachePolicy::default()).await
+    }
+
+    /// Create a new Instance, expiring and evicting entries according to `policy`.
+    /// This will also create or migrate database tables via the connection.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if an error occurs during table creation.
+    pub async fn with_policy(
+        connection: tokio_rusqlite::Connection,
+        policy: CachePolicy,
+    ) -> tokio_rusqlite::Result<Self> {
         connection
             .call(|connection| {
                 super::create_tables(connection)?;
                 Ok(())
             })
             .await?;
-        Ok(RusqliteUrlFetcherCache { connection })
+        Ok(RusqliteUrlFetcherCache {
+            connection,
+            policy,
+            now: super::unix_now,
+        })
+    }
+
+    /// Open the database file at `path`, creating it if necessary.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can't be opened, or table creation fails.
+    pub async fn open(path: impl AsRef<Path>, policy: CachePolicy) -> tokio_rusqlite::Result<Self> {
+        let connection = tokio_rusqlite::Connection::open(path).await?;
+        Self::with_policy(connection, policy).await
+    }
+
+    #[must_use]
+    pub fn policy(&self) -> &CachePolicy {
+        &self.policy
+    }
+
+    async fn store_failure(
+        &self,
+        url: url::Url,
+        table: Table,
+        message: Box<str>,
+    ) -> tokio_rusqlite::Result<()> {
+        let (now, policy) = ((self.now)(), self.policy);
+        self.connection
+            .call(move |connection| {
+                super::store_failure(connection, &url, table, &message, now, &policy)?;
+                Ok(())
+            })
+            .await
     }
 }
 
@@ -49,22 +103,40 @@ impl UrlFetcherCache for RusqliteUrlFetcherCache {
         url: url::Url,
         value: Option<url::Url>,
     ) -> Result<(), Self::StoreError> {
+        let (now, policy) = ((self.now)(), self.policy);
         self.connection
             .call(move |connection| {
-                super::store_redirect(connection, &url, value.as_ref())?;
+                super::store_redirect(connection, &url, value.as_ref(), now, &policy)?;
                 Ok(())
             })
             .await
     }
 
     async fn store_result(&self, url: url::Url, value: Box<str>) -> Result<(), Self::StoreError> {
+        let (now, policy) = ((self.now)(), self.policy);
         self.connection
             .call(move |connection| {
-                super::store_result(connection, &url, value.as_ref())?;
+                super::store_result(connection, &url, value.as_ref(), now, &policy)?;
                 Ok(())
             })
             .await
     }
+
+    async fn store_redirect_failure(
+        &self,
+        url: url::Url,
+        message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        self.store_failure(url, Table::Redirect, message).await
+    }
+
+    async fn store_result_failure(
+        &self,
+        url: url::Url,
+        message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        self.store_failure(url, Table::Result, message).await
+    }
 }
 
 #[async_trait]
@@ -72,16 +144,18 @@ impl UrlFetcher for RusqliteUrlFetcherCache {
     type Error = CacheError<FetchError>;
 
     async fn fetch_redirect_url(&self, url: url::Url) -> Result<Option<url::Url>, Self::Error> {
+        let (now, policy) = ((self.now)(), self.policy);
         let result = self
             .connection
             .call(move |connection| {
-                let fetch_return = super::fetch_redirect_url(connection, &url)?;
+                let fetch_return = super::fetch_redirect_url(connection, &url, now, &policy)?;
                 Ok(fetch_return)
             })
             .await;
 
         match result {
             Ok(Ok(FetchReturn::Found(value))) => Ok(value),
+            Ok(Ok(FetchReturn::Failed(message))) => Err(CacheError::CachedFailure(message)),
             Ok(Ok(FetchReturn::NotThere)) => Err(CacheError::NoCacheValue),
             Ok(Err(err)) => Err(CacheError::CacheFetchError(Box::new(
                 FetchError::InvalidUrlError(err),
@@ -93,16 +167,18 @@ impl UrlFetcher for RusqliteUrlFetcherCache {
     }
 
     async fn fetch_result(&self, url: url::Url) -> Result<Box<str>, Self::Error> {
+        let (now, policy) = ((self.now)(), self.policy);
         let result = self
             .connection
             .call(move |connection| {
-                let fetch_return = super::fetch_result(connection, &url)?;
+                let fetch_return = super::fetch_result(connection, &url, now, &policy)?;
                 Ok(fetch_return)
             })
             .await;
 
         match result {
             Ok(FetchReturn::Found(value)) => Ok(value),
+            Ok(FetchReturn::Failed(message)) => Err(CacheError::CachedFailure(message)),
             Ok(FetchReturn::NotThere) => Err(CacheError::NoCacheValue),
             Err(err) => Err(CacheError::CacheFetchError(Box::new(FetchError::SQLError(
                 err,
@@ -111,15 +187,97 @@ impl UrlFetcher for RusqliteUrlFetcherCache {
     }
 }
 
+#[async_trait]
+impl CacheMaintenance for RusqliteUrlFetcherCache {
+    type MaintenanceError = FetchError;
+
+    async fn entries(&self) -> Result<Vec<EntryMetadata>, Self::MaintenanceError> {
+        let rows = self
+            .connection
+            .call(|connection| Ok(rusqlite_maintenance::entries(connection)?))
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(rusqlite_maintenance::MetadataRow::into_metadata)
+            .collect::<Result<_, _>>()?)
+    }
+
+    async fn entry(
+        &self,
+        url: Url,
+        kind: EntryKind,
+    ) -> Result<Option<EntryMetadata>, Self::MaintenanceError> {
+        let row = self
+            .connection
+            .call(move |connection| Ok(rusqlite_maintenance::entry(connection, &url, kind)?))
+            .await?;
+        Ok(row
+            .map(rusqlite_maintenance::MetadataRow::into_metadata)
+            .transpose()?)
+    }
+
+    async fn invalidate(&self, url: Url) -> Result<usize, Self::MaintenanceError> {
+        Ok(self
+            .connection
+            .call(move |connection| Ok(rusqlite_maintenance::invalidate(connection, &url)?))
+            .await?)
+    }
+
+    async fn invalidate_prefix(&self, prefix: &str) -> Result<usize, Self::MaintenanceError> {
+        let prefix = prefix.to_owned();
+        Ok(self
+            .connection
+            .call(move |connection| {
+                Ok(rusqlite_maintenance::invalidate_prefix(
+                    connection, &prefix,
+                )?)
+            })
+            .await?)
+    }
+
+    async fn vacuum(&self) -> Result<usize, Self::MaintenanceError> {
+        let (now, policy) = ((self.now)(), self.policy);
+        Ok(self
+            .connection
+            .call(move |connection| Ok(rusqlite_maintenance::vacuum(connection, now, &policy)?))
+            .await?)
+    }
+
+    async fn export(&self) -> Result<Vec<CacheEntry>, Self::MaintenanceError> {
+        let rows = self
+            .connection
+            .call(|connection| Ok(rusqlite_maintenance::export(connection)?))
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(rusqlite_maintenance::EntryRow::into_entry)
+            .collect::<Result<_, _>>()?)
+    }
+
+    async fn import(&self, entries: Vec<CacheEntry>) -> Result<usize, Self::MaintenanceError> {
+        let policy = self.policy;
+        Ok(self
+            .connection
+            .call(move |connection| {
+                Ok(rusqlite_maintenance::import(connection, &entries, &policy)?)
+            })
+            .await?)
+    }
+}
+
 #[allow(clippy::unwrap_used, clippy::expect_used)]
 #[cfg(test)]
 mod test {
+    use std::time::Duration;
+
     use tokio_rusqlite::Connection;
     use url::Url;
 
     use crate::url::url_fetcher::{
         UrlFetcher,
         cache::{CacheError, UrlFetcherCache},
+        maintenance::{CacheMaintenance, EntryKind},
+        rusqlite::CachePolicy,
     };
 
     use super::RusqliteUrlFetcherCache;
@@ -263,4 +421,88 @@ mod test {
         );
         assert_eq!(cache.fetch_result(url1.clone()).await.unwrap(), val1.into());
     }
+
+    #[tokio::test]
+    async fn expiry_and_failures() {
+        let c = Connection::open_in_memory().await.unwrap();
+        let mut cache = RusqliteUrlFetcherCache::with_policy(
+            c,
+            CachePolicy {
+                result_ttl: Some(Duration::from_secs(10)),
+                failure_ttl: Some(Duration::from_secs(10)),
+                ..CachePolicy::forever()
+            },
+        )
+        .await
+        .unwrap();
+        cache.now = || 1_000;
+
+        let url1 = Url::parse("https://google.com/").unwrap();
+        let url2 = Url::parse("https://bing.com/").unwrap();
+
+        cache
+            .store_result(url1.clone(), "VALUE".into())
+            .await
+            .unwrap();
+        cache
+            .store_redirect_failure(url2.clone(), "Not found".into())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            cache.fetch_result(url1.clone()).await.unwrap(),
+            "VALUE".into()
+        );
+        assert!(matches!(
+            cache.fetch_redirect_url(url2.clone()).await,
+            Err(CacheError::CachedFailure(_))
+        ));
+
+        cache.now = || 1_011;
+        assert!(matches!(
+            cache.fetch_result(url1.clone()).await,
+            Err(CacheError::NoCacheValue)
+        ));
+        assert!(matches!(
+            cache.fetch_redirect_url(url2.clone()).await,
+            Err(CacheError::NoCacheValue)
+        ));
+    }
+
+    #[tokio::test]
+    async fn maintenance() {
+        let cache = setup_cache().await;
+
+        let url1 = Url::parse("https://google.com/").unwrap();
+        let url2 = Url::parse("https://bing.com/").unwrap();
+
+        cache
+            .store_redirect(url1.clone(), Some(url2.clone()))
+            .await
+            .unwrap();
+        cache
+            .store_result(url2.clone(), "VALUE".into())
+            .await
+            .unwrap();
+
+        assert_eq!(cache.entries().await.unwrap().len(), 2);
+        assert_eq!(
+            cache
+                .entry(url2.clone(), EntryKind::Result)
+                .await
+                .unwrap()
+                .unwrap()
+                .size,
+            5
+        );
+
+        let exported = cache.export().await.unwrap();
+        assert_eq!(cache.invalidate(url1.clone()).await.unwrap(), 1);
+        assert_eq!(cache.invalidate_prefix("https://bing").await.unwrap(), 1);
+        assert_eq!(cache.vacuum().await.unwrap(), 0);
+        assert!(cache.entries().await.unwrap().is_empty());
+
+        assert_eq!(cache.import(exported.clone()).await.unwrap(), 2);
+        assert_eq!(cache.export().await.unwrap(), exported);
+    }
 }
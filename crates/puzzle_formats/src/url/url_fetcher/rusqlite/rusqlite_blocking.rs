@@ -1,11 +1,16 @@
 #![cfg(feature = "rusqlite")]
 
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use url::Url;
+
 use crate::url::url_fetcher::{
     BlockingUrlFetcher,
     cache::{BlockingUrlFetcherCache, CacheError},
+    maintenance::{BlockingCacheMaintenance, CacheEntry, EntryKind, EntryMetadata},
 };
 
-use super::FetchReturn;
+use super::{CachePolicy, FetchReturn, Table, rusqlite_maintenance};
 
 #[derive(Debug, thiserror::Error)]
 pub enum BlockingFetchError {
@@ -15,20 +20,54 @@ pub enum BlockingFetchError {
     InvalidUrlError(#[from] url::ParseError),
 }
 
+/// Cache in an `SQLite` database.
+///
+/// The connection is used by one thread at a time, which makes the cache `Sync`,
+/// so it can be shared, for example as an async cache via `into_async` of the `tokio` feature.
 pub struct RusqliteBlockingUrlFetcherCache {
-    connection: rusqlite::Connection,
+    connection: Mutex<rusqlite::Connection>,
+    policy: CachePolicy,
+    now: fn() -> i64,
 }
 
 impl RusqliteBlockingUrlFetcherCache {
-    /// Create a new Instance.
-    /// This will also create database tables via the connection.
+    /// Create a new Instance with the default [`CachePolicy`].
+    /// This will also create or migrate database tables via the connection.
     ///
     /// # Errors
     ///
     /// This function will return an error if an error occurs during table creation.
     pub fn new(connection: rusqlite::Connection) -> rusqlite::Result<Self> {
+        Self::with_policy(connection, CachePolicy::default())
+    }
+
+    /// Create a new Instance, expiring and evicting entries according to `policy`.
+    /// This will also create or migrate database tables via the connection.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if an error occurs during table creation.
+    pub fn with_policy(
+        connection: rusqlite::Connection,
+        policy: CachePolicy,
+    ) -> rusqlite::Result<Self> {
         super::create_tables(&connection)?;
-        Ok(RusqliteBlockingUrlFetcherCache { connection })
+        Ok(RusqliteBlockingUrlFetcherCache {
+            connection: Mutex::new(connection),
+            policy,
+            now: super::unix_now,
+        })
+    }
+
+    #[must_use]
+    pub fn policy(&self) -> &CachePolicy {
+        &self.policy
+    }
+
+    fn connection(&self) -> MutexGuard<'_, rusqlite::Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
     }
 }
 
@@ -41,7 +80,13 @@ impl BlockingUrlFetcherCache for RusqliteBlockingUrlFetcherCache {
         url: url::Url,
         value: Option<url::Url>,
     ) -> Result<(), Self::StoreError> {
-        super::store_redirect(&self.connection, &url, value.as_ref())
+        super::store_redirect(
+            &self.connection(),
+            &url,
+            value.as_ref(),
+            (self.now)(),
+            &self.policy,
+        )
     }
 
     fn store_result_blocking(
@@ -49,7 +94,43 @@ impl BlockingUrlFetcherCache for RusqliteBlockingUrlFetcherCache {
         url: url::Url,
         value: Box<str>,
     ) -> Result<(), Self::StoreError> {
-        super::store_result(&self.connection, &url, value.as_ref())
+        super::store_result(
+            &self.connection(),
+            &url,
+            value.as_ref(),
+            (self.now)(),
+            &self.policy,
+        )
+    }
+
+    fn store_redirect_failure_blocking(
+        &self,
+        url: url::Url,
+        message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        super::store_failure(
+            &self.connection(),
+            &url,
+            Table::Redirect,
+            &message,
+            (self.now)(),
+            &self.policy,
+        )
+    }
+
+    fn store_result_failure_blocking(
+        &self,
+        url: url::Url,
+        message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        super::store_failure(
+            &self.connection(),
+            &url,
+            Table::Result,
+            &message,
+            (self.now)(),
+            &self.policy,
+        )
     }
 }
 
@@ -57,8 +138,9 @@ impl BlockingUrlFetcher for RusqliteBlockingUrlFetcherCache {
     type Error = CacheError<BlockingFetchError>;
 
     fn fetch_redirect_url_blocking(&self, url: url::Url) -> Result<Option<url::Url>, Self::Error> {
-        match super::fetch_redirect_url(&self.connection, &url) {
+        match super::fetch_redirect_url(&self.connection(), &url, (self.now)(), &self.policy) {
             Ok(Ok(FetchReturn::Found(value))) => Ok(value),
+            Ok(Ok(FetchReturn::Failed(message))) => Err(CacheError::CachedFailure(message)),
             Ok(Ok(FetchReturn::NotThere)) => Err(CacheError::NoCacheValue),
             Ok(Err(err)) => Err(CacheError::CacheFetchError(Box::new(
                 BlockingFetchError::InvalidUrlError(err),
@@ -70,8 +152,9 @@ impl BlockingUrlFetcher for RusqliteBlockingUrlFetcherCache {
     }
 
     fn fetch_result_blocking(&self, url: url::Url) -> Result<Box<str>, Self::Error> {
-        match super::fetch_result(&self.connection, &url) {
+        match super::fetch_result(&self.connection(), &url, (self.now)(), &self.policy) {
             Ok(FetchReturn::Found(value)) => Ok(value),
+            Ok(FetchReturn::Failed(message)) => Err(CacheError::CachedFailure(message)),
             Ok(FetchReturn::NotThere) => Err(CacheError::NoCacheValue),
             Err(err) => Err(CacheError::CacheFetchError(Box::new(
                 BlockingFetchError::SQLError(err),
@@ -80,24 +163,109 @@ impl BlockingUrlFetcher for RusqliteBlockingUrlFetcherCache {
     }
 }
 
+impl BlockingCacheMaintenance for RusqliteBlockingUrlFetcherCache {
+    type MaintenanceError = BlockingFetchError;
+
+    fn entries_blocking(&self) -> Result<Vec<EntryMetadata>, Self::MaintenanceError> {
+        Ok(rusqlite_maintenance::entries(&self.connection())?
+            .into_iter()
+            .map(rusqlite_maintenance::MetadataRow::into_metadata)
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn entry_blocking(
+        &self,
+        url: Url,
+        kind: EntryKind,
+    ) -> Result<Option<EntryMetadata>, Self::MaintenanceError> {
+        Ok(rusqlite_maintenance::entry(&self.connection(), &url, kind)?
+            .map(rusqlite_maintenance::MetadataRow::into_metadata)
+            .transpose()?)
+    }
+
+    fn invalidate_blocking(&self, url: Url) -> Result<usize, Self::MaintenanceError> {
+        Ok(rusqlite_maintenance::invalidate(&self.connection(), &url)?)
+    }
+
+    fn invalidate_prefix_blocking(&self, prefix: &str) -> Result<usize, Self::MaintenanceError> {
+        Ok(rusqlite_maintenance::invalidate_prefix(
+            &self.connection(),
+            prefix,
+        )?)
+    }
+
+    fn vacuum_blocking(&self) -> Result<usize, Self::MaintenanceError> {
+        Ok(rusqlite_maintenance::vacuum(
+            &self.connection(),
+            (self.now)(),
+            &self.policy,
+        )?)
+    }
+
+    fn export_blocking(&self) -> Result<Vec<CacheEntry>, Self::MaintenanceError> {
+        Ok(rusqlite_maintenance::export(&self.connection())?
+            .into_iter()
+            .map(rusqlite_maintenance::EntryRow::into_entry)
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn import_blocking(&self, entries: Vec<CacheEntry>) -> Result<usize, Self::MaintenanceError> {
+        Ok(rusqlite_maintenance::import(
+            &self.connection(),
+            &entries,
+            &self.policy,
+        )?)
+    }
+}
+
 #[allow(clippy::unwrap_used, clippy::expect_used)]
 #[cfg(test)]
 mod test {
+    use std::{
+        cell::Cell,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
     use rusqlite::Connection;
     use url::Url;
 
     use crate::url::url_fetcher::{
         BlockingUrlFetcher,
-        cache::{BlockingUrlFetcherCache, CacheError},
+        cache::{
+            BlockingUrlFetcherCache, BlockingUrlFetcherCacheExt, CacheError, CachedFetcherError,
+        },
+        maintenance::{BlockingCacheMaintenance, EntryKind, EntryValue, unix_now},
+        rusqlite::CachePolicy,
     };
 
     use super::RusqliteBlockingUrlFetcherCache;
 
+    thread_local! {
+        static NOW: Cell<i64> = const { Cell::new(1_000) };
+    }
+
+    fn now() -> i64 {
+        NOW.get()
+    }
+
+    fn set_now(now: i64) {
+        NOW.set(now);
+    }
+
     fn setup_cache() -> RusqliteBlockingUrlFetcherCache {
         let c = Connection::open_in_memory().unwrap();
         RusqliteBlockingUrlFetcherCache::new(c).unwrap()
     }
 
+    fn setup_cache_with(policy: CachePolicy) -> RusqliteBlockingUrlFetcherCache {
+        set_now(1_000);
+        let c = Connection::open_in_memory().unwrap();
+        let mut cache = RusqliteBlockingUrlFetcherCache::with_policy(c, policy).unwrap();
+        cache.now = now;
+        cache
+    }
+
     #[test]
     fn redirect() {
         let cache = setup_cache();
@@ -267,4 +435,393 @@ mod test {
             val1.into()
         );
     }
+
+    #[test]
+    fn expiry() {
+        let cache = setup_cache_with(CachePolicy {
+            result_ttl: Some(Duration::from_secs(10)),
+            ..CachePolicy::forever()
+        });
+
+        let url1 = Url::parse("https://google.com/").unwrap();
+        let url2 = Url::parse("https://bing.com/").unwrap();
+
+        cache
+            .store_result_blocking(url1.clone(), "VALUE".into())
+            .unwrap();
+        cache
+            .store_redirect_blocking(url1.clone(), Some(url2.clone()))
+            .unwrap();
+
+        set_now(1_010);
+        assert_eq!(
+            cache.fetch_result_blocking(url1.clone()).unwrap(),
+            "VALUE".into()
+        );
+
+        set_now(1_011);
+        assert!(matches!(
+            cache.fetch_result_blocking(url1.clone()),
+            Err(CacheError::NoCacheValue)
+        ));
+        assert_eq!(
+            cache.fetch_redirect_url_blocking(url1.clone()).unwrap(),
+            Some(url2.clone())
+        );
+
+        cache
+            .store_result_blocking(url1.clone(), "NEWVALUE".into())
+            .unwrap();
+        assert_eq!(
+            cache.fetch_result_blocking(url1.clone()).unwrap(),
+            "NEWVALUE".into()
+        );
+    }
+
+    #[test]
+    fn negative_caching() {
+        let cache = setup_cache_with(CachePolicy {
+            failure_ttl: Some(Duration::from_mins(1)),
+            ..CachePolicy::forever()
+        });
+
+        let url1 = Url::parse("https://google.com/").unwrap();
+
+        cache
+            .store_result_failure_blocking(url1.clone(), "Not found".into())
+            .unwrap();
+        assert!(matches!(
+            cache.fetch_result_blocking(url1.clone()),
+            Err(CacheError::CachedFailure(message)) if &*message == "Not found"
+        ));
+        assert!(matches!(
+            cache.fetch_redirect_url_blocking(url1.clone()),
+            Err(CacheError::NoCacheValue)
+        ));
+
+        set_now(1_061);
+        assert!(matches!(
+            cache.fetch_result_blocking(url1.clone()),
+            Err(CacheError::NoCacheValue)
+        ));
+
+        cache
+            .store_result_failure_blocking(url1.clone(), "Not found".into())
+            .unwrap();
+        cache
+            .store_result_blocking(url1.clone(), "VALUE".into())
+            .unwrap();
+        assert_eq!(
+            cache.fetch_result_blocking(url1.clone()).unwrap(),
+            "VALUE".into()
+        );
+    }
+
+    #[test]
+    fn negative_caching_disabled() {
+        let cache = setup_cache_with(CachePolicy::forever());
+
+        let url1 = Url::parse("https://google.com/").unwrap();
+
+        cache
+            .store_redirect_failure_blocking(url1.clone(), "Not found".into())
+            .unwrap();
+        assert!(matches!(
+            cache.fetch_redirect_url_blocking(url1.clone()),
+            Err(CacheError::NoCacheValue)
+        ));
+    }
+
+    #[test]
+    fn eviction() {
+        let cache = setup_cache_with(CachePolicy {
+            max_entries: Some(2),
+            ..CachePolicy::forever()
+        });
+
+        let url1 = Url::parse("https://google.com/").unwrap();
+        let url2 = Url::parse("https://bing.com/").unwrap();
+        let url3 = Url::parse("https://yahoo.com/").unwrap();
+
+        cache
+            .store_result_blocking(url1.clone(), "VALUE1".into())
+            .unwrap();
+        set_now(1_001);
+        cache
+            .store_result_blocking(url2.clone(), "VALUE2".into())
+            .unwrap();
+        set_now(1_002);
+        cache.fetch_result_blocking(url1.clone()).unwrap();
+        set_now(1_003);
+        cache
+            .store_result_blocking(url3.clone(), "VALUE3".into())
+            .unwrap();
+
+        assert_eq!(
+            cache.fetch_result_blocking(url1.clone()).unwrap(),
+            "VALUE1".into()
+        );
+        assert!(matches!(
+            cache.fetch_result_blocking(url2.clone()),
+            Err(CacheError::NoCacheValue)
+        ));
+        assert_eq!(
+            cache.fetch_result_blocking(url3.clone()).unwrap(),
+            "VALUE3".into()
+        );
+    }
+
+    #[test]
+    fn failure_eviction() {
+        let cache = setup_cache_with(CachePolicy {
+            failure_ttl: Some(Duration::from_secs(10)),
+            max_entries: Some(2),
+            ..CachePolicy::forever()
+        });
+        let failures = |cache: &RusqliteBlockingUrlFetcherCache| -> usize {
+            cache
+                .connection()
+                .query_row("SELECT COUNT(*) FROM failure_cache", (), |row| row.get(0))
+                .unwrap()
+        };
+
+        let url1 = Url::parse("https://google.com/").unwrap();
+        let url2 = Url::parse("https://bing.com/").unwrap();
+        let url3 = Url::parse("https://yahoo.com/").unwrap();
+
+        for (i, url) in [&url1, &url2, &url3].into_iter().enumerate() {
+            set_now(1_000 + i64::try_from(i).unwrap());
+            cache
+                .store_result_failure_blocking(url.clone(), "Not found".into())
+                .unwrap();
+        }
+        assert_eq!(failures(&cache), 2);
+        assert!(matches!(
+            cache.fetch_result_blocking(url1.clone()),
+            Err(CacheError::NoCacheValue)
+        ));
+        assert!(matches!(
+            cache.fetch_result_blocking(url3.clone()),
+            Err(CacheError::CachedFailure(_))
+        ));
+
+        // Expired failures are dropped with the next one
+        set_now(1_020);
+        cache
+            .store_redirect_failure_blocking(url1, "Not found".into())
+            .unwrap();
+        assert_eq!(failures(&cache), 1);
+    }
+
+    #[test]
+    fn migrate_legacy_tables() {
+        let c = Connection::open_in_memory().unwrap();
+        c.execute_batch(
+            "CREATE TABLE redirect_cache (
+                request_url     TEXT NOT NULL PRIMARY KEY,
+                redirect_url    TEXT
+            );
+            CREATE TABLE result_cache (
+                request_url     TEXT NOT NULL PRIMARY KEY,
+                response_value  TEXT NOT NULL
+            );
+            INSERT INTO redirect_cache VALUES ('https://google.com/', 'https://bing.com/');
+            INSERT INTO result_cache VALUES ('https://google.com/', 'OLDVALUE');",
+        )
+        .unwrap();
+
+        let cache = RusqliteBlockingUrlFetcherCache::new(c).unwrap();
+        let url1 = Url::parse("https://google.com/").unwrap();
+
+        // Old entries count as stored by the migration, so they don't expire right away
+        assert_eq!(
+            cache.fetch_redirect_url_blocking(url1.clone()).unwrap(),
+            Some(Url::parse("https://bing.com/").unwrap())
+        );
+        assert_eq!(
+            cache.fetch_result_blocking(url1.clone()).unwrap(),
+            "OLDVALUE".into()
+        );
+        let stored_at: i64 = cache
+            .connection()
+            .query_row("SELECT stored_at FROM result_cache", [], |row| row.get(0))
+            .unwrap();
+        assert!(stored_at >= unix_now() - 60);
+
+        // Migrating again keeps the data
+        let cache = RusqliteBlockingUrlFetcherCache::new(
+            cache
+                .connection
+                .into_inner()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        )
+        .unwrap();
+        assert!(cache.fetch_redirect_url_blocking(url1).is_ok());
+    }
+
+    #[test]
+    fn migrate_concurrently() {
+        let path = std::env::temp_dir().join(format!(
+            "puzzle-formats-migrate-{}.sqlite",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE redirect_cache (
+                    request_url     TEXT NOT NULL PRIMARY KEY,
+                    redirect_url    TEXT
+                );
+                CREATE TABLE result_cache (
+                    request_url     TEXT NOT NULL PRIMARY KEY,
+                    response_value  TEXT NOT NULL
+                );",
+            )
+            .unwrap();
+
+        let barrier = std::sync::Barrier::new(8);
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let connection = Connection::open(&path).unwrap();
+                        barrier.wait();
+                        RusqliteBlockingUrlFetcherCache::new(connection).map(drop)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        let _ = std::fs::remove_file(&path);
+
+        for result in results {
+            result.unwrap();
+        }
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Not found: {0}")]
+    struct NotFound(Url);
+
+    #[derive(Default)]
+    struct FailingFetcher {
+        requests: AtomicUsize,
+    }
+
+    impl BlockingUrlFetcher for FailingFetcher {
+        type Error = NotFound;
+
+        fn fetch_redirect_url_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            Err(NotFound(url))
+        }
+
+        fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            Err(NotFound(url))
+        }
+    }
+
+    #[test]
+    fn cached_fetcher_failures() {
+        let cache = setup_cache_with(CachePolicy {
+            failure_ttl: Some(Duration::from_mins(1)),
+            ..CachePolicy::forever()
+        });
+        let fetcher = FailingFetcher::default().with_cache_blocking(cache);
+        let url1 = Url::parse("https://google.com/").unwrap();
+
+        assert!(matches!(
+            fetcher.fetch_result_blocking(url1.clone()),
+            Err(CachedFetcherError::FetchError(_))
+        ));
+        assert!(matches!(
+            fetcher.fetch_result_blocking(url1.clone()),
+            Err(CachedFetcherError::CachedFailure(message)) if &*message == "Not found: https://google.com/"
+        ));
+        assert!(matches!(
+            fetcher.fetch_result_fresh_blocking(url1.clone()),
+            Err(CachedFetcherError::FetchError(_))
+        ));
+
+        let (_, fetcher) = fetcher.into_parts();
+        assert_eq!(fetcher.requests.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn maintenance() {
+        let cache = setup_cache_with(CachePolicy {
+            result_ttl: Some(Duration::from_secs(10)),
+            failure_ttl: Some(Duration::from_mins(1)),
+            ..CachePolicy::forever()
+        });
+
+        let short = Url::parse("https://bit.ly/a").unwrap();
+        let short2 = Url::parse("https://bit.ly/b").unwrap();
+        let puzzle = Url::parse("https://sudokupad.app/api/puzzle/abc").unwrap();
+
+        cache
+            .store_redirect_blocking(short.clone(), Some(puzzle.clone()))
+            .unwrap();
+        cache
+            .store_redirect_failure_blocking(short2.clone(), "Not found".into())
+            .unwrap();
+        cache
+            .store_result_blocking(puzzle.clone(), "VALUE".into())
+            .unwrap();
+        set_now(1_005);
+        cache.fetch_result_blocking(puzzle.clone()).unwrap();
+
+        let entries = cache.entries_blocking().unwrap();
+        let kinds: Vec<_> = entries.iter().map(|entry| entry.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                EntryKind::Redirect,
+                EntryKind::RedirectFailure,
+                EntryKind::Result
+            ]
+        );
+
+        let metadata = cache
+            .entry_blocking(puzzle.clone(), EntryKind::Result)
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.size, 5);
+        assert_eq!(metadata.stored_at, 1_000);
+        assert_eq!(metadata.last_hit, Some(1_005));
+        assert!(
+            cache
+                .entry_blocking(puzzle.clone(), EntryKind::Redirect)
+                .unwrap()
+                .is_none()
+        );
+
+        // Export into a fresh cache
+        let exported = cache.export_blocking().unwrap();
+        assert_eq!(
+            exported[0].value,
+            EntryValue::Redirect {
+                redirect_url: Some(puzzle.clone())
+            }
+        );
+        let copy = setup_cache_with(CachePolicy::forever());
+        assert_eq!(copy.import_blocking(exported.clone()).unwrap(), 3);
+        assert_eq!(copy.export_blocking().unwrap(), exported);
+
+        assert_eq!(
+            cache.invalidate_prefix_blocking("https://bit.ly/").unwrap(),
+            2
+        );
+        assert_eq!(cache.invalidate_blocking(short.clone()).unwrap(), 0);
+        assert_eq!(cache.entries_blocking().unwrap().len(), 1);
+
+        set_now(1_011);
+        assert_eq!(cache.vacuum_blocking().unwrap(), 1);
+        assert!(cache.entries_blocking().unwrap().is_empty());
+    }
 }
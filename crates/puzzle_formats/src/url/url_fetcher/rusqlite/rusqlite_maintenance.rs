@@ -0,0 +1,234 @@
+use rusqlite::{
+    OptionalExtension,
+    types::{FromSql, FromSqlError, FromSqlResult, ValueRef},
+};
+use url::Url;
+
+use crate::url::url_fetcher::maintenance::{CacheEntry, EntryKind, EntryMetadata, EntryValue};
+
+use super::{CachePolicy, Table};
+
+/// All entries of all tables, with the columns `request_url, kind, value, stored_at, last_hit`.
+const ENTRIES: &str = "
+    SELECT request_url, 'redirect' AS kind, redirect_url AS value, stored_at, last_hit
+        FROM redirect_cache
+    UNION ALL SELECT request_url, 'result', response_value, stored_at, last_hit
+        FROM result_cache
+    UNION ALL SELECT request_url, kind || '_failure', message, stored_at, NULL
+        FROM failure_cache";
+
+impl FromSql for EntryKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let kind = value.as_str()?;
+        EntryKind::ALL
+            .into_iter()
+            .find(|entry_kind| entry_kind.as_str() == kind)
+            .ok_or(FromSqlError::InvalidType)
+    }
+}
+
+/// Metadata as stored, the Url is parsed outside of the connection.
+#[derive(Debug)]
+pub(super) struct MetadataRow {
+    url: String,
+    kind: EntryKind,
+    size: usize,
+    stored_at: i64,
+    last_hit: Option<i64>,
+}
+
+impl MetadataRow {
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            url: row.get(0)?,
+            kind: row.get(1)?,
+            size: row.get(2)?,
+            stored_at: row.get(3)?,
+            last_hit: row.get(4)?,
+        })
+    }
+
+    pub(super) fn into_metadata(self) -> Result<EntryMetadata, url::ParseError> {
+        Ok(EntryMetadata {
+            url: Url::parse(&self.url)?,
+            kind: self.kind,
+            size: self.size,
+            stored_at: self.stored_at,
+            last_hit: self.last_hit,
+        })
+    }
+}
+
+/// An entry as stored, the Urls are parsed outside of the connection.
+#[derive(Debug)]
+pub(super) struct EntryRow {
+    url: String,
+    kind: EntryKind,
+    value: Option<String>,
+    stored_at: i64,
+    last_hit: Option<i64>,
+}
+
+impl EntryRow {
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            url: row.get(0)?,
+            kind: row.get(1)?,
+            value: row.get(2)?,
+            stored_at: row.get(3)?,
+            last_hit: row.get(4)?,
+        })
+    }
+
+    pub(super) fn into_entry(self) -> Result<CacheEntry, url::ParseError> {
+        let text = || self.value.clone().unwrap_or_default().into_boxed_str();
+        let value = match self.kind {
+            EntryKind::Redirect => EntryValue::Redirect {
+                redirect_url: self.value.as_deref().map(Url::parse).transpose()?,
+            },
+            EntryKind::Result => EntryValue::Result {
+                response_value: text(),
+            },
+            EntryKind::RedirectFailure => EntryValue::RedirectFailure { message: text() },
+            EntryKind::ResultFailure => EntryValue::ResultFailure { message: text() },
+        };
+        Ok(CacheEntry {
+            url: Url::parse(&self.url)?,
+            value,
+            stored_at: self.stored_at,
+            last_hit: self.last_hit,
+        })
+    }
+}
+
+pub(super) fn entries(connection: &rusqlite::Connection) -> rusqlite::Result<Vec<MetadataRow>> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT request_url, kind, COALESCE(LENGTH(CAST(value AS BLOB)), 0), stored_at, last_hit
+            FROM ({ENTRIES}) ORDER BY request_url, kind"
+    ))?;
+    statement.query_map((), MetadataRow::from_row)?.collect()
+}
+
+pub(super) fn entry(
+    connection: &rusqlite::Connection,
+    url: &Url,
+    kind: EntryKind,
+) -> rusqlite::Result<Option<MetadataRow>> {
+    connection
+        .query_row(
+            &format!(
+                "SELECT request_url, kind, COALESCE(LENGTH(CAST(value AS BLOB)), 0), stored_at, last_hit
+                    FROM ({ENTRIES}) WHERE request_url=?1 AND kind=?2"
+            ),
+            (url.to_string(), kind.as_str()),
+            MetadataRow::from_row,
+        )
+        .optional()
+}
+
+pub(super) fn invalidate(connection: &rusqlite::Connection, url: &Url) -> rusqlite::Result<usize> {
+    let url = url.to_string();
+    let mut deleted = 0;
+    for table in ["redirect_cache", "result_cache", "failure_cache"] {
+        deleted += connection.execute(
+            &format!("DELETE FROM {table} WHERE request_url=?1"),
+            (&url,),
+        )?;
+    }
+    Ok(deleted)
+}
+
+pub(super) fn invalidate_prefix(
+    connection: &rusqlite::Connection,
+    prefix: &str,
+) -> rusqlite::Result<usize> {
+    let mut deleted = 0;
+    for table in ["redirect_cache", "result_cache", "failure_cache"] {
+        deleted += connection.execute(
+            &format!("DELETE FROM {table} WHERE substr(request_url, 1, length(?1))=?1"),
+            (prefix,),
+        )?;
+    }
+    Ok(deleted)
+}
+
+/// Oldest `stored_at` that has not expired yet, everything is expired without a TTL for failures.
+fn expiry_cutoff(ttl: Option<std::time::Duration>, now: i64) -> Option<i64> {
+    ttl.map(|ttl| now.saturating_sub(i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX)))
+}
+
+pub(super) fn vacuum(
+    connection: &rusqlite::Connection,
+    now: i64,
+    policy: &CachePolicy,
+) -> rusqlite::Result<usize> {
+    let mut deleted = 0;
+    for table in [Table::Redirect, Table::Result] {
+        if let Some(cutoff) = expiry_cutoff(table.ttl(policy), now) {
+            deleted += connection.execute(
+                &format!("DELETE FROM {} WHERE stored_at < ?1", table.name()),
+                (cutoff,),
+            )?;
+        }
+    }
+    deleted += connection.execute(
+        "DELETE FROM failure_cache WHERE stored_at < ?1",
+        (expiry_cutoff(policy.failure_ttl, now).unwrap_or(i64::MAX),),
+    )?;
+
+    connection.execute_batch("VACUUM")?;
+    Ok(deleted)
+}
+
+pub(super) fn export(connection: &rusqlite::Connection) -> rusqlite::Result<Vec<EntryRow>> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT request_url, kind, value, stored_at, last_hit
+            FROM ({ENTRIES}) ORDER BY request_url, kind"
+    ))?;
+    statement.query_map((), EntryRow::from_row)?.collect()
+}
+
+pub(super) fn import(
+    connection: &rusqlite::Connection,
+    entries: &[CacheEntry],
+    policy: &CachePolicy,
+) -> rusqlite::Result<usize> {
+    let transaction = connection.unchecked_transaction()?;
+    for entry in entries {
+        let url = entry.url.to_string();
+        match &entry.value {
+            EntryValue::Redirect { redirect_url } => transaction.execute(
+                "INSERT OR REPLACE INTO redirect_cache (request_url, redirect_url, stored_at, last_hit)
+                    VALUES (?1, ?2, ?3, ?4)",
+                (
+                    url,
+                    redirect_url.as_ref().map(Url::to_string),
+                    entry.stored_at,
+                    entry.last_hit,
+                ),
+            )?,
+            EntryValue::Result { response_value } => transaction.execute(
+                "INSERT OR REPLACE INTO result_cache (request_url, response_value, stored_at, last_hit)
+                    VALUES (?1, ?2, ?3, ?4)",
+                (url, response_value.as_ref(), entry.stored_at, entry.last_hit),
+            )?,
+            EntryValue::RedirectFailure { message } | EntryValue::ResultFailure { message } => {
+                let table = if entry.value.kind() == EntryKind::RedirectFailure {
+                    Table::Redirect
+                } else {
+                    Table::Result
+                };
+                transaction.execute(
+                    "INSERT OR REPLACE INTO failure_cache (request_url, kind, message, stored_at)
+                        VALUES (?1, ?2, ?3, ?4)",
+                    (url, table.kind(), message.as_ref(), entry.stored_at),
+                )?
+            }
+        };
+    }
+    super::evict(&transaction, Table::Redirect, policy.max_entries)?;
+    super::evict(&transaction, Table::Result, policy.max_entries)?;
+    transaction.commit()?;
+
+    Ok(entries.len())
+}
@@ -1,14 +1,17 @@
 #![cfg(feature = "tokio")]
 
-use std::{error::Error, sync::Arc};
+use std::{error::Error, io, sync::Arc};
 
 use async_trait::async_trait;
-use tokio::task::JoinError;
+use tokio::{
+    runtime::{Handle, Runtime},
+    task::JoinError,
+};
 use url::Url;
 
 use super::{
     BlockingUrlFetcher, UrlFetcher,
-    cache::{BlockingUrlFetcherCache, CacheError, UrlFetcherCache},
+    cache::{BlockingUrlFetcherCache, CacheError, IntoCacheError, UrlFetcherCache},
 };
 
 pub struct TokioUrlFetcher<F> {
@@ -21,6 +24,8 @@ pub enum TokioFetcherError<E> {
     JoinError(#[from] Box<JoinError>),
     #[error("Error fetching: {0}")]
     FetchError(Box<E>),
+    #[error("Error storing: {0}")]
+    StoreError(Box<E>),
 }
 
 #[async_trait]
@@ -46,6 +51,22 @@ where
             .map_err(|err| TokioFetcherError::JoinError(err.into()))?
             .map_err(|err| TokioFetcherError::FetchError(err.into()))
     }
+
+    async fn fetch_redirect_url_fresh(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || inner.fetch_redirect_url_fresh_blocking(url))
+            .await
+            .map_err(|err| TokioFetcherError::JoinError(err.into()))?
+            .map_err(|err| TokioFetcherError::FetchError(err.into()))
+    }
+
+    async fn fetch_result_fresh(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || inner.fetch_result_fresh_blocking(url))
+            .await
+            .map_err(|err| TokioFetcherError::JoinError(err.into()))?
+            .map_err(|err| TokioFetcherError::FetchError(err.into()))
+    }
 }
 
 pub trait UrlFetcherTokioExt: BlockingUrlFetcher + Sized {
@@ -62,10 +83,32 @@ where
     }
 }
 
+impl<E> IntoCacheError<TokioFetcherError<E>> for TokioFetcherError<CacheError<E>> {
+    /// Move the cache states out of the Tokio error, keeping only failures wrapped.
+    fn into_cache_error(self) -> CacheError<TokioFetcherError<E>> {
+        let (err, store) = match self {
+            Self::JoinError(err) => {
+                return CacheError::CacheFetchError(TokioFetcherError::JoinError(err).into());
+            }
+            Self::FetchError(err) => (err, false),
+            Self::StoreError(err) => (err, true),
+        };
+        match *err {
+            CacheError::NoCacheValue => CacheError::NoCacheValue,
+            CacheError::CachedFailure(message) => CacheError::CachedFailure(message),
+            CacheError::CacheFetchError(err) if store => {
+                CacheError::CacheFetchError(TokioFetcherError::StoreError(err).into())
+            }
+            CacheError::CacheFetchError(err) => {
+                CacheError::CacheFetchError(TokioFetcherError::FetchError(err).into())
+            }
+        }
+    }
+}
+
 #[async_trait]
 impl<F> UrlFetcherCache for TokioUrlFetcher<F>
 where
-    TokioUrlFetcher<F>: UrlFetcher<Error = CacheError<TokioFetcherError<F::FetchError>>>,
     F: BlockingUrlFetcherCache + Send + Sync + 'static,
     F::FetchError: Send + Sync,
     F::StoreError: Send + Sync,
@@ -73,20 +116,362 @@ where
     type FetchError = TokioFetcherError<F::FetchError>;
     type StoreError = TokioFetcherError<F::StoreError>;
 
-    #[allow(clippy::missing_errors_doc)]
     async fn store_redirect(&self, url: Url, value: Option<Url>) -> Result<(), Self::StoreError> {
         let inner = self.inner.clone();
         tokio::task::spawn_blocking(move || inner.store_redirect_blocking(url, value))
             .await
             .map_err(|err| TokioFetcherError::JoinError(err.into()))?
-            .map_err(|err| TokioFetcherError::FetchError(err.into()))
+            .map_err(|err| TokioFetcherError::StoreError(err.into()))
     }
-    #[allow(clippy::missing_errors_doc)]
+
     async fn store_result(&self, url: Url, value: Box<str>) -> Result<(), Self::StoreError> {
         let inner = self.inner.clone();
         tokio::task::spawn_blocking(move || inner.store_result_blocking(url, value))
             .await
             .map_err(|err| TokioFetcherError::JoinError(err.into()))?
-            .map_err(|err| TokioFetcherError::FetchError(err.into()))
+            .map_err(|err| TokioFetcherError::StoreError(err.into()))
+    }
+
+    async fn store_redirect_failure(
+        &self,
+        url: Url,
+        message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || inner.store_redirect_failure_blocking(url, message))
+            .await
+            .map_err(|err| TokioFetcherError::JoinError(err.into()))?
+            .map_err(|err| TokioFetcherError::StoreError(err.into()))
+    }
+
+    async fn store_result_failure(
+        &self,
+        url: Url,
+        message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || inner.store_result_failure_blocking(url, message))
+            .await
+            .map_err(|err| TokioFetcherError::JoinError(err.into()))?
+            .map_err(|err| TokioFetcherError::StoreError(err.into()))
+    }
+}
+
+/// Blocking fetcher driving an async fetcher to completion on a Tokio runtime.
+///
+/// The blocking methods must not be called from within an async context of that runtime,
+/// as blocking on it there panics.
+pub struct BlockingTokioUrlFetcher<F> {
+    inner: F,
+    runtime: BridgeRuntime,
+}
+
+enum BridgeRuntime {
+    Owned(Runtime),
+    Borrowed(Handle),
+}
+
+impl<F> BlockingTokioUrlFetcher<F> {
+    /// Drive `inner` on its own current thread runtime.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the runtime can't be created.
+    pub fn new(inner: F) -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self::with_runtime(inner, runtime))
+    }
+
+    /// Drive `inner` on the given runtime, owned by the fetcher.
+    pub fn with_runtime(inner: F, runtime: Runtime) -> Self {
+        Self {
+            inner,
+            runtime: BridgeRuntime::Owned(runtime),
+        }
+    }
+
+    /// Drive `inner` on the runtime of `handle`, owned elsewhere.
+    pub fn with_handle(inner: F, handle: Handle) -> Self {
+        Self {
+            inner,
+            runtime: BridgeRuntime::Borrowed(handle),
+        }
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+        match &self.runtime {
+            BridgeRuntime::Owned(runtime) => runtime.block_on(future),
+            BridgeRuntime::Borrowed(handle) => handle.block_on(future),
+        }
+    }
+}
+
+impl<F> BlockingUrlFetcher for BlockingTokioUrlFetcher<F>
+where
+    F: UrlFetcher + Sync,
+{
+    type Error = F::Error;
+
+    fn fetch_redirect_url_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.block_on(self.inner.fetch_redirect_url(url))
+    }
+
+    fn fetch_result_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.block_on(self.inner.fetch_result(url))
+    }
+
+    fn fetch_redirect_url_fresh_blocking(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.block_on(self.inner.fetch_redirect_url_fresh(url))
+    }
+
+    fn fetch_result_fresh_blocking(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.block_on(self.inner.fetch_result_fresh(url))
+    }
+}
+
+impl<F> BlockingUrlFetcherCache for BlockingTokioUrlFetcher<F>
+where
+    F: UrlFetcherCache + UrlFetcher<Error = CacheError<<F as UrlFetcherCache>::FetchError>> + Sync,
+{
+    type FetchError = F::FetchError;
+    type StoreError = F::StoreError;
+
+    fn store_redirect_blocking(
+        &self,
+        url: Url,
+        value: Option<Url>,
+    ) -> Result<(), Self::StoreError> {
+        self.block_on(self.inner.store_redirect(url, value))
+    }
+
+    fn store_result_blocking(&self, url: Url, value: Box<str>) -> Result<(), Self::StoreError> {
+        self.block_on(self.inner.store_result(url, value))
+    }
+
+    fn store_redirect_failure_blocking(
+        &self,
+        url: Url,
+        message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        self.block_on(self.inner.store_redirect_failure(url, message))
+    }
+
+    fn store_result_failure_blocking(
+        &self,
+        url: Url,
+        message: Box<str>,
+    ) -> Result<(), Self::StoreError> {
+        self.block_on(self.inner.store_result_failure(url, message))
+    }
+}
+
+pub trait UrlFetcherBlockingExt: UrlFetcher + Sized {
+    /// Drive this fetcher on its own current thread runtime.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the runtime can't be created.
+    fn into_blocking(self) -> io::Result<BlockingTokioUrlFetcher<Self>>;
+}
+
+impl<F> UrlFetcherBlockingExt for F
+where
+    F: UrlFetcher + Sized,
+{
+    fn into_blocking(self) -> io::Result<BlockingTokioUrlFetcher<Self>> {
+        BlockingTokioUrlFetcher::new(self)
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use crate::url::url_fetcher::{
+        BlockingUrlFetcher,
+        cache::{
+            BlockingUrlFetcherCache, BlockingUrlFetcherCacheExt, CacheError, CachedFetcherError,
+        },
+        maintenance::{CacheEntry, EntryValue},
+        memory::MemoryUrlFetcherCache,
+        replay::{ReplayError, ReplayFetcher},
+    };
+
+    use super::{BlockingTokioUrlFetcher, UrlFetcherBlockingExt};
+
+    #[test]
+    fn owned_runtime() {
+        let url = Url::parse("https://bit.ly/a").unwrap();
+        let cache = MemoryUrlFetcherCache::new().into_blocking().unwrap();
+
+        assert!(matches!(
+            cache.fetch_redirect_url_blocking(url.clone()),
+            Err(CacheError::NoCacheValue)
+        ));
+        cache.store_redirect_blocking(url.clone(), None).unwrap();
+        assert_eq!(cache.fetch_redirect_url_blocking(url).unwrap(), None);
+    }
+
+    #[test]
+    fn borrowed_runtime() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let url = Url::parse("https://sudokupad.app/api/puzzle/abc").unwrap();
+        let replay = ReplayFetcher::new([CacheEntry {
+            url: url.clone(),
+            value: EntryValue::Result {
+                response_value: "VALUE".into(),
+            },
+            stored_at: 0,
+            last_hit: None,
+        }]);
+
+        let fetcher = BlockingTokioUrlFetcher::with_handle(replay, runtime.handle().clone())
+            .with_cache_blocking(BlockingTokioUrlFetcher::with_handle(
+                MemoryUrlFetcherCache::new(),
+                runtime.handle().clone(),
+            ));
+        assert_eq!(
+            fetcher.fetch_result_blocking(url.clone()).unwrap(),
+            "VALUE".into()
+        );
+        assert!(matches!(
+            fetcher.fetch_redirect_url_blocking(url.clone()),
+            Err(CachedFetcherError::FetchError(err))
+                if matches!(*err, ReplayError::NotRecorded { .. })
+        ));
+
+        let (cache, _) = fetcher.into_parts();
+        assert_eq!(cache.into_inner().len(), 1);
+    }
+
+    #[cfg(feature = "rusqlite")]
+    mod rusqlite {
+        use url::Url;
+
+        use crate::url::url_fetcher::{
+            UrlFetcher,
+            cache::{
+                CacheError, CachedFetcherError, IntoCacheError, UrlFetcherCache, UrlFetcherCacheExt,
+            },
+            maintenance::{CacheEntry, EntryValue},
+            replay::ReplayFetcher,
+            rusqlite::{CachePolicy, RusqliteBlockingUrlFetcherCache},
+            tokio::{TokioFetcherError, TokioUrlFetcher, UrlFetcherTokioExt},
+        };
+
+        fn in_memory(policy: CachePolicy) -> TokioUrlFetcher<RusqliteBlockingUrlFetcherCache> {
+            let connection = rusqlite::Connection::open_in_memory().unwrap();
+            RusqliteBlockingUrlFetcherCache::with_policy(connection, policy)
+                .unwrap()
+                .into_async()
+        }
+
+        #[tokio::test]
+        async fn store_and_fetch() {
+            let cache = in_memory(CachePolicy::default());
+
+            let url1 = Url::parse("https://google.com/").unwrap();
+            let url2 = Url::parse("https://bing.com/").unwrap();
+
+            assert!(matches!(
+                cache
+                    .fetch_redirect_url(url1.clone())
+                    .await
+                    .map_err(IntoCacheError::into_cache_error),
+                Err(CacheError::NoCacheValue)
+            ));
+
+            cache
+                .store_redirect(url1.clone(), Some(url2.clone()))
+                .await
+                .unwrap();
+            cache
+                .store_result(url1.clone(), "VALUE".into())
+                .await
+                .unwrap();
+
+            assert_eq!(
+                cache.fetch_redirect_url(url1.clone()).await.unwrap(),
+                Some(url2)
+            );
+            assert_eq!(cache.fetch_result(url1).await.unwrap(), "VALUE".into());
+        }
+
+        #[tokio::test]
+        async fn cached_fetcher() {
+            let policy = CachePolicy {
+                failure_ttl: Some(std::time::Duration::from_mins(1)),
+                ..CachePolicy::default()
+            };
+            let url = Url::parse("https://sudokupad.app/api/puzzle/abc").unwrap();
+            let replay = ReplayFetcher::new([CacheEntry {
+                url: url.clone(),
+                value: EntryValue::Result {
+                    response_value: "VALUE".into(),
+                },
+                stored_at: 0,
+                last_hit: None,
+            }]);
+
+            let fetcher = replay.with_cache(in_memory(policy));
+            assert_eq!(
+                fetcher.fetch_result(url.clone()).await.unwrap(),
+                "VALUE".into()
+            );
+            assert!(matches!(
+                fetcher.fetch_redirect_url(url.clone()).await,
+                Err(CachedFetcherError::FetchError(_))
+            ));
+            assert!(matches!(
+                fetcher.fetch_redirect_url(url.clone()).await,
+                Err(CachedFetcherError::CachedFailure(_))
+            ));
+
+            // Served from the cache, the replay fetcher is gone
+            let (cache, _) = fetcher.into_parts();
+            let fetcher = ReplayFetcher::default().with_cache(cache);
+            assert_eq!(fetcher.fetch_result(url).await.unwrap(), "VALUE".into());
+        }
+
+        #[tokio::test]
+        async fn store_error() {
+            let path = std::env::temp_dir().join(format!(
+                "puzzle-formats-tokio-cache-{}.sqlite",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            let cache =
+                RusqliteBlockingUrlFetcherCache::new(rusqlite::Connection::open(&path).unwrap())
+                    .unwrap()
+                    .into_async();
+            rusqlite::Connection::open(&path)
+                .unwrap()
+                .execute_batch("DROP TABLE result_cache")
+                .unwrap();
+
+            let url = Url::parse("https://google.com/").unwrap();
+            let stored = cache.store_result(url.clone(), "VALUE".into()).await;
+            let fetched = cache
+                .fetch_result(url)
+                .await
+                .map_err(IntoCacheError::into_cache_error);
+            drop(cache);
+            let _ = std::fs::remove_file(&path);
+
+            assert!(matches!(stored, Err(TokioFetcherError::StoreError(_))));
+            assert!(matches!(
+                fetched,
+                Err(CacheError::CacheFetchError(err)) if matches!(*err, TokioFetcherError::FetchError(_))
+            ));
+        }
     }
 }
@@ -11,6 +11,9 @@ use super::{
     unresolved_url::{UnresolvedUrl, UnresolvedUrlInner},
 };
 
+/// Hosts only used to shorten links, commonly used for puzzle links.
+const URL_SHORTENERS: &[&str] = &["tinyurl.com", "shorturl.at", "bit.ly", "is.gd", "t.ly"];
+
 #[derive(Debug, thiserror::Error)]
 pub enum ParseError {
     #[error("Invalid Url Scheme: {0}")]
@@ -25,6 +28,7 @@ pub enum ParseError {
     UrlParseError(#[source] url::ParseError),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UrlValue {
     Resolved(ResolvedUrl),
     Unresolved(UnresolvedUrl),
@@ -39,6 +43,21 @@ impl UrlValue {
         Self::Unresolved(UnresolvedUrl::new(inner))
     }
 
+    /// Whether the Url leads to a single puzzle, once resolved.
+    /// Links of known Url shorteners are assumed to.
+    pub(crate) fn is_puzzle_link(&self) -> bool {
+        match self {
+            UrlValue::Resolved(_) => true,
+            UrlValue::Unresolved(unresolved) => match unresolved.inner() {
+                UnresolvedUrlInner::FPuzzles(_) | UnresolvedUrlInner::SudokuPad(_) => true,
+                UnresolvedUrlInner::LogicMasters(_) => false,
+                UnresolvedUrlInner::Unknown(url) => url
+                    .domain()
+                    .is_some_and(|domain| URL_SHORTENERS.contains(&domain)),
+            },
+        }
+    }
+
     #[allow(clippy::missing_errors_doc)]
     pub fn parse(url: impl IntoUrl) -> Result<Self, ParseError> {
         use ResolvedUrlInner as R;
@@ -61,9 +80,10 @@ impl UrlValue {
         let mut query_pairs = url.query_pairs();
 
         match domain {
-            "sudokupad.app" | "alpha.sudokupad.app" | "beta.sudokupad.app" => {
-                use SudokuPadFullUrl as F;
-
+            "sudokupad.app"
+            | "alpha.sudokupad.app"
+            | "beta.sudokupad.app"
+            | "app.crackingthecryptic.com" => {
                 let puzzleid = query_pairs.find_map(|(k, v)| (k == "puzzleid").then_some(v));
 
                 let puzzleid = puzzleid.unwrap_or_else(|| {
@@ -77,28 +97,14 @@ impl UrlValue {
                     Cow::Owned(puzzleid)
                 });
 
-                if let Some(puzzleid) = puzzleid
-                    .strip_prefix("fpuzzles")
-                    .or_else(|| puzzleid.strip_prefix("fpuz"))
-                {
-                    return Ok(Self::resolved(R::SudokuPad(F::FPuz(puzzleid.into()))));
-                }
-
-                if let Some(puzzleid) = puzzleid
-                    .strip_prefix("scl")
-                    .or_else(|| puzzleid.strip_prefix("ctc"))
-                {
-                    return Ok(Self::resolved(R::SudokuPad(F::Scl(puzzleid.into()))));
-                }
-
-                if let Some(puzzleid) = puzzleid.strip_prefix("scf") {
-                    return Ok(Self::resolved(R::SudokuPad(F::Scf(puzzleid.into()))));
+                if let Some(full_url) = SudokuPadFullUrl::from_puzzleid(&puzzleid) {
+                    return Ok(Self::resolved(R::SudokuPad(full_url)));
                 }
 
                 Ok(Self::unresolved(U::SudokuPad(puzzleid.into())))
             }
             "f-puzzles.com" | "www.f-puzzles.com" => {
-                if segments.next().is_some() {
+                if segments.any(|segment| !segment.is_empty()) {
                     return Err(ParseError::UnknownPage(url));
                 }
 
@@ -129,8 +135,25 @@ impl UrlValue {
                     puzzleid.into_owned().into_boxed_str(),
                 )))
             }
+            "logic-masters.de" | "www.logic-masters.de" => {
+                if !segments.eq(["Raetselportal", "Raetsel", "zeigen.php"]) {
+                    return Err(ParseError::UnknownPage(url));
+                }
+
+                let puzzleid = query_pairs.find_map(|(k, v)| (k == "id").then_some(v));
+
+                let Some(puzzleid) = puzzleid.filter(|id| !id.is_empty()) else {
+                    return Err(ParseError::MissingId(url));
+                };
+
+                Ok(Self::unresolved(U::LogicMasters(
+                    puzzleid.into_owned().into(),
+                )))
+            }
             "swaroopg92.github.io" => {
-                if !(segments.next() == Some("penpa-edit") && segments.next().is_none()) {
+                if !(segments.next() == Some("penpa-edit")
+                    && segments.all(|segment| segment.is_empty() || segment == "index.html"))
+                {
                     return Ok(Self::unresolved(U::Unknown(Box::new(url))));
                 }
 
@@ -0,0 +1,73 @@
+local regions = {}
+
+---@param row integer
+---@param column integer
+---@return string
+function regions.cell(row, column)
+    return "R" .. row .. "C" .. column
+end
+
+---@param size integer
+---@return integer height
+---@return integer width
+function regions.box_dims(size)
+    local height = 1
+    for n = 1, size do
+        if n * n > size then
+            break
+        end
+        if size % n == 0 then
+            height = n
+        end
+    end
+    return height, size // height
+end
+
+---@param size integer
+---@return string[][]
+function regions.rows(size)
+    local rows = {}
+    for row = 1, size do
+        local cells = {}
+        for column = 1, size do
+            cells[#cells + 1] = regions.cell(row, column)
+        end
+        rows[row] = cells
+    end
+    return rows
+end
+
+---@param size integer
+---@return string[][]
+function regions.columns(size)
+    local columns = {}
+    for column = 1, size do
+        local cells = {}
+        for row = 1, size do
+            cells[#cells + 1] = regions.cell(row, column)
+        end
+        columns[column] = cells
+    end
+    return columns
+end
+
+---@param size integer
+---@return string[][]
+function regions.boxes(size)
+    local height, width = regions.box_dims(size)
+    local boxes = {}
+    for box = 0, size - 1 do
+        local top = (box // (size // width)) * height
+        local left = (box % (size // width)) * width
+        local cells = {}
+        for row = top + 1, top + height do
+            for column = left + 1, left + width do
+                cells[#cells + 1] = regions.cell(row, column)
+            end
+        end
+        boxes[box + 1] = cells
+    end
+    return boxes
+end
+
+return regions
@@ -0,0 +1,247 @@
+use std::{
+    fmt,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU8, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::cancel::CancelToken;
+
+/// The work was stopped by the [`CancelToken`] of its [`Job`] before it finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Cancelled")]
+pub struct Cancelled;
+
+/// Stage of a [`Job`], each with its own progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Phase {
+    #[default]
+    Waiting,
+    /// Running the Lua scripts of the puzzles.
+    Building,
+    /// Searching for solutions.
+    Solving,
+    Finished,
+}
+
+impl Phase {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Waiting => "Waiting",
+            Self::Building => "Building",
+            Self::Solving => "Solving",
+            Self::Finished => "Finished",
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Waiting => 0,
+            Self::Building => 1,
+            Self::Solving => 2,
+            Self::Finished => 3,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Building,
+            2 => Self::Solving,
+            3 => Self::Finished,
+            _ => Self::Waiting,
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Handle to long running work, shared by the threads doing it and those observing it.
+///
+/// Clones refer to the same job, workers report progress with [`Job::start`] and [`Job::advance`]
+/// and stop once [`Job::is_cancelled`], observers take [`Job::snapshot`]s.
+#[derive(Debug, Clone, Default)]
+pub struct Job {
+    cancel: CancelToken,
+    progress: Arc<Progress>,
+}
+
+#[derive(Debug, Default)]
+struct Progress {
+    phase: AtomicU8,
+    done: AtomicU64,
+    /// Steps of the phase, 0 if unknown.
+    total: AtomicU64,
+    /// Start of the current phase.
+    started: Mutex<Option<Instant>>,
+}
+
+/// Progress of a [`Job`] at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressSnapshot {
+    pub phase: Phase,
+    pub done: u64,
+    pub total: Option<u64>,
+    /// Time since the phase started.
+    pub elapsed: Duration,
+    /// Time until the phase is finished, estimated from its progress so far.
+    pub eta: Option<Duration>,
+}
+
+impl Job {
+    #[must_use]
+    pub fn new() -> Job {
+        Job::default()
+    }
+
+    /// Token cancelling this job, to hand to work that only knows about cancellation like Lua scripts.
+    #[must_use]
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an error if the job is cancelled.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Enter `phase` with `total` steps if known, restarting the progress.
+    pub fn start(&self, phase: Phase, total: Option<u64>) {
+        *self
+            .progress
+            .started
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+        self.progress.done.store(0, Ordering::Relaxed);
+        self.progress
+            .total
+            .store(total.unwrap_or(0), Ordering::Relaxed);
+        self.progress.phase.store(phase.to_u8(), Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, steps: u64) {
+        self.progress.done.fetch_add(steps, Ordering::Relaxed);
+    }
+
+    pub fn finish(&self) {
+        self.start(Phase::Finished, None);
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        let phase = Phase::from_u8(self.progress.phase.load(Ordering::Relaxed));
+        let done = self.progress.done.load(Ordering::Relaxed);
+        let total = Some(self.progress.total.load(Ordering::Relaxed)).filter(|total| *total != 0);
+        let elapsed = self
+            .progress
+            .started
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .map(|started| started.elapsed())
+            .unwrap_or_default();
+        let eta = total
+            .filter(|total| done != 0 && done <= *total)
+            .map(|total| {
+                let nanos = elapsed.as_nanos() * u128::from(total - done) / u128::from(done);
+                Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+            });
+        ProgressSnapshot {
+            phase,
+            done,
+            total,
+            elapsed,
+            eta,
+        }
+    }
+}
+
+impl ProgressSnapshot {
+    /// Share of the phase that is done, in percent.
+    #[must_use]
+    pub fn percent(&self) -> Option<u64> {
+        self.total
+            .map(|total| u128::from(self.done.min(total)) * 100 / u128::from(total))
+            .and_then(|percent| u64::try_from(percent).ok())
+    }
+}
+
+/// Formats as `<phase> <percent>%, <eta>s left`, leaving out what is unknown.
+impl fmt::Display for ProgressSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.phase)?;
+        match self.percent() {
+            Some(percent) => write!(f, " {percent}%")?,
+            None if self.done != 0 => write!(f, " {}", self.done)?,
+            None => {}
+        }
+        if let Some(eta) = self.eta {
+            write!(f, ", {}s left", eta.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use super::{Cancelled, Job, Phase};
+
+    #[test]
+    fn progress() {
+        let job = Job::new();
+        assert_eq!(job.snapshot().phase, Phase::Waiting);
+        assert_eq!(job.snapshot().to_string(), "Waiting");
+
+        job.start(Phase::Building, Some(4));
+        job.clone().advance(1);
+        let snapshot = job.snapshot();
+        assert_eq!(snapshot.phase, Phase::Building);
+        assert_eq!((snapshot.done, snapshot.total), (1, Some(4)));
+        assert_eq!(snapshot.percent(), Some(25));
+        assert!(snapshot.eta.is_some());
+        assert!(snapshot.to_string().starts_with("Building 25%, "));
+
+        job.start(Phase::Solving, None);
+        job.advance(7);
+        let snapshot = job.snapshot();
+        assert_eq!(
+            (snapshot.done, snapshot.total, snapshot.eta),
+            (7, None, None)
+        );
+        assert_eq!(snapshot.to_string(), "Solving 7");
+
+        job.finish();
+        assert_eq!(job.snapshot().phase, Phase::Finished);
+    }
+
+    #[test]
+    fn cancel() {
+        let job = Job::new();
+        let token = job.cancel_token();
+        assert_eq!(job.check(), Ok(()));
+        token.cancel();
+        assert!(job.clone().is_cancelled());
+        assert_eq!(job.check(), Err(Cancelled));
+    }
+}
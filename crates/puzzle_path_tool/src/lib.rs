@@ -1,3 +1,9 @@
+pub mod cancel;
+pub mod job;
+pub mod lua;
+pub mod puzzle;
+pub mod solver;
+
 #[must_use]
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
@@ -0,0 +1,370 @@
+use std::path::Path;
+
+use mlua::{IntoLuaMulti, Lua};
+
+use crate::{cancel::CancelToken, puzzle::Puzzle};
+
+mod field;
+pub mod loader;
+mod puzzle;
+pub(crate) mod registry;
+pub mod sandbox;
+pub mod type_defs;
+pub mod workspace;
+
+use field::FieldModule;
+use puzzle::PuzzleModule;
+use registry::TableRegistrar;
+use sandbox::{ExecutionPolicy, LimitError};
+use type_defs::TypeDefs;
+use workspace::WorkspaceModule;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptError {
+    #[error("Lua Error: {0}")]
+    Lua(#[from] mlua::Error),
+    #[error("Error reading Script `{path}`: {source}")]
+    Io {
+        path: Box<Path>,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Script `{script}` returned {found} instead of a Puzzle")]
+    NotAPuzzle {
+        script: Box<str>,
+        found: &'static str,
+    },
+    #[error("Puzzle `{0}` is registered more than once")]
+    DuplicatePuzzle(Box<str>),
+    #[error("Unknown puzzle `{name}`, available puzzles: {available}")]
+    UnknownPuzzle { name: Box<str>, available: Box<str> },
+    #[error("Module `{name}` not found, searched: {searched}")]
+    ModuleNotFound { name: Box<str>, searched: Box<str> },
+    #[error("Path `{}` leads outside of `{}`", path.display(), dir.display())]
+    OutsideDir { path: Box<Path>, dir: Box<Path> },
+    #[error("Import cycle: {chain}")]
+    ImportCycle { chain: Box<str> },
+    #[error("{0}")]
+    Limit(LimitError),
+}
+
+/// Lua state with the puzzle api installed.
+pub struct LuaRuntime {
+    lua: Lua,
+}
+
+impl LuaRuntime {
+    /// Create a new Lua state with the default [`ExecutionPolicy`] and install all globals exposed to scripts.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if installing the api fails.
+    pub fn new() -> Result<Self, ScriptError> {
+        Self::with_policy(ExecutionPolicy::default())
+    }
+
+    /// Create a new Lua state restricted by `policy` and install all globals exposed to scripts.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if creating the state or installing the api fails.
+    pub fn with_policy(policy: ExecutionPolicy) -> Result<Self, ScriptError> {
+        Self::with_cancel_token(policy, CancelToken::new())
+    }
+
+    /// Create a new Lua state restricted by `policy`, whose scripts stop once `cancel` is cancelled.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if creating the state or installing the api fails.
+    pub fn with_cancel_token(
+        policy: ExecutionPolicy,
+        cancel: CancelToken,
+    ) -> Result<Self, ScriptError> {
+        let lua = sandbox::new_state(policy, cancel)?;
+        install_api(&lua)?;
+        Ok(Self { lua })
+    }
+
+    /// Execute a script from a file and return its result.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can not be read, or the script fails.
+    pub fn exec_file(&self, path: &Path) -> Result<mlua::Value, ScriptError> {
+        self.exec_file_with(path, ())
+    }
+
+    /// Execute a script from a file with arguments, which are available to it as `...`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can not be read, or the script fails.
+    pub fn exec_file_with(
+        &self,
+        path: &Path,
+        args: impl IntoLuaMulti,
+    ) -> Result<mlua::Value, ScriptError> {
+        loader::record_dependency(&self.lua, path)?;
+        let source = std::fs::read_to_string(path).map_err(|source| ScriptError::Io {
+            path: path.into(),
+            source,
+        })?;
+        self.run(|| {
+            let value = self
+                .lua
+                .load(source)
+                .set_name(format!("@{}", path.display()))
+                .call(args)?;
+            Ok(value)
+        })
+    }
+
+    /// Execute a puzzle script, which has to return the constructed puzzle.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the script fails, or does not return a puzzle.
+    pub fn load_puzzle(&self, path: &Path) -> Result<Puzzle, ScriptError> {
+        let value = self.exec_file_with(path, self.lua.create_table()?)?;
+        self.to_puzzle(&value, &path.display().to_string())
+    }
+
+    /// Extract the puzzle returned by a script.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the value is not a puzzle.
+    pub fn to_puzzle(&self, value: &mlua::Value, script: &str) -> Result<Puzzle, ScriptError> {
+        let not_a_puzzle = |found| ScriptError::NotAPuzzle {
+            script: script.into(),
+            found,
+        };
+        match value {
+            mlua::Value::UserData(data) => data
+                .borrow::<Puzzle>()
+                .map(|puzzle| puzzle.clone())
+                .map_err(|_| not_a_puzzle("userdata")),
+            value => Err(not_a_puzzle(value.type_name())),
+        }
+    }
+
+    /// Execute a script and return its result.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the script fails.
+    pub fn exec(&self, source: &str, name: &str) -> Result<mlua::Value, ScriptError> {
+        self.run(|| {
+            let value = self.lua.load(source).set_name(format!("@{name}")).eval()?;
+            Ok(value)
+        })
+    }
+
+    #[must_use]
+    pub fn lua(&self) -> &Lua {
+        &self.lua
+    }
+}
+
+fn install_api(lua: &Lua) -> mlua::Result<()> {
+    loader::install(lua)?;
+    TableRegistrar::install::<FieldModule>(lua)?;
+    TableRegistrar::install::<PuzzleModule>(lua)?;
+    TableRegistrar::install::<WorkspaceModule>(lua)?;
+    Ok(())
+}
+
+/// Describe every global, function, class and operator exposed to scripts.
+///
+/// # Errors
+///
+/// This function will return an error if describing a module fails,
+/// or if the parameter names of a function do not match its signature.
+pub fn type_defs() -> mlua::Result<TypeDefs> {
+    use field::{
+        Bool, Char, Field, FieldBase, Float, Id, Int, IntExpr, IntFieldInstance, RefField,
+        RootEntry, RootEntryInstance, SubEntry, SubEntryInstance,
+    };
+
+    let mut defs = TypeDefs::default();
+
+    defs.module::<FieldModule>()?;
+    defs.module::<PuzzleModule>()?;
+    defs.module::<WorkspaceModule>()?;
+
+    defs.class::<FieldBase>()?;
+    defs.class::<Field<Int>>()?;
+    defs.class::<Field<Id>>()?;
+    defs.class::<Field<Char>>()?;
+    defs.class::<Field<Float>>()?;
+    defs.class::<Field<Bool>>()?;
+    defs.class::<RefField>()?;
+    defs.class::<RootEntry>()?;
+    defs.class::<RootEntryInstance>()?;
+    defs.class::<SubEntry>()?;
+    defs.class::<SubEntryInstance>()?;
+    defs.class::<IntFieldInstance>()?;
+    defs.class::<IntExpr>()?;
+    defs.class::<Puzzle>()?;
+
+    Ok(defs)
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use std::{
+        ops::Deref,
+        path::{Path, PathBuf},
+    };
+
+    use super::{LuaRuntime, type_defs};
+
+    /// A fresh directory below the system temp directory, removed when dropped.
+    pub(super) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(super) fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("puzzpt-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn type_defs_are_complete() {
+        let defs = type_defs().unwrap();
+
+        assert!(
+            defs.undefined_types().is_empty(),
+            "Undefined types: {:?}",
+            defs.undefined_types()
+        );
+    }
+
+    #[test]
+    fn type_defs_match_globals() {
+        let runtime = LuaRuntime::new().unwrap();
+        let defs = type_defs().unwrap();
+
+        for module in defs.modules() {
+            let table: mlua::Table = runtime.lua().globals().get(module.name()).unwrap();
+            for function in module.functions() {
+                assert!(
+                    table.get::<mlua::Function>(function.name()).is_ok(),
+                    "Missing function {}.{}",
+                    module.name(),
+                    function.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn meta_output() {
+        let meta = type_defs().unwrap().to_meta();
+
+        assert!(meta.starts_with("---@meta\n"));
+        assert!(meta.contains("field = {}\n"));
+        assert!(
+            meta.contains(
+                "---@param id IdField\n---@return RefField\nfunction field.ref(id) end\n"
+            )
+        );
+        assert!(meta.contains("---@class IntField: Field\n"));
+        assert!(meta.contains(
+            "---@param min integer\n---@param max integer\n---@return SubEntryInstance\nfunction sub_entry:in_range(min, max) end\n"
+        ));
+        assert!(meta.contains("---@operator add(integer|IntFieldInstance|IntExpr): IntExpr\n"));
+    }
+
+    #[test]
+    fn query() {
+        let runtime = LuaRuntime::new().unwrap();
+
+        let value: String = runtime
+            .lua()
+            .load(
+                r"
+                local arrow = { id = field.id(), x = field.int() }
+                local cell = { id = field.id(), arrow = field.ref(arrow.id) }
+
+                local arr = field.root(arrow.id):any()
+                local x = arr:field(arrow.x)
+                local cells = arr:sub(cell.arrow):in_range(1, 3)
+
+                return tostring(2 * x + 1)
+                ",
+            )
+            .eval()
+            .unwrap();
+
+        assert!(value.starts_with("((2 * #"), "{value}");
+        assert!(value.ends_with(") + 1)"), "{value}");
+    }
+
+    #[test]
+    fn quantifiers() {
+        let runtime = LuaRuntime::new().unwrap();
+
+        let value: String = runtime
+            .lua()
+            .load(
+                r"
+                local arrow = { id = field.id() }
+                local cell = { id = field.id(), arrow = field.ref(arrow.id) }
+
+                local arr = field.root(arrow.id):at_least(2)
+                return tostring(arr) .. ', ' .. tostring(arr:sub(cell.arrow):exactly(3))
+                ",
+            )
+            .eval()
+            .unwrap();
+        assert!(value.starts_with("at_least(2) #"), "{value}");
+        assert!(value.contains(", exactly(3) #"), "{value}");
+
+        for (query, message) in [
+            ("root:sub(field.ref(field.id()))", "not the queried table"),
+            ("root:sub(ref):at_most(-1)", "Invalid count: -1 is negative"),
+            ("root:sub(ref):in_range(3, 1)", "Invalid range"),
+            (
+                "field.root(id):at_least(-2)",
+                "Invalid count: -2 is negative",
+            ),
+        ] {
+            let err = runtime
+                .exec(
+                    &format!(
+                        "local id = field.id()\nlocal ref = field.ref(id)\nlocal root = field.root(id):any()\n{query}"
+                    ),
+                    "test",
+                )
+                .unwrap_err();
+            assert!(err.to_string().contains(message), "{query}: {err}");
+        }
+    }
+
+    #[test]
+    fn wrong_field_kind() {
+        let runtime = LuaRuntime::new().unwrap();
+
+        let result = runtime.exec("field.root(field.int())", "test");
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,414 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    sync::atomic::{self, AtomicUsize},
+};
+
+use mlua::{FromLua, Lua, MetaMethod, UserDataRef};
+
+use super::registry::{ClassRegistrar, LuaClass, LuaModule, ModuleRegistrar, user_data_from_class};
+
+/// The `field` global, used to declare tables and query them.
+pub(crate) struct FieldModule;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldId {
+    value: usize,
+}
+
+static COUNTER: AtomicUsize = AtomicUsize::new(1);
+impl FieldId {
+    fn new() -> FieldId {
+        FieldId {
+            value: COUNTER.fetch_add(1, atomic::Ordering::Relaxed),
+        }
+    }
+}
+
+pub(crate) trait FieldKind: 'static {
+    const CLASS: &'static str;
+}
+
+macro_rules! field_kinds {
+    ($($kind:ident => $class:literal),* $(,)?) => {
+        $(
+            #[derive(Debug, Clone, Copy)]
+            pub(crate) struct $kind;
+
+            impl FieldKind for $kind {
+                const CLASS: &'static str = $class;
+            }
+        )*
+    };
+}
+
+field_kinds! {
+    Int => "IntField",
+    Id => "IdField",
+    Char => "CharField",
+    Float => "FloatField",
+    Bool => "BoolField",
+}
+
+/// A declared field of a table.
+#[derive(Debug)]
+pub(crate) struct Field<K> {
+    id: FieldId,
+    _kind: PhantomData<K>,
+}
+
+impl<K> Clone for Field<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for Field<K> {}
+
+impl<K> Field<K> {
+    fn new() -> Self {
+        Self {
+            id: FieldId::new(),
+            _kind: PhantomData,
+        }
+    }
+}
+
+/// A field referencing the id field of another table.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RefField {
+    id: FieldId,
+    target: FieldId,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RootEntry {
+    table: FieldId,
+}
+
+/// How many entries a query matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Quantifier {
+    Any,
+    All,
+    Exactly(i64),
+    AtLeast(i64),
+    AtMost(i64),
+    InRange(i64, i64),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RootEntryInstance {
+    table: FieldId,
+    quantifier: Quantifier,
+}
+
+/// The entries of another table referencing a queried entry.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SubEntry {
+    parent: FieldId,
+    via: RefField,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SubEntryInstance {
+    entry: SubEntry,
+    quantifier: Quantifier,
+}
+
+/// The value of an int field of a queried entry.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IntFieldInstance {
+    table: FieldId,
+    field: FieldId,
+}
+
+/// An integer expression over field instances, built with arithmetic operators.
+#[derive(Debug, Clone)]
+pub(crate) enum IntExpr {
+    Const(i64),
+    Field(IntFieldInstance),
+    Binary(Box<IntExpr>, BinaryOp, Box<IntExpr>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+impl LuaModule for FieldModule {
+    const NAME: &'static str = "field";
+
+    fn register<R: ModuleRegistrar>(registrar: &mut R) -> mlua::Result<()> {
+        registrar.function("int", &[], |_, ()| Ok(Field::<Int>::new()))?;
+        registrar.function("id", &[], |_, ()| Ok(Field::<Id>::new()))?;
+        registrar.function("char", &[], |_, ()| Ok(Field::<Char>::new()))?;
+        registrar.function("float", &[], |_, ()| Ok(Field::<Float>::new()))?;
+        registrar.function("bool", &[], |_, ()| Ok(Field::<Bool>::new()))?;
+        registrar.function("ref", &["id"], |_, (id,): (UserDataRef<Field<Id>>,)| {
+            Ok(RefField {
+                id: FieldId::new(),
+                target: id.id,
+            })
+        })?;
+        registrar.function("root", &["id"], |_, (id,): (UserDataRef<Field<Id>>,)| {
+            Ok(RootEntry { table: id.id })
+        })?;
+        Ok(())
+    }
+}
+
+/// Abstract base class of all fields, only present in the type definitions.
+pub(crate) struct FieldBase;
+
+user_data_from_class!(
+    FieldBase,
+    Field<Int>,
+    Field<Id>,
+    Field<Char>,
+    Field<Float>,
+    Field<Bool>,
+    RefField,
+    RootEntry,
+    RootEntryInstance,
+    SubEntry,
+    SubEntryInstance,
+    IntFieldInstance,
+    IntExpr,
+);
+
+impl LuaClass for FieldBase {
+    const NAME: &'static str = "Field";
+
+    fn register<R: ClassRegistrar<Self>>(_registrar: &mut R) {}
+}
+
+impl<K: FieldKind> LuaClass for Field<K>
+where
+    Field<K>: mlua::UserData,
+{
+    const NAME: &'static str = K::CLASS;
+    const PARENT: Option<&'static str> = Some(FieldBase::NAME);
+
+    fn register<R: ClassRegistrar<Self>>(_registrar: &mut R) {}
+}
+
+impl LuaClass for RefField {
+    const NAME: &'static str = "RefField";
+    const PARENT: Option<&'static str> = Some(FieldBase::NAME);
+
+    fn register<R: ClassRegistrar<Self>>(_registrar: &mut R) {}
+}
+
+impl LuaClass for RootEntry {
+    const NAME: &'static str = "RootEntry";
+
+    fn register<R: ClassRegistrar<Self>>(registrar: &mut R) {
+        registrar.method("any", &[], |_, this, ()| Ok(this.instance(Quantifier::Any)));
+        registrar.method("at_least", &["n"], |_, this, (n,): (i64,)| {
+            Ok(this.instance(Quantifier::AtLeast(count(n)?)))
+        });
+    }
+}
+
+impl RootEntry {
+    fn instance(self, quantifier: Quantifier) -> RootEntryInstance {
+        RootEntryInstance {
+            table: self.table,
+            quantifier,
+        }
+    }
+}
+
+impl LuaClass for RootEntryInstance {
+    const NAME: &'static str = "RootEntryInstance";
+
+    fn register<R: ClassRegistrar<Self>>(registrar: &mut R) {
+        registrar.method(
+            "field",
+            &["field"],
+            |_, this, (field,): (UserDataRef<Field<Int>>,)| {
+                Ok(IntFieldInstance {
+                    table: this.table,
+                    field: field.id,
+                })
+            },
+        );
+        registrar.method(
+            "sub",
+            &["field"],
+            |_, this, (field,): (UserDataRef<RefField>,)| {
+                if field.target != this.table {
+                    return Err(mlua::Error::runtime(format!(
+                        "Field #{} references table #{}, not the queried table #{}",
+                        field.id.value, field.target.value, this.table.value
+                    )));
+                }
+                Ok(SubEntry {
+                    parent: this.table,
+                    via: *field,
+                })
+            },
+        );
+        registrar.meta_method(MetaMethod::ToString, |_, this, ()| {
+            Ok(format!("{} #{}", this.quantifier, this.table.value))
+        });
+    }
+}
+
+impl LuaClass for SubEntry {
+    const NAME: &'static str = "SubEntry";
+
+    fn register<R: ClassRegistrar<Self>>(registrar: &mut R) {
+        registrar.method("any", &[], |_, this, ()| Ok(this.instance(Quantifier::Any)));
+        registrar.method("all", &[], |_, this, ()| Ok(this.instance(Quantifier::All)));
+        registrar.method("exactly", &["n"], |_, this, (n,): (i64,)| {
+            Ok(this.instance(Quantifier::Exactly(count(n)?)))
+        });
+        registrar.method("at_least", &["n"], |_, this, (n,): (i64,)| {
+            Ok(this.instance(Quantifier::AtLeast(count(n)?)))
+        });
+        registrar.method("at_most", &["n"], |_, this, (n,): (i64,)| {
+            Ok(this.instance(Quantifier::AtMost(count(n)?)))
+        });
+        registrar.method(
+            "in_range",
+            &["min", "max"],
+            |_, this, (min, max): (i64, i64)| {
+                if min > max {
+                    return Err(mlua::Error::runtime(format!(
+                        "Invalid range: min ({min}) is greater than max ({max})"
+                    )));
+                }
+                Ok(this.instance(Quantifier::InRange(count(min)?, max)))
+            },
+        );
+    }
+}
+
+impl SubEntry {
+    fn instance(self, quantifier: Quantifier) -> SubEntryInstance {
+        SubEntryInstance {
+            entry: self,
+            quantifier,
+        }
+    }
+}
+
+impl LuaClass for SubEntryInstance {
+    const NAME: &'static str = "SubEntryInstance";
+
+    fn register<R: ClassRegistrar<Self>>(registrar: &mut R) {
+        registrar.meta_method(MetaMethod::ToString, |_, this, ()| {
+            Ok(format!(
+                "{} #{} of #{}",
+                this.quantifier, this.entry.via.id.value, this.entry.parent.value
+            ))
+        });
+    }
+}
+
+/// Check the number of entries of a quantifier.
+fn count(n: i64) -> mlua::Result<i64> {
+    if n < 0 {
+        return Err(mlua::Error::runtime(format!(
+            "Invalid count: {n} is negative"
+        )));
+    }
+    Ok(n)
+}
+
+impl fmt::Display for Quantifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Quantifier::Any => write!(f, "any"),
+            Quantifier::All => write!(f, "all"),
+            Quantifier::Exactly(n) => write!(f, "exactly({n})"),
+            Quantifier::AtLeast(n) => write!(f, "at_least({n})"),
+            Quantifier::AtMost(n) => write!(f, "at_most({n})"),
+            Quantifier::InRange(min, max) => write!(f, "in_range({min}, {max})"),
+        }
+    }
+}
+
+impl LuaClass for IntFieldInstance {
+    const NAME: &'static str = "IntFieldInstance";
+
+    fn register<R: ClassRegistrar<Self>>(registrar: &mut R) {
+        register_arithmetic(registrar);
+        registrar.meta_method(MetaMethod::ToString, |_, this, ()| {
+            Ok(IntExpr::Field(*this).to_string())
+        });
+    }
+}
+
+impl LuaClass for IntExpr {
+    const NAME: &'static str = "IntExpr";
+
+    fn register<R: ClassRegistrar<Self>>(registrar: &mut R) {
+        register_arithmetic(registrar);
+        registrar.meta_method(MetaMethod::ToString, |_, this, ()| Ok(this.to_string()));
+    }
+}
+
+fn register_arithmetic<T, R: ClassRegistrar<T>>(registrar: &mut R) {
+    registrar.operator(MetaMethod::Add, |_, (a, b): (IntOperand, IntOperand)| {
+        Ok(IntExpr::binary(a, BinaryOp::Add, b))
+    });
+    registrar.operator(MetaMethod::Sub, |_, (a, b): (IntOperand, IntOperand)| {
+        Ok(IntExpr::binary(a, BinaryOp::Sub, b))
+    });
+    registrar.operator(MetaMethod::Mul, |_, (a, b): (IntOperand, IntOperand)| {
+        Ok(IntExpr::binary(a, BinaryOp::Mul, b))
+    });
+}
+
+/// Either operand of an arithmetic operator.
+pub(crate) struct IntOperand(IntExpr);
+
+impl FromLua for IntOperand {
+    fn from_lua(value: mlua::Value, lua: &Lua) -> mlua::Result<Self> {
+        if let mlua::Value::UserData(data) = &value {
+            if let Ok(instance) = data.borrow::<IntFieldInstance>() {
+                return Ok(Self(IntExpr::Field(*instance)));
+            }
+            if let Ok(expr) = data.borrow::<IntExpr>() {
+                return Ok(Self(expr.clone()));
+            }
+        }
+        i64::from_lua(value, lua).map(|n| Self(IntExpr::Const(n)))
+    }
+}
+
+impl super::registry::LuaType for IntOperand {
+    fn lua_type() -> std::borrow::Cow<'static, str> {
+        std::borrow::Cow::Borrowed("integer|IntFieldInstance|IntExpr")
+    }
+}
+
+impl IntExpr {
+    fn binary(a: IntOperand, op: BinaryOp, b: IntOperand) -> Self {
+        Self::Binary(Box::new(a.0), op, Box::new(b.0))
+    }
+}
+
+impl fmt::Display for IntExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntExpr::Const(n) => write!(f, "{n}"),
+            IntExpr::Field(instance) => {
+                write!(f, "#{}.{}", instance.table.value, instance.field.value)
+            }
+            IntExpr::Binary(a, op, b) => {
+                let op = match op {
+                    BinaryOp::Add => "+",
+                    BinaryOp::Sub => "-",
+                    BinaryOp::Mul => "*",
+                };
+                write!(f, "({a} {op} {b})")
+            }
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Component, Path, PathBuf},
+};
+
+use mlua::{Lua, Table, Value};
+
+use super::{LuaRuntime, ScriptError};
+
+/// Rule modules embedded in the binary, available to every script.
+const BUILTIN_MODULES: &[(&str, &str)] = &[
+    (
+        "arrow.arrow_rule",
+        include_str!("../../scripts/arrow/arrow_rule.lua"),
+    ),
+    (
+        "arrow.ded1.ded1_ded",
+        include_str!("../../scripts/arrow/ded1/ded1_ded.lua"),
+    ),
+    (
+        "sudoku.regions",
+        include_str!("../../scripts/sudoku/regions.lua"),
+    ),
+];
+
+/// State of the `require` function installed by [`install`].
+struct ModuleLoader {
+    script_dir: Option<PathBuf>,
+    /// Names of the modules currently being loaded, outermost first.
+    loading: Vec<Box<str>>,
+    /// Files each loaded module used, including those of the modules it required, by module name.
+    files: HashMap<Box<str>, BTreeSet<PathBuf>>,
+    /// Files used since the last [`LuaRuntime::take_dependencies`].
+    dependencies: BTreeSet<PathBuf>,
+}
+
+/// Where a module was found.
+enum ModuleSource {
+    File(PathBuf),
+    Builtin(&'static str),
+}
+
+/// Replace the global `require`.
+///
+/// Modules are looked up in `package.preload`, then relative to the script directory,
+/// then in the built-in library. Results are cached in `package.loaded`.
+pub(super) fn install(lua: &Lua) -> mlua::Result<()> {
+    lua.set_app_data(ModuleLoader {
+        script_dir: None,
+        loading: Vec::new(),
+        files: HashMap::new(),
+        dependencies: BTreeSet::new(),
+    });
+    let require = lua.create_function(|lua, (name,): (String,)| require(lua, &name))?;
+    lua.globals().set("require", require)
+}
+
+fn require(lua: &Lua, name: &str) -> mlua::Result<Value> {
+    let package: Table = lua.globals().get("package")?;
+    let loaded: Table = package.get("loaded")?;
+    let cached: Value = loaded.get(name)?;
+    if !cached.is_nil() {
+        let mut state = loader(lua)?;
+        if let Some(files) = state.files.get(name).cloned() {
+            state.dependencies.extend(files);
+        }
+        return Ok(cached);
+    }
+
+    {
+        let mut state = loader(lua)?;
+        if state.loading.iter().any(|module| **module == *name) {
+            let mut chain: Vec<&str> = state.loading.iter().map(|module| &**module).collect();
+            chain.push(name);
+            return Err(mlua::Error::external(ScriptError::ImportCycle {
+                chain: chain.join(" -> ").into(),
+            }));
+        }
+        state.loading.push(name.into());
+    }
+
+    // Collect the files of this module apart from those of the script requiring it
+    let outer = std::mem::take(&mut loader(lua)?.dependencies);
+    let result = load(lua, &package, name);
+    {
+        let mut state = loader(lua)?;
+        state.loading.pop();
+        let files = std::mem::replace(&mut state.dependencies, outer);
+        state.dependencies.extend(files.iter().cloned());
+        if result.is_ok() {
+            state.files.insert(name.into(), files);
+        }
+    }
+
+    let value = match result? {
+        Value::Nil => Value::Boolean(true),
+        value => value,
+    };
+    loaded.set(name, &value)?;
+    Ok(value)
+}
+
+fn load(lua: &Lua, package: &Table, name: &str) -> mlua::Result<Value> {
+    let preload: Table = package.get("preload")?;
+    if let Some(function) = preload.get::<Option<mlua::Function>>(name)? {
+        return function.call(name);
+    }
+
+    let script_dir = loader(lua)?.script_dir.clone();
+    match find(script_dir.as_deref(), name)? {
+        ModuleSource::File(path) => {
+            record_dependency(lua, &path)?;
+            let source = std::fs::read_to_string(&path).map_err(|source| {
+                mlua::Error::external(ScriptError::Io {
+                    path: path.as_path().into(),
+                    source,
+                })
+            })?;
+            lua.load(source)
+                .set_name(format!("@{}", path.display()))
+                .call((name, path.display().to_string()))
+        }
+        ModuleSource::Builtin(source) => lua
+            .load(source)
+            .set_name(format!("=[builtin {name}]"))
+            .call(name),
+    }
+}
+
+fn find(script_dir: Option<&Path>, name: &str) -> mlua::Result<ModuleSource> {
+    let mut searched = Vec::new();
+
+    if let Some(dir) = script_dir {
+        let relative = name.replace('.', "/");
+        let module = join_inside(dir, Path::new(&relative)).map_err(mlua::Error::external)?;
+        for candidate in [dir.join(format!("{relative}.lua")), module.join("init.lua")] {
+            if candidate.is_file() {
+                return Ok(ModuleSource::File(candidate));
+            }
+            searched.push(candidate.display().to_string());
+        }
+    }
+
+    if let Some((_, source)) = BUILTIN_MODULES.iter().find(|(module, _)| *module == name) {
+        return Ok(ModuleSource::Builtin(source));
+    }
+    searched.push(String::from("built-in library"));
+
+    Err(mlua::Error::external(ScriptError::ModuleNotFound {
+        name: name.into(),
+        searched: searched.join(", ").into(),
+    }))
+}
+
+/// `path` relative to `dir`, rejecting paths that are rooted or contain `..`, which could leave `dir`.
+pub(super) fn join_inside(dir: &Path, path: &Path) -> Result<PathBuf, ScriptError> {
+    if path.as_os_str().is_empty()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(ScriptError::OutsideDir {
+            path: path.into(),
+            dir: dir.into(),
+        });
+    }
+    Ok(dir.join(path))
+}
+
+/// Record a file used by a script.
+pub(super) fn record_dependency(lua: &Lua, path: &Path) -> mlua::Result<()> {
+    loader(lua)?.dependencies.insert(path.to_path_buf());
+    Ok(())
+}
+
+fn loader(lua: &Lua) -> mlua::Result<mlua::AppDataRefMut<'_, ModuleLoader>> {
+    lua.app_data_mut::<ModuleLoader>()
+        .ok_or_else(|| mlua::Error::runtime("Module loader is not installed"))
+}
+
+/// Names of all modules in the built-in library.
+pub fn builtin_modules() -> impl Iterator<Item = &'static str> {
+    BUILTIN_MODULES.iter().map(|(name, _)| *name)
+}
+
+impl LuaRuntime {
+    /// Set the directory `require` resolves modules against.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the module loader was removed from the state.
+    pub fn set_script_dir(&self, dir: impl Into<PathBuf>) -> Result<(), ScriptError> {
+        loader(&self.lua)?.script_dir = Some(dir.into());
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn script_dir(&self) -> Option<PathBuf> {
+        self.lua
+            .app_data_ref::<ModuleLoader>()
+            .and_then(|loader| loader.script_dir.clone())
+    }
+
+    /// Take the files scripts executed or required since the last call, including cached modules.
+    #[must_use]
+    pub fn take_dependencies(&self) -> BTreeSet<PathBuf> {
+        self.lua
+            .app_data_mut::<ModuleLoader>()
+            .map(|mut loader| std::mem::take(&mut loader.dependencies))
+            .unwrap_or_default()
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use crate::lua::{LuaRuntime, test::TempDir};
+
+    fn script_dir(name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        std::fs::create_dir_all(dir.join("lib/nested")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn resolve_and_cache() {
+        let dir = script_dir("resolve");
+        std::fs::write(
+            dir.join("lib/counter.lua"),
+            "loads = (loads or 0) + 1\nreturn { loads = loads }",
+        )
+        .unwrap();
+        std::fs::write(dir.join("lib/nested/init.lua"), "return 'init'").unwrap();
+
+        let runtime = LuaRuntime::new().unwrap();
+        runtime.set_script_dir(dir.to_path_buf()).unwrap();
+
+        let loads: i64 = runtime
+            .lua()
+            .load("require('lib.counter'); return require('lib.counter').loads")
+            .eval()
+            .unwrap();
+        assert_eq!(loads, 1);
+
+        let nested: String = runtime
+            .lua()
+            .load("return require('lib.nested')")
+            .eval()
+            .unwrap();
+        assert_eq!(nested, "init");
+    }
+
+    #[test]
+    fn builtin_modules() {
+        let runtime = LuaRuntime::new().unwrap();
+
+        for name in super::builtin_modules() {
+            runtime
+                .lua()
+                .load(format!("require('{name}')"))
+                .exec()
+                .unwrap();
+        }
+
+        let cells: i64 = runtime
+            .lua()
+            .load("return #require('sudoku.regions').boxes(6)[1]")
+            .eval()
+            .unwrap();
+        assert_eq!(cells, 6);
+    }
+
+    #[test]
+    fn script_dir_overrides_builtin() {
+        let dir = script_dir("override");
+        std::fs::create_dir_all(dir.join("sudoku")).unwrap();
+        std::fs::write(dir.join("sudoku/regions.lua"), "return 'mine'").unwrap();
+
+        let runtime = LuaRuntime::new().unwrap();
+        runtime.set_script_dir(dir.to_path_buf()).unwrap();
+
+        let value: String = runtime
+            .lua()
+            .load("return require('sudoku.regions')")
+            .eval()
+            .unwrap();
+        assert_eq!(value, "mine");
+    }
+
+    #[test]
+    fn dependencies() {
+        let dir = script_dir("dependencies");
+        std::fs::write(dir.join("lib/a.lua"), "return require('lib.b')").unwrap();
+        std::fs::write(dir.join("lib/b.lua"), "return 1").unwrap();
+        std::fs::write(dir.join("main.lua"), "return require('lib.a')").unwrap();
+
+        let runtime = LuaRuntime::new().unwrap();
+        runtime.set_script_dir(dir.to_path_buf()).unwrap();
+
+        runtime.exec_file(&dir.join("main.lua")).unwrap();
+        let expected = [
+            dir.join("lib/a.lua"),
+            dir.join("lib/b.lua"),
+            dir.join("main.lua"),
+        ];
+        assert!(runtime.take_dependencies().iter().eq(&expected));
+
+        // Cached modules are still dependencies of later scripts, with all modules they required
+        runtime.exec("require('lib.b')", "cached").unwrap();
+        assert!(runtime.take_dependencies().iter().eq(&expected[1..2]));
+        runtime.exec("require('lib.a')", "cached").unwrap();
+        assert!(runtime.take_dependencies().iter().eq(&expected[..2]));
+        assert!(runtime.take_dependencies().is_empty());
+    }
+
+    #[test]
+    fn import_cycle() {
+        let dir = script_dir("cycle");
+        std::fs::write(dir.join("lib/a.lua"), "return require('lib.b')").unwrap();
+        std::fs::write(dir.join("lib/b.lua"), "return require('lib.nested.c')").unwrap();
+        std::fs::write(dir.join("lib/nested/c.lua"), "return require('lib.b')").unwrap();
+
+        let runtime = LuaRuntime::new().unwrap();
+        runtime.set_script_dir(dir.to_path_buf()).unwrap();
+
+        let err = runtime.exec("require('lib.a')", "main").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Import cycle: lib.a -> lib.b -> lib.nested.c -> lib.b"),
+            "{err}"
+        );
+
+        // The failed loads must not stay on the stack
+        std::fs::write(dir.join("lib/nested/c.lua"), "return 'c'").unwrap();
+        runtime.exec("require('lib.a')", "main").unwrap();
+    }
+
+    #[test]
+    fn module_outside_script_dir() {
+        let dir = script_dir("outside");
+        std::fs::write(dir.join("secret.lua"), "return 'secret'").unwrap();
+        std::fs::write(dir.join("lib/nested/c.lua"), "return 'c'").unwrap();
+        let runtime = LuaRuntime::new().unwrap();
+        runtime.set_script_dir(dir.join("lib")).unwrap();
+
+        for name in ["..secret", "/etc/passwd", ""] {
+            let err = runtime
+                .exec(&format!("require({name:?})"), "main")
+                .unwrap_err();
+            assert!(err.to_string().contains("outside of"), "{name}: {err}");
+        }
+        runtime.exec("require('nested.c')", "main").unwrap();
+    }
+
+    #[test]
+    fn module_not_found() {
+        let runtime = LuaRuntime::new().unwrap();
+
+        let err = runtime
+            .exec("require('missing.module')", "main")
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Module `missing.module` not found"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::borrow::Cow;
+
+use mlua::{FromLua, Lua};
+
+use crate::puzzle::{Cell, Constraint, Puzzle};
+
+use super::registry::{
+    ClassRegistrar, LuaClass, LuaModule, LuaType, ModuleRegistrar, user_data_from_class,
+};
+
+/// The `puzzle` global, used to construct puzzles.
+pub(crate) struct PuzzleModule;
+
+impl LuaModule for PuzzleModule {
+    const NAME: &'static str = "puzzle";
+
+    fn register<R: ModuleRegistrar>(registrar: &mut R) -> mlua::Result<()> {
+        registrar.function("new", &["size"], |_, (size,): (Option<u8>,)| {
+            Puzzle::new(size.unwrap_or(9)).map_err(mlua::Error::external)
+        })?;
+        Ok(())
+    }
+}
+
+user_data_from_class!(Puzzle);
+
+impl LuaClass for Puzzle {
+    const NAME: &'static str = "Puzzle";
+
+    fn register<R: ClassRegistrar<Self>>(registrar: &mut R) {
+        registrar.method_mut("title", &["text"], |_, this, (text,): (String,)| {
+            this.set_title(text);
+            Ok(())
+        });
+        registrar.method_mut("author", &["text"], |_, this, (text,): (String,)| {
+            this.set_author(text);
+            Ok(())
+        });
+        registrar.method_mut("rules", &["text"], |_, this, (text,): (String,)| {
+            this.set_rules(text);
+            Ok(())
+        });
+        registrar.method_mut(
+            "given",
+            &["cell", "value"],
+            |_, this, (cell, value): (Cell, i64)| {
+                this.set_given(cell, value).map_err(mlua::Error::external)
+            },
+        );
+        registrar.method_mut(
+            "killer",
+            &["cells", "sum"],
+            |_, this, (cells, sum): (Vec<Cell>, Option<u32>)| {
+                add_constraint(
+                    this,
+                    Constraint::Killer {
+                        cells: cells.into(),
+                        sum,
+                    },
+                )
+            },
+        );
+        registrar.method_mut(
+            "thermometer",
+            &["cells"],
+            |_, this, (cells,): (Vec<Cell>,)| {
+                add_constraint(
+                    this,
+                    Constraint::Thermometer {
+                        cells: cells.into(),
+                    },
+                )
+            },
+        );
+        registrar.method_mut(
+            "arrow",
+            &["bulb", "line"],
+            |_, this, (bulb, line): (Vec<Cell>, Vec<Cell>)| {
+                add_constraint(
+                    this,
+                    Constraint::Arrow {
+                        bulb: bulb.into(),
+                        line: line.into(),
+                    },
+                )
+            },
+        );
+        registrar.method_mut("positive_diagonal", &[], |_, this, ()| {
+            add_constraint(this, Constraint::PositiveDiagonal)
+        });
+        registrar.method_mut("negative_diagonal", &[], |_, this, ()| {
+            add_constraint(this, Constraint::NegativeDiagonal)
+        });
+        registrar.method_mut("anti_knight", &[], |_, this, ()| {
+            add_constraint(this, Constraint::AntiKnight)
+        });
+        registrar.method_mut("anti_king", &[], |_, this, ()| {
+            add_constraint(this, Constraint::AntiKing)
+        });
+    }
+}
+
+fn add_constraint(puzzle: &mut Puzzle, constraint: Constraint) -> mlua::Result<()> {
+    puzzle
+        .add_constraint(constraint)
+        .map_err(mlua::Error::external)
+}
+
+/// Cells are passed as f-puzzles style strings, e.g. `"R1C1"`.
+impl FromLua for Cell {
+    fn from_lua(value: mlua::Value, lua: &Lua) -> mlua::Result<Self> {
+        let text = String::from_lua(value, lua)?;
+        text.parse().map_err(mlua::Error::external)
+    }
+}
+
+impl LuaType for Cell {
+    fn lua_type() -> Cow<'static, str> {
+        Cow::Borrowed("string")
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use crate::{
+        lua::LuaRuntime,
+        puzzle::{Cell, Constraint},
+    };
+
+    #[test]
+    fn construct() {
+        let runtime = LuaRuntime::new().unwrap();
+
+        let value = runtime
+            .exec(
+                r#"
+                local p = puzzle.new(6)
+                p:title("Small")
+                p:given("R1C1", 3)
+                p:killer({ "R1C2", "R2C2" }, 7)
+                p:thermometer({ "R3C3", "R3C4", "R3C5" })
+                p:anti_king()
+                return p
+                "#,
+                "construct",
+            )
+            .unwrap();
+        let puzzle = runtime.to_puzzle(&value, "construct").unwrap();
+
+        assert_eq!(puzzle.size(), 6);
+        assert_eq!(puzzle.title(), "Small");
+        assert_eq!(puzzle.givens().get(Cell::new(0, 0)), Some(3));
+        assert_eq!(puzzle.constraints().len(), 3);
+        assert_eq!(puzzle.constraints()[2], Constraint::AntiKing);
+    }
+
+    #[test]
+    fn invalid_values() {
+        let runtime = LuaRuntime::new().unwrap();
+
+        assert!(runtime.exec("puzzle.new(4):given('R1C1', 5)", "a").is_err());
+        assert!(runtime.exec("puzzle.new(4):given('R5C1', 1)", "b").is_err());
+        assert!(runtime.exec("puzzle.new(4):given('X', 1)", "c").is_err());
+        assert!(runtime.exec("puzzle.new(17)", "d").is_err());
+    }
+}
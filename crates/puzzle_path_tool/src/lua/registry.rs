@@ -0,0 +1,413 @@
+use std::borrow::Cow;
+
+use mlua::{
+    FromLuaMulti, IntoLuaMulti, Lua, MetaMethod, Table, UserData, UserDataMethods, UserDataRef,
+};
+
+use super::type_defs::{ClassDef, FunctionDef, ModuleDef, OperatorDef, ParamDef, TypeDefs};
+
+/// Name of a value's type, as seen by `LuaLS`.
+pub(crate) trait LuaType {
+    fn lua_type() -> Cow<'static, str>;
+}
+
+/// Names of a function's parameter types, as seen by `LuaLS`.
+pub(crate) trait LuaParams {
+    fn lua_types() -> Vec<Cow<'static, str>>;
+}
+
+/// A userdata type exposed to scripts.
+///
+/// Methods are registered once through [`LuaClass::register`],
+/// which is used both for installing the userdata and for describing it in the type definitions.
+pub(crate) trait LuaClass: UserData + Sized + 'static {
+    const NAME: &'static str;
+    const PARENT: Option<&'static str> = None;
+
+    fn register<R: ClassRegistrar<Self>>(registrar: &mut R);
+}
+
+/// A global table of functions exposed to scripts.
+pub(crate) trait LuaModule {
+    const NAME: &'static str;
+
+    #[allow(clippy::missing_errors_doc)]
+    fn register<R: ModuleRegistrar>(registrar: &mut R) -> mlua::Result<()>;
+}
+
+pub(crate) trait ClassRegistrar<T> {
+    fn method<F, A, R>(&mut self, name: &'static str, params: &[&'static str], method: F)
+    where
+        F: Fn(&Lua, &T, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti + LuaParams,
+        R: IntoLuaMulti + LuaType;
+
+    fn method_mut<F, A, R>(&mut self, name: &'static str, params: &[&'static str], method: F)
+    where
+        F: FnMut(&Lua, &mut T, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti + LuaParams,
+        R: IntoLuaMulti + LuaType;
+
+    /// Register an operator, which receives both operands in source order.
+    fn operator<F, O, R>(&mut self, meta: MetaMethod, function: F)
+    where
+        F: Fn(&Lua, (O, O)) -> mlua::Result<R> + 'static,
+        O: mlua::FromLua + LuaType,
+        R: IntoLuaMulti + LuaType;
+
+    /// Register a metamethod, that is not part of the type definitions (e.g. `__tostring`).
+    fn meta_method<F, A, R>(&mut self, meta: MetaMethod, method: F)
+    where
+        F: Fn(&Lua, &T, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti,
+        R: IntoLuaMulti;
+}
+
+pub(crate) trait ModuleRegistrar {
+    #[allow(clippy::missing_errors_doc)]
+    fn function<F, A, R>(
+        &mut self,
+        name: &'static str,
+        params: &[&'static str],
+        function: F,
+    ) -> mlua::Result<()>
+    where
+        F: Fn(&Lua, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti + LuaParams,
+        R: IntoLuaMulti + LuaType;
+}
+
+/// Implement [`UserData`] by forwarding to [`LuaClass::register`].
+macro_rules! user_data_from_class {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl ::mlua::UserData for $ty {
+                fn add_methods<M: ::mlua::UserDataMethods<Self>>(methods: &mut M) {
+                    <Self as $crate::lua::registry::LuaClass>::register(
+                        &mut $crate::lua::registry::MethodRegistrar::new(methods),
+                    );
+                }
+            }
+        )*
+    };
+}
+pub(crate) use user_data_from_class;
+
+pub(crate) struct MethodRegistrar<'a, M> {
+    methods: &'a mut M,
+}
+
+impl<'a, M> MethodRegistrar<'a, M> {
+    pub(crate) fn new(methods: &'a mut M) -> Self {
+        Self { methods }
+    }
+}
+
+impl<T, M> ClassRegistrar<T> for MethodRegistrar<'_, M>
+where
+    M: UserDataMethods<T>,
+{
+    fn method<F, A, R>(&mut self, name: &'static str, _params: &[&'static str], method: F)
+    where
+        F: Fn(&Lua, &T, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti + LuaParams,
+        R: IntoLuaMulti + LuaType,
+    {
+        self.methods.add_method(name, method);
+    }
+
+    fn method_mut<F, A, R>(&mut self, name: &'static str, _params: &[&'static str], method: F)
+    where
+        F: FnMut(&Lua, &mut T, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti + LuaParams,
+        R: IntoLuaMulti + LuaType,
+    {
+        self.methods.add_method_mut(name, method);
+    }
+
+    fn operator<F, O, R>(&mut self, meta: MetaMethod, function: F)
+    where
+        F: Fn(&Lua, (O, O)) -> mlua::Result<R> + 'static,
+        O: mlua::FromLua + LuaType,
+        R: IntoLuaMulti + LuaType,
+    {
+        self.methods.add_meta_function(meta, function);
+    }
+
+    fn meta_method<F, A, R>(&mut self, meta: MetaMethod, method: F)
+    where
+        F: Fn(&Lua, &T, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti,
+        R: IntoLuaMulti,
+    {
+        self.methods.add_meta_method(meta, method);
+    }
+}
+
+pub(crate) struct ClassRecorder {
+    class: ClassDef,
+    /// The first method that could not be described, class registration can't fail.
+    error: Option<mlua::Error>,
+}
+
+impl ClassRecorder {
+    pub(crate) fn describe<T: LuaClass>() -> mlua::Result<ClassDef> {
+        let mut recorder = Self {
+            class: ClassDef::new(T::NAME, T::PARENT),
+            error: None,
+        };
+        <T as LuaClass>::register(&mut recorder);
+        match recorder.error {
+            Some(err) => Err(err),
+            None => Ok(recorder.class),
+        }
+    }
+
+    fn push_method(&mut self, method: mlua::Result<FunctionDef>) {
+        match method {
+            Ok(method) => self.class.push_method(method),
+            Err(err) => {
+                self.error.get_or_insert(err);
+            }
+        }
+    }
+}
+
+impl<T> ClassRegistrar<T> for ClassRecorder {
+    fn method<F, A, R>(&mut self, name: &'static str, params: &[&'static str], _method: F)
+    where
+        F: Fn(&Lua, &T, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti + LuaParams,
+        R: IntoLuaMulti + LuaType,
+    {
+        self.push_method(function_def::<A, R>(name, params));
+    }
+
+    fn method_mut<F, A, R>(&mut self, name: &'static str, params: &[&'static str], _method: F)
+    where
+        F: FnMut(&Lua, &mut T, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti + LuaParams,
+        R: IntoLuaMulti + LuaType,
+    {
+        self.push_method(function_def::<A, R>(name, params));
+    }
+
+    fn operator<F, O, R>(&mut self, meta: MetaMethod, _function: F)
+    where
+        F: Fn(&Lua, (O, O)) -> mlua::Result<R> + 'static,
+        O: mlua::FromLua + LuaType,
+        R: IntoLuaMulti + LuaType,
+    {
+        let operator = meta.name().trim_start_matches("__");
+        let operand = (meta != MetaMethod::Unm).then(O::lua_type);
+        self.class
+            .push_operator(OperatorDef::new(operator, operand, R::lua_type()));
+    }
+
+    fn meta_method<F, A, R>(&mut self, _meta: MetaMethod, _method: F)
+    where
+        F: Fn(&Lua, &T, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti,
+        R: IntoLuaMulti,
+    {
+    }
+}
+
+pub(crate) struct TableRegistrar<'a> {
+    lua: &'a Lua,
+    table: Table,
+}
+
+impl<'a> TableRegistrar<'a> {
+    /// Create the module's global table and register its functions into it.
+    pub(crate) fn install<T: LuaModule>(lua: &'a Lua) -> mlua::Result<()> {
+        let mut registrar = Self {
+            lua,
+            table: lua.create_table()?,
+        };
+        T::register(&mut registrar)?;
+        lua.globals().set(T::NAME, registrar.table)
+    }
+}
+
+impl ModuleRegistrar for TableRegistrar<'_> {
+    fn function<F, A, R>(
+        &mut self,
+        name: &'static str,
+        _params: &[&'static str],
+        function: F,
+    ) -> mlua::Result<()>
+    where
+        F: Fn(&Lua, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti + LuaParams,
+        R: IntoLuaMulti + LuaType,
+    {
+        self.table.set(name, self.lua.create_function(function)?)
+    }
+}
+
+pub(crate) struct ModuleRecorder {
+    module: ModuleDef,
+}
+
+impl ModuleRecorder {
+    pub(crate) fn describe<T: LuaModule>() -> mlua::Result<ModuleDef> {
+        let mut recorder = Self {
+            module: ModuleDef::new(T::NAME),
+        };
+        T::register(&mut recorder)?;
+        Ok(recorder.module)
+    }
+}
+
+impl ModuleRegistrar for ModuleRecorder {
+    fn function<F, A, R>(
+        &mut self,
+        name: &'static str,
+        params: &[&'static str],
+        _function: F,
+    ) -> mlua::Result<()>
+    where
+        F: Fn(&Lua, A) -> mlua::Result<R> + 'static,
+        A: FromLuaMulti + LuaParams,
+        R: IntoLuaMulti + LuaType,
+    {
+        self.module
+            .push_function(function_def::<A, R>(name, params)?);
+        Ok(())
+    }
+}
+
+impl TypeDefs {
+    pub(crate) fn module<T: LuaModule>(&mut self) -> mlua::Result<()> {
+        self.push_module(ModuleRecorder::describe::<T>()?);
+        Ok(())
+    }
+
+    pub(crate) fn class<T: LuaClass>(&mut self) -> mlua::Result<()> {
+        self.push_class(ClassRecorder::describe::<T>()?);
+        Ok(())
+    }
+}
+
+fn function_def<A: LuaParams, R: LuaType>(
+    name: &'static str,
+    params: &[&'static str],
+) -> mlua::Result<FunctionDef> {
+    let types = A::lua_types();
+    if params.len() != types.len() {
+        return Err(mlua::Error::runtime(format!(
+            "Parameter names of `{name}` do not match its signature: {} names for {} parameters",
+            params.len(),
+            types.len()
+        )));
+    }
+
+    let params = params
+        .iter()
+        .zip(types)
+        .map(|(param, lua_type)| ParamDef::new(param, lua_type))
+        .collect();
+
+    Ok(FunctionDef::new(name, params, R::lua_type()))
+}
+
+macro_rules! lua_type {
+    ($lua_type:literal: $($ty:ty),*) => {
+        $(
+            impl LuaType for $ty {
+                fn lua_type() -> Cow<'static, str> {
+                    Cow::Borrowed($lua_type)
+                }
+            }
+        )*
+    };
+}
+
+lua_type!("nil": ());
+lua_type!("boolean": bool);
+lua_type!("integer": i8, i16, i32, i64, u8, u16, u32, usize);
+lua_type!("number": f32, f64);
+lua_type!("string": String, mlua::String);
+lua_type!("table": Table);
+lua_type!("function": mlua::Function);
+lua_type!("any": mlua::Value);
+
+impl<T: LuaType> LuaType for Option<T> {
+    fn lua_type() -> Cow<'static, str> {
+        Cow::Owned(format!("{}?", T::lua_type()))
+    }
+}
+
+impl<T: LuaType> LuaType for Vec<T> {
+    fn lua_type() -> Cow<'static, str> {
+        Cow::Owned(format!("{}[]", T::lua_type()))
+    }
+}
+
+impl<T: LuaClass> LuaType for UserDataRef<T> {
+    fn lua_type() -> Cow<'static, str> {
+        Cow::Borrowed(T::NAME)
+    }
+}
+
+impl<T: LuaClass> LuaType for T {
+    fn lua_type() -> Cow<'static, str> {
+        Cow::Borrowed(T::NAME)
+    }
+}
+
+macro_rules! lua_params {
+    ($($name:ident),*) => {
+        impl<$($name: LuaType),*> LuaParams for ($($name,)*) {
+            fn lua_types() -> Vec<Cow<'static, str>> {
+                vec![$($name::lua_type()),*]
+            }
+        }
+    };
+}
+
+lua_params!();
+lua_params!(A);
+lua_params!(A, B);
+lua_params!(A, B, C);
+lua_params!(A, B, C, D);
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use mlua::UserData;
+
+    use super::{
+        ClassRecorder, ClassRegistrar, LuaClass, LuaModule, ModuleRecorder, ModuleRegistrar,
+    };
+
+    /// Names a single parameter of a function taking two.
+    struct Mismatched;
+
+    impl UserData for Mismatched {}
+
+    impl LuaClass for Mismatched {
+        const NAME: &'static str = "Mismatched";
+
+        fn register<R: ClassRegistrar<Self>>(registrar: &mut R) {
+            registrar.method("add", &["a"], |_, _, (a, b): (i64, i64)| Ok(a + b));
+        }
+    }
+
+    impl LuaModule for Mismatched {
+        const NAME: &'static str = "mismatched";
+
+        fn register<R: ModuleRegistrar>(registrar: &mut R) -> mlua::Result<()> {
+            registrar.function("add", &["a"], |_, (a, b): (i64, i64)| Ok(a + b))
+        }
+    }
+
+    #[test]
+    fn mismatched_params() {
+        let err = ClassRecorder::describe::<Mismatched>().unwrap_err();
+        assert!(err.to_string().contains("`add`"), "{err}");
+        let err = ModuleRecorder::describe::<Mismatched>().unwrap_err();
+        assert!(err.to_string().contains("`add`"), "{err}");
+    }
+}
@@ -0,0 +1,508 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use mlua::{Debug, HookTriggers, Lua, StdLib, VmState};
+
+use crate::cancel::CancelToken;
+
+use super::{LuaRuntime, ScriptError};
+
+/// Number of VM instructions between two checks of the budget.
+const HOOK_INTERVAL: u32 = 1000;
+
+/// What scripts run by a [`LuaRuntime`] are allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutionPolicy {
+    /// Load the `io` and `os` libraries and allow `dofile`/`loadfile`.
+    pub system_libs: bool,
+    /// Maximum number of VM instructions of a single execution.
+    pub instruction_limit: Option<u64>,
+    /// Maximum wall time of a single execution.
+    pub time_limit: Option<Duration>,
+    /// Maximum memory of the whole Lua state, in bytes.
+    pub memory_limit: Option<usize>,
+}
+
+impl Default for ExecutionPolicy {
+    fn default() -> Self {
+        Self {
+            system_libs: false,
+            instruction_limit: None,
+            time_limit: Some(Duration::from_secs(30)),
+            memory_limit: Some(256 * 1024 * 1024),
+        }
+    }
+}
+
+impl ExecutionPolicy {
+    /// A policy without any restrictions, for trusted scripts.
+    #[must_use]
+    pub fn unrestricted() -> Self {
+        Self {
+            system_libs: true,
+            instruction_limit: None,
+            time_limit: None,
+            memory_limit: None,
+        }
+    }
+}
+
+/// Position in a script, as far as it is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub script: Box<str>,
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "`{}` at line {line}", self.script),
+            None => write!(f, "`{}`", self.script),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum LimitKind {
+    #[error("Instruction limit of {0} exceeded")]
+    Instructions(u64),
+    #[error("Time limit of {0:?} exceeded")]
+    Time(Duration),
+    #[error("Memory limit of {0} bytes exceeded")]
+    Memory(usize),
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{kind} in {}", location.as_ref().map_or_else(|| String::from("unknown script"), ToString::to_string))]
+pub struct LimitError {
+    pub kind: LimitKind,
+    /// Last known position of the script, unknown if it failed before the first check.
+    pub location: Option<Location>,
+}
+
+/// Budget of the current execution, checked by the instruction hook.
+struct Budget {
+    policy: ExecutionPolicy,
+    cancel: CancelToken,
+    /// Nesting of [`LuaRuntime::run`], only the outermost call starts a new budget.
+    depth: usize,
+    started: Instant,
+    instructions: u64,
+    location: Option<Location>,
+    /// Set once a limit is exceeded, so scripts can not `pcall` their way around it.
+    exceeded: Option<LimitError>,
+}
+
+impl Budget {
+    fn check(&self) -> Option<LimitKind> {
+        if self.cancel.is_cancelled() {
+            return Some(LimitKind::Cancelled);
+        }
+        if let Some(limit) = self.policy.instruction_limit
+            && self.instructions > limit
+        {
+            return Some(LimitKind::Instructions(limit));
+        }
+        if let Some(limit) = self.policy.time_limit
+            && self.started.elapsed() > limit
+        {
+            return Some(LimitKind::Time(limit));
+        }
+        None
+    }
+}
+
+/// Re-raise errors caught by `pcall`/`xpcall` once a limit is exceeded.
+const PROTECTED_CALLS: &str = r"
+local check = ...
+local pcall, xpcall = pcall, xpcall
+
+local function checked(ok, ...)
+    if not ok then
+        check()
+    end
+    return ok, ...
+end
+
+function _G.pcall(f, ...)
+    return checked(pcall(f, ...))
+end
+
+function _G.xpcall(f, handler, ...)
+    return checked(xpcall(f, handler, ...))
+end
+";
+
+/// Move the hook to every resumed coroutine and back to the resuming thread afterwards.
+const HOOKED_COROUTINES: &str = r#"
+local check, hook = ...
+local close, create, resume, running = coroutine.close, coroutine.create, coroutine.resume, coroutine.running
+
+local function resumed(ok, ...)
+    hook(running())
+    if not ok then
+        check()
+    end
+    return ok, ...
+end
+
+function coroutine.resume(co, ...)
+    if type(co) == "thread" then
+        hook(co)
+    end
+    return resumed(resume(co, ...))
+end
+
+local hooked_resume = coroutine.resume
+
+function coroutine.wrap(f)
+    local co = create(f)
+    local function unwrap(ok, ...)
+        if not ok then
+            close(co)
+            error((...), 0)
+        end
+        return ...
+    end
+    return function(...)
+        return unwrap(hooked_resume(co, ...))
+    end
+end
+"#;
+
+fn hook_triggers() -> HookTriggers {
+    HookTriggers::new().every_nth_instruction(HOOK_INTERVAL)
+}
+
+/// Count instructions and stop the execution once the [`Budget`] is exceeded.
+fn budget_hook(lua: &Lua, debug: &Debug) -> mlua::Result<VmState> {
+    let location = Location {
+        script: debug
+            .source()
+            .short_src
+            .map_or_else(|| "?".into(), Into::into),
+        line: usize::try_from(debug.curr_line())
+            .ok()
+            .filter(|line| *line > 0),
+    };
+    let Some(mut budget) = lua.app_data_mut::<Budget>() else {
+        return Ok(VmState::Continue);
+    };
+    budget.instructions += u64::from(HOOK_INTERVAL);
+    budget.location = Some(location.clone());
+    if let Some(error) = &budget.exceeded {
+        return Err(mlua::Error::external(error.clone()));
+    }
+    if let Some(kind) = budget.check() {
+        let error = LimitError {
+            kind,
+            location: Some(location),
+        };
+        budget.exceeded = Some(error.clone());
+        return Err(mlua::Error::external(error));
+    }
+    Ok(VmState::Continue)
+}
+
+/// Create a Lua state following `policy`.
+pub(super) fn new_state(policy: ExecutionPolicy, cancel: CancelToken) -> mlua::Result<Lua> {
+    let mut libs = StdLib::COROUTINE | StdLib::TABLE | StdLib::STRING | StdLib::UTF8;
+    libs |= StdLib::MATH | StdLib::PACKAGE;
+    if policy.system_libs {
+        libs |= StdLib::IO | StdLib::OS;
+    }
+    let lua = Lua::new_with(libs, mlua::LuaOptions::default())?;
+
+    let globals = lua.globals();
+    if !policy.system_libs {
+        globals.raw_remove("dofile")?;
+        globals.raw_remove("loadfile")?;
+    }
+    let package: mlua::Table = globals.get("package")?;
+    package.raw_remove("loadlib")?;
+    package.raw_remove("searchers")?;
+
+    // Only allow loading source code, precompiled chunks can break the VM
+    let load: mlua::Function = globals.get("load")?;
+    // The arguments are chunk, chunkname, mode and env. Lua tells a missing env apart from an
+    // explicit nil, only a given env replaces the globals of the chunk.
+    let text_load = lua.create_function(move |lua, mut args: mlua::MultiValue| {
+        let len = args.len().max(3);
+        args.resize(len, mlua::Value::Nil);
+        args[2] = mlua::Value::String(lua.create_string("t")?);
+        load.call::<mlua::MultiValue>(args)
+    })?;
+    globals.set("load", text_load)?;
+
+    if let Some(limit) = policy.memory_limit {
+        lua.set_memory_limit(limit)?;
+    }
+
+    lua.set_app_data(Budget {
+        policy,
+        cancel,
+        depth: 0,
+        started: Instant::now(),
+        instructions: 0,
+        location: None,
+        exceeded: None,
+    });
+    lua.set_hook(hook_triggers(), |lua, debug| budget_hook(lua, &debug));
+
+    let check = lua.create_function(|lua, ()| match lua.app_data_ref::<Budget>() {
+        Some(budget) => match &budget.exceeded {
+            Some(error) => Err(mlua::Error::external(error.clone())),
+            None => Ok(()),
+        },
+        None => Ok(()),
+    })?;
+    lua.load(PROTECTED_CALLS)
+        .set_name("=[sandbox]")
+        .call::<()>(&check)?;
+
+    // Lua threads only inherit the hook, mlua ignores it on any but the hooked one
+    let hook = lua.create_function(|_, thread: mlua::Thread| {
+        thread.set_hook(hook_triggers(), |lua, debug| budget_hook(lua, &debug));
+        Ok(())
+    })?;
+    lua.load(HOOKED_COROUTINES)
+        .set_name("=[sandbox]")
+        .call::<()>((check, hook))?;
+
+    Ok(lua)
+}
+
+impl LuaRuntime {
+    /// Run `f` as one execution, with a fresh budget unless it is nested in another one.
+    ///
+    /// Errors caused by exceeded limits are turned into [`ScriptError::Limit`].
+    pub(crate) fn run<T>(
+        &self,
+        f: impl FnOnce() -> Result<T, ScriptError>,
+    ) -> Result<T, ScriptError> {
+        if let Some(mut budget) = self.lua.app_data_mut::<Budget>() {
+            if budget.depth == 0 {
+                budget.started = Instant::now();
+                budget.instructions = 0;
+                budget.location = None;
+                budget.exceeded = None;
+            }
+            budget.depth += 1;
+        }
+
+        let result = f();
+
+        let Some(mut budget) = self.lua.app_data_mut::<Budget>() else {
+            return result;
+        };
+        budget.depth -= 1;
+        result.map_err(|err| match err {
+            ScriptError::Lua(err) => {
+                if let Some(limit) = err.chain().find_map(|e| e.downcast_ref::<LimitError>()) {
+                    return ScriptError::Limit(limit.clone());
+                }
+                if let Some(limit) = budget.policy.memory_limit
+                    && is_memory_error(&err)
+                {
+                    return ScriptError::Limit(LimitError {
+                        kind: LimitKind::Memory(limit),
+                        location: budget.location.clone(),
+                    });
+                }
+                ScriptError::Lua(err)
+            }
+            err => err,
+        })
+    }
+
+    /// Token to cancel running scripts from another thread.
+    #[must_use]
+    pub fn cancel_token(&self) -> CancelToken {
+        self.lua
+            .app_data_ref::<Budget>()
+            .map(|budget| budget.cancel.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn is_memory_error(err: &mlua::Error) -> bool {
+    match err {
+        mlua::Error::MemoryError(_) => true,
+        mlua::Error::CallbackError { cause, .. } | mlua::Error::WithContext { cause, .. } => {
+            is_memory_error(cause)
+        }
+        _ => false,
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::lua::{LuaRuntime, ScriptError};
+
+    use super::{ExecutionPolicy, LimitError, LimitKind};
+
+    fn limited(policy: ExecutionPolicy) -> LuaRuntime {
+        LuaRuntime::with_policy(policy).unwrap()
+    }
+
+    fn limit_error(result: Result<mlua::Value, ScriptError>) -> LimitError {
+        match result {
+            Err(ScriptError::Limit(limit)) => limit,
+            other => panic!("Expected a limit error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn no_system_libs() {
+        let runtime = LuaRuntime::new().unwrap();
+
+        for name in ["os", "io", "debug", "dofile", "loadfile"] {
+            let value = runtime.exec(&format!("return {name}"), "libs").unwrap();
+            assert!(value.is_nil(), "{name} is available");
+        }
+        assert!(runtime.exec("return string.dump", "libs").is_ok());
+
+        let bytecode = runtime
+            .exec("return load(string.dump(function() end), 'x', 'b')", "libs")
+            .unwrap();
+        assert!(bytecode.is_nil());
+
+        let runtime = limited(ExecutionPolicy::unrestricted());
+        assert!(!runtime.exec("return os", "libs").unwrap().is_nil());
+    }
+
+    #[test]
+    fn load_keeps_globals() {
+        let runtime = LuaRuntime::new().unwrap();
+
+        let value = runtime
+            .exec("return load('return string.upper(\"a\")')()", "load")
+            .unwrap();
+        assert_eq!(value.as_str().unwrap(), "A");
+        let value = runtime
+            .exec(
+                "return load('return puzzle ~= nil', 'chunk', 't')()",
+                "load",
+            )
+            .unwrap();
+        assert_eq!(value.as_boolean(), Some(true));
+
+        let value = runtime
+            .exec("return load('return x', 'chunk', 't', { x = 4 })()", "load")
+            .unwrap();
+        assert_eq!(value.as_integer(), Some(4));
+        assert!(
+            runtime
+                .exec("return load('return string', 'chunk', 't', nil)()", "load")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn instruction_limit() {
+        let runtime = limited(ExecutionPolicy {
+            instruction_limit: Some(100_000),
+            ..ExecutionPolicy::default()
+        });
+
+        let limit = limit_error(runtime.exec("local x = 0\nwhile true do x = x + 1 end", "loop"));
+        assert_eq!(limit.kind, LimitKind::Instructions(100_000));
+        let location = limit.location.unwrap();
+        assert_eq!(&*location.script, "loop");
+        assert_eq!(location.line, Some(2));
+
+        // Every execution gets a new budget
+        runtime.exec("for i = 1, 1000 do end", "short").unwrap();
+    }
+
+    #[test]
+    fn time_limit_survives_pcall() {
+        let runtime = limited(ExecutionPolicy {
+            time_limit: Some(Duration::from_millis(50)),
+            ..ExecutionPolicy::default()
+        });
+
+        let limit = limit_error(runtime.exec(
+            "while true do pcall(function() while true do end end) end",
+            "pcall",
+        ));
+        assert_eq!(limit.kind, LimitKind::Time(Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn time_limit_in_coroutines() {
+        let runtime = limited(ExecutionPolicy {
+            time_limit: Some(Duration::from_millis(50)),
+            ..ExecutionPolicy::default()
+        });
+
+        for script in [
+            "coroutine.wrap(function() while true do end end)()",
+            "coroutine.resume(coroutine.create(function() while true do end end))",
+            "local co = coroutine.wrap(function() coroutine.wrap(function() while true do end end)() end)\nco()",
+        ] {
+            let limit = limit_error(runtime.exec(script, "coroutine"));
+            assert_eq!(limit.kind, LimitKind::Time(Duration::from_millis(50)));
+        }
+
+        // The main thread is still limited once a coroutine returned
+        let limit = limit_error(runtime.exec(
+            "coroutine.resume(coroutine.create(function() coroutine.yield() end))\nwhile true do end",
+            "coroutine",
+        ));
+        assert_eq!(limit.kind, LimitKind::Time(Duration::from_millis(50)));
+
+        let value = runtime
+            .exec(
+                "local co = coroutine.wrap(function(a) local b = coroutine.yield(a + 1) return b * 2 end)\nreturn co(1) + co(4)",
+                "coroutine",
+            )
+            .unwrap();
+        assert_eq!(value.as_integer(), Some(10));
+    }
+
+    #[test]
+    fn memory_limit() {
+        let runtime = limited(ExecutionPolicy {
+            memory_limit: Some(8 * 1024 * 1024),
+            ..ExecutionPolicy::default()
+        });
+
+        let limit = limit_error(runtime.exec(
+            "local t = {}\nfor i = 1, 1e8 do t[i] = tostring(i) end",
+            "memory",
+        ));
+        assert_eq!(limit.kind, LimitKind::Memory(8 * 1024 * 1024));
+
+        // The state is still usable afterwards
+        runtime.exec("return 1", "after").unwrap();
+    }
+
+    #[test]
+    fn cancel() {
+        let runtime = LuaRuntime::new().unwrap();
+        let token = runtime.cancel_token();
+
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            token.cancel();
+        });
+        let limit = limit_error(runtime.exec("while true do end", "cancel"));
+        handle.join().unwrap();
+
+        assert_eq!(limit.kind, LimitKind::Cancelled);
+        assert_eq!(
+            limit.to_string(),
+            "Cancelled in `cancel` at line 1",
+            "{limit}"
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::{borrow::Cow, fmt::Write};
+
+/// Description of everything exposed to scripts, used to emit `---@meta` definitions.
+#[derive(Debug, Clone, Default)]
+pub struct TypeDefs {
+    modules: Vec<ModuleDef>,
+    classes: Vec<ClassDef>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleDef {
+    name: &'static str,
+    functions: Vec<FunctionDef>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassDef {
+    name: &'static str,
+    parent: Option<&'static str>,
+    methods: Vec<FunctionDef>,
+    operators: Vec<OperatorDef>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionDef {
+    name: &'static str,
+    params: Vec<ParamDef>,
+    returns: Cow<'static, str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamDef {
+    name: &'static str,
+    lua_type: Cow<'static, str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OperatorDef {
+    operator: &'static str,
+    operand: Option<Cow<'static, str>>,
+    result: Cow<'static, str>,
+}
+
+/// Types known to `LuaLS` without a `---@class` definition.
+const BUILTIN_TYPES: &[&str] = &[
+    "any", "nil", "boolean", "integer", "number", "string", "table", "function",
+];
+
+impl TypeDefs {
+    pub(crate) fn push_module(&mut self, module: ModuleDef) {
+        self.modules.push(module);
+    }
+
+    pub(crate) fn push_class(&mut self, class: ClassDef) {
+        self.classes.push(class);
+    }
+
+    #[must_use]
+    pub fn modules(&self) -> &[ModuleDef] {
+        &self.modules
+    }
+
+    #[must_use]
+    pub fn classes(&self) -> &[ClassDef] {
+        &self.classes
+    }
+
+    /// Type names that are referenced by a signature, but neither builtin nor defined as a class.
+    #[must_use]
+    pub fn undefined_types(&self) -> Vec<&str> {
+        let functions = self
+            .modules
+            .iter()
+            .flat_map(|module| &module.functions)
+            .chain(self.classes.iter().flat_map(|class| &class.methods));
+
+        let mut referenced: Vec<&str> = functions
+            .flat_map(|function| {
+                function
+                    .params
+                    .iter()
+                    .map(|param| param.lua_type.as_ref())
+                    .chain([function.returns.as_ref()])
+            })
+            .chain(self.classes.iter().flat_map(|class| {
+                class
+                    .operators
+                    .iter()
+                    .flat_map(|op| op.operand.iter().chain([&op.result]))
+                    .map(AsRef::as_ref)
+            }))
+            .chain(self.classes.iter().filter_map(|class| class.parent))
+            .flat_map(|lua_type| lua_type.split('|'))
+            .map(|lua_type| lua_type.trim_end_matches(['?', '[', ']']))
+            .filter(|lua_type| {
+                // Function types are checked by `LuaLS` itself
+                !lua_type.starts_with("fun(")
+                    && !BUILTIN_TYPES.contains(lua_type)
+                    && !self.classes.iter().any(|class| class.name == *lua_type)
+            })
+            .collect();
+
+        referenced.sort_unstable();
+        referenced.dedup();
+        referenced
+    }
+
+    /// Render the definitions as a `LuaLS` `---@meta` file.
+    #[must_use]
+    pub fn to_meta(&self) -> String {
+        let mut out = String::new();
+        // Writing into a String can not fail.
+        let _ = self.write_meta(&mut out);
+        out
+    }
+
+    fn write_meta(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(out, "---@meta")?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "-- Generated by puzzle-path-tool {}, do not edit.",
+            env!("CARGO_PKG_VERSION")
+        )?;
+
+        for module in &self.modules {
+            writeln!(out)?;
+            writeln!(out, "{} = {{}}", module.name)?;
+
+            for function in &module.functions {
+                writeln!(out)?;
+                function.write_meta(out, module.name, '.')?;
+            }
+        }
+
+        for class in &self.classes {
+            writeln!(out)?;
+            class.write_meta(out)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ModuleDef {
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            functions: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push_function(&mut self, function: FunctionDef) {
+        self.functions.push(function);
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    #[must_use]
+    pub fn functions(&self) -> &[FunctionDef] {
+        &self.functions
+    }
+}
+
+impl ClassDef {
+    pub(crate) fn new(name: &'static str, parent: Option<&'static str>) -> Self {
+        Self {
+            name,
+            parent,
+            methods: Vec::new(),
+            operators: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push_method(&mut self, method: FunctionDef) {
+        self.methods.push(method);
+    }
+
+    pub(crate) fn push_operator(&mut self, operator: OperatorDef) {
+        self.operators.push(operator);
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    #[must_use]
+    pub fn methods(&self) -> &[FunctionDef] {
+        &self.methods
+    }
+
+    #[must_use]
+    pub fn operators(&self) -> &[OperatorDef] {
+        &self.operators
+    }
+
+    fn write_meta(&self, out: &mut String) -> std::fmt::Result {
+        match self.parent {
+            Some(parent) => writeln!(out, "---@class {}: {parent}", self.name)?,
+            None => writeln!(out, "---@class {}", self.name)?,
+        }
+
+        for operator in &self.operators {
+            match &operator.operand {
+                Some(operand) => writeln!(
+                    out,
+                    "---@operator {}({operand}): {}",
+                    operator.operator, operator.result
+                )?,
+                None => writeln!(
+                    out,
+                    "---@operator {}: {}",
+                    operator.operator, operator.result
+                )?,
+            }
+        }
+
+        if self.methods.is_empty() {
+            return Ok(());
+        }
+
+        let local = snake_case(self.name);
+        writeln!(out, "local {local} = {{}}")?;
+
+        for method in &self.methods {
+            writeln!(out)?;
+            method.write_meta(out, &local, ':')?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FunctionDef {
+    pub(crate) fn new(
+        name: &'static str,
+        params: Vec<ParamDef>,
+        returns: Cow<'static, str>,
+    ) -> Self {
+        Self {
+            name,
+            params,
+            returns,
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    #[must_use]
+    pub fn params(&self) -> &[ParamDef] {
+        &self.params
+    }
+
+    #[must_use]
+    pub fn returns(&self) -> &str {
+        &self.returns
+    }
+
+    fn write_meta(&self, out: &mut String, owner: &str, separator: char) -> std::fmt::Result {
+        for param in &self.params {
+            writeln!(out, "---@param {} {}", param.name, param.lua_type)?;
+        }
+
+        if self.returns != "nil" {
+            writeln!(out, "---@return {}", self.returns)?;
+        }
+
+        let params: Vec<&str> = self.params.iter().map(|param| param.name).collect();
+        writeln!(
+            out,
+            "function {owner}{separator}{}({}) end",
+            self.name,
+            params.join(", ")
+        )
+    }
+}
+
+impl ParamDef {
+    pub(crate) fn new(name: &'static str, lua_type: Cow<'static, str>) -> Self {
+        Self { name, lua_type }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    #[must_use]
+    pub fn lua_type(&self) -> &str {
+        &self.lua_type
+    }
+}
+
+impl OperatorDef {
+    pub(crate) fn new(
+        operator: &'static str,
+        operand: Option<Cow<'static, str>>,
+        result: Cow<'static, str>,
+    ) -> Self {
+        Self {
+            operator,
+            operand,
+            result,
+        }
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i != 0 {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
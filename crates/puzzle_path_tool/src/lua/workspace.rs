@@ -0,0 +1,401 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use mlua::{FromLua, Function, Lua, Table, Value};
+
+use crate::puzzle::Puzzle;
+
+use super::{
+    LuaRuntime, ScriptError,
+    loader::join_inside,
+    registry::{LuaModule, LuaType, ModuleRegistrar},
+};
+
+/// The `workspace` global, used by workspace scripts to register puzzles.
+pub(crate) struct WorkspaceModule;
+
+/// Registrations of a workspace script, collected while it runs.
+struct WorkspaceState {
+    dir: PathBuf,
+    defaults: Table,
+    puzzles: Vec<WorkspacePuzzle>,
+}
+
+/// A named puzzle registered by a workspace script.
+#[derive(Debug)]
+pub struct WorkspacePuzzle {
+    name: Box<str>,
+    build: PuzzleBuild,
+    options: Option<Table>,
+}
+
+#[derive(Debug, Clone)]
+enum PuzzleBuild {
+    Function(Function),
+    Script(PathBuf),
+}
+
+/// A loaded workspace script, with all its registered puzzles.
+#[derive(Debug)]
+pub struct Workspace {
+    path: Box<Path>,
+    defaults: Table,
+    puzzles: Vec<WorkspacePuzzle>,
+}
+
+impl LuaModule for WorkspaceModule {
+    const NAME: &'static str = "workspace";
+
+    fn register<R: ModuleRegistrar>(registrar: &mut R) -> mlua::Result<()> {
+        registrar.function("options", &["options"], |lua, (options,): (Table,)| {
+            let state = state(lua)?;
+            for pair in options.pairs::<Value, Value>() {
+                let (key, value) = pair?;
+                state.defaults.set(key, value)?;
+            }
+            Ok(())
+        })?;
+        registrar.function(
+            "module",
+            &["name", "path"],
+            |lua, (name, path): (String, String)| {
+                let path = join_inside(&state(lua)?.dir, Path::new(&path))
+                    .map_err(mlua::Error::external)?;
+                let loader = lua.create_function(move |lua, ()| {
+                    super::loader::record_dependency(lua, &path)?;
+                    let source = std::fs::read_to_string(&path).map_err(|err| {
+                        mlua::Error::runtime(format!(
+                            "Error reading module `{}`: {err}",
+                            path.display()
+                        ))
+                    })?;
+                    lua.load(source)
+                        .set_name(format!("@{}", path.display()))
+                        .eval::<Value>()
+                })?;
+                let package: Table = lua.globals().get("package")?;
+                let preload: Table = package.get("preload")?;
+                preload.set(name, loader)
+            },
+        )?;
+        registrar.function(
+            "puzzle",
+            &["name", "build", "options"],
+            |lua, (name, build, options): (String, PuzzleBuild, Option<Table>)| {
+                let mut state = state(lua)?;
+                if state.puzzles.iter().any(|puzzle| *puzzle.name == *name) {
+                    return Err(mlua::Error::external(ScriptError::DuplicatePuzzle(
+                        name.into(),
+                    )));
+                }
+                let build = match build {
+                    PuzzleBuild::Script(path) => PuzzleBuild::Script(
+                        join_inside(&state.dir, &path).map_err(mlua::Error::external)?,
+                    ),
+                    build @ PuzzleBuild::Function(_) => build,
+                };
+                state.puzzles.push(WorkspacePuzzle {
+                    name: name.into(),
+                    build,
+                    options,
+                });
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+}
+
+fn state(lua: &Lua) -> mlua::Result<mlua::AppDataRefMut<'_, WorkspaceState>> {
+    lua.app_data_mut::<WorkspaceState>().ok_or_else(|| {
+        mlua::Error::runtime("`workspace` functions can only be used in workspace scripts")
+    })
+}
+
+/// A puzzle is built by a function receiving the options, or by a script relative to the workspace.
+impl FromLua for PuzzleBuild {
+    fn from_lua(value: Value, lua: &Lua) -> mlua::Result<Self> {
+        match value {
+            Value::Function(function) => Ok(Self::Function(function)),
+            value => String::from_lua(value, lua).map(|path| Self::Script(path.into())),
+        }
+    }
+}
+
+impl LuaType for PuzzleBuild {
+    fn lua_type() -> Cow<'static, str> {
+        Cow::Borrowed("string|fun(options: table): Puzzle")
+    }
+}
+
+impl LuaRuntime {
+    /// Run a workspace script and collect its registered puzzles.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the script can not be read, or fails.
+    pub fn load_workspace(&self, path: &Path) -> Result<Workspace, ScriptError> {
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        self.lua.set_app_data(WorkspaceState {
+            dir,
+            defaults: self.lua.create_table()?,
+            puzzles: Vec::new(),
+        });
+
+        let result = self.exec_file(path);
+
+        let state = self.lua.remove_app_data::<WorkspaceState>();
+        result?;
+
+        let Some(state) = state else {
+            return Err(mlua::Error::runtime("Workspace state was removed by the script").into());
+        };
+
+        Ok(Workspace {
+            path: path.into(),
+            defaults: state.defaults,
+            puzzles: state.puzzles,
+        })
+    }
+}
+
+impl Workspace {
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[must_use]
+    pub fn puzzles(&self) -> &[WorkspacePuzzle] {
+        &self.puzzles
+    }
+
+    /// Select puzzles by name, in the order given. No names select all puzzles.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a name is not registered in the workspace.
+    pub fn select<S: AsRef<str>>(&self, names: &[S]) -> Result<Vec<&WorkspacePuzzle>, ScriptError> {
+        if names.is_empty() {
+            return Ok(self.puzzles.iter().collect());
+        }
+
+        names
+            .iter()
+            .map(|name| {
+                let name = name.as_ref();
+                self.puzzles
+                    .iter()
+                    .find(|puzzle| &*puzzle.name == name)
+                    .ok_or_else(|| ScriptError::UnknownPuzzle {
+                        name: name.into(),
+                        available: self
+                            .puzzles
+                            .iter()
+                            .map(|puzzle| &*puzzle.name)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                            .into(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Build a registered puzzle, with the workspace defaults merged into its options.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the build fails, or does not return a puzzle.
+    pub fn build(
+        &self,
+        runtime: &LuaRuntime,
+        puzzle: &WorkspacePuzzle,
+    ) -> Result<Puzzle, ScriptError> {
+        let lua = runtime.lua();
+        let options = self.options(lua, puzzle)?;
+
+        let value = runtime.run(|| match &puzzle.build {
+            PuzzleBuild::Function(function) => Ok(function.call::<Value>(options.clone())?),
+            PuzzleBuild::Script(path) => runtime.exec_file_with(path, options.clone()),
+        })?;
+
+        let mut built = runtime.to_puzzle(&value, &puzzle.name)?;
+
+        if built.author().is_empty()
+            && let Some(author) = options.get::<Option<String>>("author")?
+        {
+            built.set_author(author);
+        }
+        if built.rules().is_empty()
+            && let Some(rules) = options.get::<Option<String>>("rules")?
+        {
+            built.set_rules(rules);
+        }
+        if built.title().is_empty() {
+            built.set_title(&*puzzle.name);
+        }
+
+        Ok(built)
+    }
+
+    fn options(&self, lua: &Lua, puzzle: &WorkspacePuzzle) -> mlua::Result<Table> {
+        let options = lua.create_table()?;
+        for source in [Some(&self.defaults), puzzle.options.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            for pair in source.pairs::<Value, Value>() {
+                let (key, value) = pair?;
+                options.set(key, value)?;
+            }
+        }
+        options.set("name", &*puzzle.name)?;
+        Ok(options)
+    }
+}
+
+impl WorkspacePuzzle {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use crate::lua::{LuaRuntime, ScriptError, test::TempDir};
+
+    use super::WorkspacePuzzle;
+
+    /// The workspace script is `ws.lua` in the returned directory.
+    fn write_workspace() -> TempDir {
+        let dir = TempDir::new("workspace");
+        std::fs::create_dir_all(dir.join("puzzles")).unwrap();
+
+        std::fs::write(
+            dir.join("helpers.lua"),
+            r#"
+            local helpers = {}
+            function helpers.base(options)
+                local p = puzzle.new(options.size)
+                p:given("R1C1", 1)
+                return p
+            end
+            return helpers
+            "#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("puzzles/c.lua"),
+            r"
+            local options = ...
+            local p = require('helpers').base(options)
+            p:title('From file ' .. options.name)
+            return p
+            ",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("ws.lua"),
+            r#"
+            workspace.options({ size = 4, author = "Default Author" })
+            workspace.module("helpers", "helpers.lua")
+
+            workspace.puzzle("a", function(options)
+                return require("helpers").base(options)
+            end)
+            workspace.puzzle("b", function(options)
+                local p = require("helpers").base(options)
+                p:author("Someone")
+                return p
+            end, { size = 6 })
+            workspace.puzzle("c", "puzzles/c.lua")
+            workspace.puzzle("broken", function() return 1 end)
+            "#,
+        )
+        .unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn select_and_build() {
+        let dir = write_workspace();
+        let runtime = LuaRuntime::new().unwrap();
+        let workspace = runtime.load_workspace(&dir.join("ws.lua")).unwrap();
+
+        let names: Vec<_> = workspace
+            .puzzles()
+            .iter()
+            .map(WorkspacePuzzle::name)
+            .collect();
+        assert_eq!(names, ["a", "b", "c", "broken"]);
+
+        let selected = workspace.select(&["c", "a"]).unwrap();
+        assert_eq!(selected[0].name(), "c");
+        assert_eq!(selected[1].name(), "a");
+        assert_eq!(workspace.select::<&str>(&[]).unwrap().len(), 4);
+        assert!(matches!(
+            workspace.select(&["missing"]),
+            Err(ScriptError::UnknownPuzzle { .. })
+        ));
+
+        let a = workspace.build(&runtime, selected[1]).unwrap();
+        assert_eq!(a.size(), 4);
+        assert_eq!(a.title(), "a");
+        assert_eq!(a.author(), "Default Author");
+
+        let b = workspace
+            .build(&runtime, workspace.select(&["b"]).unwrap()[0])
+            .unwrap();
+        assert_eq!(b.size(), 6);
+        assert_eq!(b.author(), "Someone");
+
+        let c = workspace.build(&runtime, selected[0]).unwrap();
+        assert_eq!(c.title(), "From file c");
+
+        let broken = workspace.build(&runtime, workspace.select(&["broken"]).unwrap()[0]);
+        assert!(matches!(broken, Err(ScriptError::NotAPuzzle { .. })));
+    }
+
+    #[test]
+    fn duplicate_puzzle() {
+        let runtime = LuaRuntime::new().unwrap();
+        let dir = TempDir::new("duplicate");
+        let path = dir.join("ws.lua");
+        std::fs::write(
+            &path,
+            "workspace.puzzle('a', 'a.lua')\nworkspace.puzzle('a', 'b.lua')",
+        )
+        .unwrap();
+
+        assert!(runtime.load_workspace(&path).is_err());
+    }
+
+    #[test]
+    fn paths_outside_of_workspace_dir() {
+        let runtime = LuaRuntime::new().unwrap();
+        let dir = TempDir::new("escape");
+        let path = dir.join("ws.lua");
+
+        for script in [
+            "workspace.module('m', '../m.lua')",
+            "workspace.module('m', '/etc/passwd')",
+            "workspace.puzzle('a', 'puzzles/../../a.lua')",
+            "workspace.puzzle('a', '/tmp/a.lua')",
+        ] {
+            std::fs::write(&path, script).unwrap();
+            let err = runtime.load_workspace(&path).unwrap_err();
+            assert!(err.to_string().contains("outside of"), "{script}: {err}");
+        }
+    }
+
+    #[test]
+    fn outside_of_workspace() {
+        let runtime = LuaRuntime::new().unwrap();
+        assert!(runtime.exec("workspace.puzzle('a', 'a.lua')", "x").is_err());
+    }
+}
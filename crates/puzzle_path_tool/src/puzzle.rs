@@ -0,0 +1,540 @@
+use std::{fmt, str::FromStr, sync::LazyLock};
+
+use puzzle_formats::format::full::{self, FullCell, FullConstraint, FullPuzzle};
+use regex::Regex;
+
+pub use puzzle_formats::format::full::MAX_SIZE;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum PuzzleError {
+    #[error("Invalid grid size {0}, expected 1 to {MAX_SIZE}")]
+    InvalidSize(u8),
+    #[error("Invalid cell `{0}`, expected format `R<row>C<col>`")]
+    InvalidCell(Box<str>),
+    #[error("Cell {cell} is outside of the {size}x{size} grid")]
+    CellOutOfGrid { cell: Cell, size: u8 },
+    #[error("Value {value} in cell {cell} is outside of 1 to {size}")]
+    ValueOutOfRange { cell: Cell, value: i64, size: u8 },
+    #[error("Constraint `{0}` needs at least one cell")]
+    EmptyConstraint(&'static str),
+}
+
+/// A cell of the grid, zero based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cell {
+    row: u8,
+    column: u8,
+}
+
+impl Cell {
+    #[must_use]
+    pub const fn new(row: u8, column: u8) -> Self {
+        Self { row, column }
+    }
+
+    #[must_use]
+    pub const fn row(self) -> u8 {
+        self.row
+    }
+
+    #[must_use]
+    pub const fn column(self) -> u8 {
+        self.column
+    }
+
+    #[must_use]
+    pub fn index(self, size: u8) -> usize {
+        usize::from(self.row) * usize::from(size) + usize::from(self.column)
+    }
+}
+
+/// Formats as one based `R<row>C<col>`, as used by f-puzzles.
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "R{}C{}",
+            u16::from(self.row) + 1,
+            u16::from(self.column) + 1
+        )
+    }
+}
+
+static CELL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    #[allow(clippy::unwrap_used)]
+    Regex::new(r"^[Rr](?<row>\d+)[Cc](?<column>\d+)$").unwrap()
+});
+
+impl FromStr for Cell {
+    type Err = PuzzleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || PuzzleError::InvalidCell(s.into());
+        let captures = CELL_RE.captures(s).ok_or_else(invalid)?;
+
+        let parse = |name: &str| -> Result<u8, PuzzleError> {
+            let value: u8 = captures[name].parse().map_err(|_| invalid())?;
+            value.checked_sub(1).ok_or_else(invalid)
+        };
+
+        Ok(Cell::new(parse("row")?, parse("column")?))
+    }
+}
+
+/// Values of all cells of a square grid, empty cells are `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid {
+    size: u8,
+    values: Box<[u8]>,
+}
+
+impl Grid {
+    /// Create an empty grid.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the size is 0 or larger than [`MAX_SIZE`].
+    pub fn new(size: u8) -> Result<Self, PuzzleError> {
+        if size == 0 || size > MAX_SIZE {
+            return Err(PuzzleError::InvalidSize(size));
+        }
+        let cells = usize::from(size) * usize::from(size);
+        Ok(Self {
+            size,
+            values: vec![0; cells].into_boxed_slice(),
+        })
+    }
+
+    #[must_use]
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    #[must_use]
+    pub fn get(&self, cell: Cell) -> Option<u8> {
+        self.values
+            .get(cell.index(self.size))
+            .copied()
+            .filter(|value| *value != 0)
+    }
+
+    /// Set or clear the value of a cell.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the cell or value is outside of the grid.
+    pub fn set(&mut self, cell: Cell, value: Option<u8>) -> Result<(), PuzzleError> {
+        self.check_cell(cell)?;
+        let raw = value.unwrap_or(0);
+        if raw > self.size {
+            return Err(PuzzleError::ValueOutOfRange {
+                cell,
+                value: i64::from(raw),
+                size: self.size,
+            });
+        }
+        self.values[cell.index(self.size)] = raw;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an error if the cell is outside of the grid.
+    pub fn check_cell(&self, cell: Cell) -> Result<(), PuzzleError> {
+        if cell.row >= self.size || cell.column >= self.size {
+            return Err(PuzzleError::CellOutOfGrid {
+                cell,
+                size: self.size,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = Cell> + use<> {
+        let size = self.size;
+        (0..size).flat_map(move |row| (0..size).map(move |column| Cell::new(row, column)))
+    }
+
+    /// Number of filled cells.
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.values.iter().filter(|value| **value != 0).count()
+    }
+
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.values.iter().all(|value| *value != 0)
+    }
+
+    /// Values row by row, 0 for empty cells.
+    #[must_use]
+    pub fn to_rows(&self) -> Box<[Box<[u8]>]> {
+        self.values
+            .chunks(usize::from(self.size))
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Height and width of a box, the same as in all puzzle formats, see [`full::box_dims`].
+    #[must_use]
+    pub fn box_dims(&self) -> (u8, u8) {
+        full::box_dims(self.size)
+    }
+}
+
+/// Formats one row per line, empty cells as `.`.
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.size {
+            for column in 0..self.size {
+                if column != 0 && self.size > 9 {
+                    write!(f, " ")?;
+                }
+                match self.get(Cell::new(row, column)) {
+                    Some(value) if self.size > 9 => write!(f, "{value:>2}")?,
+                    Some(value) => write!(f, "{value}")?,
+                    None if self.size > 9 => write!(f, " .")?,
+                    None => write!(f, ".")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    /// Cells in the cage contain no repeats and sum to the optional total.
+    Killer {
+        cells: Box<[Cell]>,
+        sum: Option<u32>,
+    },
+    /// Values strictly increase from the bulb (first cell).
+    Thermometer { cells: Box<[Cell]> },
+    /// Values along the line sum to the value of the bulb cells.
+    Arrow {
+        bulb: Box<[Cell]>,
+        line: Box<[Cell]>,
+    },
+    /// The diagonal from bottom left to top right contains no repeats.
+    PositiveDiagonal,
+    /// The diagonal from top left to bottom right contains no repeats.
+    NegativeDiagonal,
+    /// Cells a knight's move apart can not contain the same value.
+    AntiKnight,
+    /// Cells a king's move apart can not contain the same value.
+    AntiKing,
+}
+
+impl Constraint {
+    /// Short name of the constraint type, used for summaries, see [`FullConstraint::kind`].
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        FullConstraint::from(self).kind()
+    }
+
+    pub(crate) fn cells(&self) -> impl Iterator<Item = &Cell> {
+        let (a, b): (&[Cell], &[Cell]) = match self {
+            Constraint::Killer { cells, .. } | Constraint::Thermometer { cells } => (cells, &[]),
+            Constraint::Arrow { bulb, line } => (bulb, line),
+            _ => (&[], &[]),
+        };
+        a.iter().chain(b)
+    }
+}
+
+/// A puzzle as constructed by a script: givens, global rules and constraints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    title: Box<str>,
+    author: Box<str>,
+    rules: Box<str>,
+    givens: Grid,
+    constraints: Vec<Constraint>,
+}
+
+impl Puzzle {
+    /// Create a puzzle without givens or constraints.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the size is not supported.
+    pub fn new(size: u8) -> Result<Self, PuzzleError> {
+        Ok(Self {
+            title: "".into(),
+            author: "".into(),
+            rules: "".into(),
+            givens: Grid::new(size)?,
+            constraints: Vec::new(),
+        })
+    }
+
+    #[must_use]
+    pub fn size(&self) -> u8 {
+        self.givens.size()
+    }
+
+    #[must_use]
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    #[must_use]
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    #[must_use]
+    pub fn rules(&self) -> &str {
+        &self.rules
+    }
+
+    pub fn set_title(&mut self, title: impl Into<Box<str>>) {
+        self.title = title.into();
+    }
+
+    pub fn set_author(&mut self, author: impl Into<Box<str>>) {
+        self.author = author.into();
+    }
+
+    pub fn set_rules(&mut self, rules: impl Into<Box<str>>) {
+        self.rules = rules.into();
+    }
+
+    #[must_use]
+    pub fn givens(&self) -> &Grid {
+        &self.givens
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an error if the cell or value is outside of the grid.
+    pub fn set_given(&mut self, cell: Cell, value: i64) -> Result<(), PuzzleError> {
+        let size = self.size();
+        let value = u8::try_from(value)
+            .ok()
+            .filter(|value| (1..=size).contains(value))
+            .ok_or(PuzzleError::ValueOutOfRange { cell, value, size })?;
+        self.givens.set(cell, Some(value))
+    }
+
+    #[must_use]
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    /// # Errors
+    ///
+    /// This function will return an error if the constraint references cells outside of the grid.
+    pub fn add_constraint(&mut self, constraint: Constraint) -> Result<(), PuzzleError> {
+        for cell in constraint.cells() {
+            self.givens.check_cell(*cell)?;
+        }
+        match &constraint {
+            Constraint::Killer { cells, .. } | Constraint::Thermometer { cells }
+                if cells.is_empty() =>
+            {
+                return Err(PuzzleError::EmptyConstraint(constraint.kind()));
+            }
+            Constraint::Arrow { bulb, .. } if bulb.is_empty() => {
+                return Err(PuzzleError::EmptyConstraint(constraint.kind()));
+            }
+            _ => {}
+        }
+        self.constraints.push(constraint);
+        Ok(())
+    }
+}
+
+impl From<&Constraint> for FullConstraint {
+    fn from(constraint: &Constraint) -> Self {
+        let cells = |cells: &[Cell]| {
+            cells
+                .iter()
+                .map(|cell| FullCell::new(cell.row, cell.column))
+                .collect()
+        };
+        match constraint {
+            Constraint::Killer { cells: killer, sum } => FullConstraint::Killer {
+                cells: cells(killer),
+                sum: *sum,
+            },
+            Constraint::Thermometer { cells: thermometer } => FullConstraint::Thermometer {
+                cells: cells(thermometer),
+            },
+            Constraint::Arrow { bulb, line } => FullConstraint::Arrow {
+                bulb: cells(bulb),
+                line: cells(line),
+            },
+            Constraint::PositiveDiagonal => FullConstraint::PositiveDiagonal,
+            Constraint::NegativeDiagonal => FullConstraint::NegativeDiagonal,
+            Constraint::AntiKnight => FullConstraint::AntiKnight,
+            Constraint::AntiKing => FullConstraint::AntiKing,
+        }
+    }
+}
+
+impl From<&Puzzle> for FullPuzzle {
+    fn from(puzzle: &Puzzle) -> Self {
+        let givens = puzzle
+            .givens
+            .to_rows()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| Some(*value).filter(|value| *value != 0))
+                    .collect()
+            })
+            .collect();
+        let constraints = puzzle
+            .constraints
+            .iter()
+            .map(FullConstraint::from)
+            .collect();
+
+        FullPuzzle {
+            size: puzzle.size(),
+            title: puzzle.title.clone(),
+            author: puzzle.author.clone(),
+            rules: puzzle.rules.clone(),
+            givens,
+            constraints,
+            solution: None,
+        }
+    }
+}
+
+/// Loads puzzles of every format converted to [`FullPuzzle`].
+impl TryFrom<&FullPuzzle> for Puzzle {
+    type Error = PuzzleError;
+
+    fn try_from(full: &FullPuzzle) -> Result<Self, Self::Error> {
+        let cell = |cell: &FullCell| Cell::new(cell.row, cell.column);
+        let cells = |cells: &[FullCell]| cells.iter().map(cell).collect();
+
+        let mut puzzle = Puzzle::new(full.size)?;
+        puzzle.set_title(full.title.clone());
+        puzzle.set_author(full.author.clone());
+        puzzle.set_rules(full.rules.clone());
+
+        for (row, values) in (0..).zip(&full.givens) {
+            for (column, value) in (0..).zip(values) {
+                if let Some(value) = value {
+                    puzzle.set_given(Cell::new(row, column), (*value).into())?;
+                }
+            }
+        }
+
+        for constraint in &full.constraints {
+            puzzle.add_constraint(match constraint {
+                FullConstraint::Killer { cells: killer, sum } => Constraint::Killer {
+                    cells: cells(killer),
+                    sum: *sum,
+                },
+                FullConstraint::Thermometer { cells: thermometer } => Constraint::Thermometer {
+                    cells: cells(thermometer),
+                },
+                FullConstraint::Arrow { bulb, line } => Constraint::Arrow {
+                    bulb: cells(bulb),
+                    line: cells(line),
+                },
+                FullConstraint::PositiveDiagonal => Constraint::PositiveDiagonal,
+                FullConstraint::NegativeDiagonal => Constraint::NegativeDiagonal,
+                FullConstraint::AntiKnight => Constraint::AntiKnight,
+                FullConstraint::AntiKing => Constraint::AntiKing,
+            })?;
+        }
+        Ok(puzzle)
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use puzzle_formats::format::full::{FullCell, FullConstraint, FullPuzzle};
+
+    use super::{Cell, Constraint, Grid, Puzzle, PuzzleError};
+
+    #[test]
+    fn parse_cell() {
+        assert_eq!("R1C1".parse::<Cell>().unwrap(), Cell::new(0, 0));
+        assert_eq!("r9c3".parse::<Cell>().unwrap(), Cell::new(8, 2));
+        assert_eq!(Cell::new(3, 11).to_string(), "R4C12");
+
+        assert!("R0C1".parse::<Cell>().is_err());
+        assert!("R1".parse::<Cell>().is_err());
+        assert!("R1C1 ".parse::<Cell>().is_err());
+    }
+
+    #[test]
+    fn box_dims() {
+        assert_eq!(Grid::new(9).unwrap().box_dims(), (3, 3));
+        assert_eq!(Grid::new(6).unwrap().box_dims(), (2, 3));
+        assert_eq!(Grid::new(8).unwrap().box_dims(), (2, 4));
+        assert_eq!(Grid::new(16).unwrap().box_dims(), (4, 4));
+        assert_eq!(Grid::new(7).unwrap().box_dims(), (1, 7));
+        assert!(Grid::new(17).is_err());
+    }
+
+    #[test]
+    fn givens_and_constraints() {
+        let mut puzzle = Puzzle::new(4).unwrap();
+
+        puzzle.set_given(Cell::new(0, 0), 4).unwrap();
+        assert_eq!(puzzle.givens().get(Cell::new(0, 0)), Some(4));
+        assert!(matches!(
+            puzzle.set_given(Cell::new(0, 1), 5),
+            Err(PuzzleError::ValueOutOfRange { .. })
+        ));
+        assert!(matches!(
+            puzzle.set_given(Cell::new(4, 0), 1),
+            Err(PuzzleError::CellOutOfGrid { .. })
+        ));
+
+        assert!(
+            puzzle
+                .add_constraint(Constraint::Thermometer {
+                    cells: [Cell::new(0, 0), Cell::new(0, 5)].into()
+                })
+                .is_err()
+        );
+        assert!(
+            puzzle
+                .add_constraint(Constraint::Killer {
+                    cells: [].into(),
+                    sum: Some(3)
+                })
+                .is_err()
+        );
+        puzzle.add_constraint(Constraint::AntiKing).unwrap();
+        assert_eq!(puzzle.constraints(), &[Constraint::AntiKing]);
+    }
+
+    #[test]
+    fn from_full() {
+        let mut full = FullPuzzle::new(4).unwrap();
+        full.title = "Full".into();
+        full.givens[1][2] = Some(3);
+        full.constraints = [FullConstraint::Thermometer {
+            cells: [FullCell::new(0, 0), FullCell::new(0, 1)].into(),
+        }]
+        .into();
+
+        let puzzle = Puzzle::try_from(&full).unwrap();
+        assert_eq!(puzzle.title(), "Full");
+        assert_eq!(puzzle.givens().get(Cell::new(1, 2)), Some(3));
+        assert_eq!(puzzle.givens().count(), 1);
+        assert_eq!(
+            puzzle.constraints(),
+            &[Constraint::Thermometer {
+                cells: [Cell::new(0, 0), Cell::new(0, 1)].into()
+            }]
+        );
+        assert_eq!(puzzle.givens().to_rows()[1].as_ref(), &[0, 0, 3, 0]);
+        assert_eq!(FullPuzzle::from(&puzzle), full);
+
+        full.givens[0][0] = Some(5);
+        assert!(Puzzle::try_from(&full).is_err());
+    }
+}
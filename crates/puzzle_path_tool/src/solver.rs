@@ -0,0 +1,982 @@
+use std::{collections::BTreeSet, num::NonZeroUsize};
+
+use crate::{
+    job::{Cancelled, Job, Phase},
+    puzzle::{Cell, Constraint, Grid, Puzzle},
+};
+
+mod parallel;
+
+/// Progress steps of a whole search, split between the branches of each cell by its number of candidates.
+const SEARCH_PROGRESS: u64 = 1 << 32;
+
+/// Result of [`Solver::uniqueness`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Uniqueness {
+    NoSolution,
+    Unique(Grid),
+    /// The first two solutions in search order, as witness that the puzzle is not unique.
+    Multiple(Grid, Grid),
+}
+
+/// A value placed on the way to a solution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolveStep {
+    pub cell: Cell,
+    pub value: u8,
+    pub kind: StepKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKind {
+    /// The only candidate left, after the constraints narrowed the candidates of the cell.
+    Forced,
+    /// A value tried by the search, the following steps are forced by it.
+    Guess,
+}
+
+/// Bookkeeping of a running [`Solver::search`].
+struct Search<'a> {
+    limit: usize,
+    solutions: Vec<Grid>,
+    job: Option<&'a Job>,
+}
+
+impl Search<'_> {
+    fn advance(&self, steps: u64) {
+        if let Some(job) = self.job {
+            job.advance(steps);
+        }
+    }
+}
+
+/// Candidates of a cell, bit `n` is set if the cell can contain `n`.
+type Mask = u32;
+
+/// Backtracking solver for the givens and constraints of a [`Puzzle`].
+///
+/// Every assignment removes the value from all cells that can not share it, the other
+/// constraints narrow the remaining candidates. The search always branches on the first cell
+/// with the fewest candidates, trying values in increasing order or in an order derived from the
+/// [seed](Solver::with_seed), so results are deterministic for any number of
+/// [threads](Solver::with_threads).
+#[derive(Debug, Clone)]
+pub struct Solver {
+    size: u8,
+    givens: Grid,
+    /// Cells which can not contain the same value, by cell index.
+    peers: Box<[Box<[usize]>]>,
+    rules: Box<[Rule]>,
+    /// All cells, by cell index.
+    cells: Box<[Cell]>,
+    seed: Option<u64>,
+    threads: NonZeroUsize,
+}
+
+#[derive(Debug, Clone)]
+enum Rule {
+    /// Values of the cells sum to `sum`, they are distinct through the peers.
+    Sum {
+        cells: Box<[usize]>,
+        sum: u32,
+    },
+    Increasing {
+        cells: Box<[usize]>,
+    },
+    /// The bulb cells read as a number equal the sum of the line.
+    Arrow {
+        bulb: Box<[usize]>,
+        line: Box<[usize]>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    candidates: Box<[Mask]>,
+    /// 0 for cells without a value yet.
+    values: Box<[u8]>,
+}
+
+impl Solver {
+    #[must_use]
+    pub fn new(puzzle: &Puzzle) -> Self {
+        let size = puzzle.size();
+        let cells = usize::from(size) * usize::from(size);
+        let index = |cell: &Cell| cell.index(size);
+
+        let mut peers = vec![BTreeSet::new(); cells];
+        let mut group = |group: &[usize]| {
+            for &a in group {
+                for &b in group {
+                    if a != b {
+                        peers[a].insert(b);
+                    }
+                }
+            }
+        };
+
+        let (height, width) = puzzle.givens().box_dims();
+        for n in 0..size {
+            group(
+                &(0..size)
+                    .map(|i| index(&Cell::new(n, i)))
+                    .collect::<Vec<_>>(),
+            );
+            group(
+                &(0..size)
+                    .map(|i| index(&Cell::new(i, n)))
+                    .collect::<Vec<_>>(),
+            );
+            let (top, left) = (n / (size / width) * height, n % (size / width) * width);
+            group(
+                &(0..size)
+                    .map(|i| index(&Cell::new(top + i / width, left + i % width)))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        let mut rules = Vec::new();
+        let mut neighbours: Vec<(i16, i16)> = Vec::new();
+        for constraint in puzzle.constraints() {
+            match constraint {
+                Constraint::Killer { cells, sum } => {
+                    let cells: Box<[usize]> = cells.iter().map(index).collect();
+                    group(&cells);
+                    if let Some(sum) = sum {
+                        rules.push(Rule::Sum { cells, sum: *sum });
+                    }
+                }
+                Constraint::Thermometer { cells } => rules.push(Rule::Increasing {
+                    cells: cells.iter().map(index).collect(),
+                }),
+                Constraint::Arrow { bulb, line } => rules.push(Rule::Arrow {
+                    bulb: bulb.iter().map(index).collect(),
+                    line: line.iter().map(index).collect(),
+                }),
+                Constraint::PositiveDiagonal => group(
+                    &(0..size)
+                        .map(|i| index(&Cell::new(size - 1 - i, i)))
+                        .collect::<Vec<_>>(),
+                ),
+                Constraint::NegativeDiagonal => {
+                    group(
+                        &(0..size)
+                            .map(|i| index(&Cell::new(i, i)))
+                            .collect::<Vec<_>>(),
+                    );
+                }
+                Constraint::AntiKnight => neighbours.extend([
+                    (-2, -1),
+                    (-2, 1),
+                    (-1, -2),
+                    (-1, 2),
+                    (1, -2),
+                    (1, 2),
+                    (2, -1),
+                    (2, 1),
+                ]),
+                Constraint::AntiKing => neighbours.extend([(-1, -1), (-1, 1), (1, -1), (1, 1)]),
+            }
+        }
+
+        for cell in puzzle.givens().cells() {
+            for (rows, columns) in &neighbours {
+                let row = i16::from(cell.row()) + rows;
+                let column = i16::from(cell.column()) + columns;
+                if let (Ok(row), Ok(column)) = (u8::try_from(row), u8::try_from(column))
+                    && row < size
+                    && column < size
+                {
+                    peers[index(&cell)].insert(index(&Cell::new(row, column)));
+                }
+            }
+        }
+
+        Self {
+            size,
+            givens: puzzle.givens().clone(),
+            peers: peers
+                .into_iter()
+                .map(|peers| peers.into_iter().collect())
+                .collect(),
+            rules: rules.into(),
+            cells: puzzle.givens().cells().collect(),
+            seed: None,
+            threads: NonZeroUsize::MIN,
+        }
+    }
+
+    /// Try the values of each cell in an order shuffled by `seed`, instead of increasing.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Split the search between `threads` threads, which find the same solutions as one.
+    #[must_use]
+    pub fn with_threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Up to `limit` solutions, in search order.
+    #[must_use]
+    pub fn solutions(&self, limit: usize) -> Vec<Grid> {
+        // Without a job the search is never cancelled.
+        self.run(limit, None).unwrap_or_default()
+    }
+
+    /// Like [`Solver::solutions`], reporting the explored share of the search tree
+    /// as [`Phase::Solving`] progress of `job`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the job is cancelled before the search finished.
+    pub fn solutions_with(&self, limit: usize, job: &Job) -> Result<Vec<Grid>, Cancelled> {
+        job.start(Phase::Solving, Some(SEARCH_PROGRESS));
+        self.run(limit, Some(job))
+    }
+
+    /// Search for two solutions, to tell whether the puzzle has exactly one.
+    #[must_use]
+    pub fn uniqueness(&self) -> Uniqueness {
+        classify(self.run(2, None).unwrap_or_default())
+    }
+
+    /// Like [`Solver::uniqueness`], reporting progress to `job`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the job is cancelled before the search finished.
+    pub fn uniqueness_with(&self, job: &Job) -> Result<Uniqueness, Cancelled> {
+        job.start(Phase::Solving, Some(SEARCH_PROGRESS));
+        self.run(2, Some(job)).map(classify)
+    }
+
+    fn run(&self, limit: usize, job: Option<&Job>) -> Result<Vec<Grid>, Cancelled> {
+        if self.threads.get() > 1 {
+            return parallel::run(self, limit, job);
+        }
+        let mut search = Search {
+            limit,
+            solutions: Vec::new(),
+            job,
+        };
+        if limit != 0
+            && let Some(state) = self.initial_state()
+        {
+            self.search(&state, SEARCH_PROGRESS, &mut search)?;
+        }
+        Ok(search.solutions)
+    }
+
+    /// Values placed on the way to the first solution, in order and without the givens.
+    /// `None` if the puzzle has no solution.
+    #[must_use]
+    pub fn solving_path(&self) -> Option<Vec<SolveStep>> {
+        // Without a job the search is never cancelled.
+        self.trace_path(None).unwrap_or_default()
+    }
+
+    /// Like [`Solver::solving_path`], stopping once `job` is cancelled.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the job is cancelled before the path was found.
+    pub fn solving_path_with(&self, job: &Job) -> Result<Option<Vec<SolveStep>>, Cancelled> {
+        self.trace_path(Some(job))
+    }
+
+    fn trace_path(&self, job: Option<&Job>) -> Result<Option<Vec<SolveStep>>, Cancelled> {
+        let Some(state) = self.initial_state() else {
+            return Ok(None);
+        };
+        let mut path = Vec::new();
+        let givens = State {
+            candidates: Box::default(),
+            values: self
+                .cells
+                .iter()
+                .map(|cell| self.givens.get(*cell).unwrap_or(0))
+                .collect(),
+        };
+        self.record_forced(&givens, &state, &mut path);
+        Ok(self.trace(&state, &mut path, job)?.then_some(path))
+    }
+
+    fn initial_state(&self) -> Option<State> {
+        let cells = self.peers.len();
+        let all = ((1 << (self.size + 1)) - 1) & !1;
+        let mut state = State {
+            candidates: vec![all; cells].into(),
+            values: vec![0; cells].into(),
+        };
+        for cell in self.givens.cells() {
+            if let Some(value) = self.givens.get(cell) {
+                let index = cell.index(self.size);
+                state.candidates[index] &= 1 << value;
+                if state.candidates[index] == 0 {
+                    return None;
+                }
+            }
+        }
+        self.propagate(&mut state).then_some(state)
+    }
+
+    /// Values of the candidates of the cell at `index`, in the order the search tries them.
+    fn order(&self, index: usize, candidates: Mask) -> Vec<u8> {
+        let mut order: Vec<u8> = values(candidates).collect();
+        if let Some(seed) = self.seed {
+            order.sort_by_key(|value| mix(seed, index, *value));
+        }
+        order
+    }
+
+    /// The first cell with the fewest candidates, `None` if all cells have a value.
+    fn next_cell(state: &State) -> Option<usize> {
+        (0..state.values.len())
+            .filter(|index| state.values[*index] == 0)
+            .min_by_key(|index| state.candidates[*index].count_ones())
+    }
+
+    /// Depth first search below `state`, which makes up `weight` of the progress.
+    fn search(&self, state: &State, weight: u64, search: &mut Search<'_>) -> Result<(), Cancelled> {
+        if let Some(job) = search.job {
+            job.check()?;
+        }
+        let Some(index) = Self::next_cell(state) else {
+            search.solutions.push(self.grid(state));
+            search.advance(weight);
+            return Ok(());
+        };
+
+        let order = self.order(index, state.candidates[index]);
+        for (branch, value) in order.iter().copied().enumerate() {
+            let weight = share(weight, order.len(), branch);
+            let mut next = state.clone();
+            next.candidates[index] = 1 << value;
+            if self.propagate(&mut next) {
+                self.search(&next, weight, search)?;
+                if search.solutions.len() >= search.limit {
+                    return Ok(());
+                }
+            } else {
+                search.advance(weight);
+            }
+        }
+        Ok(())
+    }
+
+    /// Search like [`Solver::search`] for the first solution, recording the steps leading to it.
+    fn trace(
+        &self,
+        state: &State,
+        path: &mut Vec<SolveStep>,
+        job: Option<&Job>,
+    ) -> Result<bool, Cancelled> {
+        if let Some(job) = job {
+            job.check()?;
+        }
+        let Some(index) = Self::next_cell(state) else {
+            return Ok(true);
+        };
+
+        for value in self.order(index, state.candidates[index]) {
+            let mut next = state.clone();
+            next.candidates[index] = 1 << value;
+            if !self.propagate(&mut next) {
+                continue;
+            }
+            let len = path.len();
+            path.push(SolveStep {
+                cell: self.cells[index],
+                value,
+                kind: StepKind::Guess,
+            });
+            next.values[index] = 0;
+            self.record_forced(state, &next, path);
+            next.values[index] = value;
+            if self.trace(&next, path, job)? {
+                return Ok(true);
+            }
+            path.truncate(len);
+        }
+        Ok(false)
+    }
+
+    /// Record the values `after` has and `before` has not as forced steps.
+    fn record_forced(&self, before: &State, after: &State, path: &mut Vec<SolveStep>) {
+        for (index, (before, after)) in before.values.iter().zip(&after.values).enumerate() {
+            if *before == 0 && *after != 0 {
+                path.push(SolveStep {
+                    cell: self.cells[index],
+                    value: *after,
+                    kind: StepKind::Forced,
+                });
+            }
+        }
+    }
+
+    /// Assign all cells with a single candidate and narrow the rules until nothing changes.
+    /// Returns `false` if a cell has no candidates left or a rule can't be satisfied.
+    fn propagate(&self, state: &mut State) -> bool {
+        loop {
+            let mut changed = false;
+            for index in 0..state.values.len() {
+                let candidates = state.candidates[index];
+                if candidates == 0 {
+                    return false;
+                }
+                if state.values[index] != 0 || candidates.count_ones() != 1 {
+                    continue;
+                }
+                #[allow(clippy::cast_possible_truncation)]
+                let value = candidates.trailing_zeros() as u8;
+                state.values[index] = value;
+                changed = true;
+                for &peer in &self.peers[index] {
+                    if state.values[peer] == value {
+                        return false;
+                    }
+                    state.candidates[peer] &= !candidates;
+                }
+            }
+
+            for rule in &self.rules {
+                match rule.narrow(state) {
+                    None => return false,
+                    Some(narrowed) => changed |= narrowed,
+                }
+            }
+
+            if !changed {
+                return true;
+            }
+        }
+    }
+
+    fn grid(&self, state: &State) -> Grid {
+        let mut grid = self.givens.clone();
+        for cell in self.givens.cells() {
+            // Values are in 1..=size, as they came from the candidates
+            grid.set(cell, Some(state.values[cell.index(self.size)]))
+                .ok();
+        }
+        grid
+    }
+}
+
+impl Rule {
+    /// Remove candidates that can't satisfy the rule.
+    /// Returns whether candidates were removed, `None` if the rule can't be satisfied.
+    fn narrow(&self, state: &mut State) -> Option<bool> {
+        let before: Vec<Mask> = self.cells().map(|index| state.candidates[index]).collect();
+
+        match self {
+            Rule::Sum { cells, sum } => narrow_sum(state, cells, *sum)?,
+            Rule::Increasing { cells } => {
+                let mut lowest = 0;
+                for &index in cells {
+                    state.candidates[index] &= !((2 << lowest) - 1);
+                    lowest = min(state.candidates[index])?;
+                }
+                let mut highest = 31;
+                for &index in cells.iter().rev() {
+                    state.candidates[index] &= (1 << highest) - 1;
+                    highest = max(state.candidates[index])?;
+                }
+            }
+            Rule::Arrow { bulb, line } => narrow_arrow(state, bulb, line)?,
+        }
+
+        let mut changed = false;
+        for (index, before) in self.cells().zip(before) {
+            if state.candidates[index] == 0 {
+                return None;
+            }
+            changed |= state.candidates[index] != before;
+        }
+        Some(changed)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = usize> {
+        let (a, b): (&[usize], &[usize]) = match self {
+            Rule::Sum { cells, .. } | Rule::Increasing { cells } => (cells, &[]),
+            Rule::Arrow { bulb, line } => (bulb, line),
+        };
+        a.iter().chain(b).copied()
+    }
+}
+
+/// Each value must leave a sum the other empty cells can still reach with distinct values.
+fn narrow_sum(state: &mut State, cells: &[usize], sum: u32) -> Option<()> {
+    let filled: u32 = cells
+        .iter()
+        .map(|index| u32::from(state.values[*index]))
+        .sum();
+    let empty: Vec<usize> = cells
+        .iter()
+        .copied()
+        .filter(|index| state.values[*index] == 0)
+        .collect();
+    let remaining = sum.checked_sub(filled)?;
+    if empty.is_empty() {
+        return (remaining == 0).then_some(());
+    }
+
+    let available = empty
+        .iter()
+        .fold(0, |mask, index| mask | state.candidates[*index]);
+    for &index in &empty {
+        let mut candidates = state.candidates[index];
+        for value in values(candidates) {
+            let rest = remaining.checked_sub(value.into());
+            let others = available & !(1 << value);
+            let reachable = rest.is_some_and(|rest| {
+                let count = empty.len() - 1;
+                smallest_sum(others, count).is_some_and(|smallest| smallest <= rest)
+                    && largest_sum(others, count).is_some_and(|largest| rest <= largest)
+            });
+            if !reachable {
+                candidates &= !(1 << value);
+            }
+        }
+        state.candidates[index] = candidates;
+    }
+    Some(())
+}
+
+/// Narrow the line by the range of the bulb number, and a single bulb cell by the line sum.
+fn narrow_arrow(state: &mut State, bulb: &[usize], line: &[usize]) -> Option<()> {
+    let number = |pick: fn(Mask) -> Option<u8>| {
+        bulb.iter().try_fold(0u32, |number, index| {
+            let digit = u32::from(pick(state.candidates[*index])?);
+            let shift = if digit >= 10 { 100 } else { 10 };
+            Some(number.saturating_mul(shift).saturating_add(digit))
+        })
+    };
+    let (bulb_min, bulb_max) = (number(min)?, number(max)?);
+
+    let line_min: u32 = line
+        .iter()
+        .map(|index| min(state.candidates[*index]).map(u32::from))
+        .sum::<Option<u32>>()?;
+    let line_max: u32 = line
+        .iter()
+        .map(|index| max(state.candidates[*index]).map(u32::from))
+        .sum::<Option<u32>>()?;
+    if line_min > bulb_max || line_max < bulb_min {
+        return None;
+    }
+
+    for &index in line {
+        let candidates = state.candidates[index];
+        let (own_min, own_max) = (u32::from(min(candidates)?), u32::from(max(candidates)?));
+        for value in values(candidates) {
+            let value_u32 = u32::from(value);
+            if value_u32 + line_min - own_min > bulb_max
+                || value_u32 + line_max - own_max < bulb_min
+            {
+                state.candidates[index] &= !(1 << value);
+            }
+        }
+    }
+
+    if let [index] = bulb {
+        for value in values(state.candidates[*index]) {
+            if !(line_min..=line_max).contains(&u32::from(value)) {
+                state.candidates[*index] &= !(1 << value);
+            }
+        }
+    }
+    Some(())
+}
+
+/// Progress of the branch numbered `branch` out of `count`, which split `weight` between them.
+/// The last branch takes the remainder, so the shares add up to the weight.
+fn share(weight: u64, count: usize, branch: usize) -> u64 {
+    let count = u64::try_from(count).unwrap_or(u64::MAX).max(1);
+    let share = weight / count;
+    if u64::try_from(branch + 1).is_ok_and(|branch| branch == count) {
+        weight - share * (count - 1)
+    } else {
+        share
+    }
+}
+
+/// Hash of the seed, cell and value, ordering the values of a cell (splitmix64).
+fn mix(seed: u64, index: usize, value: u8) -> u64 {
+    let mut hash = seed
+        ^ u64::try_from(index)
+            .unwrap_or_default()
+            .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ u64::from(value);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^ (hash >> 31)
+}
+
+fn classify(solutions: Vec<Grid>) -> Uniqueness {
+    let mut solutions = solutions.into_iter();
+    match (solutions.next(), solutions.next()) {
+        (None, _) => Uniqueness::NoSolution,
+        (Some(solution), None) => Uniqueness::Unique(solution),
+        (Some(first), Some(second)) => Uniqueness::Multiple(first, second),
+    }
+}
+
+fn values(candidates: Mask) -> impl Iterator<Item = u8> {
+    (1..32u8).filter(move |value| candidates & (1 << value) != 0)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn min(candidates: Mask) -> Option<u8> {
+    (candidates != 0).then(|| candidates.trailing_zeros() as u8)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn max(candidates: Mask) -> Option<u8> {
+    (candidates != 0).then(|| 31 - candidates.leading_zeros() as u8)
+}
+
+fn smallest_sum(candidates: Mask, count: usize) -> Option<u32> {
+    let smallest: Vec<u8> = values(candidates).take(count).collect();
+    (smallest.len() == count).then(|| smallest.into_iter().map(u32::from).sum())
+}
+
+fn largest_sum(candidates: Mask, count: usize) -> Option<u32> {
+    let values: Vec<u8> = values(candidates).collect();
+    (values.len() >= count).then(|| {
+        values
+            .iter()
+            .rev()
+            .take(count)
+            .copied()
+            .map(u32::from)
+            .sum()
+    })
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use crate::{
+        job::{Cancelled, Job, Phase},
+        puzzle::{Cell, Constraint, Grid, Puzzle},
+    };
+
+    use std::num::NonZeroUsize;
+
+    use super::{Solver, StepKind, Uniqueness};
+
+    fn puzzle(givens: &str) -> Puzzle {
+        let size = givens.lines().count();
+        let mut puzzle = Puzzle::new(u8::try_from(size).unwrap()).unwrap();
+        for (row, line) in (0..).zip(givens.lines()) {
+            for (column, value) in (0..).zip(line.chars()) {
+                if let Some(value) = value.to_digit(10) {
+                    puzzle
+                        .set_given(Cell::new(row, column), value.into())
+                        .unwrap();
+                }
+            }
+        }
+        puzzle
+    }
+
+    fn is_valid(grid: &Grid) -> bool {
+        fn distinct(grid: &Grid, mut cells: impl Iterator<Item = Cell>) -> bool {
+            let mut seen = 0u32;
+            cells.all(|cell| {
+                let bit = 1 << grid.get(cell).unwrap_or(0);
+                let new = seen & bit == 0;
+                seen |= bit;
+                new
+            })
+        }
+
+        let size = grid.size();
+        grid.is_complete()
+            && (0..size).all(|n| {
+                distinct(grid, (0..size).map(|i| Cell::new(n, i)))
+                    && distinct(grid, (0..size).map(|i| Cell::new(i, n)))
+            })
+    }
+
+    #[test]
+    fn classic() {
+        let puzzle = puzzle(
+            "53..7....\n6..195...\n.98....6.\n8...6...3\n4..8.3..1\n7...2...6\n.6....28.\n...419..5\n....8..79\n",
+        );
+        let Uniqueness::Unique(solution) = Solver::new(&puzzle).uniqueness() else {
+            panic!("classic puzzle is unique");
+        };
+        assert!(is_valid(&solution));
+        assert_eq!(
+            solution.to_string().lines().next(),
+            Some("534678912"),
+            "{solution}"
+        );
+    }
+
+    #[test]
+    fn threads_and_seeds() {
+        let threads = |threads| NonZeroUsize::new(threads).unwrap();
+        let empty = puzzle("....\n....\n....\n....\n");
+        let sequential = Solver::new(&empty).solutions(1000);
+        for count in [2, 3, 8] {
+            let parallel = Solver::new(&empty).with_threads(threads(count));
+            assert_eq!(parallel.solutions(1000), sequential);
+            assert_eq!(parallel.solutions(5), sequential[..5]);
+        }
+
+        let seeded = Solver::new(&empty).with_seed(7).solutions(5);
+        assert!(seeded.iter().all(is_valid));
+        assert_ne!(seeded, sequential[..5]);
+        for count in [2, 8] {
+            let parallel = Solver::new(&empty)
+                .with_seed(7)
+                .with_threads(threads(count));
+            assert_eq!(parallel.solutions(5), seeded);
+        }
+
+        let classic = puzzle(
+            "53..7....\n6..195...\n.98....6.\n8...6...3\n4..8.3..1\n7...2...6\n.6....28.\n...419..5\n....8..79\n",
+        );
+        let parallel = Solver::new(&classic).with_threads(threads(4));
+        assert_eq!(parallel.uniqueness(), Solver::new(&classic).uniqueness());
+        let conflict = puzzle("11..\n....\n....\n....\n");
+        let parallel = Solver::new(&conflict).with_threads(threads(4));
+        assert_eq!(parallel.uniqueness(), Uniqueness::NoSolution);
+
+        let job = Job::new();
+        job.cancel();
+        let parallel = Solver::new(&empty).with_threads(threads(4));
+        assert_eq!(parallel.uniqueness_with(&job), Err(Cancelled));
+    }
+
+    #[test]
+    fn parallel_uniqueness_witnesses() {
+        let empty = puzzle(&".........\n".repeat(9));
+        for seed in [None, Some(3)] {
+            let solver = |threads| {
+                let solver = Solver::new(&empty).with_threads(NonZeroUsize::new(threads).unwrap());
+                match seed {
+                    Some(seed) => solver.with_seed(seed),
+                    None => solver,
+                }
+            };
+            let Uniqueness::Multiple(first, second) = solver(1).uniqueness() else {
+                panic!("puzzle has many solutions");
+            };
+            assert!(is_valid(&first) && is_valid(&second));
+            assert_eq!(solver(1).solutions(2), [first.clone(), second.clone()]);
+            for threads in [2, 4, 8] {
+                let job = Job::new();
+                assert_eq!(
+                    solver(threads).uniqueness_with(&job),
+                    Ok(Uniqueness::Multiple(first.clone(), second.clone())),
+                    "{threads} threads, seed {seed:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn solving_path() {
+        let unique = puzzle("1...\n..3.\n.4..\n...2\n");
+        let path = Solver::new(&unique).solving_path().unwrap();
+        assert_eq!(path.len(), 12);
+        let Uniqueness::Unique(solution) = Solver::new(&unique).uniqueness() else {
+            panic!("puzzle is unique");
+        };
+        for step in &path {
+            assert_eq!(solution.get(step.cell), Some(step.value));
+        }
+
+        let empty = puzzle("....\n....\n....\n....\n");
+        let path = Solver::new(&empty).solving_path().unwrap();
+        assert_eq!(path.len(), 16);
+        assert_eq!(path[0].kind, StepKind::Guess);
+
+        let conflict = puzzle("11..\n....\n....\n....\n");
+        assert_eq!(Solver::new(&conflict).solving_path(), None);
+    }
+
+    #[test]
+    fn no_and_multiple_solutions() {
+        let conflict = puzzle("11..\n....\n....\n....\n");
+        assert_eq!(Solver::new(&conflict).uniqueness(), Uniqueness::NoSolution);
+
+        let empty = puzzle("....\n....\n....\n....\n");
+        let Uniqueness::Multiple(first, second) = Solver::new(&empty).uniqueness() else {
+            panic!("empty grid has many solutions");
+        };
+        assert!(is_valid(&first) && is_valid(&second));
+        assert_ne!(first, second);
+        assert_eq!(Solver::new(&empty).solutions(1000).len(), 288);
+    }
+
+    #[test]
+    fn progress_and_cancel() {
+        let empty = puzzle("....\n....\n....\n....\n");
+        let job = Job::new();
+        assert_eq!(
+            Solver::new(&empty)
+                .solutions_with(1000, &job)
+                .unwrap()
+                .len(),
+            288
+        );
+        let snapshot = job.snapshot();
+        assert_eq!(snapshot.phase, Phase::Solving);
+        assert_eq!(snapshot.percent(), Some(100));
+        assert_eq!(snapshot.total, Some(snapshot.done));
+
+        job.cancel();
+        assert_eq!(Solver::new(&empty).uniqueness_with(&job), Err(Cancelled));
+        assert_eq!(Solver::new(&empty).solving_path_with(&job), Err(Cancelled));
+    }
+
+    #[test]
+    fn constraints() {
+        let mut empty = puzzle("....\n....\n....\n....\n");
+        let count = |puzzle: &Puzzle| Solver::new(puzzle).solutions(1000).len();
+        assert_eq!(count(&empty), 288);
+
+        let mut thermo = empty.clone();
+        thermo
+            .add_constraint(Constraint::Thermometer {
+                cells: [Cell::new(0, 0), Cell::new(0, 1), Cell::new(1, 1)].into(),
+            })
+            .unwrap();
+        for solution in Solver::new(&thermo).solutions(1000) {
+            let value = |row, column| solution.get(Cell::new(row, column)).unwrap();
+            assert!(value(0, 0) < value(0, 1) && value(0, 1) < value(1, 1));
+        }
+
+        let mut killer = empty.clone();
+        killer
+            .add_constraint(Constraint::Killer {
+                cells: [Cell::new(0, 0), Cell::new(1, 0)].into(),
+                sum: Some(3),
+            })
+            .unwrap();
+        for solution in Solver::new(&killer).solutions(1000) {
+            let value = |row| solution.get(Cell::new(row, 0)).unwrap();
+            assert_eq!(value(0) + value(1), 3);
+        }
+
+        empty
+            .add_constraint(Constraint::Arrow {
+                bulb: [Cell::new(0, 0)].into(),
+                line: [Cell::new(0, 1), Cell::new(1, 1)].into(),
+            })
+            .unwrap();
+        let arrows = Solver::new(&empty).solutions(1000);
+        assert!(!arrows.is_empty());
+        for solution in arrows {
+            let value = |row, column| solution.get(Cell::new(row, column)).unwrap();
+            assert_eq!(value(0, 0), value(0, 1) + value(1, 1));
+        }
+
+        let with = |constraints: &[Constraint]| {
+            let mut puzzle = puzzle("....\n....\n....\n....\n");
+            for constraint in constraints {
+                puzzle.add_constraint(constraint.clone()).unwrap();
+            }
+            Solver::new(&puzzle).solutions(1000)
+        };
+
+        let knight = with(&[Constraint::AntiKnight]);
+        assert_eq!(knight.len(), 24);
+        assert!(knight.iter().all(|solution| no_repeat(solution, &KNIGHT)));
+
+        let diagonals = with(&[Constraint::PositiveDiagonal, Constraint::NegativeDiagonal]);
+        assert_eq!(diagonals.len(), 48);
+        for solution in &diagonals {
+            let diagonal = |cell: fn(u8) -> Cell| {
+                (0..4)
+                    .map(|index| solution.get(cell(index)).unwrap())
+                    .collect::<std::collections::BTreeSet<_>>()
+                    .len()
+            };
+            assert_eq!(diagonal(|index| Cell::new(index, index)), 4);
+            assert_eq!(diagonal(|index| Cell::new(3 - index, index)), 4);
+        }
+
+        // A 4x4 has no anti-king solution, with or without diagonals.
+        assert!(with(&[Constraint::AntiKing]).is_empty());
+        assert!(
+            with(&[
+                Constraint::PositiveDiagonal,
+                Constraint::NegativeDiagonal,
+                Constraint::AntiKing,
+            ])
+            .is_empty()
+        );
+
+        let mut king = Puzzle::new(9).unwrap();
+        king.add_constraint(Constraint::PositiveDiagonal).unwrap();
+        king.add_constraint(Constraint::AntiKing).unwrap();
+        let kings = Solver::new(&king).solutions(100);
+        assert_eq!(kings.len(), 100);
+        for solution in &kings {
+            assert!(no_repeat(solution, &KING));
+            let diagonal: std::collections::BTreeSet<_> = (0..9)
+                .map(|index| solution.get(Cell::new(8 - index, index)).unwrap())
+                .collect();
+            assert_eq!(diagonal.len(), 9);
+        }
+    }
+
+    const KNIGHT: [(i8, i8); 8] = [
+        (-2, -1),
+        (-2, 1),
+        (-1, -2),
+        (-1, 2),
+        (1, -2),
+        (1, 2),
+        (2, -1),
+        (2, 1),
+    ];
+    const KING: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+    /// Whether no two cells a move of `offsets` apart have the same value.
+    fn no_repeat(solution: &Grid, offsets: &[(i8, i8)]) -> bool {
+        let size = solution.size();
+        let cells = || (0..size).flat_map(|row| (0..size).map(move |column| (row, column)));
+        cells().all(|(row, column)| {
+            offsets.iter().all(|&(rows, columns)| {
+                let (Some(other_row), Some(other_column)) = (
+                    row.checked_add_signed(rows),
+                    column.checked_add_signed(columns),
+                ) else {
+                    return true;
+                };
+                other_row >= size
+                    || other_column >= size
+                    || solution.get(Cell::new(row, column))
+                        != solution.get(Cell::new(other_row, other_column))
+            })
+        })
+    }
+
+    #[test]
+    fn anti_knight_uniqueness() {
+        let mut givens = puzzle("123.\n....\n....\n....\n");
+        assert_eq!(Solver::new(&givens).solutions(1000).len(), 12);
+        assert!(matches!(
+            Solver::new(&givens).uniqueness(),
+            Uniqueness::Multiple(..)
+        ));
+
+        givens.add_constraint(Constraint::AntiKnight).unwrap();
+        let Uniqueness::Unique(solution) = Solver::new(&givens).uniqueness() else {
+            panic!("expected a unique solution");
+        };
+        assert_eq!(&solution, puzzle("1234\n4321\n3412\n2143\n").givens());
+        assert!(no_repeat(&solution, &KNIGHT));
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        Condvar, Mutex, PoisonError, RwLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    job::{Cancelled, Job},
+    puzzle::Grid,
+};
+
+use super::{SEARCH_PROGRESS, Solver, State, share};
+
+/// How long idle workers wait for work before they look at the job again.
+const CANCEL_POLL: Duration = Duration::from_millis(10);
+
+/// Branches taken from the root to a subtree. Keys order subtrees the way the sequential
+/// search visits them, which keeps the solutions independent of the threads finding them.
+type Key = Vec<u8>;
+
+/// A subtree of the search, its state is not propagated yet.
+struct Task {
+    key: Key,
+    state: State,
+    weight: u64,
+}
+
+/// Work and results shared by all workers.
+///
+/// Each worker takes the most recent task of its own queue, continuing depth first,
+/// and steals the oldest task of the others, which is the largest subtree left.
+struct Shared<'a> {
+    queues: Box<[Mutex<VecDeque<Task>>]>,
+    /// Tasks queued or running, the search is done once none are left.
+    pending: AtomicUsize,
+    /// Workers waiting on `wake` for tasks.
+    idle: AtomicUsize,
+    /// Guards the checks of idle workers against missing a notification.
+    waiting: Mutex<()>,
+    wake: Condvar,
+    /// Set once a worker panicked.
+    stop: AtomicBool,
+    found: Mutex<BTreeMap<Key, Grid>>,
+    /// Key of the last solution needed, once `limit` solutions are found.
+    /// Subtrees ordered after it can not change the result and are dropped.
+    bound: RwLock<Option<Key>>,
+    limit: usize,
+    job: Option<&'a Job>,
+}
+
+/// Marks a task as done when dropped, also when exploring it panicked.
+struct Running<'a, 'b>(&'a Shared<'b>);
+
+/// The first `limit` solutions in search order, searched by the threads of the solver.
+pub(super) fn run(
+    solver: &Solver,
+    limit: usize,
+    job: Option<&Job>,
+) -> Result<Vec<Grid>, Cancelled> {
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+    let Some(state) = solver.initial_state() else {
+        return Ok(Vec::new());
+    };
+
+    let threads = solver.threads.get();
+    let shared = Shared {
+        queues: (0..threads).map(|_| Mutex::default()).collect(),
+        pending: AtomicUsize::new(0),
+        idle: AtomicUsize::new(0),
+        waiting: Mutex::new(()),
+        wake: Condvar::new(),
+        stop: AtomicBool::new(false),
+        found: Mutex::default(),
+        bound: RwLock::default(),
+        limit,
+        job,
+    };
+    shared.push(
+        0,
+        Task {
+            key: Key::new(),
+            state,
+            weight: SEARCH_PROGRESS,
+        },
+    );
+
+    let results: Vec<Result<(), Cancelled>> = thread::scope(|scope| {
+        let shared = &shared;
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| scope.spawn(move || solver.work(worker, shared)))
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    results.into_iter().collect::<Result<(), Cancelled>>()?;
+
+    let found = shared
+        .found
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner);
+    Ok(found.into_values().take(limit).collect())
+}
+
+impl Solver {
+    fn work(&self, worker: usize, shared: &Shared<'_>) -> Result<(), Cancelled> {
+        loop {
+            if let Some(job) = shared.job {
+                job.check()?;
+            }
+            if shared.stop.load(Ordering::Acquire) {
+                return Ok(());
+            }
+            if let Some(task) = shared.pop(worker) {
+                let _running = Running(shared);
+                self.explore(worker, task, shared)?;
+            } else if !shared.wait() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Follow the first branch of the task depth first, queueing the other branches.
+    fn explore(&self, worker: usize, mut task: Task, shared: &Shared<'_>) -> Result<(), Cancelled> {
+        loop {
+            if let Some(job) = shared.job {
+                job.check()?;
+            }
+            if shared.stop.load(Ordering::Acquire) {
+                return Ok(());
+            }
+            if shared.is_pruned(&task.key) || !self.propagate(&mut task.state) {
+                shared.advance(task.weight);
+                return Ok(());
+            }
+            let Some(index) = Self::next_cell(&task.state) else {
+                shared.solution(task.key, self.grid(&task.state));
+                shared.advance(task.weight);
+                return Ok(());
+            };
+
+            let order = self.order(index, task.state.candidates[index]);
+            let mut branches: Vec<Task> = order
+                .iter()
+                .enumerate()
+                .map(|(branch, value)| {
+                    let mut state = task.state.clone();
+                    state.candidates[index] = 1 << value;
+                    let mut key = task.key.clone();
+                    key.push(u8::try_from(branch).unwrap_or(u8::MAX));
+                    Task {
+                        key,
+                        state,
+                        weight: share(task.weight, order.len(), branch),
+                    }
+                })
+                .collect();
+            // Queued in reverse, so the worker itself takes them in search order.
+            branches.reverse();
+            let Some(first) = branches.pop() else {
+                return Ok(());
+            };
+            for branch in branches {
+                shared.push(worker, branch);
+            }
+            task = first;
+        }
+    }
+}
+
+impl Shared<'_> {
+    fn push(&self, worker: usize, task: Task) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        self.queues[worker]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push_back(task);
+        if self.idle.load(Ordering::SeqCst) != 0 {
+            let _waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+            self.wake.notify_one();
+        }
+    }
+
+    /// The next task of the worker's own queue, or the oldest one stolen from another worker.
+    fn pop(&self, worker: usize) -> Option<Task> {
+        let own = self.queues[worker]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_back();
+        own.or_else(|| {
+            (1..self.queues.len()).find_map(|offset| {
+                self.queues[(worker + offset) % self.queues.len()]
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .pop_front()
+            })
+        })
+    }
+
+    /// Park the idle worker until there may be work, `false` once the search is over.
+    fn wait(&self) -> bool {
+        let waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        self.idle.fetch_add(1, Ordering::SeqCst);
+        let running = self.pending.load(Ordering::SeqCst) != 0 && !self.stop.load(Ordering::SeqCst);
+        let queued = self.queues.iter().any(|queue| {
+            !queue
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .is_empty()
+        });
+        if running && !queued {
+            // Woken by new tasks or the end of the search, and regularly to notice cancellation.
+            let _waiting = self
+                .wake
+                .wait_timeout(waiting, CANCEL_POLL)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        self.idle.fetch_sub(1, Ordering::SeqCst);
+        running
+    }
+
+    /// Wake all idle workers, to notice the end of the search.
+    fn wake_all(&self) {
+        let _waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        self.wake.notify_all();
+    }
+
+    fn is_pruned(&self, key: &Key) -> bool {
+        self.bound
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .is_some_and(|bound| key > bound)
+    }
+
+    /// Keep the solution if it is one of the first `limit`, and tighten the bound.
+    fn solution(&self, key: Key, grid: Grid) {
+        let mut found = self.found.lock().unwrap_or_else(PoisonError::into_inner);
+        found.insert(key, grid);
+        if found.len() > self.limit {
+            found.pop_last();
+        }
+        if found.len() == self.limit {
+            *self.bound.write().unwrap_or_else(PoisonError::into_inner) =
+                found.last_key_value().map(|(key, _)| key.clone());
+        }
+    }
+
+    fn advance(&self, steps: u64) {
+        if let Some(job) = self.job {
+            job.advance(steps);
+        }
+    }
+}
+
+impl Drop for Running<'_, '_> {
+    fn drop(&mut self) {
+        let shared = self.0;
+        if thread::panicking() {
+            shared.stop.store(true, Ordering::SeqCst);
+        }
+        if shared.pending.fetch_sub(1, Ordering::SeqCst) == 1 || thread::panicking() {
+            shared.wake_all();
+        }
+    }
+}
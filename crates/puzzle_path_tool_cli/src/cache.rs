@@ -0,0 +1,234 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+
+use anyhow::Context;
+use puzzle_formats::url::url_fetcher::{
+    maintenance::{
+        CacheEntry, CacheMaintenance, EntryKind, EntryMetadata, read_json_lines, unix_now,
+        write_json_lines,
+    },
+    rusqlite::RusqliteUrlFetcherCache,
+};
+use url::Url;
+
+use crate::{
+    commands::{CacheCommand, CacheOptions, CacheStrategie},
+    fetcher,
+};
+
+pub(super) fn run(options: &CacheOptions, command: CacheCommand) -> anyhow::Result<()> {
+    check_strategy(options.strategy, &command)?;
+    let rt = tokio::runtime::Runtime::new().context("Starting tokio runtime")?;
+    rt.block_on(async move {
+        let cache = fetcher::open(options).await?;
+        run_command(&cache, command).await
+    })
+}
+
+async fn run_command(cache: &RusqliteUrlFetcherCache, command: CacheCommand) -> anyhow::Result<()> {
+    let now = unix_now();
+    match command {
+        CacheCommand::List { prefix } => {
+            let entries = cache.entries().await?;
+            println!(
+                "{:<16} {:>10} {:>8} {:>8}  URL",
+                "KIND", "SIZE", "STORED", "HIT"
+            );
+            for entry in entries.iter().filter(|entry| {
+                prefix
+                    .as_deref()
+                    .is_none_or(|prefix| entry.url.as_str().starts_with(prefix))
+            }) {
+                println!(
+                    "{:<16} {:>10} {:>8} {:>8}  {}",
+                    entry.kind.as_str(),
+                    entry.size,
+                    age(now, entry.stored_at),
+                    entry
+                        .last_hit
+                        .map_or_else(|| "never".into(), |hit| age(now, hit)),
+                    entry.url
+                );
+            }
+        }
+        CacheCommand::Inspect { url } => {
+            let url = Url::parse(&url).with_context(|| format!("Parsing Url {url}"))?;
+            let mut found = false;
+            for kind in EntryKind::ALL {
+                if let Some(entry) = cache.entry(url.clone(), kind).await? {
+                    print_metadata(now, &entry);
+                    found = true;
+                }
+            }
+            if !found {
+                println!("No entries for {url}");
+            }
+        }
+        CacheCommand::Invalidate { url, prefix } => {
+            let deleted = if prefix {
+                cache.invalidate_prefix(&url).await?
+            } else {
+                let url = Url::parse(&url).with_context(|| format!("Parsing Url {url}"))?;
+                cache.invalidate(url).await?
+            };
+            println!("Deleted {deleted} entries");
+        }
+        CacheCommand::Vacuum => {
+            let deleted = cache.vacuum().await?;
+            println!("Deleted {deleted} expired entries");
+        }
+        CacheCommand::Export { path } => {
+            let entries = cache.export().await?;
+            if let Some(path) = path {
+                let path = Path::new(&path);
+                let file =
+                    File::create(path).with_context(|| format!("Creating {}", path.display()))?;
+                write_json_lines(BufWriter::new(file), &entries)
+                    .with_context(|| format!("Writing {}", path.display()))?;
+            } else {
+                write_json_lines(io::stdout().lock(), &entries).context("Writing to stdout")?;
+            }
+            eprintln!("Exported {} entries", entries.len());
+        }
+        CacheCommand::Import {
+            path,
+            keep_timestamps,
+        } => {
+            let path = Path::new(&path);
+            let file = File::open(path).with_context(|| format!("Opening {}", path.display()))?;
+            let mut entries: Vec<CacheEntry> = read_json_lines(BufReader::new(file))
+                .with_context(|| format!("Reading {}", path.display()))?;
+            if !keep_timestamps {
+                restamp(&mut entries, now);
+            }
+            let imported = cache.import(entries).await?;
+            println!("Imported {imported} entries");
+        }
+    }
+
+    Ok(())
+}
+
+/// Fail for strategies without a cache database, and for changes to a `read-only` one.
+fn check_strategy(strategy: CacheStrategie, command: &CacheCommand) -> anyhow::Result<()> {
+    match strategy {
+        CacheStrategie::None | CacheStrategie::Memory => {
+            anyhow::bail!("Cache commands need a cache database, use `--cache sqlite`")
+        }
+        CacheStrategie::ReadOnly
+            if matches!(
+                command,
+                CacheCommand::Invalidate { .. }
+                    | CacheCommand::Vacuum
+                    | CacheCommand::Import { .. }
+            ) =>
+        {
+            anyhow::bail!("The cache database is read-only, use `--cache sqlite` to change it")
+        }
+        CacheStrategie::Sqlite | CacheStrategie::ReadOnly | CacheStrategie::Refresh => Ok(()),
+    }
+}
+
+/// Mark imported entries as stored at `now` and never read.
+fn restamp(entries: &mut [CacheEntry], now: i64) {
+    for entry in entries {
+        entry.stored_at = now;
+        entry.last_hit = None;
+    }
+}
+
+fn print_metadata(now: i64, entry: &EntryMetadata) {
+    println!("{}", entry.kind.as_str());
+    println!("  size:     {} bytes", entry.size);
+    println!("  stored:   {} ago", age(now, entry.stored_at));
+    match entry.last_hit {
+        Some(hit) => println!("  last hit: {} ago", age(now, hit)),
+        None => println!("  last hit: never"),
+    }
+}
+
+/// Time since `timestamp` in its largest whole unit, e.g. `3h`.
+fn age(now: i64, timestamp: i64) -> String {
+    let seconds = now.saturating_sub(timestamp).max(0);
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3_600 => format!("{}m", seconds / 60),
+        3_600..86_400 => format!("{}h", seconds / 3_600),
+        _ => format!("{}d", seconds / 86_400),
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use puzzle_formats::url::url_fetcher::{
+        UrlFetcher,
+        maintenance::{CacheEntry, CacheMaintenance, EntryValue, unix_now, write_json_lines},
+        rusqlite::{CachePolicy, RusqliteUrlFetcherCache},
+    };
+    use url::Url;
+
+    use crate::commands::{CacheCommand, CacheStrategie};
+
+    #[test]
+    fn strategies() {
+        use CacheStrategie::{Memory, ReadOnly, Refresh, Sqlite};
+
+        let list = CacheCommand::List { prefix: None };
+        for strategy in [Sqlite, ReadOnly, Refresh] {
+            assert!(super::check_strategy(strategy, &list).is_ok());
+        }
+        for strategy in [CacheStrategie::None, Memory] {
+            assert!(super::check_strategy(strategy, &list).is_err());
+        }
+
+        assert!(super::check_strategy(Sqlite, &CacheCommand::Vacuum).is_ok());
+        assert!(super::check_strategy(ReadOnly, &CacheCommand::Vacuum).is_err());
+        assert!(super::check_strategy(ReadOnly, &CacheCommand::Export { path: None }).is_ok());
+    }
+
+    #[tokio::test]
+    async fn import_restamps_entries() {
+        let dir = std::env::temp_dir().join(format!("puzzpt-cache-import-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("entries.jsonl");
+        let url = Url::parse("https://google.com/").unwrap();
+        let entry = CacheEntry {
+            url: url.clone(),
+            value: EntryValue::Result {
+                response_value: "VALUE".into(),
+            },
+            stored_at: 1_000,
+            last_hit: Some(2_000),
+        };
+        let mut file = Vec::new();
+        write_json_lines(&mut file, &[entry]).unwrap();
+        std::fs::write(&path, file).unwrap();
+
+        let import = |keep_timestamps| CacheCommand::Import {
+            path: path.clone().into_os_string(),
+            keep_timestamps,
+        };
+        let cache = RusqliteUrlFetcherCache::open(dir.join("cache.sqlite"), CachePolicy::default())
+            .await
+            .unwrap();
+
+        // Old entries expire right away with the default policy
+        super::run_command(&cache, import(true)).await.unwrap();
+        let exported = cache.export().await.unwrap();
+        assert_eq!(exported[0].stored_at, 1_000);
+        assert!(cache.fetch_result(url.clone()).await.is_err());
+
+        super::run_command(&cache, import(false)).await.unwrap();
+        let exported = cache.export().await.unwrap();
+        assert!(exported[0].stored_at >= unix_now() - 60);
+        assert_eq!(exported[0].last_hit, None);
+        assert_eq!(cache.fetch_result(url).await.unwrap(), "VALUE".into());
+
+        drop(cache);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
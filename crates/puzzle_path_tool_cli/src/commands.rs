@@ -1,4 +1,4 @@
-use std::ffi::OsString;
+use std::{ffi::OsString, num::NonZeroUsize};
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
 
@@ -8,45 +8,146 @@ use clap::{Args, Parser, Subcommand, ValueEnum};
 pub(super) struct Cli {
     #[command(subcommand)]
     pub(super) task: Task,
+
+    #[command(flatten)]
+    pub(super) cache_options: CacheOptions,
+}
+
+/// How Urls are cached, shared by all commands touching Urls.
+#[derive(Args, Debug, Clone)]
+pub(super) struct CacheOptions {
+    /// How fetched Urls are cached
+    #[arg(short = 'c', long = "cache", global = true, default_value = "sqlite")]
+    pub(super) strategy: CacheStrategie,
+
+    /// Path of the cache database, defaults to `puzzpt/url_cache.sqlite` in the user cache directory
+    #[arg(long = "cache-path", global = true)]
+    pub(super) path: Option<OsString>,
 }
 
 #[derive(Debug, Subcommand)]
 #[command(about = "TODO(1): task type", long_about = None)]
 pub(super) enum Task {
-    /// TODO(1.1): Explaining Stub Command
-    Stub {
-        //TODO
+    /// Convert a puzzle between formats and share Urls
+    Convert {
+        /// Url, path of a json file, or `-` to read json from stdin
+        input: String,
+
+        /// Format of json input, detected from its fields if not given
+        #[arg(short = 'f', long = "from")]
+        from: Option<PuzzleJsonFormat>,
+
+        /// Format written to the output
+        #[arg(short = 't', long = "to")]
+        to: ConvertFormat,
+
+        /// Output file, stdout if not given
+        #[arg(short = 'o', long = "output")]
+        output: Option<OsString>,
+    },
+    /// Solve a puzzle and check that its solution is unique
+    Solve {
+        /// Url, path of a json file, or `-` to read json from stdin
+        input: String,
+
+        /// Format of json input, detected from its fields if not given
+        #[arg(short = 'f', long = "from")]
+        from: Option<PuzzleJsonFormat>,
+
+        /// Write the puzzle with its unique solution to this file, in the format it was loaded in
+        #[arg(short = 'o', long = "output")]
+        output: Option<OsString>,
+
+        /// Threads searching for solutions, defaults to the number of cores
+        #[arg(long = "threads")]
+        threads: Option<NonZeroUsize>,
+
+        /// Print a share Url of the puzzle with its unique solution
+        #[arg(short = 's', long = "share")]
+        share: Option<ExportFormat>,
+
+        /// Solve even if rules the solver does not support are ignored, the result may be wrong
+        #[arg(long = "ignore-unsupported")]
+        ignore_unsupported: bool,
+    },
+    /// Print a summary of the puzzle behind a Url
+    Inspect {
+        url: String,
+
+        /// Print the summary as json
+        #[arg(short = 'j', long = "json")]
+        json: bool,
     },
     /// TODO(1.2): Explaining Gen Command
     Gen {
         #[command(subcommand)]
-        input: Input,
+        input: Option<Input>,
 
         #[command(flatten)]
         generation_options: GenerationOptions,
     },
+    /// Inspect and maintain the Url cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub(super) enum CacheCommand {
+    /// List all entries with their size and age
+    List {
+        /// Only list entries whose Url starts with this prefix
+        #[arg(short = 'p', long = "prefix")]
+        prefix: Option<String>,
+    },
+    /// Show the metadata of all entries of a Url
+    Inspect { url: String },
+    /// Delete all entries of a Url
+    Invalidate {
+        url: String,
+        /// Delete all entries whose Url starts with the given value instead
+        #[arg(short = 'p', long = "prefix")]
+        prefix: bool,
+    },
+    /// Delete expired entries and compact the database
+    Vacuum,
+    /// Write all entries as json lines
+    Export {
+        /// Output file, stdout if not given
+        path: Option<OsString>,
+    },
+    /// Read entries written by `export`, replacing existing ones
+    ///
+    /// Imported entries count as stored now, so they don't expire based on when they were exported.
+    Import {
+        path: OsString,
+        /// Keep the stored and last hit times of the exported entries
+        #[arg(long = "keep-timestamps")]
+        keep_timestamps: bool,
+    },
 }
 
 #[derive(Args, Debug)]
 #[command(about = "TODO(2): Generation Options", long_about = None)]
 pub(super) struct GenerationOptions {
-    ///TODO(2.1): Script-Dir option text
+    /// Directory `require` resolves Lua modules against, defaults to the directory of the input script
     #[arg(short = 's', long = "script-dir")]
     pub(super) scriptdir: Option<OsString>,
 
-    ///TODO(2.2): Seed option text
+    /// Seed ordering the search for solutions, the same seed gives the same results
     #[arg(short = 'r', long = "rand-seed")]
     pub(super) seed: Option<String>,
 
-    ///TODO(2.3): Watch option text
+    /// Threads searching for solutions, defaults to the number of cores
+    #[arg(long = "threads")]
+    pub(super) threads: Option<NonZeroUsize>,
+
+    /// Keep running and rebuild the puzzles affected by changes of their Lua scripts
     #[arg(short = 'w', long = "watch")]
     pub(super) watch: bool,
 
-    ///TODO(2.4): Cache Strategies option text
-    #[arg(short = 'c', long = "cache")]
-    pub(super) cache: Option<CacheStrategie>,
-
-    ///TODO(2.5): Script-Dir option text
+    /// Write `LuaLS` type definitions (`---@meta`) of the script api to this path
     #[arg(short = 't', long = "type-defs")]
     pub(super) typedefinitions: Option<OsString>,
 }
@@ -57,12 +158,15 @@ pub(super) struct OutputOptions {
     ///TODO(3.1): Explaining UI command
     #[arg(short = 'u', long = "web-ui")]
     pub(super) ui: bool,
-    ///TODO(3.2): Explaining fulljson command
+    /// Write the built puzzles with their solving path as versioned json to this path
     #[arg(short = 'j', long = "json-output")]
     pub(super) json_path: Option<OsString>,
-    ///TODO(3.3): Explaining Export command
+    /// Export every built puzzle as a file of this format, and print its share Url
     #[arg(short = 'f', long = "export-format")]
     pub(super) export_format: Option<ExportFormat>,
+    /// Path of exported files, `{name}` is replaced by the puzzle name and `{format}` by the export format
+    #[arg(long = "export-name", default_value = "{name}.{format}.json")]
+    pub(super) export_name: String,
 }
 
 #[derive(Debug, Subcommand)]
@@ -72,16 +176,16 @@ pub(super) enum Input {
     PuzzleLua {
         #[command(flatten)]
         output_options: OutputOptions,
-        ///TODO(4.1.1): Explain Path to lua file
+        /// Path to a Lua script returning a single puzzle
         path: OsString,
     },
     ///TODO(4.2): Explaining Workspacelua Command
     WorkspaceLua {
         #[command(flatten)]
         output_options: OutputOptions,
-        ///TODO(4.2.1): Explain Path to lua file
+        /// Path to a workspace Lua script registering named puzzles
         path: OsString,
-        ///TODO(4.2.2): Explain optional puzzlenames
+        /// Names of the puzzles to build, all registered puzzles if none are given
         #[arg(last = true)]
         puzzlenames: Vec<String>,
     },
@@ -89,11 +193,49 @@ pub(super) enum Input {
 
 #[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
 pub(super) enum ExportFormat {
+    #[value(name = "sudokupad")]
     SudokuPad,
+    #[value(name = "fpuzzles")]
+    FPuzzles,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum PuzzleJsonFormat {
+    /// f-puzzles json
+    #[value(name = "fpuzzles")]
+    FPuzzles,
+    /// `SudokuPad` scl json
+    Scl,
+    /// Json of the full puzzle model
+    Full,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum ConvertFormat {
+    /// f-puzzles json
+    #[value(name = "fpuzzles")]
     FPuzzles,
+    /// `SudokuPad` scl json
+    Scl,
+    /// Json of the full puzzle model
+    Full,
+    /// f-puzzles share Url
+    #[value(name = "fpuzzles-url")]
+    FPuzzlesUrl,
+    /// `SudokuPad` share Url
+    SudokupadUrl,
 }
 
 #[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
 pub(super) enum CacheStrategie {
-    ExampleCacheStragegie,
+    /// Always fetch, without storing anything
+    None,
+    /// Keep fetched values in memory for a single run
+    Memory,
+    /// Keep fetched values in the cache database
+    Sqlite,
+    /// Use values of the cache database, but never store into it
+    ReadOnly,
+    /// Always fetch, replacing the values in the cache database
+    Refresh,
 }
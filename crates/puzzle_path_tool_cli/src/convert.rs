@@ -0,0 +1,125 @@
+use std::{
+    ffi::OsStr,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use anyhow::{Context, bail};
+use puzzle_formats::{
+    format::{FormatKind, PuzzleFormat, full::Converted},
+    url::{Resolved, ResolvedUrl, ShareTarget, UrlValue},
+};
+use url::Url;
+
+use crate::{
+    commands::{CacheOptions, ConvertFormat, PuzzleJsonFormat},
+    fetcher,
+};
+
+pub(super) fn run(
+    options: &CacheOptions,
+    input: &str,
+    from: Option<PuzzleJsonFormat>,
+    to: ConvertFormat,
+    output: Option<&OsStr>,
+) -> anyhow::Result<()> {
+    let puzzle = load(options, input, from)?;
+
+    let converted = match to {
+        ConvertFormat::FPuzzles => json(&puzzle, FormatKind::FPuzzles)?,
+        ConvertFormat::Scl => json(&puzzle, FormatKind::SudokupadScl)?,
+        ConvertFormat::Full => json(&puzzle, FormatKind::Full)?,
+        ConvertFormat::FPuzzlesUrl => share(&puzzle, ShareTarget::FPuzzles)?,
+        ConvertFormat::SudokupadUrl => share(&puzzle, ShareTarget::SudokuPad)?,
+    };
+
+    for dropped in &converted.dropped {
+        eprintln!("Warning: `{dropped}` has no equivalent in the output and was dropped");
+    }
+
+    let mut text = converted.puzzle;
+    text.push('\n');
+    match output {
+        Some(path) => {
+            let path = Path::new(path);
+            fs::write(path, text).with_context(|| format!("Writing {}", path.display()))?;
+        }
+        None => print!("{text}"),
+    }
+    Ok(())
+}
+
+/// Load a puzzle from a Url, a json file or `-` for json on stdin.
+/// Urls are resolved with the fetcher configured by the [`CacheOptions`].
+pub(super) fn load(
+    options: &CacheOptions,
+    input: &str,
+    from: Option<PuzzleJsonFormat>,
+) -> anyhow::Result<PuzzleFormat> {
+    let kind = from.map(|from| match from {
+        PuzzleJsonFormat::FPuzzles => FormatKind::FPuzzles,
+        PuzzleJsonFormat::Scl => FormatKind::SudokupadScl,
+        PuzzleJsonFormat::Full => FormatKind::Full,
+    });
+
+    let json = if input == "-" {
+        let mut json = String::new();
+        io::stdin()
+            .read_to_string(&mut json)
+            .context("Reading stdin")?;
+        json
+    } else if let Some(url) = Url::parse(input)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+    {
+        if from.is_some() {
+            bail!("`--from` only applies to json input, the format of Urls is known");
+        }
+        return load_url(options, &url);
+    } else {
+        fs::read_to_string(input).with_context(|| format!("Reading {input}"))?
+    };
+
+    PuzzleFormat::from_json(&json, kind).with_context(|| format!("Parsing {input}"))
+}
+
+fn load_url(options: &CacheOptions, url: &Url) -> anyhow::Result<PuzzleFormat> {
+    let resolved = match UrlValue::parse(url.clone()).with_context(|| format!("Parsing {url}"))? {
+        UrlValue::Resolved(resolved) => resolved,
+        UrlValue::Unresolved(unresolved) => {
+            let (resolution, _) = fetcher::resolve(options, &unresolved)
+                .with_context(|| format!("Resolving {url}"))?;
+            match resolution.into_resolved() {
+                Resolved::Puzzle(resolved) => resolved,
+                Resolved::Page(_) => {
+                    bail!("{url} resolves to a page listing several links, not a single puzzle")
+                }
+            }
+        }
+    };
+    resolved
+        .decode()
+        .with_context(|| format!("Decoding the puzzle of {url}"))
+}
+
+fn json(puzzle: &PuzzleFormat, kind: FormatKind) -> anyhow::Result<Converted<String>> {
+    let converted = puzzle.convert(kind)?;
+    let json = converted.puzzle.to_json()?;
+    Ok(Converted {
+        puzzle: json,
+        dropped: converted.dropped,
+    })
+}
+
+pub(super) fn share(
+    puzzle: &PuzzleFormat,
+    target: ShareTarget,
+) -> anyhow::Result<Converted<String>> {
+    let converted = ResolvedUrl::encode(puzzle, target)?;
+    let url = converted.puzzle.url().context("Building the share Url")?;
+    Ok(Converted {
+        puzzle: url.into(),
+        dropped: converted.dropped,
+    })
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use puzzle_formats::url::{
+    Resolution, ResolutionOptions, UnresolvedUrl,
+    url_fetcher::{
+        UrlFetcher,
+        cache::{CachedFetcher, ReadOnlyCache},
+        memory::MemoryUrlFetcherCache,
+        reqwest::ReqwestUrlFetcher,
+        rusqlite::{CachePolicy, RusqliteUrlFetcherCache},
+    },
+};
+use url::Url;
+
+use crate::commands::{CacheOptions, CacheStrategie};
+
+/// Records the Urls requested from the network, so cache hits can be told apart.
+pub(super) struct Network<F = ReqwestUrlFetcher> {
+    fetcher: F,
+    requests: Mutex<HashSet<Url>>,
+}
+
+/// The fetcher configured by the [`CacheOptions`], used by every command touching Urls.
+pub(super) enum Fetcher<F = ReqwestUrlFetcher> {
+    Uncached(Network<F>),
+    Memory(CachedFetcher<MemoryUrlFetcherCache, Network<F>>),
+    Sqlite(CachedFetcher<RusqliteUrlFetcherCache, Network<F>>),
+    ReadOnly(CachedFetcher<ReadOnlyCache<RusqliteUrlFetcherCache>, Network<F>>),
+    /// Bypasses the cache when fetching, but still stores the fetched values.
+    Refresh(CachedFetcher<RusqliteUrlFetcherCache, Network<F>>),
+}
+
+/// Error of any of the [`Fetcher`] variants.
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub(super) struct FetcherError(Box<dyn Error + Send + Sync>);
+
+fn boxed<E: Error + Send + Sync + 'static>(err: E) -> FetcherError {
+    FetcherError(Box::new(err))
+}
+
+impl Fetcher {
+    pub(super) async fn open(options: &CacheOptions) -> anyhow::Result<Self> {
+        let fetcher = ReqwestUrlFetcher::builder()
+            .build()
+            .context("Creating http client")?;
+        Self::with_network(options, fetcher).await
+    }
+}
+
+impl<F> Fetcher<F>
+where
+    F: UrlFetcher + Send + Sync,
+    F::Error: Error + Send + Sync + 'static,
+{
+    /// Fetch from the network with `fetcher`, cached as configured by the [`CacheOptions`].
+    async fn with_network(options: &CacheOptions, fetcher: F) -> anyhow::Result<Self> {
+        let fetcher = Network {
+            fetcher,
+            requests: Mutex::default(),
+        };
+
+        Ok(match options.strategy {
+            CacheStrategie::None => Self::Uncached(fetcher),
+            CacheStrategie::Memory => {
+                Self::Memory(CachedFetcher::new(MemoryUrlFetcherCache::new(), fetcher))
+            }
+            CacheStrategie::Sqlite => {
+                Self::Sqlite(CachedFetcher::new(open(options).await?, fetcher))
+            }
+            CacheStrategie::ReadOnly => Self::ReadOnly(CachedFetcher::new(
+                ReadOnlyCache::new(open(options).await?),
+                fetcher,
+            )),
+            CacheStrategie::Refresh => {
+                Self::Refresh(CachedFetcher::new(open(options).await?, fetcher))
+            }
+        })
+    }
+
+    /// Urls requested from the network so far, all other requests were answered by the cache.
+    pub(super) fn network_requests(&self) -> HashSet<Url> {
+        let network = match self {
+            Self::Uncached(fetcher) => fetcher,
+            Self::Memory(fetcher) => fetcher.fetcher(),
+            Self::Sqlite(fetcher) | Self::Refresh(fetcher) => fetcher.fetcher(),
+            Self::ReadOnly(fetcher) => fetcher.fetcher(),
+        };
+        network
+            .requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl<F> Network<F> {
+    fn record(&self, url: &Url) {
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(url.clone());
+    }
+}
+
+#[async_trait]
+impl<F> UrlFetcher for Network<F>
+where
+    F: UrlFetcher + Send + Sync,
+    F::Error: Error + Send + Sync + 'static,
+{
+    type Error = FetcherError;
+
+    async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        self.record(&url);
+        self.fetcher.fetch_redirect_url(url).await.map_err(boxed)
+    }
+
+    async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        self.record(&url);
+        self.fetcher.fetch_result(url).await.map_err(boxed)
+    }
+}
+
+#[async_trait]
+impl<F> UrlFetcher for Fetcher<F>
+where
+    F: UrlFetcher + Send + Sync,
+    F::Error: Error + Send + Sync + 'static,
+{
+    type Error = FetcherError;
+
+    async fn fetch_redirect_url(&self, url: Url) -> Result<Option<Url>, Self::Error> {
+        match self {
+            Self::Uncached(fetcher) => fetcher.fetch_redirect_url(url).await,
+            Self::Memory(fetcher) => fetcher.fetch_redirect_url(url).await.map_err(boxed),
+            Self::Sqlite(fetcher) => fetcher.fetch_redirect_url(url).await.map_err(boxed),
+            Self::ReadOnly(fetcher) => fetcher.fetch_redirect_url(url).await.map_err(boxed),
+            Self::Refresh(fetcher) => fetcher.fetch_redirect_url_fresh(url).await.map_err(boxed),
+        }
+    }
+
+    async fn fetch_result(&self, url: Url) -> Result<Box<str>, Self::Error> {
+        match self {
+            Self::Uncached(fetcher) => fetcher.fetch_result(url).await,
+            Self::Memory(fetcher) => fetcher.fetch_result(url).await.map_err(boxed),
+            Self::Sqlite(fetcher) => fetcher.fetch_result(url).await.map_err(boxed),
+            Self::ReadOnly(fetcher) => fetcher.fetch_result(url).await.map_err(boxed),
+            Self::Refresh(fetcher) => fetcher.fetch_result_fresh(url).await.map_err(boxed),
+        }
+    }
+}
+
+/// Resolve `url` with the fetcher configured by the [`CacheOptions`], on a new runtime.
+/// Also returns the Urls that were requested from the network.
+pub(super) fn resolve(
+    options: &CacheOptions,
+    url: &UnresolvedUrl,
+) -> anyhow::Result<(Resolution, HashSet<Url>)> {
+    let rt = tokio::runtime::Runtime::new().context("Starting tokio runtime")?;
+    rt.block_on(async {
+        let fetcher = Fetcher::open(options).await?;
+        let resolution = url.resolve(&fetcher, &ResolutionOptions::default()).await?;
+        Ok((resolution, fetcher.network_requests()))
+    })
+}
+
+/// The cache database configured by the [`CacheOptions`].
+pub(super) async fn open(options: &CacheOptions) -> anyhow::Result<RusqliteUrlFetcherCache> {
+    let path = database_path(options)?;
+    RusqliteUrlFetcherCache::open(&path, CachePolicy::default())
+        .await
+        .with_context(|| format!("Opening cache {}", path.display()))
+}
+
+/// The `--cache-path`, or `puzzpt/url_cache.sqlite` in the user cache directory
+/// (`$XDG_CACHE_HOME` or `~/.cache`, `~/Library/Caches` on macOS and `%LOCALAPPDATA%` on Windows).
+/// Parent directories of the default path are created.
+/// Without a user cache directory the `--cache-path` is required.
+pub(super) fn database_path(options: &CacheOptions) -> anyhow::Result<PathBuf> {
+    if let Some(path) = &options.path {
+        return Ok(PathBuf::from(path));
+    }
+
+    let Some(directory) = dirs::cache_dir() else {
+        anyhow::bail!("No user cache directory found, pass the cache database with `--cache-path`");
+    };
+    let directory = directory.join("puzzpt");
+    fs::create_dir_all(&directory)
+        .with_context(|| format!("Creating cache directory {}", directory.display()))?;
+    Ok(directory.join("url_cache.sqlite"))
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use std::{convert::Infallible, path::Path};
+
+    use async_trait::async_trait;
+    use puzzle_formats::url::url_fetcher::UrlFetcher;
+    use url::Url;
+
+    use crate::commands::{CacheOptions, CacheStrategie};
+
+    use super::Fetcher;
+
+    /// Answers every result request with a fixed value.
+    struct Stub(&'static str);
+
+    #[async_trait]
+    impl UrlFetcher for Stub {
+        type Error = Infallible;
+
+        async fn fetch_redirect_url(&self, _url: Url) -> Result<Option<Url>, Self::Error> {
+            Ok(None)
+        }
+
+        async fn fetch_result(&self, _url: Url) -> Result<Box<str>, Self::Error> {
+            Ok(self.0.into())
+        }
+    }
+
+    /// Fetch `url` with a [`Stub`] answering `value`,
+    /// returning the fetched value and whether it came from the network.
+    async fn fetch(
+        strategy: CacheStrategie,
+        path: &Path,
+        url: &Url,
+        value: &'static str,
+    ) -> (Box<str>, bool) {
+        let options = CacheOptions {
+            strategy,
+            path: Some(path.into()),
+        };
+        let fetcher = Fetcher::with_network(&options, Stub(value)).await.unwrap();
+        let result = fetcher.fetch_result(url.clone()).await.unwrap();
+        (result, fetcher.network_requests().contains(url))
+    }
+
+    #[tokio::test]
+    async fn strategies() {
+        let dir = std::env::temp_dir().join(format!("puzzpt-fetcher-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cache.sqlite");
+        let url = Url::parse("https://google.com/").unwrap();
+        let other = Url::parse("https://bing.com/").unwrap();
+
+        // Memory keeps nothing on disk
+        let (value, network) = fetch(CacheStrategie::Memory, &path, &url, "MEMORY").await;
+        assert_eq!((&*value, network), ("MEMORY", true));
+        assert!(!path.exists());
+
+        let (value, network) = fetch(CacheStrategie::Sqlite, &path, &url, "OLD").await;
+        assert_eq!((&*value, network), ("OLD", true));
+        let (value, network) = fetch(CacheStrategie::Sqlite, &path, &url, "UNUSED").await;
+        assert_eq!((&*value, network), ("OLD", false));
+
+        // Refresh fetches even when cached, and stores the fetched value
+        let (value, network) = fetch(CacheStrategie::Refresh, &path, &url, "NEW").await;
+        assert_eq!((&*value, network), ("NEW", true));
+        let (value, network) = fetch(CacheStrategie::ReadOnly, &path, &url, "UNUSED").await;
+        assert_eq!((&*value, network), ("NEW", false));
+
+        // ReadOnly never stores
+        let (value, network) = fetch(CacheStrategie::ReadOnly, &path, &other, "READ").await;
+        assert_eq!((&*value, network), ("READ", true));
+        let (value, network) = fetch(CacheStrategie::Sqlite, &path, &other, "STORED").await;
+        assert_eq!((&*value, network), ("STORED", true));
+
+        let (value, network) = fetch(CacheStrategie::None, &path, &url, "NONE").await;
+        assert_eq!((&*value, network), ("NONE", true));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use puzzle_formats::{
+    format::full::FullPuzzle,
+    url::{Resolved, ResolvedUrl, UrlValue},
+};
+use serde::Serialize;
+
+use crate::{commands::CacheOptions, fetcher};
+
+/// Everything `inspect` prints, also its json output.
+#[derive(Debug, Serialize)]
+struct Report {
+    url: String,
+    /// Requests made while resolving, in order.
+    redirects: Vec<Hop>,
+    #[serde(flatten)]
+    content: Content,
+}
+
+#[derive(Debug, Serialize)]
+struct Hop {
+    url: String,
+    /// Answered by the Url cache instead of the network.
+    cached: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Content {
+    Puzzle {
+        source: &'static str,
+        #[serde(flatten)]
+        decoded: Decoded,
+    },
+    /// A page linking to puzzles, instead of a single puzzle.
+    Page {
+        title: Option<String>,
+        author: Option<String>,
+        links: usize,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Decoded {
+    Summary(Summary),
+    Error { error: String },
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    size: u8,
+    title: String,
+    author: String,
+    rules: String,
+    givens: usize,
+    solution: bool,
+    /// Count of each constraint type.
+    constraints: BTreeMap<&'static str, usize>,
+    /// Elements of the source format without an equivalent in the puzzle model.
+    unrecognized: Vec<String>,
+}
+
+pub(super) fn run(options: &CacheOptions, url: &str, json: bool) -> anyhow::Result<()> {
+    let report = report(options, url)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print(&report);
+    }
+    Ok(())
+}
+
+fn report(options: &CacheOptions, url: &str) -> anyhow::Result<Report> {
+    let (resolved, redirects) =
+        match UrlValue::parse(url).with_context(|| format!("Parsing {url}"))? {
+            UrlValue::Resolved(resolved) => (Resolved::Puzzle(resolved), Vec::new()),
+            UrlValue::Unresolved(unresolved) => {
+                let (resolution, network) = fetcher::resolve(options, &unresolved)
+                    .with_context(|| format!("Resolving {url}"))?;
+                let redirects = resolution
+                    .chain()
+                    .iter()
+                    .map(|hop| Hop {
+                        url: hop.to_string(),
+                        cached: !network.contains(hop),
+                    })
+                    .collect();
+                (resolution.into_resolved(), redirects)
+            }
+        };
+
+    let content = match resolved {
+        Resolved::Puzzle(resolved) => Content::Puzzle {
+            source: resolved.source().as_str(),
+            decoded: decode(&resolved),
+        },
+        Resolved::Page(page) => Content::Page {
+            title: page.title().map(Into::into),
+            author: page.author().map(Into::into),
+            links: page.links().len(),
+        },
+    };
+
+    Ok(Report {
+        url: url.into(),
+        redirects,
+        content,
+    })
+}
+
+/// Decoding errors are part of the report, the source and redirects are still useful.
+fn decode(resolved: &ResolvedUrl) -> Decoded {
+    let full = resolved
+        .decode()
+        .map_err(anyhow::Error::from)
+        .and_then(|puzzle| Ok(puzzle.to_full()?));
+    match full {
+        Ok(full) => Decoded::Summary(summary(&full.puzzle, full.dropped)),
+        Err(err) => Decoded::Error {
+            error: format!("{err:#}"),
+        },
+    }
+}
+
+fn summary(puzzle: &FullPuzzle, dropped: Vec<Box<str>>) -> Summary {
+    let mut constraints = BTreeMap::new();
+    for constraint in &puzzle.constraints {
+        *constraints.entry(constraint.kind()).or_default() += 1;
+    }
+    Summary {
+        size: puzzle.size,
+        title: puzzle.title.to_string(),
+        author: puzzle.author.to_string(),
+        rules: puzzle.rules.to_string(),
+        givens: puzzle.givens.iter().flatten().flatten().count(),
+        solution: puzzle.solution.is_some(),
+        constraints,
+        unrecognized: dropped.into_iter().map(Into::into).collect(),
+    }
+}
+
+fn print(report: &Report) {
+    println!("Url:      {}", report.url);
+    match &report.content {
+        Content::Puzzle { source, decoded } => {
+            println!("Source:   {source}");
+            match decoded {
+                Decoded::Summary(summary) => print_summary(summary),
+                Decoded::Error { error } => println!("Error:    {error}"),
+            }
+        }
+        Content::Page {
+            title,
+            author,
+            links,
+        } => {
+            println!("Source:   puzzle page");
+            println!("Title:    {}", title.as_deref().unwrap_or("-"));
+            println!("Author:   {}", author.as_deref().unwrap_or("-"));
+            println!("Links:    {links}");
+        }
+    }
+
+    if !report.redirects.is_empty() {
+        println!("Redirects:");
+        for (index, hop) in report.redirects.iter().enumerate() {
+            let from = if hop.cached { "cache" } else { "network" };
+            println!("  {}. [{from:<7}] {}", index + 1, hop.url);
+        }
+    }
+}
+
+fn print_summary(summary: &Summary) {
+    let or_dash = |value: &str| {
+        if value.is_empty() {
+            "-".into()
+        } else {
+            value.to_owned()
+        }
+    };
+    println!("Size:     {0}x{0}", summary.size);
+    println!("Title:    {}", or_dash(&summary.title));
+    println!("Author:   {}", or_dash(&summary.author));
+    let rules = or_dash(&summary.rules);
+    let mut rules = rules.lines();
+    println!("Rules:    {}", rules.next().unwrap_or_default());
+    for line in rules {
+        println!("          {line}");
+    }
+    println!("Givens:   {}", summary.givens);
+    println!("Solution: {}", if summary.solution { "yes" } else { "no" });
+
+    if summary.constraints.is_empty() {
+        println!("Constraints: none");
+    } else {
+        println!("Constraints:");
+        for (kind, count) in &summary.constraints {
+            println!("  {kind:<12} {count}");
+        }
+    }
+
+    if !summary.unrecognized.is_empty() {
+        println!("Unrecognized:");
+        for name in &summary.unrecognized {
+            println!("  {name}");
+        }
+    }
+}
@@ -1,22 +1,75 @@
-use clap::Parser;
+use std::path::Path;
 
+use clap::{CommandFactory, Parser, error::ErrorKind};
+
+mod cache;
 mod commands;
+mod convert;
+mod fetcher;
+mod inspect;
+mod progress;
 #[allow(dead_code)]
 mod run_application;
+mod solve;
 
-fn main() {
+fn main() -> anyhow::Result<()> {
     let args = commands::Cli::parse();
 
     match args.task {
-        commands::Task::Stub {} => todo!(),
+        commands::Task::Convert {
+            input,
+            from,
+            to,
+            output,
+        } => {
+            convert::run(&args.cache_options, &input, from, to, output.as_deref())?;
+        }
+        commands::Task::Solve {
+            input,
+            from,
+            output,
+            threads,
+            share,
+            ignore_unsupported,
+        } => {
+            solve::run(
+                &args.cache_options,
+                &input,
+                from,
+                output.as_deref(),
+                threads,
+                share,
+                ignore_unsupported,
+            )?;
+        }
+        commands::Task::Inspect { url, json } => {
+            inspect::run(&args.cache_options, &url, json)?;
+        }
         commands::Task::Gen {
             input,
             generation_options,
         } => {
-            let main_runner = run_application::MainRunner::new(input, generation_options);
+            if input.is_none() && generation_options.typedefinitions.is_none() {
+                commands::Cli::command()
+                    .error(
+                        ErrorKind::MissingRequiredArgument,
+                        "`gen` needs an input or `--type-defs`",
+                    )
+                    .exit();
+            }
+
+            if let Some(path) = &generation_options.typedefinitions {
+                run_application::write_type_defs(Path::new(path))?;
+            }
 
-            println!("Joined all tasks from main");
-            main_runner.join_all_tasks();
+            if let Some(input) = input {
+                run_application::MainRunner::new(input, generation_options).join_all_tasks()?;
+            }
+        }
+        commands::Task::Cache { command } => {
+            cache::run(&args.cache_options, command)?;
         }
     }
+
+    Ok(())
 }
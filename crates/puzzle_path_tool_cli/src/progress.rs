@@ -0,0 +1,91 @@
+use std::{
+    io::{IsTerminal, Write},
+    time::Duration,
+};
+
+use puzzle_path_tool::job::{Job, Phase, ProgressSnapshot};
+
+/// How often the progress is redrawn.
+const INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 30;
+
+/// Draws the progress of a [`Job`] as a bar on stderr, and cancels the job on Ctrl-C.
+///
+/// Runs on the current tokio runtime until stopped.
+#[derive(Debug)]
+pub(super) struct ProgressTask {
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl ProgressTask {
+    /// Start drawing, passing every changed snapshot to `report` as well.
+    pub(super) fn spawn(
+        job: Job,
+        report: impl FnMut(ProgressSnapshot) + Send + 'static,
+    ) -> ProgressTask {
+        let handle = tokio::spawn(async move {
+            tokio::join!(cancel_on_ctrl_c(job.clone()), draw(job, report));
+        });
+        ProgressTask { handle }
+    }
+
+    pub(super) fn stop(self) {
+        self.handle.abort();
+        clear_line();
+    }
+}
+
+/// The first Ctrl-C cancels the job gracefully, the second one quits right away.
+async fn cancel_on_ctrl_c(job: Job) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+    clear_line();
+    eprintln!("Cancelling, press Ctrl-C again to quit");
+    job.cancel();
+    if tokio::signal::ctrl_c().await.is_ok() {
+        std::process::exit(130);
+    }
+}
+
+async fn draw(job: Job, mut report: impl FnMut(ProgressSnapshot)) {
+    let terminal = std::io::stderr().is_terminal();
+    let mut interval = tokio::time::interval(INTERVAL);
+    let mut last = String::new();
+    loop {
+        interval.tick().await;
+        let snapshot = job.snapshot();
+        let text = snapshot.to_string();
+        if text == last {
+            continue;
+        }
+        report(snapshot);
+        if terminal {
+            if matches!(snapshot.phase, Phase::Waiting | Phase::Finished) {
+                clear_line();
+            } else {
+                eprint!("\r{} {text}\x1b[K", bar(&snapshot));
+                let _ = std::io::stderr().flush();
+            }
+        }
+        last = text;
+    }
+}
+
+fn bar(snapshot: &ProgressSnapshot) -> String {
+    let filled = snapshot.percent().map_or(0, |percent| {
+        usize::try_from(percent).map_or(BAR_WIDTH, |percent| percent * BAR_WIDTH / 100)
+    });
+    format!(
+        "[{}{}]",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH.saturating_sub(filled))
+    )
+}
+
+fn clear_line() {
+    if std::io::stderr().is_terminal() {
+        eprint!("\r\x1b[K");
+        let _ = std::io::stderr().flush();
+    }
+}
@@ -1,21 +1,56 @@
-use crate::commands::{GenerationOptions, Input, OutputOptions};
-use std::thread::JoinHandle;
+use crate::{
+    commands::{GenerationOptions, Input, OutputOptions},
+    progress::ProgressTask,
+};
+use anyhow::Context;
+use build::{BuildJob, BuildOutput, BuildSource};
+use export::ExportOutput;
+use json_output::{Generator, JsonOutput};
+use puzzle_path_tool::{
+    job::{Job, ProgressSnapshot},
+    puzzle::Puzzle,
+};
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    thread::JoinHandle,
+};
 use tokio::sync::mpsc::{self, Receiver, Sender};
+use watch::WatchTask;
 
 #[cfg(feature = "ui")]
 use tokio_stream::StreamExt;
 
+mod build;
+mod export;
+mod json_output;
 #[cfg(feature = "ui")]
 mod run_ui;
+mod watch;
 
 #[derive(Debug)]
 struct BuildingTask {
-    join_handler: tokio::task::JoinHandle<()>,
+    /// Whether all puzzles were built and published.
+    join_handler: tokio::task::JoinHandle<bool>,
 }
 
-#[derive(Debug)]
-struct WatchTask {
-    join_handler: tokio::task::JoinHandle<()>,
+impl BuildingTask {
+    fn spawn(job: BuildJob, output: BuildOutput) -> BuildingTask {
+        let join_handler = tokio::spawn(async move {
+            let report = tokio::task::spawn_blocking(move || job.build(None))
+                .await
+                .context("Building task failed")
+                .and_then(|report| report);
+            match report {
+                Ok(report) => output.publish(&report).await,
+                Err(err) => {
+                    output.publish_error(&err).await;
+                    false
+                }
+            }
+        });
+        BuildingTask { join_handler }
+    }
 }
 
 #[derive(Debug)]
@@ -38,14 +73,21 @@ enum UIWindow {
 }
 
 impl UIWindow {
+    fn command_sender(&self) -> Option<Sender<UICommand>> {
+        match self {
+            UIWindow::Closed => None,
+            UIWindow::SetUp { sender, .. } | UIWindow::Running { sender, .. } => {
+                Some(sender.clone())
+            }
+        }
+    }
+
     #[allow(unused_variables)]
     fn set_up_ui(self) -> UIWindow {
         if let UIWindow::SetUp { sender, receiver } = self {
             #[cfg(feature = "ui")]
             {
-                println!("Building UI message handler");
                 let message_handler = tokio::spawn(async move {
-                    println!("Finished UI message handler");
                     let mut stream = tokio_stream::wrappers::ReceiverStream::new(receiver);
                     while let Some(ui_message) = stream.next().await {
                         match ui_message {
@@ -58,8 +100,6 @@ impl UIWindow {
                             }
                         }
                     }
-
-                    println!("Ending UI message handler");
                 });
                 UIWindow::Running {
                     sender,
@@ -68,7 +108,6 @@ impl UIWindow {
             }
             #[cfg(not(feature = "ui"))]
             {
-                println!("TODO: 'UI feature not activated' Warning");
                 UIWindow::Closed
             }
         } else {
@@ -84,10 +123,11 @@ enum UIMessage {
     WindowClosed,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 enum UICommand {
-    //TODO
-    CommandToUI,
+    PuzzleBuilt { name: String, puzzle: Box<Puzzle> },
+    BuildFailed { name: String, error: String },
+    Progress { snapshot: ProgressSnapshot },
 }
 
 #[derive(Debug)]
@@ -95,51 +135,96 @@ pub(super) struct ApplicationRunner {
     watch: Option<WatchTask>,
     window: UIWindow,
     builder: Option<BuildingTask>,
+    json: Option<JsonOutput>,
+    export: Option<ExportOutput>,
+    progress: Option<ProgressTask>,
 }
 
 impl ApplicationRunner {
-    fn run_new(input: Input, _options: GenerationOptions) -> (Option<UIFlags>, JoinHandle<()>) {
+    fn run_new(
+        input: Input,
+        options: GenerationOptions,
+    ) -> (Option<UIFlags>, JoinHandle<anyhow::Result<()>>) {
         let mut runner = ApplicationRunner {
             watch: None,
             window: UIWindow::Closed,
             builder: None,
+            json: None,
+            export: None,
+            progress: None,
         };
-        let ui_flags = match input {
+        let (source, output_options) = match input {
             Input::PuzzleLua {
-                path: _,
+                path,
                 output_options,
-            } => {
-                //main_runner.set_generation_options(generation_options);
-                //main_runner.build_with_lua_file(path);
-                runner.setup_output(&output_options)
-            }
+            } => (BuildSource::PuzzleLua { path: path.into() }, output_options),
             Input::WorkspaceLua {
-                path: _,
-                puzzlenames: _,
+                path,
+                puzzlenames,
                 output_options,
-            } => {
-                //main_runner.set_generation_options(generation_options);
-                //main_runner.build_with_workspace(path, puzzlenames);
-                runner.setup_output(&output_options)
-            }
+            } => (
+                BuildSource::WorkspaceLua {
+                    path: path.into(),
+                    puzzlenames,
+                },
+                output_options,
+            ),
         };
+        let generator = Generator::new(source.path(), options.seed);
+        let threads = crate::solve::threads(options.threads);
+        let ui_flags = runner.setup_output(&output_options, generator, threads);
+        if let (BuildSource::WorkspaceLua { .. }, Some(export)) = (&source, &runner.export)
+            && !export.is_per_puzzle()
+        {
+            eprintln!(
+                "Warning: the export name has no `{{name}}`, all puzzles of the workspace are written to the same file"
+            );
+        }
+        let handle = Job::new();
+        let job = BuildJob {
+            source,
+            script_dir: options.scriptdir.map(PathBuf::from),
+            handle: handle.clone(),
+        };
+        let watch = options.watch;
 
         let handle = std::thread::spawn(move || {
-            let rt = tokio::runtime::Runtime::new();
-            match rt {
-                Ok(rt) => rt.block_on(async move {
-                    runner.window = runner.window.set_up_ui();
-
-                    runner.join_all_tasks().await;
-                }),
-                Err(_) => todo!(),
+            let rt = tokio::runtime::Runtime::new().context("Creating the tokio runtime")?;
+            let success = rt.block_on(async move {
+                runner.window = runner.window.set_up_ui();
+                let ui = runner.window.command_sender();
+                let progress_ui = ui.clone();
+                runner.progress = Some(ProgressTask::spawn(handle.clone(), move |snapshot| {
+                    if let Some(ui) = &progress_ui {
+                        let _ = ui.try_send(UICommand::Progress { snapshot });
+                    }
+                }));
+                let output =
+                    BuildOutput::new(ui, runner.json.clone(), runner.export.clone(), handle);
+                if watch {
+                    runner.watch = Some(WatchTask::spawn(job, output));
+                } else {
+                    runner.builder = Some(BuildingTask::spawn(job, output));
+                }
+
+                runner.join_all_tasks().await
+            });
+            if success {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("Generating the puzzles failed"))
             }
         });
 
         (ui_flags, handle)
     }
 
-    fn setup_output(&mut self, options: &OutputOptions) -> Option<UIFlags> {
+    fn setup_output(
+        &mut self,
+        options: &OutputOptions,
+        generator: Generator,
+        threads: NonZeroUsize,
+    ) -> Option<UIFlags> {
         let ui_flags = if options.ui {
             #[cfg(feature = "ui")]
             {
@@ -159,25 +244,37 @@ impl ApplicationRunner {
             #[cfg(not(feature = "ui"))]
             {
                 let _ = self;
-                println!("TODO: ui-features not implemented");
+                eprintln!(
+                    "Warning: puzzpt was built without the `ui` feature, the ui is not shown"
+                );
                 None
             }
         } else {
             None
         };
-        if let Some(format) = &options.export_format {
-            println!("TODO: handle output Format {format:?}");
-        }
-        if let Some(path) = &options.json_path {
-            println!("TODO: handle output Json path {}", path.display());
-        }
+        self.export = options
+            .export_format
+            .map(|format| ExportOutput::new(format, options.export_name.clone()));
+        self.json = options
+            .json_path
+            .as_ref()
+            .map(|path| JsonOutput::new(path.into(), generator, threads));
 
         ui_flags
     }
 
-    async fn join_all_tasks(self) {
-        //Add all tasks
-        println!("joining all remaining logic tasks");
+    /// Wait for all tasks, `false` if building or watching failed.
+    async fn join_all_tasks(self) -> bool {
+        let mut success = true;
+        if let Some(builder) = self.builder {
+            success &= builder.join_handler.await.unwrap_or(false);
+        }
+        if let Some(watch) = self.watch {
+            success &= watch.join_handler.await.unwrap_or(false);
+        }
+        if let Some(progress) = self.progress {
+            progress.stop();
+        }
         if let UIWindow::Running {
             message_handler,
             sender: _,
@@ -185,13 +282,14 @@ impl ApplicationRunner {
         {
             let _ = message_handler.await;
         }
+        success
     }
 }
 
 #[derive(Debug)]
 pub(super) struct MainRunner {
     ui_flags: Option<UIFlags>,
-    logic_thread_handler: std::thread::JoinHandle<()>,
+    logic_thread_handler: std::thread::JoinHandle<anyhow::Result<()>>,
 }
 
 impl MainRunner {
@@ -204,7 +302,6 @@ impl MainRunner {
     }
 
     fn run_ui(mut self) -> MainRunner {
-        println!("Run UI with {:?}", self.ui_flags);
         #[cfg(feature = "ui")]
         if let Some(flags) = self.ui_flags {
             run_ui::run(flags);
@@ -213,10 +310,30 @@ impl MainRunner {
         self
     }
 
-    pub(super) fn join_all_tasks(mut self) {
+    /// Run the ui if requested, then wait for the generation to finish.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any puzzle failed to build or publish,
+    /// the input script failed, or the runtime could not be started.
+    pub(super) fn join_all_tasks(mut self) -> anyhow::Result<()> {
         self = self.run_ui();
-        //TODO: remove println
-        let e = self.logic_thread_handler.join();
-        println!("join logic thread: {e:?}");
+        self.logic_thread_handler
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
     }
 }
+
+pub(super) fn write_type_defs(path: &Path) -> anyhow::Result<()> {
+    let defs = puzzle_path_tool::lua::type_defs()?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Creating directory {}", parent.display()))?;
+    }
+    std::fs::write(path, defs.to_meta())
+        .with_context(|| format!("Writing type definitions to {}", path.display()))?;
+
+    println!("Type definitions written to {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use puzzle_path_tool::{
+    job::{Job, Phase},
+    lua::{LuaRuntime, sandbox::ExecutionPolicy},
+    puzzle::Puzzle,
+};
+use tokio::sync::mpsc::Sender;
+
+use super::{UICommand, export::ExportOutput, json_output::JsonOutput};
+
+/// What to build, taken from the [`Input`](crate::commands::Input) command.
+#[derive(Debug, Clone)]
+pub(super) struct BuildJob {
+    pub(super) source: BuildSource,
+    /// Root of `require`, defaults to the directory of the input script.
+    pub(super) script_dir: Option<PathBuf>,
+    /// Cancellation and progress of the builds.
+    pub(super) handle: Job,
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum BuildSource {
+    PuzzleLua {
+        path: PathBuf,
+    },
+    WorkspaceLua {
+        path: PathBuf,
+        puzzlenames: Vec<String>,
+    },
+}
+
+/// Result of building some or all puzzles of a [`BuildJob`].
+#[derive(Debug)]
+pub(super) struct BuildReport {
+    /// Files used to load the input script, a change of these affects every puzzle.
+    pub(super) dependencies: BTreeSet<PathBuf>,
+    pub(super) puzzles: Vec<BuiltPuzzle>,
+}
+
+#[derive(Debug)]
+pub(super) struct BuiltPuzzle {
+    pub(super) name: String,
+    pub(super) result: Result<Puzzle, String>,
+    /// Files used to build only this puzzle.
+    pub(super) dependencies: BTreeSet<PathBuf>,
+}
+
+impl BuildJob {
+    /// Build the requested puzzles, restricted to `only` if given.
+    ///
+    /// Failing puzzles are reported individually, only a failing input script or cancelling is an error.
+    pub(super) fn build(&self, only: Option<&BTreeSet<String>>) -> anyhow::Result<BuildReport> {
+        let runtime =
+            LuaRuntime::with_cancel_token(ExecutionPolicy::default(), self.handle.cancel_token())?;
+        let script_dir = self
+            .script_dir
+            .clone()
+            .or_else(|| self.source.path().parent().map(Path::to_path_buf));
+        if let Some(dir) = script_dir {
+            runtime.set_script_dir(dir)?;
+        }
+
+        match &self.source {
+            BuildSource::PuzzleLua { path } => {
+                self.handle.start(Phase::Building, Some(1));
+                let result = runtime.load_puzzle(path).map_err(|err| err.to_string());
+                let name = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                self.handle.check()?;
+                self.handle.advance(1);
+                Ok(BuildReport {
+                    dependencies: runtime.take_dependencies(),
+                    puzzles: vec![BuiltPuzzle {
+                        name,
+                        result,
+                        dependencies: BTreeSet::new(),
+                    }],
+                })
+            }
+            BuildSource::WorkspaceLua { path, puzzlenames } => {
+                let workspace = runtime.load_workspace(path)?;
+                let dependencies = runtime.take_dependencies();
+                let selected: Vec<_> = workspace
+                    .select(puzzlenames)?
+                    .into_iter()
+                    .filter(|puzzle| only.is_none_or(|only| only.contains(puzzle.name())))
+                    .collect();
+                self.handle
+                    .start(Phase::Building, u64::try_from(selected.len()).ok());
+                let mut puzzles = Vec::new();
+                for puzzle in selected {
+                    let result = workspace
+                        .build(&runtime, puzzle)
+                        .map_err(|err| err.to_string());
+                    self.handle.check()?;
+                    self.handle.advance(1);
+                    puzzles.push(BuiltPuzzle {
+                        name: puzzle.name().to_owned(),
+                        result,
+                        dependencies: runtime.take_dependencies(),
+                    });
+                }
+                Ok(BuildReport {
+                    dependencies,
+                    puzzles,
+                })
+            }
+        }
+    }
+}
+
+impl BuildSource {
+    pub(super) fn path(&self) -> &Path {
+        match self {
+            BuildSource::PuzzleLua { path } | BuildSource::WorkspaceLua { path, .. } => path,
+        }
+    }
+}
+
+/// Where built puzzles are published to.
+#[derive(Debug, Clone)]
+pub(super) struct BuildOutput {
+    ui: Option<Sender<UICommand>>,
+    json: Option<JsonOutput>,
+    export: Option<ExportOutput>,
+    /// The job of the builds, finished once their results are published.
+    handle: Job,
+}
+
+impl BuildOutput {
+    pub(super) fn new(
+        ui: Option<Sender<UICommand>>,
+        json: Option<JsonOutput>,
+        export: Option<ExportOutput>,
+        handle: Job,
+    ) -> BuildOutput {
+        BuildOutput {
+            ui,
+            json,
+            export,
+            handle,
+        }
+    }
+
+    /// Publish the built puzzles, `false` if any of them failed to build or publish.
+    pub(super) async fn publish(&self, report: &BuildReport) -> bool {
+        let mut success = true;
+        if let Some(json) = &self.json {
+            // Solving the puzzles blocks, without holding up other tasks.
+            match tokio::task::block_in_place(|| json.write(report, &self.handle)) {
+                Ok(()) => println!("Json output written to {}", json.path().display()),
+                Err(err) => {
+                    eprintln!("Error writing json output: {err:#}");
+                    success = false;
+                }
+            }
+        }
+
+        for built in &report.puzzles {
+            let command = match &built.result {
+                Ok(puzzle) => {
+                    print_puzzle(&built.name, puzzle);
+                    if let Some(export) = &self.export
+                        && let Err(err) = export.write(&built.name, puzzle)
+                    {
+                        eprintln!("Error exporting puzzle `{}`: {err:#}", built.name);
+                        success = false;
+                    }
+                    UICommand::PuzzleBuilt {
+                        name: built.name.clone(),
+                        puzzle: Box::new(puzzle.clone()),
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Error building puzzle `{}`: {err}", built.name);
+                    success = false;
+                    UICommand::BuildFailed {
+                        name: built.name.clone(),
+                        error: err.clone(),
+                    }
+                }
+            };
+            if let Some(ui) = &self.ui {
+                let _ = ui.send(command).await;
+            }
+        }
+        self.handle.finish();
+        success
+    }
+
+    pub(super) async fn publish_error(&self, err: &anyhow::Error) {
+        self.handle.finish();
+        eprintln!("Error building puzzles: {err:#}");
+        if let Some(ui) = &self.ui {
+            let _ = ui
+                .send(UICommand::BuildFailed {
+                    name: String::new(),
+                    error: format!("{err:#}"),
+                })
+                .await;
+        }
+    }
+}
+
+fn print_puzzle(name: &str, puzzle: &Puzzle) {
+    println!(
+        "Puzzle `{name}`: \"{}\" by {}, {size}x{size}, {} givens, {} constraints",
+        puzzle.title(),
+        if puzzle.author().is_empty() {
+            "unknown"
+        } else {
+            puzzle.author()
+        },
+        puzzle.givens().count(),
+        puzzle.constraints().len(),
+        size = puzzle.size(),
+    );
+    print!("{}", puzzle.givens());
+}
@@ -0,0 +1,112 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, bail};
+use puzzle_formats::{
+    format::{FormatKind, PuzzleFormat, full::FullPuzzle},
+    url::ShareTarget,
+};
+use puzzle_path_tool::puzzle::Puzzle;
+
+use crate::{commands::ExportFormat, convert};
+
+/// Writes built puzzles as files of an external format, and prints their share Url.
+#[derive(Debug, Clone)]
+pub(super) struct ExportOutput {
+    format: ExportFormat,
+    /// Path of the exported file, see [`ExportOutput::path`].
+    template: String,
+}
+
+impl ExportOutput {
+    pub(super) fn new(format: ExportFormat, template: String) -> ExportOutput {
+        ExportOutput { format, template }
+    }
+
+    /// Whether every puzzle gets its own file, or all are written to the same path.
+    pub(super) fn is_per_puzzle(&self) -> bool {
+        self.template.contains("{name}")
+    }
+
+    /// The template with `{name}` replaced by the puzzle name and `{format}` by the export format.
+    ///
+    /// Names come from scripts, so only plain file names are accepted, which can't leave the directory.
+    fn path(&self, name: &str) -> anyhow::Result<PathBuf> {
+        if self.is_per_puzzle() && !is_file_name(name) {
+            bail!("Puzzle name `{name}` can not be used as a file name");
+        }
+        let format = match self.format {
+            ExportFormat::SudokuPad => "sudokupad",
+            ExportFormat::FPuzzles => "fpuzzles",
+        };
+        Ok(self
+            .template
+            .replace("{name}", name)
+            .replace("{format}", format)
+            .into())
+    }
+
+    pub(super) fn write(&self, name: &str, puzzle: &Puzzle) -> anyhow::Result<()> {
+        let (kind, target) = match self.format {
+            ExportFormat::SudokuPad => (FormatKind::SudokupadScl, ShareTarget::SudokuPad),
+            ExportFormat::FPuzzles => (FormatKind::FPuzzles, ShareTarget::FPuzzles),
+        };
+        let full = PuzzleFormat::Full(FullPuzzle::from(puzzle).into());
+        let converted = full.convert(kind)?;
+        for dropped in &converted.dropped {
+            eprintln!(
+                "Warning: `{dropped}` of puzzle `{name}` has no equivalent in the export and was dropped"
+            );
+        }
+
+        let path = self.path(name)?;
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Creating directory {}", parent.display()))?;
+        }
+        let mut json = converted.puzzle.to_json()?;
+        json.push('\n');
+        std::fs::write(&path, json).with_context(|| format!("Writing {}", path.display()))?;
+        println!("Puzzle `{name}` exported to {}", path.display());
+
+        // Converting once more is lossless, everything was dropped above already.
+        let url = convert::share(&converted.puzzle, target)?;
+        println!("Share Url of `{name}`: {}", url.puzzle);
+        Ok(())
+    }
+}
+
+fn is_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    !name.contains(['/', '\\'])
+        && matches!(components.next(), Some(Component::Normal(_)))
+        && components.next().is_none()
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use crate::commands::ExportFormat;
+
+    use super::ExportOutput;
+
+    #[test]
+    fn path_of_puzzle() {
+        let output = ExportOutput::new(ExportFormat::FPuzzles, "out/{name}.{format}.json".into());
+        assert_eq!(
+            output.path("classic 1").unwrap(),
+            PathBuf::from("out/classic 1.fpuzzles.json")
+        );
+
+        for name in ["../../x", "..", ".", "", "/etc/foo", "a/b", "a\\b"] {
+            assert!(output.path(name).is_err(), "{name}");
+        }
+
+        let single = ExportOutput::new(ExportFormat::SudokuPad, "out/all.json".into());
+        assert_eq!(single.path("../x").unwrap(), PathBuf::from("out/all.json"));
+    }
+}
@@ -0,0 +1,438 @@
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use anyhow::Context;
+use puzzle_formats::format::full::FullPuzzle;
+use puzzle_path_tool::{
+    job::{Cancelled, Job},
+    puzzle::Puzzle,
+    solver::{SolveStep, Solver, StepKind, Uniqueness},
+};
+use serde::Serialize;
+
+use super::build::BuildReport;
+
+/// Version of the document layout, bumped on breaking changes.
+/// The layout is described by `docs/output/puzzle_output.schema.json`.
+const VERSION: u32 = 1;
+/// `$id` of the published schema.
+const SCHEMA: &str = "puzzle-path-tool/puzzle-output/1.0.0";
+
+/// Versioned json document of all built puzzles, written by `--json-output`.
+#[derive(Debug, Serialize)]
+struct Document<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: u32,
+    generator: &'a Generator,
+    puzzles: &'a [PuzzleEntry],
+}
+
+/// How the puzzles were generated.
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct Generator {
+    tool: &'static str,
+    version: &'static str,
+    script: String,
+    seed: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PuzzleEntry {
+    name: String,
+    #[serde(flatten)]
+    result: EntryResult,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum EntryResult {
+    Built {
+        puzzle: FullPuzzle,
+        solving: Solving,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct Solving {
+    status: SolvingStatus,
+    /// Steps to the first solution found, empty without a solution.
+    path: Vec<Step>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum SolvingStatus {
+    Unique,
+    Multiple,
+    NoSolution,
+}
+
+#[derive(Debug, Serialize)]
+struct Step {
+    row: u8,
+    column: u8,
+    value: u8,
+    kind: &'static str,
+}
+
+impl Generator {
+    pub(super) fn new(script: &Path, seed: Option<String>) -> Generator {
+        Generator {
+            tool: "puzzpt",
+            version: env!("CARGO_PKG_VERSION"),
+            script: script.to_string_lossy().into_owned(),
+            seed,
+        }
+    }
+
+    /// The seed as a number, taken as is if it is one and hashed otherwise (FNV-1a),
+    /// which stays the same across platforms and versions.
+    fn solver_seed(&self) -> Option<u64> {
+        let seed = self.seed.as_deref()?;
+        Some(seed.parse().unwrap_or_else(|_| {
+            seed.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+        }))
+    }
+}
+
+/// Keeps the entries of all published puzzles, so rebuilds of single puzzles rewrite the whole document.
+#[derive(Debug, Clone)]
+pub(super) struct JsonOutput {
+    path: PathBuf,
+    generator: Generator,
+    threads: NonZeroUsize,
+    entries: Arc<Mutex<Vec<PuzzleEntry>>>,
+}
+
+impl JsonOutput {
+    pub(super) fn new(path: PathBuf, generator: Generator, threads: NonZeroUsize) -> JsonOutput {
+        JsonOutput {
+            path,
+            generator,
+            threads,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Solve the puzzles of the report and rewrite the document.
+    pub(super) fn write(&self, report: &BuildReport, job: &Job) -> anyhow::Result<()> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        for built in &report.puzzles {
+            let result = match &built.result {
+                Ok(puzzle) => {
+                    let mut solver = Solver::new(puzzle).with_threads(self.threads);
+                    if let Some(seed) = self.generator.solver_seed() {
+                        solver = solver.with_seed(seed);
+                    }
+                    solve(&solver, puzzle, job)?
+                }
+                Err(err) => EntryResult::Failed { error: err.clone() },
+            };
+            let entry = PuzzleEntry {
+                name: built.name.clone(),
+                result,
+            };
+            match entries
+                .iter_mut()
+                .find(|existing| existing.name == entry.name)
+            {
+                Some(existing) => *existing = entry,
+                None => entries.push(entry),
+            }
+        }
+
+        let document = Document {
+            schema: SCHEMA,
+            version: VERSION,
+            generator: &self.generator,
+            puzzles: &entries,
+        };
+        let mut json = serde_json::to_string_pretty(&document)?;
+        json.push('\n');
+        if let Some(parent) = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Creating directory {}", parent.display()))?;
+        }
+        std::fs::write(&self.path, json).with_context(|| format!("Writing {}", self.path.display()))
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// The full model of the puzzle, with its solution if it is unique.
+fn solve(solver: &Solver, puzzle: &Puzzle, job: &Job) -> Result<EntryResult, Cancelled> {
+    let mut full = FullPuzzle::from(puzzle);
+    let status = match solver.uniqueness_with(job)? {
+        Uniqueness::NoSolution => SolvingStatus::NoSolution,
+        Uniqueness::Multiple(..) => SolvingStatus::Multiple,
+        Uniqueness::Unique(solution) => {
+            full.solution = Some(solution.to_rows());
+            SolvingStatus::Unique
+        }
+    };
+    let path = match status {
+        SolvingStatus::NoSolution => Vec::new(),
+        SolvingStatus::Unique | SolvingStatus::Multiple => solver
+            .solving_path_with(job)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(step)
+            .collect(),
+    };
+    Ok(EntryResult::Built {
+        puzzle: full,
+        solving: Solving { status, path },
+    })
+}
+
+fn step(step: SolveStep) -> Step {
+    Step {
+        row: step.cell.row(),
+        column: step.cell.column(),
+        value: step.value,
+        kind: match step.kind {
+            StepKind::Forced => "forced",
+            StepKind::Guess => "guess",
+        },
+    }
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use puzzle_path_tool::{
+        job::Job,
+        puzzle::{Cell, Constraint, Puzzle},
+        solver::Solver,
+    };
+    use serde_json::{Map, Value};
+
+    use super::{Document, EntryResult, Generator, PuzzleEntry, SCHEMA, VERSION};
+
+    const SCHEMA_JSON: &str = include_str!("../../../../docs/output/puzzle_output.schema.json");
+
+    /// Errors of `value` against the subset of json schema draft 7 the published schema uses.
+    fn validate(root: &Value, schema: &Value, value: &Value, at: &str) -> Vec<String> {
+        let schema = match schema.get("$ref").and_then(Value::as_str) {
+            Some(reference) => {
+                let name = reference.strip_prefix("#/definitions/").unwrap();
+                &root["definitions"][name]
+            }
+            None => schema,
+        };
+        let mut errors = Vec::new();
+        let mut fail = |message: String| errors.push(format!("{at}: {message}"));
+
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                types => vec![types.as_str().unwrap()],
+            };
+            let matches = |name: &str| match name {
+                "object" => value.is_object(),
+                "array" => value.is_array(),
+                "string" => value.is_string(),
+                "integer" => value.is_i64() || value.is_u64(),
+                "null" => value.is_null(),
+                name => panic!("Unsupported type {name}"),
+            };
+            if !types.iter().any(|name| matches(name)) {
+                fail(format!("{value} is not of type {types:?}"));
+            }
+        }
+        if let Some(expected) = schema.get("const")
+            && value != expected
+        {
+            fail(format!("{value} is not {expected}"));
+        }
+        if let Some(Value::Array(options)) = schema.get("enum")
+            && !options.contains(value)
+        {
+            fail(format!("{value} is not one of {options:?}"));
+        }
+        if let Some(number) = value.as_f64() {
+            if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64)
+                && number < minimum
+            {
+                fail(format!("{value} is less than {minimum}"));
+            }
+            if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64)
+                && number > maximum
+            {
+                fail(format!("{value} is greater than {maximum}"));
+            }
+        }
+        if let (Some(items), Value::Array(values)) = (schema.get("items"), value) {
+            for (index, item) in values.iter().enumerate() {
+                errors.extend(validate(root, items, item, &format!("{at}/{index}")));
+            }
+        }
+        if let Value::Object(object) = value {
+            errors.extend(validate_object(root, schema, object, at));
+        }
+        if let Some(Value::Array(options)) = schema.get("oneOf") {
+            let valid = options
+                .iter()
+                .filter(|option| validate(root, option, value, at).is_empty())
+                .count();
+            if valid != 1 {
+                errors.push(format!("{at}: {valid} instead of one oneOf options match"));
+            }
+        }
+        errors
+    }
+
+    fn validate_object(
+        root: &Value,
+        schema: &Value,
+        object: &Map<String, Value>,
+        at: &str,
+    ) -> Vec<String> {
+        let mut errors = Vec::new();
+        let empty = Map::new();
+        let properties = schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .unwrap_or(&empty);
+        let patterns = schema
+            .get("patternProperties")
+            .and_then(Value::as_object)
+            .unwrap_or(&empty);
+
+        for name in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(name) {
+                errors.push(format!("{at}: missing `{name}`"));
+            }
+        }
+        for (name, value) in object {
+            let at = format!("{at}/{name}");
+            let mut known = false;
+            if let Some(property) = properties.get(name) {
+                known = true;
+                errors.extend(validate(root, property, value, &at));
+            }
+            for (pattern, property) in patterns {
+                if regex::Regex::new(pattern).unwrap().is_match(name) {
+                    known = true;
+                    errors.extend(validate(root, property, value, &at));
+                }
+            }
+            if !known && schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                errors.push(format!("{at}: unexpected property"));
+            }
+        }
+        errors
+    }
+
+    fn built(name: &str, puzzle: &Puzzle) -> PuzzleEntry {
+        PuzzleEntry {
+            name: name.into(),
+            result: super::solve(&Solver::new(puzzle), puzzle, &Job::new()).unwrap(),
+        }
+    }
+
+    #[test]
+    fn document_matches_schema() {
+        let cells = |cells: &[(u8, u8)]| -> Box<[Cell]> {
+            cells
+                .iter()
+                .map(|&(row, column)| Cell::new(row, column))
+                .collect()
+        };
+
+        let mut constrained = Puzzle::new(4).unwrap();
+        constrained.set_title("Constrained");
+        constrained.set_given(Cell::new(0, 0), 1).unwrap();
+        for constraint in [
+            Constraint::Killer {
+                cells: cells(&[(1, 0), (1, 1)]),
+                sum: Some(5),
+            },
+            Constraint::Killer {
+                cells: cells(&[(3, 2), (3, 3)]),
+                sum: None,
+            },
+            Constraint::Thermometer {
+                cells: cells(&[(2, 0), (2, 1)]),
+            },
+            Constraint::Arrow {
+                bulb: cells(&[(0, 3)]),
+                line: cells(&[(1, 3), (2, 3)]),
+            },
+            Constraint::PositiveDiagonal,
+            Constraint::AntiKing,
+        ] {
+            constrained.add_constraint(constraint).unwrap();
+        }
+
+        let mut unique = Puzzle::new(4).unwrap();
+        for (row, values) in [[1, 2, 3, 4], [3, 4, 1, 2], [2, 1, 4, 3], [4, 3, 2, 0]]
+            .into_iter()
+            .enumerate()
+        {
+            for (column, value) in values.into_iter().enumerate() {
+                if value != 0 {
+                    let cell = Cell::new(row.try_into().unwrap(), column.try_into().unwrap());
+                    unique.set_given(cell, value).unwrap();
+                }
+            }
+        }
+
+        let mut conflict = Puzzle::new(4).unwrap();
+        conflict.set_given(Cell::new(0, 0), 1).unwrap();
+        conflict.set_given(Cell::new(0, 1), 1).unwrap();
+
+        let puzzles = [
+            built("constrained", &constrained),
+            built("unique", &unique),
+            built("conflict", &conflict),
+            PuzzleEntry {
+                name: "failed".into(),
+                result: EntryResult::Failed {
+                    error: "Script error".into(),
+                },
+            },
+        ];
+        let generator = Generator::new("puzzles.lua".as_ref(), Some("seed".into()));
+        let document = Document {
+            schema: SCHEMA,
+            version: VERSION,
+            generator: &generator,
+            puzzles: &puzzles,
+        };
+        let value = serde_json::to_value(&document).unwrap();
+        assert!(value["puzzles"][1]["puzzle"]["solution"].is_array());
+        assert_eq!(value["puzzles"][2]["solving"]["status"], "no_solution");
+
+        let schema: Value = serde_json::from_str(SCHEMA_JSON).unwrap();
+        assert_eq!(schema["$id"], SCHEMA);
+        let errors = validate(&schema, &schema, &value, "");
+        assert!(errors.is_empty(), "{errors:#?}");
+
+        // The validator catches drift
+        let mut drifted = value;
+        drifted["puzzles"][0]["solving"]["steps"] = Value::Array(Vec::new());
+        assert!(!validate(&schema, &schema, &drifted, "").is_empty());
+    }
+}
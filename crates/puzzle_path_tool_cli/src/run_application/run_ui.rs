@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use iced::{
     Subscription,
     time::Duration,
@@ -8,6 +10,7 @@ use iced::{
 use tokio::sync::mpsc;
 
 use puzzle_core::explorer_collection::{ExplorerId, ExplorerObject};
+use puzzle_path_tool::job::{Phase, ProgressSnapshot};
 
 use super::{UICommand, UIMessage};
 mod views;
@@ -24,6 +27,10 @@ enum ExplorerViewState<'a> {
 #[derive(Debug)]
 struct State {
     title: String,
+    /// Progress of the running build, shown in the title.
+    progress: Option<ProgressSnapshot>,
+    /// Errors of the last build of each puzzle, empty names for errors of the whole build.
+    build_errors: BTreeMap<String, String>,
 
     sender: mpsc::Sender<UIMessage>,
 
@@ -183,7 +190,12 @@ enum SudokuCanvasMessage {
 impl State {
     fn title(&self) -> String {
         // TODO: determine title from state
-        self.title.clone()
+        match self.progress {
+            Some(progress) if progress.phase != Phase::Finished => {
+                format!("{} ({progress})", self.title)
+            }
+            _ => self.title.clone(),
+        }
     }
 
     fn new(flags: super::UIFlags) -> (Self, iced::Task<Message>) {
@@ -192,6 +204,8 @@ impl State {
         (
             State {
                 title: "Test Window".to_string(),
+                progress: None,
+                build_errors: BTreeMap::new(),
                 sender: flags.sender,
 
                 sudoku_explorer: {
@@ -250,9 +264,19 @@ impl State {
 
     pub fn update(&mut self, message: Message) {
         match message {
-            Message::Command { command } => {
-                println!("TODO: recieved {command:?} from stream");
-            }
+            Message::Command { command } => match command {
+                UICommand::PuzzleBuilt { name, puzzle } => {
+                    self.title = format!("{name}: {}", puzzle.title());
+                    self.build_errors.remove(&name);
+                    self.build_errors.remove("");
+                }
+                UICommand::BuildFailed { name, error } => {
+                    self.build_errors.insert(name, error);
+                }
+                UICommand::Progress { snapshot } => {
+                    self.progress = Some(snapshot);
+                }
+            },
             Message::FromExplorer { message } => {
                 self.update_explorer(message);
             }
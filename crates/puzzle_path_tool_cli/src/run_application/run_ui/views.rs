@@ -97,13 +97,24 @@ fn text_button<'a, T>(
         })
 }
 
-pub(super) fn path_info_view(_state: &State) -> iced::Element<'_, DetailsMessage> {
-    widget::container(widget::text("path_info_view"))
-        .padding(5)
-        .width(250)
-        .center_y(Length::Fill)
-        .style(widget::container::bordered_box)
-        .into()
+pub(super) fn path_info_view(state: &State) -> iced::Element<'_, DetailsMessage> {
+    let errors = state.build_errors.iter().map(|(name, error)| {
+        if name.is_empty() {
+            widget::text(error).into()
+        } else {
+            widget::text!("`{name}`: {error}").into()
+        }
+    });
+    widget::container(if state.build_errors.is_empty() {
+        widget::column![widget::text("path_info_view")]
+    } else {
+        widget::Column::with_children(errors).spacing(5)
+    })
+    .padding(5)
+    .width(250)
+    .center_y(Length::Fill)
+    .style(widget::container::bordered_box)
+    .into()
 }
 
 pub(super) fn control_view(state: &super::ControlState) -> iced::Element<'_, ControlsMessage> {
@@ -0,0 +1,412 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use notify::{EventKind, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use super::build::{BuildJob, BuildOutput, BuildReport};
+
+/// Events arriving within this time of each other are handled as one change.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+#[derive(Debug)]
+pub(super) struct WatchTask {
+    /// `false` if watching failed, failed builds are reported while watching.
+    pub(super) join_handler: tokio::task::JoinHandle<bool>,
+}
+
+impl WatchTask {
+    /// Build all puzzles, then rebuild the affected ones whenever a script they used changes.
+    ///
+    /// Runs until Ctrl-C is pressed.
+    pub(super) fn spawn(job: BuildJob, output: BuildOutput) -> WatchTask {
+        let join_handler = tokio::spawn(async move {
+            tokio::select! {
+                result = watch(job, output) => match result {
+                    Ok(()) => true,
+                    Err(err) => {
+                        eprintln!("Watching failed: {err:#}");
+                        false
+                    }
+                },
+                _ = tokio::signal::ctrl_c() => {
+                    println!("Stopped watching");
+                    true
+                }
+            }
+        });
+        WatchTask { join_handler }
+    }
+}
+
+/// Files each puzzle depends on, from the last successful builds.
+#[derive(Debug, Default)]
+struct Dependencies {
+    input: BTreeSet<PathBuf>,
+    puzzles: BTreeMap<String, BTreeSet<PathBuf>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Rebuild {
+    All,
+    Puzzles(BTreeSet<String>),
+}
+
+impl Dependencies {
+    /// Which puzzles have to be rebuilt after `changed` files changed.
+    fn affected(&self, changed: &BTreeSet<PathBuf>) -> Option<Rebuild> {
+        if self.input.iter().any(|path| changed.contains(path)) {
+            return Some(Rebuild::All);
+        }
+        let puzzles: BTreeSet<String> = self
+            .puzzles
+            .iter()
+            .filter(|(_, files)| files.iter().any(|path| changed.contains(path)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        (!puzzles.is_empty()).then_some(Rebuild::Puzzles(puzzles))
+    }
+
+    /// Take the files used by a build. Builds that failed keep their previous files as well,
+    /// so restoring a deleted file rebuilds them.
+    fn update(&mut self, report: &BuildReport, rebuild: &Rebuild) {
+        let failed = report.puzzles.iter().any(|built| built.result.is_err());
+        if !failed {
+            self.input.clear();
+        }
+        self.input.extend(report.dependencies.iter().cloned());
+
+        let mut previous = if *rebuild == Rebuild::All {
+            std::mem::take(&mut self.puzzles)
+        } else {
+            BTreeMap::new()
+        };
+        for built in &report.puzzles {
+            let mut files = built.dependencies.clone();
+            let old = self
+                .puzzles
+                .remove(&built.name)
+                .or_else(|| previous.remove(&built.name));
+            if built.result.is_err() {
+                files.extend(old.into_iter().flatten());
+            }
+            self.puzzles.insert(built.name.clone(), files);
+        }
+    }
+
+    fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.input.iter().chain(self.puzzles.values().flatten())
+    }
+}
+
+async fn watch(job: BuildJob, output: BuildOutput) -> anyhow::Result<()> {
+    let job = Arc::new(job);
+    let input = normalize(job.source.path());
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    })
+    .context("Creating file watcher")?;
+    let mut watched_dirs = BTreeSet::new();
+
+    let mut dependencies = Dependencies::default();
+    rebuild(&job, Rebuild::All, &mut dependencies, &output).await;
+    dependencies.input.insert(input.clone());
+    watch_dirs(&mut watcher, &mut watched_dirs, &dependencies)?;
+    println!(
+        "Watching {} files for changes, press Ctrl-C to stop",
+        dependencies.files().collect::<BTreeSet<_>>().len()
+    );
+
+    while let Some(changed) = debounced(&mut receiver, DEBOUNCE).await {
+        let Some(affected) = dependencies.affected(&changed) else {
+            // Directories of deleted files may have been recreated.
+            watch_dirs(&mut watcher, &mut watched_dirs, &dependencies)?;
+            continue;
+        };
+        match &affected {
+            Rebuild::All => println!("Input changed, rebuilding all puzzles"),
+            Rebuild::Puzzles(names) => println!(
+                "Rebuilding {}",
+                names.iter().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        }
+        rebuild(&job, affected, &mut dependencies, &output).await;
+        dependencies.input.insert(input.clone());
+        watch_dirs(&mut watcher, &mut watched_dirs, &dependencies)?;
+    }
+
+    Ok(())
+}
+
+async fn rebuild(
+    job: &Arc<BuildJob>,
+    rebuild: Rebuild,
+    dependencies: &mut Dependencies,
+    output: &BuildOutput,
+) {
+    let only = match &rebuild {
+        Rebuild::All => None,
+        Rebuild::Puzzles(names) => Some(names.clone()),
+    };
+    let builder = Arc::clone(job);
+    let report = tokio::task::spawn_blocking(move || builder.build(only.as_ref()))
+        .await
+        .context("Building task failed")
+        .and_then(|report| report);
+
+    match report {
+        Ok(mut report) => {
+            normalize_report(&mut report);
+            dependencies.update(&report, &rebuild);
+            // Failures are reported, watching goes on until they are fixed.
+            output.publish(&report).await;
+        }
+        Err(err) => output.publish_error(&err).await,
+    }
+}
+
+/// The files changed by the next events, up to a pause of `debounce` between them.
+/// `None` once the watcher is gone.
+async fn debounced(
+    receiver: &mut mpsc::UnboundedReceiver<notify::Result<notify::Event>>,
+    debounce: Duration,
+) -> Option<BTreeSet<PathBuf>> {
+    let mut changed = BTreeSet::new();
+    collect_changes(&mut changed, receiver.recv().await?);
+    while let Ok(Some(event)) = tokio::time::timeout(debounce, receiver.recv()).await {
+        collect_changes(&mut changed, event);
+    }
+    Some(changed)
+}
+
+fn collect_changes(changed: &mut BTreeSet<PathBuf>, event: notify::Result<notify::Event>) {
+    match event {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+            changed.extend(event.paths);
+        }
+        Ok(_) => {}
+        Err(err) => eprintln!("Error watching files: {err}"),
+    }
+}
+
+/// Watch the directories of all dependencies, so files replaced by editors are still noticed.
+fn watch_dirs(
+    watcher: &mut impl Watcher,
+    watched_dirs: &mut BTreeSet<PathBuf>,
+    dependencies: &Dependencies,
+) -> anyhow::Result<()> {
+    let dirs = dependency_dirs(dependencies.files());
+
+    for dir in watched_dirs.difference(&dirs) {
+        let _ = watcher.unwatch(dir);
+    }
+    for dir in dirs.difference(watched_dirs) {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Watching {}", dir.display()))?;
+    }
+    *watched_dirs = dirs;
+    Ok(())
+}
+
+/// The directory of each file, or its closest existing ancestor if the directory was deleted.
+fn dependency_dirs<'a>(files: impl Iterator<Item = &'a PathBuf>) -> BTreeSet<PathBuf> {
+    files
+        .filter_map(|path| path.ancestors().skip(1).find(|dir| dir.is_dir()))
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+fn normalize_report(report: &mut BuildReport) {
+    for files in std::iter::once(&mut report.dependencies).chain(
+        report
+            .puzzles
+            .iter_mut()
+            .map(|built| &mut built.dependencies),
+    ) {
+        *files = files.iter().map(|path| normalize(path)).collect();
+    }
+}
+
+/// Paths are compared in their canonical form, as reported by the watcher.
+fn normalize(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeSet, path::PathBuf, time::Duration};
+
+    use notify::{Event, EventKind, event::ModifyKind};
+    use tokio::sync::mpsc;
+
+    use super::{Dependencies, Rebuild, debounced, dependency_dirs};
+    use crate::run_application::build::{BuildReport, BuiltPuzzle};
+
+    fn paths(paths: &[&str]) -> BTreeSet<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    fn names(names: &[&str]) -> Rebuild {
+        Rebuild::Puzzles(names.iter().map(ToString::to_string).collect())
+    }
+
+    fn built(name: &str, ok: bool, files: &[&str]) -> BuiltPuzzle {
+        BuiltPuzzle {
+            name: name.into(),
+            result: if ok {
+                Ok(puzzle_path_tool::puzzle::Puzzle::new(4).unwrap())
+            } else {
+                Err(String::from("failed"))
+            },
+            dependencies: paths(files),
+        }
+    }
+
+    fn report(input: &[&str], puzzles: Vec<BuiltPuzzle>) -> BuildReport {
+        BuildReport {
+            dependencies: paths(input),
+            puzzles,
+        }
+    }
+
+    fn modified(path: &str) -> Event {
+        Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn shared_module_rebuilds_dependents() {
+        let mut dependencies = Dependencies::default();
+        dependencies.update(
+            &report(
+                &["/ws.lua"],
+                vec![
+                    built("a", true, &["/lib/shared.lua", "/a.lua"]),
+                    built("b", true, &["/lib/shared.lua"]),
+                    built("c", true, &["/c.lua"]),
+                ],
+            ),
+            &Rebuild::All,
+        );
+
+        assert_eq!(
+            dependencies.affected(&paths(&["/lib/shared.lua"])),
+            Some(names(&["a", "b"]))
+        );
+        assert_eq!(
+            dependencies.affected(&paths(&["/a.lua", "/c.lua"])),
+            Some(names(&["a", "c"]))
+        );
+        assert_eq!(
+            dependencies.affected(&paths(&["/ws.lua", "/a.lua"])),
+            Some(Rebuild::All)
+        );
+        assert_eq!(dependencies.affected(&paths(&["/other.lua"])), None);
+    }
+
+    #[test]
+    fn deleted_then_recreated() {
+        let mut dependencies = Dependencies::default();
+        dependencies.update(
+            &report(
+                &["/ws.lua"],
+                vec![
+                    built("a", true, &["/a.lua", "/lib/a.lua"]),
+                    built("b", true, &["/b.lua"]),
+                ],
+            ),
+            &Rebuild::All,
+        );
+
+        // Deleting the module fails the build, which no longer loads it.
+        dependencies.update(
+            &report(&["/ws.lua"], vec![built("a", false, &["/a.lua"])]),
+            &names(&["a"]),
+        );
+        assert_eq!(
+            dependencies.affected(&paths(&["/lib/a.lua"])),
+            Some(names(&["a"]))
+        );
+
+        // Once the module is back and the build succeeds, only its files are left.
+        dependencies.update(
+            &report(&["/ws.lua"], vec![built("a", true, &["/a.lua"])]),
+            &names(&["a"]),
+        );
+        assert_eq!(dependencies.affected(&paths(&["/lib/a.lua"])), None);
+        assert_eq!(
+            dependencies.affected(&paths(&["/b.lua"])),
+            Some(names(&["b"]))
+        );
+    }
+
+    #[test]
+    fn failed_script_keeps_input_files() {
+        let mut dependencies = Dependencies::default();
+        dependencies.update(
+            &report(&["/p.lua", "/lib/m.lua"], vec![built("p", true, &[])]),
+            &Rebuild::All,
+        );
+        dependencies.update(
+            &report(&["/p.lua"], vec![built("p", false, &[])]),
+            &Rebuild::All,
+        );
+        assert_eq!(
+            dependencies.affected(&paths(&["/lib/m.lua"])),
+            Some(Rebuild::All)
+        );
+    }
+
+    #[tokio::test]
+    async fn debounce_groups_events() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        for path in ["/a.lua", "/b.lua", "/a.lua"] {
+            sender.send(Ok(modified(path))).unwrap();
+        }
+        let later = sender.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            later.send(Ok(modified("/c.lua"))).unwrap();
+        });
+        drop(sender);
+
+        let debounce = Duration::from_millis(50);
+        assert_eq!(
+            debounced(&mut receiver, debounce).await,
+            Some(paths(&["/a.lua", "/b.lua"]))
+        );
+        assert_eq!(
+            debounced(&mut receiver, debounce).await,
+            Some(paths(&["/c.lua"]))
+        );
+        assert_eq!(debounced(&mut receiver, debounce).await, None);
+    }
+
+    #[test]
+    fn nested_module_dirs() {
+        let dir = std::env::temp_dir().join(format!("puzzpt-watch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        let files = [
+            dir.join("ws.lua"),
+            dir.join("a/b/c.lua"),
+            dir.join("a/b/init.lua"),
+            dir.join("gone/d.lua"),
+        ];
+
+        let dirs = dependency_dirs(files.iter());
+        std::fs::remove_dir_all(&dir).unwrap();
+        let expected: BTreeSet<PathBuf> = [dir.clone(), dir.join("a/b")].into();
+        assert_eq!(dirs, expected);
+    }
+}
@@ -0,0 +1,195 @@
+use std::{ffi::OsStr, fs, num::NonZeroUsize, path::Path};
+
+use anyhow::{Context, bail};
+use puzzle_formats::url::ShareTarget;
+use puzzle_path_tool::{
+    job::Job,
+    puzzle::{Grid, Puzzle},
+    solver::{Solver, Uniqueness},
+};
+
+use crate::{
+    commands::{CacheOptions, ExportFormat, PuzzleJsonFormat},
+    convert,
+    progress::ProgressTask,
+};
+
+pub(super) fn run(
+    options: &CacheOptions,
+    input: &str,
+    from: Option<PuzzleJsonFormat>,
+    output: Option<&OsStr>,
+    threads: Option<NonZeroUsize>,
+    share: Option<ExportFormat>,
+    ignore_unsupported: bool,
+) -> anyhow::Result<()> {
+    let mut puzzle = convert::load(options, input, from)?;
+    let full = puzzle.to_full()?;
+    check_dropped(&full.dropped, ignore_unsupported)?;
+    let solvable = Puzzle::try_from(&full.puzzle).context("Loading the puzzle into the solver")?;
+
+    let solver = Solver::new(&solvable).with_threads(self::threads(threads));
+    let solution = match solve(&solver)? {
+        Uniqueness::NoSolution => {
+            println!("No solution");
+            None
+        }
+        Uniqueness::Multiple(first, second) => {
+            println!("Multiple solutions, for example:");
+            print!("{first}");
+            println!();
+            print!("{second}");
+            println!("Differing cells: {}", differing_cells(&first, &second));
+            None
+        }
+        Uniqueness::Unique(solution) => {
+            println!("Unique solution:");
+            print!("{solution}");
+            Some(solution)
+        }
+    };
+
+    if output.is_none() && share.is_none() {
+        return Ok(());
+    }
+    let Some(solution) = solution else {
+        eprintln!("Not writing the solution, the puzzle has no unique solution");
+        return Ok(());
+    };
+
+    puzzle
+        .set_solution(&solution.to_rows())
+        .context("Writing the solution into the puzzle")?;
+
+    if let Some(path) = output {
+        let path = Path::new(path);
+        let mut json = puzzle.to_json()?;
+        json.push('\n');
+        fs::write(path, json).with_context(|| format!("Writing {}", path.display()))?;
+    }
+
+    if let Some(share) = share {
+        let target = match share {
+            ExportFormat::SudokuPad => ShareTarget::SudokuPad,
+            ExportFormat::FPuzzles => ShareTarget::FPuzzles,
+        };
+        let url = convert::share(&puzzle, target)?;
+        for dropped in &url.dropped {
+            eprintln!("Warning: `{dropped}` has no equivalent in the share Url and was dropped");
+        }
+        println!("{}", url.puzzle);
+    }
+    Ok(())
+}
+
+/// Fields only changing how the puzzle looks, which the solver can safely ignore.
+const COSMETIC: &[&str] = &[
+    "highlight",
+    "text",
+    "solution",
+    "cells",
+    "msgcorrect",
+    "foglight",
+    "triggereffect",
+];
+
+/// Fail if a dropped field may change the solutions, the verdict would be about another puzzle.
+fn check_dropped(dropped: &[Box<str>], ignore_unsupported: bool) -> anyhow::Result<()> {
+    let (cosmetic, rules): (Vec<_>, Vec<_>) = dropped
+        .iter()
+        .partition(|name| COSMETIC.contains(&name.as_ref()));
+    for name in cosmetic {
+        eprintln!("Warning: `{name}` is not supported by the solver and was ignored");
+    }
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    let names = rules
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if !ignore_unsupported {
+        bail!(
+            "The solver does not support {names}, solving without them would check a different puzzle. \
+             Pass --ignore-unsupported to solve anyway"
+        );
+    }
+    eprintln!("Warning: {names} not supported by the solver and ignored, the result may be wrong");
+    Ok(())
+}
+
+/// Search for two solutions while showing the progress, Ctrl-C stops the search.
+fn solve(solver: &Solver) -> anyhow::Result<Uniqueness> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .context("Creating the progress runtime")?;
+    let _guard = runtime.enter();
+    let job = Job::new();
+    let progress = ProgressTask::spawn(job.clone(), |_| {});
+    let uniqueness = solver.uniqueness_with(&job);
+    progress.stop();
+    uniqueness.context("Solving was cancelled")
+}
+
+/// The `--threads` option, defaulting to the number of cores.
+pub(super) fn threads(threads: Option<NonZeroUsize>) -> NonZeroUsize {
+    threads.unwrap_or_else(|| std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN))
+}
+
+fn differing_cells(first: &Grid, second: &Grid) -> String {
+    first
+        .cells()
+        .filter(|cell| first.get(*cell) != second.get(*cell))
+        .map(|cell| cell.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use puzzle_formats::format::PuzzleFormat;
+    use serde_json::json;
+
+    use super::check_dropped;
+
+    /// A 4x4 f-puzzles json, with the cells of each row in their own region if `irregular`.
+    fn fpuzzles(irregular: bool) -> PuzzleFormat {
+        let grid: Vec<Vec<_>> = (0..4)
+            .map(|row| {
+                (0..4)
+                    .map(|_| {
+                        if irregular {
+                            json!({ "region": row })
+                        } else {
+                            json!({ "highlight": "#FFA0A0" })
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let json = json!({ "size": 4, "grid": grid }).to_string();
+        PuzzleFormat::from_json(&json, None).unwrap()
+    }
+
+    #[test]
+    fn irregular_regions_are_rejected() {
+        let full = fpuzzles(true).to_full().unwrap();
+        assert_eq!(full.dropped, ["region".into()]);
+
+        let err = check_dropped(&full.dropped, false).unwrap_err();
+        assert!(err.to_string().contains("`region`"), "{err}");
+        check_dropped(&full.dropped, true).unwrap();
+    }
+
+    #[test]
+    fn cosmetic_fields_are_ignored() {
+        let full = fpuzzles(false).to_full().unwrap();
+        assert_eq!(full.dropped, ["highlight".into()]);
+        check_dropped(&full.dropped, false).unwrap();
+    }
+}